@@ -17,7 +17,7 @@ fn main() {
     
     // The backend implements Emitter trait
     backend.emit_comment("This is a comment");
-    backend.emit_line("mov rax, 42");
+    backend.emit_target_code("mov rax, 42", None);
     
     // It also implements CodeEmitter via blanket impl
     backend.emit_instruction(
@@ -11,21 +11,26 @@ fn main() {
                 return_type: IrType::Int,
                 parameters: vec![],
                 local_vars: vec![],
+                local_offsets: Default::default(),
                 instructions: vec![
                     IrInstruction::Print {
                         format_string: IrValue::StringConstant("hello_msg".to_string()),
                         args: vec![],
+                        newline: true,
                     },
                     IrInstruction::Return {
                         value: Some(IrValue::IntConstant(0)),
                         var_type: IrType::Int,
                     },
                 ],
+                is_static: false,
+                register_hints: Vec::new(),
             }
         ],
         global_strings: vec![
             ("hello_msg".to_string(), "Hello, World!".to_string()),
         ],
+        global_vars: vec![],
     };
 
     println!("=== WINDOWS X64 TARGET ===");
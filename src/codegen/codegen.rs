@@ -1,20 +1,52 @@
-use std::collections::HashMap;
-use crate::ir::{IrProgram, IrFunction, IrInstruction, IrValue, IrType};
-use crate::codegen::core::{Emitter};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Write};
+use crate::ir::{eliminate_phis, IrProgram, IrFunction, IrInstruction, IrValue, IrType};
+use crate::codegen::core::{render_lines, AsmLine, CodeEmitterWithComment, Emitter, IndentStyle, Instruction, InvalidInstruction, Operand, Register, Size};
 use crate::codegen::utils::{RegisterAllocator, StackManager};
+use crate::codegen::encoder::{parse_instruction, strip_comment};
 
-/// Modern IR backend with clean architecture
+/// Digits after the decimal point when a float is printed with a bare `%f`,
+/// as in C's `printf`. Every target uses it unless
+/// `Codegen::with_float_precision` picks another.
+pub const DEFAULT_FLOAT_PRECISION: usize = 6;
+
+/// The backend: lowers IR to assembly for any `Target`
 pub struct Codegen {
-    pub output: String,
+    /// Lines emitted since the last flush
+    pub lines: Vec<AsmLine>,
     pub stack_offset: i32,
     pub locals: HashMap<String, i32>,
     pub temp_locations: HashMap<usize, i32>, // Map temp variables to stack locations
+    pub value_types: HashMap<IrValue, IrType>, // Declared type of each local and temp
+    /// `register` variables of the current function kept in a register
+    pub register_locals: HashMap<String, Register>,
+    /// Callee-saved registers the current function uses, with the stack
+    /// slot holding the caller's value
+    pub saved_registers: Vec<(Register, i32)>,
+    /// Caller-saved registers holding `register` variables, with the stack
+    /// slot their value is kept in across calls
+    pub call_saved_registers: Vec<(Register, i32)>,
     pub data_strings: HashMap<String, String>,
+    /// Labels of the program's global variables, addressed as `[label]`
+    pub global_vars: HashSet<String>,
     pub label_count: usize,
+    pub epilogue_label: String, // Jump target for returns in the current function
+    pub indent: String,
     pub target: Box<dyn Target>,
+    pub float_precision: usize,
+    pub target_features: TargetFeatures,
+    /// Emit the target's own entry point instead of relying on the C
+    /// runtime's
+    pub no_crt: bool,
+    /// Order in which `register` variables are given registers
+    pub register_order: Vec<Register>,
+    /// First instruction rejected by operand validation, or value the IR
+    /// uses without a location; set through a shared reference by operand
+    /// lookups
+    invalid_instruction: RefCell<Option<String>>,
     #[allow(dead_code)]
     stack_manager: StackManager,
-    #[allow(dead_code)]
     register_allocator: RegisterAllocator,
 }
 
@@ -23,84 +55,300 @@ impl Codegen {
         Self::new_with_target(TargetPlatform::WindowsX64)
     }
     
+    /// Generate code for a built-in target. Panics for a
+    /// `TargetPlatform::Custom` one, which `with_target` takes instead.
     pub fn new_with_target(target_platform: TargetPlatform) -> Self {
+        Self::with_target(create_target(target_platform).unwrap_or_else(|error| panic!("{}", error)))
+    }
+
+    /// Generate code for an already constructed target, such as one
+    /// created from a custom `TargetRegistry`
+    pub fn with_target(target: Box<dyn Target>) -> Self {
         Self {
-            output: String::new(),
+            lines: Vec::new(),
             stack_offset: 0,
             locals: HashMap::new(),
             temp_locations: HashMap::new(),
+            value_types: HashMap::new(),
+            register_locals: HashMap::new(),
+            saved_registers: Vec::new(),
+            call_saved_registers: Vec::new(),
             data_strings: HashMap::new(),
+            global_vars: HashSet::new(),
             label_count: 0,
-            target: create_target(target_platform),
+            epilogue_label: String::new(),
+            indent: IndentStyle::default().unit(),
+            target,
+            float_precision: DEFAULT_FLOAT_PRECISION,
+            target_features: TargetFeatures::default(),
+            no_crt: false,
+            register_order: RegisterAllocator::DEFAULT_ORDER.to_vec(),
+            invalid_instruction: RefCell::new(None),
             stack_manager: StackManager::new(),
             register_allocator: RegisterAllocator::new(),
         }
     }
     
-    /// Generate assembly from IR program
-    pub fn generate(mut self, ir_program: &IrProgram) -> String {
+    /// Label of the newline-terminated copy of the string at `label`
+    pub fn newline_label(label: &str) -> String {
+        format!("{}_nl", label)
+    }
+
+    /// Use `style` for every indented instruction and data line
+    pub fn with_indent(mut self, style: IndentStyle) -> Self {
+        self.indent = style.unit();
+        self
+    }
+    
+    /// Print floats with `precision` digits after the decimal point
+    pub fn with_float_precision(mut self, precision: usize) -> Self {
+        self.float_precision = precision;
+        self
+    }
+
+    /// Use the instruction set extensions in `features` for float code
+    pub fn with_target_features(mut self, features: TargetFeatures) -> Self {
+        self.target_features = features;
+        self
+    }
+
+    /// Link without the C runtime: emit the target's entry stub, which
+    /// calls `main` and exits with its return value, and export it. With
+    /// the C runtime, its own entry point calls `main` and `main` returns.
+    pub fn with_no_crt(mut self, no_crt: bool) -> Self {
+        self.no_crt = no_crt;
+        self
+    }
+
+    /// Give `register` variables registers in this order instead of
+    /// `RegisterAllocator::DEFAULT_ORDER`. Registers the backend uses as
+    /// scratch are skipped; caller-saved ones are saved around every call.
+    pub fn with_register_order(mut self, order: Vec<Register>) -> Self {
+        self.register_order = order;
+        self
+    }
+
+    /// Generate assembly from IR program. Panics if the backend emits an
+    /// instruction with invalid operands; `generate_to` reports it as an
+    /// error instead.
+    pub fn generate(self, ir_program: &IrProgram) -> String {
+        match self.generate_lines(ir_program) {
+            Ok(lines) => render_lines(&lines),
+            Err(error) => panic!("code generation failed: {}", error),
+        }
+    }
+
+    /// Generate assembly from IR program as a list of lines, each tagged
+    /// with its kind, for tools that inspect or rewrite the assembly. The
+    /// lines render to exactly what `generate` returns.
+    pub fn generate_lines(self, ir_program: &IrProgram) -> io::Result<Vec<AsmLine>> {
+        let mut all_lines = Vec::new();
+        self.generate_with(ir_program, &mut |lines| {
+            all_lines.append(lines);
+            Ok(())
+        })?;
+        Ok(all_lines)
+    }
+
+    /// Generate assembly from IR program, streaming it to `writer`. Lines are
+    /// buffered only until the current function is complete, so memory use
+    /// does not grow with the size of the program. An instruction emitted
+    /// with operands it cannot take fails with `ErrorKind::InvalidData`.
+    pub fn generate_to(self, ir_program: &IrProgram, writer: &mut dyn Write) -> io::Result<()> {
+        self.generate_with(ir_program, &mut |lines| {
+            for line in lines.drain(..) {
+                writeln!(writer, "{}", line)?;
+            }
+            Ok(())
+        })?;
+        writer.flush()
+    }
+
+    /// Generate the program, handing the buffered lines to `flush` after
+    /// the header and after each function; `flush` takes them out
+    fn generate_with(mut self, ir_program: &IrProgram, flush: &mut dyn FnMut(&mut Vec<AsmLine>) -> io::Result<()>) -> io::Result<()> {
         // Assembly file header
         self.emit_section_header("MINI-C COMPILER GENERATED ASSEMBLY (FROM IR)");
         self.emit_comment(&format!("Target: {}", self.target.arch_name()));
         self.emit_comment(&format!("Calling Convention: {}", self.target.calling_convention_name()));
         self.emit_comment("Generated from: Intermediate Representation");
-        self.emit_line("");
+        self.emit_blank_line();
         
         // Assembly directives
         self.emit_comment("Assembly configuration");
         for directive in self.target.assembly_directives() {
-            self.emit_line(&directive);
+            self.emit_directive(&directive);
         }
         
         // Global and external declarations
-        for global in self.target.global_declarations(&["main"]) {
-            self.emit_line(&global);
+        let startup_code = if self.no_crt { self.target.startup_code() } else { Vec::new() };
+        let mut exported: Vec<&str> = ir_program.functions.iter()
+            .filter(|function| !function.is_static)
+            .map(|function| function.name.as_str())
+            .collect();
+        exported.extend(startup_code.iter().find_map(|line| line.strip_suffix(':')));
+        for global in self.target.global_declarations(&exported) {
+            self.emit_directive(&global);
         }
-        for external in self.target.external_declarations() {
-            self.emit_line(&external);
+        let mut externals = self.target.external_declarations();
+        // Functions called but not defined here come from another object
+        let defined: HashSet<&str> = ir_program.functions.iter().map(|function| function.name.as_str()).collect();
+        for function in &ir_program.functions {
+            let locals: HashSet<&str> = function.local_vars.iter()
+                .chain(&function.parameters)
+                .map(|(name, _)| name.as_str())
+                .collect();
+            for instruction in &function.instructions {
+                if let IrInstruction::Call { func, .. } = instruction
+                    && !defined.contains(func.as_str()) && !locals.contains(func.as_str()) {
+                    let symbol = self.target.format_function_name(func);
+                    let external = format!("extern {}", symbol.trim_end_matches(':'));
+                    if !externals.contains(&external) {
+                        externals.push(external);
+                    }
+                }
+            }
+        }
+        for external in externals {
+            self.emit_directive(&external);
         }
 
         // Data section - process global strings
         self.emit_section_header("DATA SECTION - String Literals and Constants");
-        self.emit_line(&self.target.data_section_header());
+        let header = self.target.data_section_header();
+        self.emit_directive(&header);
 
         if ir_program.global_strings.is_empty() {
             self.emit_comment("No string literals found");
         } else {
+            // Format strings of a `println` also get a copy ending in a newline
+            let mut format_labels = HashSet::new();
+            let mut newline_labels = HashSet::new();
+            for instruction in ir_program.functions.iter().flat_map(|function| &function.instructions) {
+                if let IrInstruction::Print { format_string: IrValue::StringConstant(label), newline, .. } = instruction {
+                    format_labels.insert(label.as_str());
+                    if *newline {
+                        newline_labels.insert(label.as_str());
+                    }
+                }
+            }
+
             for (label, content) in &ir_program.global_strings {
                 self.emit_comment(&format!("String constant: \"{}\"", content.replace('\n', "\\n")));
-                let formatted_literal = self.target.format_string_literal(label, content);
-                self.emit_line(&formatted_literal);
+                // Only formats have conversions; other strings are data
+                let data = if format_labels.contains(label.as_str()) {
+                    apply_float_precision(content, self.float_precision)
+                } else {
+                    content.clone()
+                };
+                let formatted_literal = self.target.format_string_literal(label, &data);
+                self.emit_data(&formatted_literal);
+                if newline_labels.contains(label.as_str()) {
+                    let formatted_literal = self.target.format_string_literal(&Self::newline_label(label), &format!("{}\n", data));
+                    self.emit_data(&formatted_literal);
+                }
                 self.data_strings.insert(label.clone(), content.clone());
             }
         }
 
+        // Global variables hold their initial value in the data section
+        for (label, var_type, value) in &ir_program.global_vars {
+            self.emit_comment(&format!("Global variable: {} {} = {}", var_type, label, self.ir_value_to_string(value)));
+            let directive = match self.ir_type_to_size(var_type) {
+                Size::Byte => "db",
+                Size::Word => "dw",
+                Size::Dword => "dd",
+                Size::Qword => "dq",
+            };
+            let initial = match value {
+                IrValue::FloatConstant(f) => f.to_bits() as i64,
+                IrValue::IntConstant(i) => *i,
+                IrValue::CharConstant(c) => *c as i64,
+                _ => 0,
+            };
+            self.emit_data(&format!("{}: {} {}", label, directive, initial));
+            self.global_vars.insert(label.clone());
+        }
+
         // Text section
         self.emit_section_header("TEXT SECTION - Executable Code");
-        self.emit_line(&self.target.text_section_header());
+        let header = self.target.text_section_header();
+        self.emit_directive(&header);
         
-        // Add startup code if needed
-        for startup_line in self.target.startup_code() {
-            self.emit_line(&startup_line);
+        // Entry point, when there is no C runtime to provide one
+        for startup_line in &startup_code {
+            self.emit_target_code(startup_line, None);
         }
 
+        self.flush_to(flush)?;
+
         // Generate code for each function
         for function in &ir_program.functions {
-            self.generate_function(function);
+            // Phis have no machine equivalent: lower them to moves first
+            if function.instructions.iter().any(|instruction| matches!(instruction, IrInstruction::Phi { .. })) {
+                let mut lowered = function.clone();
+                eliminate_phis(&mut lowered);
+                self.generate_function(&lowered);
+            } else {
+                self.generate_function(function);
+            }
+            self.flush_to(flush)?;
         }
+        Ok(())
+    }
 
-        self.output
+    /// Move the lines emitted so far out through `flush`
+    fn flush_to(&mut self, flush: &mut dyn FnMut(&mut Vec<AsmLine>) -> io::Result<()>) -> io::Result<()> {
+        if let Some(error) = self.invalid_instruction.get_mut().take() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, error));
+        }
+        flush(&mut self.lines)?;
+        self.lines.clear();
+        Ok(())
     }
 
     /// Calculate the stack space needed for a function
     pub fn calculate_stack_space(&mut self, function: &IrFunction) -> i32 {
         let mut space = 32; // Shadow space for Windows x64 ABI
+
+        // Parameters get slots of their own, for the prologue to spill the
+        // argument registers into
+        for (name, ir_type) in &function.parameters {
+            let alignment = ir_type.alignment() as i32;
+            space = (space + self.get_type_size(ir_type) + alignment - 1) / alignment * alignment;
+            self.locals.insert(name.clone(), -space);
+            self.value_types.insert(IrValue::Local(name.clone()), ir_type.clone());
+        }
         
         // Allocate space for local variables
+        let mut locals_size = 0;
         for (name, ir_type) in &function.local_vars {
             let size = self.get_type_size(ir_type);
-            space += size;
-            self.locals.insert(name.clone(), -space);
+            // Locals the generator did not lay out go below the others,
+            // rounded down to their alignment so aggregates start aligned
+            let alignment = ir_type.alignment() as i32;
+            let offset = function.local_offsets.get(name).copied().unwrap_or_else(|| {
+                let start = -(locals_size + size);
+                start - start.rem_euclid(alignment)
+            });
+            locals_size = locals_size.max(-offset);
+            self.locals.insert(name.clone(), offset - space);
+            self.value_types.insert(IrValue::Local(name.clone()), ir_type.clone());
+        }
+        space += locals_size;
+
+        // `register` variables take registers the rest of the backend
+        // never uses as scratch. Each gets a slot of its own: for the
+        // caller's value of a callee-saved register, and for the variable's
+        // value across calls in a caller-saved one.
+        for (name, register) in self.assign_register_locals(function) {
+            space += 8;
+            if self.register_allocator.is_callee_saved(register) {
+                self.saved_registers.push((register, -space));
+            } else {
+                self.call_saved_registers.push((register, -space));
+            }
+            self.register_locals.insert(name, register);
         }
         
         // Allocate space for temporary variables
@@ -112,6 +360,9 @@ impl Codegen {
                     space += 8; // Assume 8 bytes for all temps
                     self.temp_locations.insert(temp_id, -space);
                 }
+                if let Some(temp_type) = instruction.result_type() {
+                    self.value_types.insert(IrValue::Temp(temp_id), temp_type);
+                }
             }
         }
         
@@ -119,13 +370,42 @@ impl Codegen {
         (space + 15) & !15
     }
 
+    /// Pair `register`-hinted locals with free registers from the
+    /// allocator, in declaration order. Hints on floats, on variables whose
+    /// address is taken and on those past the last free register are
+    /// ignored, and those variables stay on the stack.
+    fn assign_register_locals(&mut self, function: &IrFunction) -> Vec<(String, Register)> {
+        self.register_allocator = RegisterAllocator::with_order(
+            self.target.calling_convention(), self.register_order.clone());
+
+        function.register_hints.iter()
+            .filter(|name| {
+                let mut types = function.local_vars.iter()
+                    .filter(|(local, _)| local == *name)
+                    .map(|(_, ir_type)| ir_type);
+                let first = types.next();
+                let fits = matches!(first, Some(IrType::Int | IrType::Char | IrType::Bool | IrType::Pointer(_)))
+                    && types.all(|ir_type| Some(ir_type) == first);
+                let address_taken = function.instructions.iter().any(|instruction| matches!(instruction,
+                    IrInstruction::AddressOf { src: IrValue::Local(local), .. } if local == *name));
+                fits && !address_taken
+            })
+            .map_while(|name| self.register_allocator.allocate(name.clone()).map(|register| (name.clone(), register)))
+            .collect()
+    }
+
     /// Extract temporary variable ID from instruction if present
     pub fn extract_temp_id(&self, instruction: &IrInstruction) -> Option<usize> {
         match instruction {
             IrInstruction::BinaryOp { dest, .. } |
             IrInstruction::UnaryOp { dest, .. } |
             IrInstruction::Load { dest, .. } |
-            IrInstruction::Move { dest, .. } => {
+            IrInstruction::LoadElement { dest, .. } |
+            IrInstruction::Move { dest, .. } |
+            IrInstruction::Convert { dest, .. } |
+            IrInstruction::Cast { dest, .. } |
+            IrInstruction::AddressOf { dest, .. } |
+            IrInstruction::Phi { dest, .. } => {
                 if let IrValue::Temp(id) = dest {
                     Some(*id)
                 } else {
@@ -145,46 +425,62 @@ impl Codegen {
 
     /// Get the size in bytes for an IR type
     pub fn get_type_size(&self, ir_type: &IrType) -> i32 {
-        match ir_type {
-            IrType::Int => 4,
-            IrType::Float => 8,
-            IrType::Char => 1,
-            IrType::String => 8, // Pointer size
-            IrType::Void => 0,
-            IrType::Pointer(_) => 8,
+        ir_type.size() as i32
+    }
+}
+
+/// Spell out the precision of every bare `%f` in a string, so all targets
+/// print floats alike. `%%` and conversions that already give a precision
+/// are left alone.
+fn apply_float_precision(content: &str, precision: usize) -> String {
+    let mut result = String::with_capacity(content.len());
+    let mut chars = content.chars().peekable();
+    while let Some(c) = chars.next() {
+        result.push(c);
+        if c != '%' {
+            continue;
+        }
+        match chars.peek() {
+            Some('%') => result.extend(chars.next()),
+            Some('f') => result.push_str(&format!(".{}", precision)),
+            _ => {}
         }
     }
+    result
 }
 
-// Implement the emitter traits for IrBackend
+// Implement the emitter traits for Codegen
 impl Emitter for Codegen {
-    fn emit_line(&mut self, line: &str) {
-        self.output.push_str(line);
-        self.output.push('\n');
+    fn emit_asm_line(&mut self, line: AsmLine) {
+        self.lines.push(line);
     }
 
-    fn emit_comment(&mut self, comment: &str) {
-        self.emit_line(&format!("; {}", comment));
+    fn reject_instruction(&mut self, error: InvalidInstruction) {
+        self.invalid_instruction.get_mut().get_or_insert(error.to_string());
+    }
+
+    fn indent(&self) -> &str {
+        &self.indent
     }
 }
 
-// Helper methods for IrBackend
+// Helper methods for Codegen
 impl Codegen {
     /// Emit a section header with clear visual separation
     pub fn emit_section_header(&mut self, title: &str) {
-        self.emit_line("");
-        self.emit_line(&format!("; {}", "=".repeat(60)));
-        self.emit_line(&format!("; {}", title));
-        self.emit_line(&format!("; {}", "=".repeat(60)));
-        self.emit_line("");
+        self.emit_blank_line();
+        self.emit_comment(&"=".repeat(60));
+        self.emit_comment(title);
+        self.emit_comment(&"=".repeat(60));
+        self.emit_blank_line();
     }
 
     /// Emit a subsection header with lighter visual separation
     pub fn emit_subsection_header(&mut self, title: &str) {
-        self.emit_line("");
-        self.emit_line(&format!("; {}", "-".repeat(40)));
-        self.emit_line(&format!("; {}", title));
-        self.emit_line(&format!("; {}", "-".repeat(40)));
+        self.emit_blank_line();
+        self.emit_comment(&"-".repeat(40));
+        self.emit_comment(title);
+        self.emit_comment(&"-".repeat(40));
     }
 
     /// Generate a unique label
@@ -194,9 +490,60 @@ impl Codegen {
         label
     }
 
-    /// Emit a label
-    pub fn emit_label(&mut self, label: &str) {
-        self.emit_line(&format!("{}:", label));
+    /// Emit a scalar float instruction given in its SSE2 form, switching
+    /// to the AVX form when that feature is on. The AVX arithmetic forms
+    /// read their first source from a separate operand, so the
+    /// destination is repeated there.
+    pub fn emit_float_instruction(&mut self, instruction: Instruction, operands: Vec<Operand>, comment: Option<&str>) {
+        match instruction.avx_form() {
+            Some(avx) if self.target_features.avx => {
+                let operands = match (avx.operand_count(), operands.as_slice()) {
+                    (3, [destination, source]) => vec![destination.clone(), destination.clone(), source.clone()],
+                    _ => operands,
+                };
+                self.emit_instruction_with_comment(avx, operands, comment);
+            }
+            _ => self.emit_instruction_with_comment(instruction, operands, comment),
+        }
+    }
+
+    /// Emit a line of code the target spells out as text, such as its
+    /// prologue or entry point: a label, a comment or an instruction, whose
+    /// own trailing comment is used when `comment` is None
+    pub fn emit_target_code(&mut self, text: &str, comment: Option<&str>) {
+        let text = text.trim();
+        if let Some(label) = text.strip_suffix(':') {
+            self.emit_label(label);
+            return;
+        }
+        if let Some(comment) = text.strip_prefix(';') {
+            self.emit_comment(comment.trim());
+            return;
+        }
+        let code = strip_comment(text).trim_end();
+        let own_comment = text[code.len()..].trim_start().strip_prefix(';').map(str::trim);
+        match parse_instruction(code) {
+            Ok((instruction, Some(size), operands)) => self.emit_instruction_with_size_and_comment(instruction, size, operands, comment.or(own_comment)),
+            Ok((instruction, None, operands)) => self.emit_instruction_with_comment(instruction, operands, comment.or(own_comment)),
+            Err(error) => {
+                self.invalid_instruction.get_mut().get_or_insert(format!("target code '{}': {}", text, error));
+            }
+        }
+    }
+
+    /// Record that the IR used `value` without declaring it, which fails
+    /// the generation, and return an offset to carry on with until then
+    pub fn unplaced_value(&self, value: &IrValue) -> i32 {
+        self.invalid_instruction.borrow_mut()
+            .get_or_insert_with(|| format!("{} has no stack slot in this function", self.ir_value_to_string(value)));
+        0
+    }
+
+    /// Record that the IR used `value` without a type telling how wide it
+    /// is, which fails the generation
+    pub fn untyped_value(&mut self, value: &IrValue) {
+        let error = format!("{} has no known type in this function", self.ir_value_to_string(value));
+        self.invalid_instruction.get_mut().get_or_insert(error);
     }
 
     /// Emit stack layout summary for debugging
@@ -205,12 +552,17 @@ impl Codegen {
         if self.locals.is_empty() && self.temp_locations.is_empty() {
             self.emit_comment("  No local variables or temporaries");
         } else {
-            // Clone the data to avoid borrowing issues
-            let locals = self.locals.clone();
-            let temp_locations = self.temp_locations.clone();
+            // Sort by offset so the summary is the same on every run
+            let mut locals: Vec<(String, i32)> = self.locals.iter().map(|(name, offset)| (name.clone(), *offset)).collect();
+            let mut temp_locations: Vec<(usize, i32)> = self.temp_locations.iter().map(|(id, offset)| (*id, *offset)).collect();
+            locals.sort_by_key(|(_, offset)| -offset);
+            temp_locations.sort_by_key(|(_, offset)| -offset);
             
             for (name, offset) in &locals {
-                self.emit_comment(&format!("  Local '{}' at offset {}", name, offset));
+                match self.register_locals.get(name).copied() {
+                    Some(register) => self.emit_comment(&format!("  Local '{}' in {}", name, register)),
+                    None => self.emit_comment(&format!("  Local '{}' at offset {}", name, offset)),
+                }
             }
             for (temp_id, offset) in &temp_locations {
                 self.emit_comment(&format!("  Temp %{} at offset {}", temp_id, offset));
@@ -218,13 +570,13 @@ impl Codegen {
         }
     }
 
-    /// Get the generated output
-    pub fn get_output(&self) -> &str {
-        &self.output
+    /// Text of the lines emitted since the last flush
+    pub fn get_output(&self) -> String {
+        render_lines(&self.lines)
     }
 }
 
 // Include generator implementations
 #[allow(unused_imports)]
 use crate::codegen::generators::*;
-use crate::codegen::targets::{create_target, Target, TargetPlatform};
\ No newline at end of file
+use crate::codegen::targets::{create_target, Target, TargetFeatures, TargetPlatform};
\ No newline at end of file
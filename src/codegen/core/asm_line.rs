@@ -0,0 +1,131 @@
+use std::fmt;
+
+use super::instruction::{Instruction, Operand, Size};
+
+/// What a line of generated assembly is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AsmLineKind {
+    /// A machine instruction, possibly followed by a comment
+    Instruction,
+    /// A comment on a line of its own
+    Comment,
+    /// `name:` on a line of its own
+    Label,
+    /// Sections, symbol declarations and data definitions, such as
+    /// `section .text`, `extern printf` or `str_0: db "hi", 0`
+    Directive,
+    Blank,
+}
+
+/// One line of generated assembly, recorded as the emitter wrote it and
+/// rendered to NASM text from that
+#[derive(Debug, Clone)]
+pub enum AsmLine {
+    /// `size` qualifies the instruction's sized operand when that is in
+    /// memory, as in `mov dword [rbp-4], 1`
+    Instruction {
+        indent: String,
+        instruction: Instruction,
+        size: Option<Size>,
+        operands: Vec<Operand>,
+        comment: Option<String>,
+    },
+    Comment(String),
+    Label(String),
+    /// Directive text as the target spells it
+    Directive { indent: String, text: String },
+    Blank,
+}
+
+impl AsmLine {
+    pub fn kind(&self) -> AsmLineKind {
+        match self {
+            AsmLine::Instruction { .. } => AsmLineKind::Instruction,
+            AsmLine::Comment(_) => AsmLineKind::Comment,
+            AsmLine::Label(_) => AsmLineKind::Label,
+            AsmLine::Directive { .. } => AsmLineKind::Directive,
+            AsmLine::Blank => AsmLineKind::Blank,
+        }
+    }
+
+    /// The comment of a comment line, or the one ending an instruction
+    pub fn comment(&self) -> Option<&str> {
+        match self {
+            AsmLine::Comment(comment) => Some(comment),
+            AsmLine::Instruction { comment, .. } => comment.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for AsmLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AsmLine::Instruction { indent, instruction, size, operands, comment } => {
+                let operands = operands.iter()
+                    .enumerate()
+                    .map(|(index, operand)| match size {
+                        Some(size) if index == instruction.sized_operand() && operand.is_memory() => format!("{} {}", size, operand),
+                        _ => operand.to_string(),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let mnemonic = instruction.to_string();
+                match comment {
+                    Some(comment) => write!(f, "{}{:8} {:20} ; {}", indent, mnemonic, operands, comment),
+                    None if operands.is_empty() => write!(f, "{}{:8}", indent, mnemonic),
+                    None => write!(f, "{}{:8} {}", indent, mnemonic, operands),
+                }
+            }
+            AsmLine::Comment(comment) => write!(f, "; {}", comment),
+            AsmLine::Label(name) => write!(f, "{}:", name),
+            AsmLine::Directive { indent, text } => write!(f, "{}{}", indent, text),
+            AsmLine::Blank => Ok(()),
+        }
+    }
+}
+
+/// The assembly text of `lines`, one per line
+pub fn render_lines(lines: &[AsmLine]) -> String {
+    lines.iter().map(|line| format!("{}\n", line)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codegen::core::Register;
+
+    #[test]
+    fn test_lines_render_from_what_was_emitted() {
+        let store = AsmLine::Instruction {
+            indent: "    ".to_string(),
+            instruction: Instruction::Mov,
+            size: Some(Size::Dword),
+            operands: vec![Operand::Memory { base: Register::Rbp, offset: -4 }, Operand::Immediate(1)],
+            comment: Some("store".to_string()),
+        };
+        assert_eq!(store.to_string(), "    mov      dword [rbp-4], 1     ; store");
+        assert_eq!(store.kind(), AsmLineKind::Instruction);
+        assert_eq!(store.comment(), Some("store"));
+
+        let ret = AsmLine::Instruction {
+            indent: "\t".to_string(),
+            instruction: Instruction::Ret,
+            size: None,
+            operands: vec![],
+            comment: None,
+        };
+        assert_eq!(ret.to_string(), "\tret     ");
+        assert_eq!(AsmLine::Comment("String constant".to_string()).to_string(), "; String constant");
+        assert_eq!(AsmLine::Label("main".to_string()).to_string(), "main:");
+        assert_eq!(AsmLine::Blank.to_string(), "");
+    }
+
+    #[test]
+    fn test_semicolons_in_data_are_not_comments() {
+        let data = AsmLine::Directive { indent: "    ".to_string(), text: "str_0: db \"a;b\", 0".to_string() };
+        assert_eq!(data.kind(), AsmLineKind::Directive);
+        assert_eq!(data.comment(), None);
+        assert_eq!(data.to_string(), "    str_0: db \"a;b\", 0");
+    }
+}
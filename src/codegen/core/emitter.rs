@@ -1,17 +1,65 @@
-use super::instruction::{Instruction, Operand, Size};
+use super::asm_line::AsmLine;
+use super::instruction::{Instruction, InvalidInstruction, Operand, Register, Size};
 
-pub trait Emitter {
-    fn emit_line(&mut self, line: &str);
-    fn emit_comment(&mut self, comment: &str);
-    fn emit_line_with_comment(&mut self, line: &str, comment: Option<&str>) {
-        if let Some(comment) = comment {
-            self.emit_line(&format!("{:40} ; {}", line, comment));
-        } else {
-            self.emit_line(line);
+/// Indentation unit placed before instructions and data definitions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndentStyle {
+    Spaces(usize),
+    Tab,
+}
+
+impl IndentStyle {
+    pub fn unit(&self) -> String {
+        match self {
+            IndentStyle::Spaces(count) => " ".repeat(*count),
+            IndentStyle::Tab => "\t".to_string(),
         }
     }
 }
 
+impl Default for IndentStyle {
+    fn default() -> Self {
+        IndentStyle::Spaces(4)
+    }
+}
+
+pub trait Emitter {
+    /// Record one line of assembly
+    fn emit_asm_line(&mut self, line: AsmLine);
+
+    /// Called in place of writing an instruction whose operands fail
+    /// `Instruction::validate`
+    fn reject_instruction(&mut self, error: InvalidInstruction);
+
+    /// Prefix for indented lines; labels, directives and top-level comments are not indented
+    fn indent(&self) -> &str {
+        "    "
+    }
+
+    fn emit_comment(&mut self, comment: &str) {
+        self.emit_asm_line(AsmLine::Comment(comment.to_string()));
+    }
+
+    fn emit_label(&mut self, label: &str) {
+        self.emit_asm_line(AsmLine::Label(label.to_string()));
+    }
+
+    fn emit_blank_line(&mut self) {
+        self.emit_asm_line(AsmLine::Blank);
+    }
+
+    /// Emit a directive as the target spells it, such as `section .text`
+    fn emit_directive(&mut self, text: &str) {
+        self.emit_asm_line(AsmLine::Directive { indent: String::new(), text: text.to_string() });
+    }
+
+    /// Emit a data definition at instruction indentation
+    fn emit_data(&mut self, text: &str) {
+        let indent = self.indent().to_string();
+        self.emit_asm_line(AsmLine::Directive { indent, text: text.to_string() });
+    }
+}
+
 pub trait CodeEmitter: Emitter {
     fn emit_instruction(&mut self, instruction: Instruction, operands: Vec<Operand>);
     fn emit_instruction_with_size(&mut self, instruction: Instruction, size: Size, operands: Vec<Operand>);
@@ -22,88 +70,136 @@ pub trait CodeEmitterWithComment: Emitter {
     fn emit_instruction_with_size_and_comment(&mut self, instruction: Instruction, size: Size, operands: Vec<Operand>, comment: Option<&str>);
 }
 
+/// Scratch register for immediates too wide to encode inline; no calling
+/// convention passes arguments in it
+const IMMEDIATE_SCRATCH: Register = Register::R11;
+
+/// Whether `value` can be encoded as an immediate operand of `instruction`
+/// working on `size`-wide values. Only `mov` to a 64-bit register takes a
+/// full 64-bit immediate; everything else takes at most 32 bits.
+fn immediate_fits(instruction: Instruction, destination: Option<&Operand>, size: Size, value: i64) -> bool {
+    let fits_dword = i32::try_from(value).is_ok() || u32::try_from(value).is_ok();
+    match (instruction, destination, size) {
+        (Instruction::Mov, Some(Operand::Register(_)), Size::Qword) => true,
+        (_, _, Size::Qword) => i32::try_from(value).is_ok(), // Sign-extended to 64 bits
+        (_, _, Size::Dword) => fits_dword,
+        _ => true, // Byte and word immediates are left as written
+    }
+}
+
+/// Load immediates that `instruction` cannot encode into the scratch
+/// register first, returning the operands to use in their place
+fn materialize_wide_immediates<E: Emitter>(emitter: &mut E, instruction: Instruction, mut operands: Vec<Operand>, size: Option<Size>) -> Vec<Operand> {
+    let size = operands.iter()
+        .find_map(|operand| match operand {
+            Operand::Register(reg) if !reg.is_xmm() => Some(reg.size()),
+            _ => None,
+        })
+        .or(size)
+        .unwrap_or(Size::Qword);
+
+    for index in 0..operands.len() {
+        if let Operand::Immediate(value) = operands[index]
+            && !immediate_fits(instruction, operands.first(), size, value) {
+            emitter.emit_instruction_with_comment(Instruction::Mov, vec![
+                Operand::Register(IMMEDIATE_SCRATCH),
+                Operand::Immediate(value)
+            ], Some("immediate too wide to encode inline"));
+            operands[index] = Operand::Register(IMMEDIATE_SCRATCH.sized(size));
+        }
+    }
+    operands
+}
+
+/// Validate an instruction about to be emitted, handing it to the emitter's
+/// `reject_instruction` instead when its operands are malformed
+fn accept<E: Emitter>(emitter: &mut E, instruction: Instruction, operands: &[Operand]) -> bool {
+    match instruction.validate(operands) {
+        Ok(()) => true,
+        Err(error) => {
+            emitter.reject_instruction(error);
+            false
+        }
+    }
+}
+
+/// Validate an instruction, load any immediate it cannot encode into the
+/// scratch register, and record it
+fn emit_checked<E: Emitter>(emitter: &mut E, instruction: Instruction, size: Option<Size>, operands: Vec<Operand>, comment: Option<&str>) {
+    if !accept(emitter, instruction, &operands) {
+        return;
+    }
+    let operands = materialize_wide_immediates(emitter, instruction, operands, size);
+    let indent = emitter.indent().to_string();
+    emitter.emit_asm_line(AsmLine::Instruction {
+        indent,
+        instruction,
+        size,
+        operands,
+        comment: comment.map(str::to_string),
+    });
+}
+
 impl<T: Emitter> CodeEmitter for T {
     fn emit_instruction(&mut self, instruction: Instruction, operands: Vec<Operand>) {
-        let instr_str = instruction.to_string();
-        if operands.is_empty() {
-            self.emit_line(&format!("    {:8}", instr_str));
-        } else {
-            let operands_str = operands.iter()
-                .map(|op| op.to_string())
-                .collect::<Vec<_>>()
-                .join(", ");
-            self.emit_line(&format!("    {:8} {}", instr_str, operands_str));
-        }
+        emit_checked(self, instruction, None, operands, None);
     }
 
     fn emit_instruction_with_size(&mut self, instruction: Instruction, size: Size, operands: Vec<Operand>) {
-        let size_str = match size {
-            Size::Byte => "byte",
-            Size::Word => "word", 
-            Size::Dword => "dword",
-            Size::Qword => "qword",
-        };
-        let instr_str = instruction.to_string();
-        let operands_str = operands.iter()
-            .enumerate()
-            .map(|(i, op)| {
-                if i == 0 && matches!(op, Operand::Memory { .. }) {
-                    format!("{} {}", size_str, op.to_string())
-                } else {
-                    op.to_string()
-                }
-            })
-            .collect::<Vec<_>>()
-            .join(", ");
-        self.emit_line(&format!("    {:8} {}", instr_str, operands_str));
+        emit_checked(self, instruction, Some(size), operands, None);
     }
 }
 
 impl<T: Emitter> CodeEmitterWithComment for T {
     fn emit_instruction_with_comment(&mut self, instruction: Instruction, operands: Vec<Operand>, comment: Option<&str>) {
-        let instr_str = instruction.to_string();
-        if operands.is_empty() {
-            if let Some(comment) = comment {
-                self.emit_line(&format!("    {:8}                    ; {}", instr_str, comment));
-            } else {
-                self.emit_line(&format!("    {:8}", instr_str));
-            }
-        } else {
-            let operands_str = operands.iter()
-                .map(|op| op.to_string())
-                .collect::<Vec<_>>()
-                .join(", ");
-            if let Some(comment) = comment {
-                self.emit_line(&format!("    {:8} {:20} ; {}", instr_str, operands_str, comment));
-            } else {
-                self.emit_line(&format!("    {:8} {}", instr_str, operands_str));
-            }
-        }
+        emit_checked(self, instruction, None, operands, comment);
     }
 
     fn emit_instruction_with_size_and_comment(&mut self, instruction: Instruction, size: Size, operands: Vec<Operand>, comment: Option<&str>) {
-        let size_str = match size {
-            Size::Byte => "byte",
-            Size::Word => "word", 
-            Size::Dword => "dword",
-            Size::Qword => "qword",
-        };
-        let instr_str = instruction.to_string();
-        let operands_str = operands.iter()
-            .enumerate()
-            .map(|(i, op)| {
-                if i == 0 && matches!(op, Operand::Memory { .. }) {
-                    format!("{} {}", size_str, op.to_string())
-                } else {
-                    op.to_string()
-                }
-            })
-            .collect::<Vec<_>>()
-            .join(", ");
-        if let Some(comment) = comment {
-            self.emit_line(&format!("    {:8} {:20} ; {}", instr_str, operands_str, comment));
-        } else {
-            self.emit_line(&format!("    {:8} {}", instr_str, operands_str));
+        emit_checked(self, instruction, Some(size), operands, comment);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codegen::core::Register;
+
+    /// Emitter keeping lines and rejected instructions in memory
+    #[derive(Default)]
+    struct Recorder {
+        lines: Vec<String>,
+        rejected: Vec<InvalidInstruction>,
+    }
+
+    impl Emitter for Recorder {
+        fn emit_asm_line(&mut self, line: AsmLine) {
+            self.lines.push(line.to_string());
+        }
+
+        fn reject_instruction(&mut self, error: InvalidInstruction) {
+            self.rejected.push(error);
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_instructions_are_formatted_at_instruction_indentation() {
+        let mut emitter = Recorder::default();
+        emitter.emit_comment("start");
+        emitter.emit_instruction(Instruction::Mov, vec![Operand::Register(Register::Eax), Operand::Immediate(1)]);
+        emitter.emit_instruction(Instruction::Ret, vec![]);
+
+        assert_eq!(emitter.lines, vec!["; start", "    mov      eax, 1", "    ret     "]);
+    }
+
+    #[test]
+    fn test_malformed_instruction_is_rejected() {
+        let mut emitter = Recorder::default();
+        emitter.emit_instruction(Instruction::Ret, vec![Operand::Register(Register::Eax)]);
+        emitter.emit_instruction(Instruction::Mov, vec![Operand::Register(Register::Eax)]);
+
+        assert!(emitter.lines.is_empty());
+        assert_eq!(emitter.rejected.len(), 2);
+        assert_eq!(emitter.rejected[0].to_string(), "invalid instruction 'ret eax': takes 0 operand(s), got 1");
+    }
+}
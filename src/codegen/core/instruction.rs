@@ -2,21 +2,24 @@ use std::fmt;
 
 #[derive(Debug, Clone, Copy)]
 pub enum Instruction {
-    Mov, Movsd, Movzx, Movq, Lea,
+    Mov, Movsd, Movzx, Movsx, Movsxd, Movq, Lea,
     Push, Pop,
-    Add, Sub, Imul, Idiv, Inc, Neg, Cqo, Cdq, Addsd, Subsd, Mulsd, Divsd,
+    Add, Sub, Imul, Idiv, Inc, Dec, Neg, Cqo, Cdq, Addsd, Subsd, Mulsd, Divsd,
+    Cvtsi2sd, Cvttsd2si,
+    Vmovsd, Vmovq, Vaddsd, Vsubsd, Vmulsd, Vdivsd, Vcvtsi2sd, Vcvttsd2si,
     Cmp, Test,
-    Sete, Setne, Setl, Setle, Setg, Setge,
-    Jmp, Je, Jle, Call, Ret,
-    And, Or, Xor,
+    Sete, Setne, Setl, Setle, Setg, Setge, Setb, Setbe, Seta, Setae,
+    Jmp, Je, Jne, Jl, Jle, Jg, Jge, Jb, Jbe, Ja, Jae, Call, Ret, Syscall, Ud2,
+    And, Or, Xor, Shl, Sar,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Register {
-    Rax, Rbp, Rsp, Rcx, Rdx, R8, R9, Rdi, Rsi,
-    Eax, Edx, R8d, R9d, Edi, Esi,
-    Al,
-    Xmm0, Xmm1, Xmm2, Xmm3,
+    Rax, Rbp, Rsp, Rcx, Rdx, R8, R9, R10, R11, Rdi, Rsi, Rbx, R12, R13, R14, R15,
+    Eax, Ecx, Edx, R8d, R9d, R10d, R11d, Edi, Esi, Ebx, R12d, R13d, R14d, R15d,
+    Ax, Cx, Dx, R8w, R9w, R10w, R11w, Di, Si, Bx, R12w, R13w, R14w, R15w,
+    Al, Cl, Dl, R8b, R9b, R10b, R11b, Dil, Sil, Bl, R12b, R13b, R14b, R15b,
+    Xmm0, Xmm1, Xmm2, Xmm3, Xmm4, Xmm5, Xmm6, Xmm7,
 }
 
 #[derive(Debug, Clone)]
@@ -28,7 +31,7 @@ pub enum Operand {
     String(String),
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Size {
     Byte, Word, Dword, Qword,
 }
@@ -39,6 +42,8 @@ impl Instruction {
             Instruction::Mov => "mov",
             Instruction::Movsd => "movsd",
             Instruction::Movzx => "movzx",
+            Instruction::Movsx => "movsx",
+            Instruction::Movsxd => "movsxd",
             Instruction::Movq => "movq",
             Instruction::Lea => "lea",
             Instruction::Push => "push",
@@ -48,6 +53,7 @@ impl Instruction {
             Instruction::Imul => "imul",
             Instruction::Idiv => "idiv",
             Instruction::Inc => "inc",
+            Instruction::Dec => "dec",
             Instruction::Neg => "neg",
             Instruction::Cqo => "cqo",
             Instruction::Cdq => "cdq",
@@ -55,6 +61,16 @@ impl Instruction {
             Instruction::Subsd => "subsd",
             Instruction::Mulsd => "mulsd",
             Instruction::Divsd => "divsd",
+            Instruction::Cvtsi2sd => "cvtsi2sd",
+            Instruction::Cvttsd2si => "cvttsd2si",
+            Instruction::Vmovsd => "vmovsd",
+            Instruction::Vmovq => "vmovq",
+            Instruction::Vaddsd => "vaddsd",
+            Instruction::Vsubsd => "vsubsd",
+            Instruction::Vmulsd => "vmulsd",
+            Instruction::Vdivsd => "vdivsd",
+            Instruction::Vcvtsi2sd => "vcvtsi2sd",
+            Instruction::Vcvttsd2si => "vcvttsd2si",
             Instruction::Cmp => "cmp",
             Instruction::Test => "test",
             Instruction::Sete => "sete",
@@ -63,14 +79,132 @@ impl Instruction {
             Instruction::Setle => "setle",
             Instruction::Setg => "setg",
             Instruction::Setge => "setge",
+            Instruction::Setb => "setb",
+            Instruction::Setbe => "setbe",
+            Instruction::Seta => "seta",
+            Instruction::Setae => "setae",
             Instruction::Jmp => "jmp",
             Instruction::Je => "je",
+            Instruction::Jne => "jne",
+            Instruction::Jl => "jl",
             Instruction::Jle => "jle",
+            Instruction::Jg => "jg",
+            Instruction::Jge => "jge",
+            Instruction::Jb => "jb",
+            Instruction::Jbe => "jbe",
+            Instruction::Ja => "ja",
+            Instruction::Jae => "jae",
             Instruction::Call => "call",
             Instruction::Ret => "ret",
+            Instruction::Syscall => "syscall",
+            Instruction::Ud2 => "ud2",
             Instruction::And => "and",
             Instruction::Or => "or",
             Instruction::Xor => "xor",
+            Instruction::Shl => "shl",
+            Instruction::Sar => "sar",
+        }
+    }
+}
+
+/// An instruction given operands it cannot take, such as `ret` with an
+/// operand or `mov` with an immediate destination
+#[derive(Debug, Clone)]
+pub struct InvalidInstruction {
+    pub instruction: Instruction,
+    pub operands: Vec<Operand>,
+    pub reason: String,
+}
+
+impl Instruction {
+    /// Number of explicit operands the instruction is written with
+    pub fn operand_count(&self) -> usize {
+        use Instruction::*;
+        match self {
+            Cqo | Cdq | Ret | Syscall | Ud2 => 0,
+            Push | Pop | Idiv | Inc | Dec | Neg |
+            Sete | Setne | Setl | Setle | Setg | Setge | Setb | Setbe | Seta | Setae |
+            Jmp | Je | Jne | Jl | Jle | Jg | Jge | Jb | Jbe | Ja | Jae | Call => 1,
+            Vaddsd | Vsubsd | Vmulsd | Vdivsd | Vcvtsi2sd => 3,
+            _ => 2,
+        }
+    }
+
+    /// Index of the operand an explicit size describes, which NASM wants
+    /// qualified when it is in memory: the destination, except for the
+    /// extending moves, whose size is their source's (`movzx eax, byte [rbp-1]`)
+    pub fn sized_operand(&self) -> usize {
+        match self {
+            Instruction::Movzx | Instruction::Movsx | Instruction::Movsxd => 1,
+            _ => 0,
+        }
+    }
+
+    /// The AVX (VEX-encoded) form of an SSE2 scalar float instruction.
+    /// The arithmetic and conversion forms take the first source as an
+    /// extra operand instead of reading the destination.
+    pub fn avx_form(&self) -> Option<Instruction> {
+        use Instruction::*;
+        match self {
+            Movsd => Some(Vmovsd),
+            Movq => Some(Vmovq),
+            Addsd => Some(Vaddsd),
+            Subsd => Some(Vsubsd),
+            Mulsd => Some(Vmulsd),
+            Divsd => Some(Vdivsd),
+            Cvtsi2sd => Some(Vcvtsi2sd),
+            Cvttsd2si => Some(Vcvttsd2si),
+            _ => None,
+        }
+    }
+
+    /// Check the number and kinds of `operands` before the instruction is
+    /// written out
+    pub fn validate(&self, operands: &[Operand]) -> Result<(), InvalidInstruction> {
+        use Instruction::*;
+        let reason = if operands.len() != self.operand_count() {
+            Some(format!("takes {} operand(s), got {}", self.operand_count(), operands.len()))
+        } else {
+            match (self, operands) {
+                (Je | Jne | Jl | Jle | Jg | Jge | Jb | Jbe | Ja | Jae, [target]) if !matches!(target, Operand::Label(_)) =>
+                    Some("jump target must be a label".to_string()),
+                (Pop | Idiv | Inc | Dec | Neg | Sete | Setne | Setl | Setle | Setg | Setge | Setb | Setbe | Seta | Setae, [operand])
+                    if !operand.is_register_or_memory() => Some("operand must be a register or memory".to_string()),
+                (Lea, [_, source]) if !matches!(source, Operand::Memory { .. } | Operand::Label(_) | Operand::String(_)) =>
+                    Some("source must be a memory address".to_string()),
+                (Movzx | Movsx | Movsxd | Lea | Imul | Addsd | Subsd | Mulsd | Divsd | Cvtsi2sd | Cvttsd2si | Vcvttsd2si, [destination, _])
+                    if !matches!(destination, Operand::Register(_)) => Some("destination must be a register".to_string()),
+                (_, [destination, first, _]) if !matches!((destination, first), (Operand::Register(_), Operand::Register(_))) =>
+                    Some("destination and first source must be registers".to_string()),
+                (_, [destination, _]) if !destination.is_register_or_memory() => Some(match self {
+                    Cmp | Test => "first operand must be a register or memory".to_string(),
+                    _ => "destination must be a register or memory".to_string(),
+                }),
+                (_, [destination, source]) if destination.is_memory() && source.is_memory() => Some("at most one operand may be in memory".to_string()),
+                _ => None,
+            }
+        };
+
+        match reason {
+            Some(reason) => Err(InvalidInstruction { instruction: *self, operands: operands.to_vec(), reason }),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Operand {
+    /// Registers and memory references; `String` operands hold a written
+    /// out address such as `[name]`
+    fn is_register_or_memory(&self) -> bool {
+        matches!(self, Operand::Register(_) | Operand::Memory { .. } | Operand::String(_))
+    }
+
+    /// Stack slots and written out addresses such as `[name]`
+    pub fn is_memory(&self) -> bool {
+        match self {
+            Operand::Memory { .. } => true,
+            Operand::String(text) => text.starts_with('['),
+            _ => false,
         }
     }
 }
@@ -85,21 +219,147 @@ impl Register {
             Register::Rdx => "rdx",
             Register::R8 => "r8",
             Register::R9 => "r9",
+            Register::R10 => "r10",
+            Register::R11 => "r11",
             Register::Rdi => "rdi",
             Register::Rsi => "rsi",
+            Register::Rbx => "rbx",
+            Register::R12 => "r12",
+            Register::R13 => "r13",
+            Register::R14 => "r14",
+            Register::R15 => "r15",
             Register::Eax => "eax",
+            Register::Ecx => "ecx",
             Register::Edx => "edx",
             Register::R8d => "r8d",
             Register::R9d => "r9d",
+            Register::R10d => "r10d",
+            Register::R11d => "r11d",
             Register::Edi => "edi",
             Register::Esi => "esi",
+            Register::Ebx => "ebx",
+            Register::R12d => "r12d",
+            Register::R13d => "r13d",
+            Register::R14d => "r14d",
+            Register::R15d => "r15d",
+            Register::Ax => "ax",
+            Register::Cx => "cx",
+            Register::Dx => "dx",
+            Register::R8w => "r8w",
+            Register::R9w => "r9w",
+            Register::R10w => "r10w",
+            Register::R11w => "r11w",
+            Register::Di => "di",
+            Register::Si => "si",
+            Register::Bx => "bx",
+            Register::R12w => "r12w",
+            Register::R13w => "r13w",
+            Register::R14w => "r14w",
+            Register::R15w => "r15w",
             Register::Al => "al",
+            Register::Cl => "cl",
+            Register::Dl => "dl",
+            Register::R8b => "r8b",
+            Register::R9b => "r9b",
+            Register::R10b => "r10b",
+            Register::R11b => "r11b",
+            Register::Dil => "dil",
+            Register::Sil => "sil",
+            Register::Bl => "bl",
+            Register::R12b => "r12b",
+            Register::R13b => "r13b",
+            Register::R14b => "r14b",
+            Register::R15b => "r15b",
             Register::Xmm0 => "xmm0",
             Register::Xmm1 => "xmm1",
             Register::Xmm2 => "xmm2",
             Register::Xmm3 => "xmm3",
+            Register::Xmm4 => "xmm4",
+            Register::Xmm5 => "xmm5",
+            Register::Xmm6 => "xmm6",
+            Register::Xmm7 => "xmm7",
+        }
+    }
+}
+
+impl Register {
+    pub fn is_xmm(&self) -> bool {
+        matches!(self, Register::Xmm0 | Register::Xmm1 | Register::Xmm2 | Register::Xmm3 |
+            Register::Xmm4 | Register::Xmm5 | Register::Xmm6 | Register::Xmm7)
+    }
+
+    /// The width of this register view. XMM registers report `Qword`, the
+    /// width of the scalar doubles they carry.
+    pub fn size(&self) -> Size {
+        match self {
+            Register::Al | Register::Cl | Register::Dl | Register::R8b |
+            Register::R9b | Register::R10b | Register::R11b | Register::Dil | Register::Sil |
+            Register::Bl | Register::R12b | Register::R13b | Register::R14b | Register::R15b => Size::Byte,
+            Register::Ax | Register::Cx | Register::Dx | Register::R8w |
+            Register::R9w | Register::R10w | Register::R11w | Register::Di | Register::Si |
+            Register::Bx | Register::R12w | Register::R13w | Register::R14w | Register::R15w => Size::Word,
+            Register::Eax | Register::Ecx | Register::Edx | Register::R8d |
+            Register::R9d | Register::R10d | Register::R11d | Register::Edi | Register::Esi |
+            Register::Ebx | Register::R12d | Register::R13d | Register::R14d | Register::R15d => Size::Dword,
+            _ => Size::Qword,
+        }
+    }
+
+    /// The 64-bit register this view is part of (`al` -> `rax`)
+    pub fn full(&self) -> Register {
+        match self {
+            Register::Eax | Register::Ax | Register::Al => Register::Rax,
+            Register::Ecx | Register::Cx | Register::Cl => Register::Rcx,
+            Register::Edx | Register::Dx | Register::Dl => Register::Rdx,
+            Register::R8d | Register::R8w | Register::R8b => Register::R8,
+            Register::R9d | Register::R9w | Register::R9b => Register::R9,
+            Register::R10d | Register::R10w | Register::R10b => Register::R10,
+            Register::R11d | Register::R11w | Register::R11b => Register::R11,
+            Register::Edi | Register::Di | Register::Dil => Register::Rdi,
+            Register::Esi | Register::Si | Register::Sil => Register::Rsi,
+            Register::Ebx | Register::Bx | Register::Bl => Register::Rbx,
+            Register::R12d | Register::R12w | Register::R12b => Register::R12,
+            Register::R13d | Register::R13w | Register::R13b => Register::R13,
+            Register::R14d | Register::R14w | Register::R14b => Register::R14,
+            Register::R15d | Register::R15w | Register::R15b => Register::R15,
+            other => *other,
+        }
+    }
+
+    /// The `size`-wide view of the same register (`rdx` at `Byte` -> `dl`).
+    /// Registers without sub-register names (`rbp`, `rsp`, XMM) are
+    /// returned unchanged.
+    pub fn sized(&self, size: Size) -> Register {
+        let views = match self.full() {
+            Register::Rax => [Register::Al, Register::Ax, Register::Eax, Register::Rax],
+            Register::Rcx => [Register::Cl, Register::Cx, Register::Ecx, Register::Rcx],
+            Register::Rdx => [Register::Dl, Register::Dx, Register::Edx, Register::Rdx],
+            Register::R8 => [Register::R8b, Register::R8w, Register::R8d, Register::R8],
+            Register::R9 => [Register::R9b, Register::R9w, Register::R9d, Register::R9],
+            Register::R10 => [Register::R10b, Register::R10w, Register::R10d, Register::R10],
+            Register::R11 => [Register::R11b, Register::R11w, Register::R11d, Register::R11],
+            Register::Rdi => [Register::Dil, Register::Di, Register::Edi, Register::Rdi],
+            Register::Rsi => [Register::Sil, Register::Si, Register::Esi, Register::Rsi],
+            Register::Rbx => [Register::Bl, Register::Bx, Register::Ebx, Register::Rbx],
+            Register::R12 => [Register::R12b, Register::R12w, Register::R12d, Register::R12],
+            Register::R13 => [Register::R13b, Register::R13w, Register::R13d, Register::R13],
+            Register::R14 => [Register::R14b, Register::R14w, Register::R14d, Register::R14],
+            Register::R15 => [Register::R15b, Register::R15w, Register::R15d, Register::R15],
+            other => return other,
+        };
+
+        match size {
+            Size::Byte => views[0],
+            Size::Word => views[1],
+            Size::Dword => views[2],
+            Size::Qword => views[3],
         }
     }
+
+    /// True when both names refer to the same physical register
+    pub fn aliases(&self, other: Register) -> bool {
+        self.full() == other.full()
+    }
 }
 
 impl fmt::Display for Instruction {
@@ -132,6 +392,15 @@ impl fmt::Display for Operand {
     }
 }
 
+impl fmt::Display for InvalidInstruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let operands: Vec<String> = self.operands.iter().map(|operand| operand.to_string()).collect();
+        write!(f, "invalid instruction '{} {}': {}", self.instruction, operands.join(", "), self.reason)
+    }
+}
+
+impl std::error::Error for InvalidInstruction {}
+
 impl fmt::Display for Size {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let size_str = match self {
@@ -143,3 +412,47 @@ impl fmt::Display for Size {
         write!(f, "{}", size_str)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sub_registers_share_the_full_register() {
+        assert_eq!(Register::Al.full(), Register::Rax);
+        assert_eq!(Register::R8b.full(), Register::R8);
+        assert_eq!(Register::Esi.full(), Register::Rsi);
+        assert!(Register::Dl.aliases(Register::Edx));
+        assert!(!Register::Al.aliases(Register::Dl));
+    }
+
+    #[test]
+    fn test_sized_views() {
+        assert_eq!(Register::Rax.sized(Size::Byte), Register::Al);
+        assert_eq!(Register::Al.sized(Size::Qword), Register::Rax);
+        assert_eq!(Register::R8.sized(Size::Dword), Register::R8d);
+        assert_eq!(Register::R9d.sized(Size::Word), Register::R9w);
+        assert_eq!(Register::Rbp.sized(Size::Byte), Register::Rbp);
+        assert_eq!(Register::Dil.size(), Size::Byte);
+        assert_eq!(Register::Xmm0.size(), Size::Qword);
+    }
+
+    #[test]
+    fn test_operand_counts_and_kinds_are_validated() {
+        let eax = || Operand::Register(Register::Eax);
+        let local = || Operand::Memory { base: Register::Rbp, offset: -8 };
+
+        assert!(Instruction::Ret.validate(&[]).is_ok());
+        assert!(Instruction::Mov.validate(&[local(), eax()]).is_ok());
+        assert!(Instruction::Idiv.validate(&[Operand::Register(Register::Ecx)]).is_ok());
+
+        let error = Instruction::Ret.validate(&[eax(), eax()]).unwrap_err();
+        assert_eq!(error.to_string(), "invalid instruction 'ret eax, eax': takes 0 operand(s), got 2");
+        assert!(Instruction::Mov.validate(&[eax()]).is_err());
+        assert!(Instruction::Idiv.validate(&[Operand::Immediate(2)]).is_err());
+        assert!(Instruction::Mov.validate(&[Operand::Immediate(1), eax()]).is_err());
+        assert!(Instruction::Add.validate(&[local(), local()]).is_err());
+        assert!(Instruction::Lea.validate(&[eax(), eax()]).is_err());
+        assert!(Instruction::Je.validate(&[eax()]).is_err());
+    }
+}
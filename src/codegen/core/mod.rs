@@ -1,10 +1,12 @@
 //! Core abstractions and traits for code generation
 
+mod asm_line;
 mod emitter;
 mod instruction;
 
-pub use emitter::{CodeEmitter, CodeEmitterWithComment, Emitter};
-pub use instruction::{Instruction, Operand, Register, Size};
+pub use asm_line::{render_lines, AsmLine, AsmLineKind};
+pub use emitter::{CodeEmitter, CodeEmitterWithComment, Emitter, IndentStyle};
+pub use instruction::{Instruction, InvalidInstruction, Operand, Register, Size};
 // pub use crate::codegen::targets::{
 //     create_target, parse_target_platform, CallingConvention,
 //     LinuxX64Target, MacOSX64Target, Target,
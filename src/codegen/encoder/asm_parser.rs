@@ -0,0 +1,220 @@
+use crate::codegen::core::{Instruction, Operand, Register, Size};
+
+/// One meaningful line of the assembly the code generator emits
+#[derive(Debug, Clone)]
+pub enum AsmItem {
+    Section(String),
+    Global(String),
+    Extern(String),
+    Label(String),
+    /// Bytes of a `db` directive
+    Data(Vec<u8>),
+    Instruction {
+        instruction: Instruction,
+        size: Option<Size>,
+        operands: Vec<Operand>,
+    },
+}
+
+const REGISTERS: [Register; 66] = [
+    Register::Rax, Register::Rbp, Register::Rsp, Register::Rcx, Register::Rdx,
+    Register::R8, Register::R9, Register::R10, Register::R11, Register::Rdi, Register::Rsi,
+    Register::Rbx, Register::R12, Register::R13, Register::R14, Register::R15,
+    Register::Eax, Register::Ecx, Register::Edx, Register::R8d, Register::R9d,
+    Register::R10d, Register::R11d, Register::Edi, Register::Esi,
+    Register::Ebx, Register::R12d, Register::R13d, Register::R14d, Register::R15d,
+    Register::Ax, Register::Cx, Register::Dx, Register::R8w, Register::R9w,
+    Register::R10w, Register::R11w, Register::Di, Register::Si,
+    Register::Bx, Register::R12w, Register::R13w, Register::R14w, Register::R15w,
+    Register::Al, Register::Cl, Register::Dl, Register::R8b, Register::R9b,
+    Register::R10b, Register::R11b, Register::Dil, Register::Sil,
+    Register::Bl, Register::R12b, Register::R13b, Register::R14b, Register::R15b,
+    Register::Xmm0, Register::Xmm1, Register::Xmm2, Register::Xmm3,
+    Register::Xmm4, Register::Xmm5, Register::Xmm6, Register::Xmm7,
+];
+
+const INSTRUCTIONS: [Instruction; 64] = [
+    Instruction::Mov, Instruction::Movsd, Instruction::Movzx, Instruction::Movsx, Instruction::Movsxd,
+    Instruction::Movq, Instruction::Lea, Instruction::Push, Instruction::Pop,
+    Instruction::Add, Instruction::Sub, Instruction::Imul, Instruction::Idiv,
+    Instruction::Inc, Instruction::Dec, Instruction::Neg, Instruction::Cqo, Instruction::Cdq,
+    Instruction::Addsd, Instruction::Subsd, Instruction::Mulsd, Instruction::Divsd,
+    Instruction::Cvtsi2sd, Instruction::Cvttsd2si,
+    Instruction::Vmovsd, Instruction::Vmovq, Instruction::Vaddsd, Instruction::Vsubsd,
+    Instruction::Vmulsd, Instruction::Vdivsd, Instruction::Vcvtsi2sd, Instruction::Vcvttsd2si,
+    Instruction::Cmp, Instruction::Test,
+    Instruction::Sete, Instruction::Setne, Instruction::Setl, Instruction::Setle,
+    Instruction::Setg, Instruction::Setge, Instruction::Setb, Instruction::Setbe,
+    Instruction::Seta, Instruction::Setae,
+    Instruction::Jmp, Instruction::Je, Instruction::Jne, Instruction::Jl,
+    Instruction::Jle, Instruction::Jg, Instruction::Jge, Instruction::Jb,
+    Instruction::Jbe, Instruction::Ja, Instruction::Jae, Instruction::Call,
+    Instruction::Ret, Instruction::Syscall, Instruction::Ud2,
+    Instruction::And, Instruction::Or, Instruction::Xor, Instruction::Shl, Instruction::Sar,
+];
+
+/// Parse NASM-syntax assembly as produced by `Codegen` back into
+/// structured instructions and data. Only the syntax the code generator
+/// itself emits is understood.
+pub fn parse_assembly(text: &str) -> Result<Vec<AsmItem>, String> {
+    let mut items = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        parse_line(strip_comment(line).trim(), &mut items)
+            .map_err(|e| format!("line {}: {}", index + 1, e))?;
+    }
+    Ok(items)
+}
+
+fn parse_line(line: &str, items: &mut Vec<AsmItem>) -> Result<(), String> {
+    if line.is_empty() {
+        return Ok(());
+    }
+
+    let (head, rest) = match line.split_once(char::is_whitespace) {
+        Some((head, rest)) => (head, rest.trim()),
+        None => (line, ""),
+    };
+
+    if let Some(label) = head.strip_suffix(':') {
+        items.push(AsmItem::Label(label.to_string()));
+        return parse_line(rest, items);
+    }
+
+    match head.to_lowercase().as_str() {
+        "bits" | "default" => {}
+        "section" => items.push(AsmItem::Section(rest.to_string())),
+        "global" => items.push(AsmItem::Global(rest.to_string())),
+        "extern" => items.push(AsmItem::Extern(rest.to_string())),
+        "db" => items.push(AsmItem::Data(parse_db(rest)?)),
+        "dw" => items.push(AsmItem::Data(parse_integers(rest, 2)?)),
+        "dd" => items.push(AsmItem::Data(parse_integers(rest, 4)?)),
+        "dq" => items.push(AsmItem::Data(parse_integers(rest, 8)?)),
+        _ => {
+            let (instruction, size, operands) = parse_instruction(line)?;
+            items.push(AsmItem::Instruction { instruction, size, operands });
+        }
+    }
+    Ok(())
+}
+
+/// Parse one instruction, such as `mov dword [rbp-4], 1`, into its
+/// mnemonic, the size qualifying its memory operand and its operands
+pub fn parse_instruction(text: &str) -> Result<(Instruction, Option<Size>, Vec<Operand>), String> {
+    let (mnemonic, rest) = match text.trim().split_once(char::is_whitespace) {
+        Some((mnemonic, rest)) => (mnemonic, rest.trim()),
+        None => (text.trim(), ""),
+    };
+    let mnemonic = mnemonic.to_lowercase();
+    let instruction = INSTRUCTIONS.iter()
+        .find(|instruction| instruction.to_string() == mnemonic)
+        .copied()
+        .ok_or_else(|| format!("unknown instruction '{}'", mnemonic))?;
+
+    let mut size = None;
+    let mut operands = Vec::new();
+    if !rest.is_empty() {
+        for operand in rest.split(',') {
+            let (operand_size, operand) = parse_operand(operand.trim())?;
+            size = size.or(operand_size);
+            operands.push(operand);
+        }
+    }
+    Ok((instruction, size, operands))
+}
+
+/// Parse one operand as NASM spells it, with the size qualifying it if any
+pub fn parse_operand(text: &str) -> Result<(Option<Size>, Operand), String> {
+    let (size, text) = match text.split_once(char::is_whitespace) {
+        Some(("byte", rest)) => (Some(Size::Byte), rest.trim()),
+        Some(("word", rest)) => (Some(Size::Word), rest.trim()),
+        Some(("dword", rest)) => (Some(Size::Dword), rest.trim()),
+        Some(("qword", rest)) => (Some(Size::Qword), rest.trim()),
+        _ => (None, text),
+    };
+
+    if let Some(inner) = text.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+        let inner = inner.trim();
+        let inner = inner.strip_prefix("rel ").unwrap_or(inner).trim();
+        let split = inner.find(['+', '-']).unwrap_or(inner.len());
+        if let Some(base) = parse_register(inner[..split].trim()) {
+            let offset = match inner[split..].trim() {
+                "" => 0,
+                displacement => displacement.replace(' ', "").parse::<i32>()
+                    .map_err(|_| format!("bad displacement in '{}'", text))?,
+            };
+            return Ok((size, Operand::Memory { base, offset }));
+        }
+        return Ok((size, Operand::Label(inner.to_string())));
+    }
+
+    if let Some(register) = parse_register(text) {
+        return Ok((size, Operand::Register(register)));
+    }
+    if let Ok(value) = text.parse::<i64>() {
+        return Ok((size, Operand::Immediate(value)));
+    }
+    if text.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '.') && !text.is_empty() {
+        return Ok((size, Operand::Label(text.to_string())));
+    }
+    Err(format!("cannot parse operand '{}'", text))
+}
+
+fn parse_register(name: &str) -> Option<Register> {
+    REGISTERS.iter().find(|register| register.to_string() == name).copied()
+}
+
+/// `db "text", 10, 0` -> bytes. nasm takes double-quoted text verbatim.
+fn parse_db(text: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    for part in split_outside_quotes(text, ',') {
+        let part = part.trim();
+        if let Some(quoted) = part.strip_prefix('"').and_then(|p| p.strip_suffix('"')) {
+            bytes.extend(quoted.as_bytes());
+        } else {
+            let value = part.parse::<i64>().map_err(|_| format!("bad db value '{}'", part))?;
+            bytes.push(u8::try_from(value).map_err(|_| format!("db value {} does not fit in a byte", value))?);
+        }
+    }
+    Ok(bytes)
+}
+
+/// `dd 1, -2` -> `width` little-endian bytes per value
+fn parse_integers(text: &str, width: usize) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    for part in text.split(',') {
+        let value = part.trim().parse::<i64>().map_err(|_| format!("bad data value '{}'", part.trim()))?;
+        bytes.extend(&value.to_le_bytes()[..width]);
+    }
+    Ok(bytes)
+}
+
+fn split_outside_quotes(text: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut in_quotes = false;
+    let mut start = 0;
+    for (index, c) in text.char_indices() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            c if c == separator && !in_quotes => {
+                parts.push(&text[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&text[start..]);
+    parts
+}
+
+/// `line` up to its comment, if any; a `;` in a quoted string is kept
+pub fn strip_comment(line: &str) -> &str {
+    let mut in_quotes = false;
+    for (index, c) in line.char_indices() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            ';' if !in_quotes => return &line[..index],
+            _ => {}
+        }
+    }
+    line
+}
@@ -0,0 +1,176 @@
+use super::x86::{EncodedText, RelocationKind};
+
+const SHT_PROGBITS: u32 = 1;
+const SHT_SYMTAB: u32 = 2;
+const SHT_STRTAB: u32 = 3;
+const SHT_RELA: u32 = 4;
+
+const SHF_WRITE: u64 = 0x1;
+const SHF_ALLOC: u64 = 0x2;
+const SHF_EXECINSTR: u64 = 0x4;
+const SHF_INFO_LINK: u64 = 0x40;
+
+const STB_LOCAL: u8 = 0;
+const STB_GLOBAL: u8 = 1;
+const STT_NOTYPE: u8 = 0;
+const STT_FUNC: u8 = 2;
+
+const R_X86_64_PC32: u64 = 2;
+const R_X86_64_PLT32: u64 = 4;
+
+// Section header indices, in the order they are written
+const TEXT_INDEX: u16 = 1;
+const DATA_INDEX: u16 = 2;
+const SYMTAB_INDEX: u32 = 4;
+const STRTAB_INDEX: u32 = 5;
+const SECTION_COUNT: u16 = 7;
+
+struct Symbol {
+    name: String,
+    info: u8,
+    section: u16,
+    value: u64,
+}
+
+/// Build an ELF64 relocatable object from an encoded `.text`, the `.data`
+/// bytes and the labels defined in them. Labels named in `globals` are
+/// exported; referenced symbols that are not defined become undefined
+/// globals for the linker to resolve.
+pub fn write_elf_object(text: &EncodedText, data: &[u8], data_labels: &[(String, usize)], globals: &[String]) -> Vec<u8> {
+    let mut locals = vec![Symbol { name: String::new(), info: 0, section: 0, value: 0 }];
+    let mut exported = Vec::new();
+
+    for (name, offset) in data_labels {
+        locals.push(Symbol { name: name.clone(), info: STB_LOCAL << 4 | STT_NOTYPE, section: DATA_INDEX, value: *offset as u64 });
+    }
+    for (name, offset) in &text.labels {
+        if globals.contains(name) {
+            exported.push(Symbol { name: name.clone(), info: STB_GLOBAL << 4 | STT_FUNC, section: TEXT_INDEX, value: *offset as u64 });
+        } else {
+            locals.push(Symbol { name: name.clone(), info: STB_LOCAL << 4 | STT_NOTYPE, section: TEXT_INDEX, value: *offset as u64 });
+        }
+    }
+    for relocation in &text.relocations {
+        let defined = locals.iter().chain(&exported).any(|symbol| symbol.name == relocation.symbol);
+        if !defined {
+            exported.push(Symbol { name: relocation.symbol.clone(), info: STB_GLOBAL << 4 | STT_NOTYPE, section: 0, value: 0 });
+        }
+    }
+
+    let first_global = locals.len() as u32;
+    let symbols: Vec<Symbol> = locals.into_iter().chain(exported).collect();
+
+    let mut strtab = vec![0u8];
+    let mut symtab = Vec::new();
+    for symbol in &symbols {
+        let name_offset = if symbol.name.is_empty() { 0 } else { push_string(&mut strtab, &symbol.name) };
+        symtab.extend(name_offset.to_le_bytes());
+        symtab.push(symbol.info);
+        symtab.push(0); // st_other: default visibility
+        symtab.extend(symbol.section.to_le_bytes());
+        symtab.extend(symbol.value.to_le_bytes());
+        symtab.extend(0u64.to_le_bytes()); // st_size
+    }
+
+    let mut rela = Vec::new();
+    for relocation in &text.relocations {
+        let symbol_index = symbols.iter().position(|symbol| symbol.name == relocation.symbol).unwrap_or(0) as u64;
+        let kind = match relocation.kind {
+            RelocationKind::Branch => R_X86_64_PLT32,
+            RelocationKind::PcRelative => R_X86_64_PC32,
+        };
+        rela.extend((relocation.offset as u64).to_le_bytes());
+        rela.extend((symbol_index << 32 | kind).to_le_bytes());
+        rela.extend(relocation.addend.to_le_bytes());
+    }
+
+    let mut shstrtab = vec![0u8];
+    let names = [".text", ".data", ".rela.text", ".symtab", ".strtab", ".shstrtab"]
+        .map(|name| push_string(&mut shstrtab, name));
+
+    // Lay out the section contents after the 64-byte header, 8-byte aligned
+    let mut out = vec![0u8; 64];
+    let place = |out: &mut Vec<u8>, bytes: &[u8]| -> u64 {
+        while !out.len().is_multiple_of(8) {
+            out.push(0);
+        }
+        let offset = out.len() as u64;
+        out.extend(bytes);
+        offset
+    };
+    let text_offset = place(&mut out, &text.bytes);
+    let data_offset = place(&mut out, data);
+    let rela_offset = place(&mut out, &rela);
+    let symtab_offset = place(&mut out, &symtab);
+    let strtab_offset = place(&mut out, &strtab);
+    let shstrtab_offset = place(&mut out, &shstrtab);
+    let section_headers_offset = place(&mut out, &[]);
+
+    // ELF header
+    let mut header = Vec::with_capacity(64);
+    header.extend([0x7F, b'E', b'L', b'F', 2, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+    header.extend(1u16.to_le_bytes()); // ET_REL
+    header.extend(62u16.to_le_bytes()); // EM_X86_64
+    header.extend(1u32.to_le_bytes()); // EV_CURRENT
+    header.extend(0u64.to_le_bytes()); // e_entry
+    header.extend(0u64.to_le_bytes()); // e_phoff
+    header.extend(section_headers_offset.to_le_bytes());
+    header.extend(0u32.to_le_bytes()); // e_flags
+    header.extend(64u16.to_le_bytes()); // e_ehsize
+    header.extend(0u16.to_le_bytes()); // e_phentsize
+    header.extend(0u16.to_le_bytes()); // e_phnum
+    header.extend(64u16.to_le_bytes()); // e_shentsize
+    header.extend(SECTION_COUNT.to_le_bytes());
+    header.extend((SECTION_COUNT - 1).to_le_bytes()); // .shstrtab is last
+    out[..64].copy_from_slice(&header);
+
+    let headers = [
+        SectionHeader::default(),
+        SectionHeader { name: names[0], kind: SHT_PROGBITS, flags: SHF_ALLOC | SHF_EXECINSTR, offset: text_offset, size: text.bytes.len(), align: 16, ..Default::default() },
+        SectionHeader { name: names[1], kind: SHT_PROGBITS, flags: SHF_WRITE | SHF_ALLOC, offset: data_offset, size: data.len(), align: 8, ..Default::default() },
+        SectionHeader { name: names[2], kind: SHT_RELA, flags: SHF_INFO_LINK, offset: rela_offset, size: rela.len(), link: SYMTAB_INDEX, info: TEXT_INDEX as u32, align: 8, entry_size: 24 },
+        SectionHeader { name: names[3], kind: SHT_SYMTAB, offset: symtab_offset, size: symtab.len(), link: STRTAB_INDEX, info: first_global, align: 8, entry_size: 24, ..Default::default() },
+        SectionHeader { name: names[4], kind: SHT_STRTAB, offset: strtab_offset, size: strtab.len(), align: 1, ..Default::default() },
+        SectionHeader { name: names[5], kind: SHT_STRTAB, offset: shstrtab_offset, size: shstrtab.len(), align: 1, ..Default::default() },
+    ];
+    for section in &headers {
+        section.write(&mut out);
+    }
+
+    out
+}
+
+#[derive(Default)]
+struct SectionHeader {
+    name: u32,
+    kind: u32,
+    flags: u64,
+    offset: u64,
+    size: usize,
+    link: u32,
+    info: u32,
+    align: u64,
+    entry_size: u64,
+}
+
+impl SectionHeader {
+    fn write(&self, out: &mut Vec<u8>) {
+        out.extend(self.name.to_le_bytes());
+        out.extend(self.kind.to_le_bytes());
+        out.extend(self.flags.to_le_bytes());
+        out.extend(0u64.to_le_bytes()); // sh_addr
+        out.extend(self.offset.to_le_bytes());
+        out.extend((self.size as u64).to_le_bytes());
+        out.extend(self.link.to_le_bytes());
+        out.extend(self.info.to_le_bytes());
+        out.extend(self.align.to_le_bytes());
+        out.extend(self.entry_size.to_le_bytes());
+    }
+}
+
+fn push_string(table: &mut Vec<u8>, name: &str) -> u32 {
+    let offset = table.len() as u32;
+    table.extend(name.as_bytes());
+    table.push(0);
+    offset
+}
@@ -0,0 +1,66 @@
+// Integrated assembler: encodes the generated assembly straight into a
+// relocatable object file so no external assembler is needed
+
+mod asm_parser;
+mod elf;
+mod x86;
+
+pub use asm_parser::{parse_assembly, parse_instruction, parse_operand, strip_comment, AsmItem};
+pub use elf::write_elf_object;
+pub use x86::{EncodedText, Encoder, Relocation, RelocationKind};
+
+use crate::codegen::core::{AsmLine, Operand};
+use crate::codegen::targets::TargetPlatform;
+
+/// Assemble the lines the code generator emitted into an object file for
+/// `platform`. Only ELF (Linux and FreeBSD) objects are written for now.
+pub fn assemble_object(lines: &[AsmLine], platform: TargetPlatform) -> Result<Vec<u8>, String> {
+    if !matches!(platform, TargetPlatform::LinuxX64 | TargetPlatform::FreeBSDX64) {
+        return Err(format!("object emission is only implemented for ELF targets, not {:?}", platform));
+    }
+
+    let mut encoder = Encoder::new();
+    let mut data = Vec::new();
+    let mut data_labels = Vec::new();
+    let mut globals = Vec::new();
+    let mut in_data = false;
+
+    for line in lines {
+        for item in line_items(line)? {
+            match item {
+                AsmItem::Section(name) => in_data = name == ".data",
+                AsmItem::Global(name) => globals.push(name),
+                AsmItem::Extern(_) => {}
+                AsmItem::Label(name) if in_data => data_labels.push((name, data.len())),
+                AsmItem::Label(name) => encoder.define_label(&name)?,
+                AsmItem::Data(bytes) if in_data => data.extend(bytes),
+                AsmItem::Data(_) => return Err("data directive outside of .data".to_string()),
+                AsmItem::Instruction { instruction, size, operands } => {
+                    encoder.encode(instruction, &operands, size)?;
+                }
+            }
+        }
+    }
+
+    Ok(write_elf_object(&encoder.finish()?, &data, &data_labels, &globals))
+}
+
+/// What the encoder needs from one emitted line. Instructions are taken as
+/// emitted; only directives, which the target spells as text, are parsed.
+fn line_items(line: &AsmLine) -> Result<Vec<AsmItem>, String> {
+    match line {
+        AsmLine::Instruction { instruction, size, operands, .. } => {
+            let operands = operands.iter()
+                .map(|operand| match operand {
+                    // Globals are addressed by text, as in `[counter]`
+                    Operand::String(text) => parse_operand(text).map(|(_, operand)| operand),
+                    operand => Ok(operand.clone()),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(vec![AsmItem::Instruction { instruction: *instruction, size: *size, operands }])
+        }
+        AsmLine::Label(name) => Ok(vec![AsmItem::Label(name.clone())]),
+        AsmLine::Directive { text, .. } => parse_assembly(text),
+        AsmLine::Comment(_) | AsmLine::Blank => Ok(Vec::new()),
+    }
+}
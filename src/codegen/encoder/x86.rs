@@ -0,0 +1,751 @@
+use std::collections::HashMap;
+use crate::codegen::core::{Instruction, Operand, Register, Size};
+
+/// How a relocation's 32-bit field is resolved by the linker
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelocationKind {
+    /// `call`/`jmp` to a symbol outside this object, through the PLT
+    Branch,
+    /// RIP-relative reference to data or code
+    PcRelative,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Relocation {
+    /// Offset of the 32-bit field in the text section
+    pub offset: usize,
+    pub symbol: String,
+    pub kind: RelocationKind,
+    pub addend: i64,
+}
+
+/// Machine code for one text section, with the labels defined in it and the
+/// references left for the linker
+#[derive(Debug, Clone, Default)]
+pub struct EncodedText {
+    pub bytes: Vec<u8>,
+    pub labels: Vec<(String, usize)>,
+    pub relocations: Vec<Relocation>,
+}
+
+/// The r/m side of a ModRM-encoded operand
+enum Rm {
+    Reg(u8),
+    Mem { base: u8, disp: i32 },
+    Rip(String),
+}
+
+/// Minimal x86-64 encoder covering the instructions the code generator emits
+///
+/// Branches always use 32-bit displacements. Branches to labels defined in
+/// the same text section are resolved by `finish`; everything else becomes
+/// a relocation.
+#[derive(Default)]
+pub struct Encoder {
+    bytes: Vec<u8>,
+    labels: HashMap<String, usize>,
+    label_order: Vec<String>,
+    branch_fixups: Vec<(usize, String)>,
+    relocations: Vec<Relocation>,
+}
+
+impl Encoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Define `name` at the current position
+    pub fn define_label(&mut self, name: &str) -> Result<(), String> {
+        if self.labels.insert(name.to_string(), self.bytes.len()).is_some() {
+            return Err(format!("label '{}' defined twice", name));
+        }
+        self.label_order.push(name.to_string());
+        Ok(())
+    }
+
+    /// Encode one instruction. `size` sizes memory operands that have no
+    /// register to take it from, as `dword` does in `mov dword [rbp-4], 1`.
+    pub fn encode(&mut self, instruction: Instruction, operands: &[Operand], size: Option<Size>) -> Result<(), String> {
+        use Instruction as I;
+
+        match (instruction, operands) {
+            (I::Ret, []) => self.bytes.push(0xC3),
+            (I::Cdq, []) => self.bytes.push(0x99),
+            (I::Cqo, []) => self.bytes.extend([0x48, 0x99]),
+            (I::Syscall, []) => self.bytes.extend([0x0F, 0x05]),
+            (I::Ud2, []) => self.bytes.extend([0x0F, 0x0B]),
+
+            (I::Push | I::Pop, [Operand::Register(reg)]) => {
+                let code = Self::gpr(*reg, Some(Size::Qword))?;
+                if code >= 8 {
+                    self.bytes.push(0x41);
+                }
+                let base = if matches!(instruction, I::Push) { 0x50 } else { 0x58 };
+                self.bytes.push(base + (code & 7));
+            }
+
+            (I::Jmp | I::Call | I::Je | I::Jne | I::Jl | I::Jle | I::Jg | I::Jge | I::Jb | I::Jbe | I::Ja | I::Jae, [Operand::Label(target)]) => {
+                match instruction {
+                    I::Jmp => self.bytes.push(0xE9),
+                    I::Call => self.bytes.push(0xE8),
+                    _ => self.bytes.extend([0x0F, Self::condition_code(instruction) + 0x80]),
+                }
+                self.branch_fixups.push((self.bytes.len(), target.clone()));
+                self.bytes.extend([0; 4]);
+            }
+
+            (I::Call, [target]) => {
+                // Indirect call through a function pointer: call r/m64
+                let rm = self.rm(target)?;
+                self.emit(None, false, false, &[0xFF], 2, &rm, 0);
+            }
+
+            (I::Mov, [Operand::Register(dest), Operand::Immediate(value)]) if Self::is_gpr(*dest) => {
+                self.encode_mov_immediate(*dest, *value)?;
+            }
+
+            (I::Mov | I::Add | I::Sub | I::Cmp | I::And | I::Or | I::Xor | I::Test, [dest, source]) => {
+                self.encode_alu(instruction, dest, source, size)?;
+            }
+
+            (I::Lea, [Operand::Register(dest), source]) => {
+                let reg = Self::gpr(*dest, None)?;
+                let rm = self.rm(source)?;
+                self.emit(None, dest.size() == Size::Qword, false, &[0x8D], reg, &rm, 0);
+            }
+
+            (I::Movzx | I::Movsx, [Operand::Register(dest), source]) => {
+                let reg = Self::gpr(*dest, None)?;
+                let source_size = Self::operand_size(source, size)
+                    .ok_or("extension source needs an explicit size")?;
+                let opcode = match (instruction, source_size) {
+                    (I::Movzx, Size::Byte) => 0xB6,
+                    (I::Movzx, Size::Word) => 0xB7,
+                    (I::Movsx, Size::Byte) => 0xBE,
+                    (I::Movsx, Size::Word) => 0xBF,
+                    _ => return Err(format!("cannot extend from a {} operand", source_size)),
+                };
+                let rm = self.rm(source)?;
+                let force_rex = Self::needs_rex_for_byte(source);
+                self.emit(None, dest.size() == Size::Qword, force_rex, &[0x0F, opcode], reg, &rm, 0);
+            }
+
+            (I::Movsxd, [Operand::Register(dest), source]) => {
+                let reg = Self::gpr(*dest, None)?;
+                let rm = self.rm(source)?;
+                self.emit(None, true, false, &[0x63], reg, &rm, 0);
+            }
+
+            (I::Imul, [Operand::Register(dest), Operand::Immediate(value)]) => {
+                let reg = Self::gpr(*dest, None)?;
+                let rm = Rm::Reg(reg);
+                let wide = dest.size() == Size::Qword;
+                if let Ok(byte) = i8::try_from(*value) {
+                    self.emit(None, wide, false, &[0x6B], reg, &rm, 1);
+                    self.bytes.push(byte as u8);
+                } else {
+                    self.emit(None, wide, false, &[0x69], reg, &rm, 4);
+                    self.push_imm32(*value)?;
+                }
+            }
+            (I::Imul, [Operand::Register(dest), source]) => {
+                let reg = Self::gpr(*dest, None)?;
+                let rm = self.rm(source)?;
+                self.emit(None, dest.size() == Size::Qword, false, &[0x0F, 0xAF], reg, &rm, 0);
+            }
+
+            (I::Idiv | I::Neg | I::Inc | I::Dec, [operand]) => {
+                let operand_size = Self::operand_size(operand, size)
+                    .ok_or_else(|| format!("{} needs an explicit operand size", instruction))?;
+                let (opcode, extension) = match instruction {
+                    I::Idiv => (0xF7, 7),
+                    I::Neg => (0xF7, 3),
+                    I::Dec => (0xFF, 1),
+                    _ => (0xFF, 0),
+                };
+                let opcode = if operand_size == Size::Byte { opcode - 1 } else { opcode };
+                let rm = self.rm(operand)?;
+                let prefix = (operand_size == Size::Word).then_some(0x66);
+                let force_rex = Self::needs_rex_for_byte(operand);
+                self.emit(prefix, operand_size == Size::Qword, force_rex, &[opcode], extension, &rm, 0);
+            }
+
+            (I::Shl | I::Sar, [operand, count]) => {
+                let operand_size = Self::operand_size(operand, size)
+                    .ok_or_else(|| format!("{} needs an explicit operand size", instruction))?;
+                let extension = if matches!(instruction, I::Shl) { 4 } else { 7 };
+                let byte_step = if operand_size == Size::Byte { 1 } else { 0 };
+                let rm = self.rm(operand)?;
+                let prefix = (operand_size == Size::Word).then_some(0x66);
+                let force_rex = Self::needs_rex_for_byte(operand);
+                let wide = operand_size == Size::Qword;
+                match count {
+                    Operand::Immediate(value) => {
+                        self.emit(prefix, wide, force_rex, &[0xC1 - byte_step], extension, &rm, 1);
+                        self.bytes.push(Self::imm8(*value)?);
+                    }
+                    Operand::Register(Register::Cl) => {
+                        self.emit(prefix, wide, force_rex, &[0xD3 - byte_step], extension, &rm, 0);
+                    }
+                    _ => return Err(format!("{} counts must be an immediate or cl", instruction)),
+                }
+            }
+
+            (I::Sete | I::Setne | I::Setl | I::Setle | I::Setg | I::Setge | I::Setb | I::Setbe | I::Seta | I::Setae, [operand]) => {
+                let rm = self.rm(operand)?;
+                let force_rex = Self::needs_rex_for_byte(operand);
+                self.emit(None, false, force_rex, &[0x0F, Self::condition_code(instruction) + 0x90], 0, &rm, 0);
+            }
+
+            (I::Movsd, [Operand::Register(dest), source]) if Self::xmm(*dest).is_some() => {
+                let reg = Self::xmm(*dest).unwrap_or(0);
+                let rm = self.rm(source)?;
+                self.emit(Some(0xF2), false, false, &[0x0F, 0x10], reg, &rm, 0);
+            }
+            (I::Movsd, [dest, Operand::Register(source)]) => {
+                let reg = Self::xmm(*source).ok_or("movsd source must be an xmm register")?;
+                let rm = self.rm(dest)?;
+                self.emit(Some(0xF2), false, false, &[0x0F, 0x11], reg, &rm, 0);
+            }
+
+            (I::Addsd | I::Subsd | I::Mulsd | I::Divsd, [Operand::Register(dest), source]) => {
+                let reg = Self::xmm(*dest).ok_or("scalar double destination must be an xmm register")?;
+                let opcode = match instruction {
+                    I::Addsd => 0x58,
+                    I::Mulsd => 0x59,
+                    I::Subsd => 0x5C,
+                    _ => 0x5E,
+                };
+                let rm = self.rm(source)?;
+                self.emit(Some(0xF2), false, false, &[0x0F, opcode], reg, &rm, 0);
+            }
+
+            (I::Cvtsi2sd, [Operand::Register(dest), source]) => {
+                let reg = Self::xmm(*dest).ok_or("cvtsi2sd destination must be an xmm register")?;
+                let wide = Self::operand_size(source, size) == Some(Size::Qword);
+                let rm = self.rm(source)?;
+                self.emit(Some(0xF2), wide, false, &[0x0F, 0x2A], reg, &rm, 0);
+            }
+            (I::Cvttsd2si, [Operand::Register(dest), source]) => {
+                let reg = Self::gpr(*dest, None)?;
+                let rm = self.rm(source)?;
+                self.emit(Some(0xF2), dest.size() == Size::Qword, false, &[0x0F, 0x2C], reg, &rm, 0);
+            }
+
+            (I::Vmovsd, [Operand::Register(dest), source]) if Self::xmm(*dest).is_some() => {
+                let reg = Self::xmm(*dest).unwrap_or(0);
+                let rm = self.rm(source)?;
+                self.emit_vex(0xF2, false, 0x10, reg, None, &rm);
+            }
+            (I::Vmovsd, [dest, Operand::Register(source)]) => {
+                let reg = Self::xmm(*source).ok_or("vmovsd source must be an xmm register")?;
+                let rm = self.rm(dest)?;
+                self.emit_vex(0xF2, false, 0x11, reg, None, &rm);
+            }
+
+            (I::Vaddsd | I::Vsubsd | I::Vmulsd | I::Vdivsd, [Operand::Register(dest), Operand::Register(first), source]) => {
+                let reg = Self::xmm(*dest).ok_or("scalar double destination must be an xmm register")?;
+                let first = Self::xmm(*first).ok_or("scalar double source must be an xmm register")?;
+                let opcode = match instruction {
+                    I::Vaddsd => 0x58,
+                    I::Vmulsd => 0x59,
+                    I::Vsubsd => 0x5C,
+                    _ => 0x5E,
+                };
+                let rm = self.rm(source)?;
+                self.emit_vex(0xF2, false, opcode, reg, Some(first), &rm);
+            }
+
+            (I::Vcvtsi2sd, [Operand::Register(dest), Operand::Register(first), source]) => {
+                let reg = Self::xmm(*dest).ok_or("vcvtsi2sd destination must be an xmm register")?;
+                let first = Self::xmm(*first).ok_or("vcvtsi2sd source must be an xmm register")?;
+                let wide = Self::operand_size(source, size) == Some(Size::Qword);
+                let rm = self.rm(source)?;
+                self.emit_vex(0xF2, wide, 0x2A, reg, Some(first), &rm);
+            }
+            (I::Vcvttsd2si, [Operand::Register(dest), source]) => {
+                let reg = Self::gpr(*dest, None)?;
+                let rm = self.rm(source)?;
+                self.emit_vex(0xF2, dest.size() == Size::Qword, 0x2C, reg, None, &rm);
+            }
+
+            (I::Vmovq, [Operand::Register(dest), Operand::Register(source)]) => {
+                let (reg, gpr, opcode) = match (Self::xmm(*dest), Self::xmm(*source)) {
+                    (Some(xmm), None) => (xmm, Self::gpr(*source, Some(Size::Qword))?, 0x6E),
+                    (None, Some(xmm)) => (xmm, Self::gpr(*dest, Some(Size::Qword))?, 0x7E),
+                    _ => return Err("vmovq needs one xmm and one 64-bit register".to_string()),
+                };
+                self.emit_vex(0x66, true, opcode, reg, None, &Rm::Reg(gpr));
+            }
+
+            (I::Movq, [Operand::Register(dest), Operand::Register(source)]) => {
+                // movq xmm, r64 and movq r64, xmm both put the xmm register in ModRM.reg
+                let (reg, gpr, opcode) = match (Self::xmm(*dest), Self::xmm(*source)) {
+                    (Some(xmm), None) => (xmm, Self::gpr(*source, Some(Size::Qword))?, 0x6E),
+                    (None, Some(xmm)) => (xmm, Self::gpr(*dest, Some(Size::Qword))?, 0x7E),
+                    _ => return Err("movq needs one xmm and one 64-bit register".to_string()),
+                };
+                self.emit(Some(0x66), true, false, &[0x0F, opcode], reg, &Rm::Reg(gpr), 0);
+            }
+
+            _ => {
+                let operands = operands.iter().map(|op| op.to_string()).collect::<Vec<_>>().join(", ");
+                return Err(format!("unsupported instruction for the integrated encoder: {} {}", instruction, operands));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Resolve branches to local labels and hand back the section
+    pub fn finish(mut self) -> Result<EncodedText, String> {
+        for (offset, target) in std::mem::take(&mut self.branch_fixups) {
+            match self.labels.get(&target) {
+                Some(&position) => {
+                    let displacement = position as i64 - (offset as i64 + 4);
+                    let displacement = i32::try_from(displacement)
+                        .map_err(|_| format!("branch to '{}' is out of range", target))?;
+                    self.bytes[offset..offset + 4].copy_from_slice(&displacement.to_le_bytes());
+                }
+                None => self.relocations.push(Relocation {
+                    offset,
+                    symbol: target,
+                    kind: RelocationKind::Branch,
+                    addend: -4,
+                }),
+            }
+        }
+
+        let labels = self.label_order.iter()
+            .map(|name| (name.clone(), self.labels[name]))
+            .collect();
+
+        Ok(EncodedText {
+            bytes: self.bytes,
+            labels,
+            relocations: self.relocations,
+        })
+    }
+
+    fn encode_mov_immediate(&mut self, dest: Register, value: i64) -> Result<(), String> {
+        let code = Self::gpr(dest, None)?;
+        let rex_b = if code >= 8 { 0x41 } else { 0x40 };
+
+        match dest.size() {
+            Size::Byte => {
+                if code >= 4 {
+                    self.bytes.push(rex_b);
+                }
+                self.bytes.extend([0xB0 + (code & 7), Self::imm8(value)?]);
+            }
+            Size::Dword => {
+                if code >= 8 {
+                    self.bytes.push(rex_b);
+                }
+                self.bytes.push(0xB8 + (code & 7));
+                self.push_imm32(value)?;
+            }
+            Size::Qword if i32::try_from(value).is_ok() => {
+                // Sign-extended imm32 form, as nasm picks for small values
+                self.emit(None, true, false, &[0xC7], 0, &Rm::Reg(code), 4);
+                self.push_imm32(value)?;
+            }
+            Size::Qword => {
+                self.bytes.extend([rex_b | 0x08, 0xB8 + (code & 7)]);
+                self.bytes.extend(value.to_le_bytes());
+            }
+            Size::Word => return Err("16-bit moves are not supported".to_string()),
+        }
+        Ok(())
+    }
+
+    fn encode_alu(&mut self, instruction: Instruction, dest: &Operand, source: &Operand, size: Option<Size>) -> Result<(), String> {
+        // (base opcode for the r/m, reg form; /digit of the immediate group)
+        let (base, extension) = match instruction {
+            Instruction::Add => (0x00, 0),
+            Instruction::Or => (0x08, 1),
+            Instruction::And => (0x20, 4),
+            Instruction::Sub => (0x28, 5),
+            Instruction::Xor => (0x30, 6),
+            Instruction::Cmp => (0x38, 7),
+            Instruction::Test => (0x84, 0),
+            _ => (0x88, 0), // mov
+        };
+
+        let operand_size = Self::operand_size(dest, size)
+            .or_else(|| Self::operand_size(source, size))
+            .ok_or_else(|| format!("{} needs an explicit operand size", instruction))?;
+        let prefix = (operand_size == Size::Word).then_some(0x66);
+        let wide = operand_size == Size::Qword;
+        let byte_step = if operand_size == Size::Byte { 0 } else { 1 };
+        let force_rex = Self::needs_rex_for_byte(dest) || Self::needs_rex_for_byte(source);
+
+        match source {
+            Operand::Immediate(value) => {
+                let rm = self.rm(dest)?;
+                match instruction {
+                    Instruction::Mov => {
+                        let immediate_len = if operand_size == Size::Byte { 1 } else { 4 };
+                        self.emit(prefix, wide, force_rex, &[0xC6 + byte_step], 0, &rm, immediate_len);
+                        self.push_sized_imm(*value, operand_size)?;
+                    }
+                    Instruction::Test => return Err("test with an immediate is not supported".to_string()),
+                    _ if operand_size == Size::Byte => {
+                        self.emit(prefix, wide, force_rex, &[0x80], extension, &rm, 1);
+                        self.bytes.push(Self::imm8(*value)?);
+                    }
+                    _ => match i8::try_from(*value) {
+                        Ok(byte) => {
+                            self.emit(prefix, wide, force_rex, &[0x83], extension, &rm, 1);
+                            self.bytes.push(byte as u8);
+                        }
+                        Err(_) => {
+                            self.emit(prefix, wide, force_rex, &[0x81], extension, &rm, 4);
+                            self.push_sized_imm(*value, operand_size)?;
+                        }
+                    },
+                }
+            }
+            Operand::Register(reg) => {
+                let reg = Self::gpr(*reg, Some(operand_size))?;
+                let rm = self.rm(dest)?;
+                self.emit(prefix, wide, force_rex, &[base + byte_step], reg, &rm, 0);
+            }
+            _ => {
+                let Operand::Register(dest_reg) = dest else {
+                    return Err(format!("{} cannot take two memory operands", instruction));
+                };
+                if matches!(instruction, Instruction::Test) {
+                    return Err("test with a memory source is not supported".to_string());
+                }
+                let reg = Self::gpr(*dest_reg, Some(operand_size))?;
+                let rm = self.rm(source)?;
+                self.emit(prefix, wide, force_rex, &[base + 2 + byte_step], reg, &rm, 0);
+            }
+        }
+        Ok(())
+    }
+
+    /// Emit prefixes, REX, opcode and the ModRM/SIB/displacement bytes.
+    /// `immediate_len` is the size of any immediate that follows, which a
+    /// RIP-relative displacement has to account for.
+    #[allow(clippy::too_many_arguments)]
+    fn emit(&mut self, prefix: Option<u8>, wide: bool, force_rex: bool, opcode: &[u8], reg: u8, rm: &Rm, immediate_len: usize) {
+        if let Some(prefix) = prefix {
+            self.bytes.push(prefix);
+        }
+
+        let rm_code = match rm {
+            Rm::Reg(code) | Rm::Mem { base: code, .. } => *code,
+            Rm::Rip(_) => 0,
+        };
+        let rex = 0x40
+            | if wide { 0x08 } else { 0 }
+            | if reg >= 8 { 0x04 } else { 0 }
+            | if rm_code >= 8 { 0x01 } else { 0 };
+        if rex != 0x40 || force_rex {
+            self.bytes.push(rex);
+        }
+        self.bytes.extend(opcode);
+        self.modrm(reg, rm, immediate_len);
+    }
+
+    /// Emit a VEX-encoded instruction from the 0F opcode map with an F2
+    /// (scalar double) or 66 prefix folded in. `source` is the extra
+    /// register operand VEX carries in `vvvv`, if the instruction has one.
+    fn emit_vex(&mut self, prefix: u8, wide: bool, opcode: u8, reg: u8, source: Option<u8>, rm: &Rm) {
+        let rm_code = match rm {
+            Rm::Reg(code) | Rm::Mem { base: code, .. } => *code,
+            Rm::Rip(_) => 0,
+        };
+        let pp = match prefix {
+            0x66 => 0b01,
+            0xF3 => 0b10,
+            _ => 0b11,
+        };
+        // R, B and vvvv are stored inverted; L stays 0 for scalar operations
+        let r = if reg >= 8 { 0 } else { 0x80 };
+        let vvvv = (!source.unwrap_or(0) & 0x0F) << 3;
+        if rm_code < 8 && !wide {
+            self.bytes.extend([0xC5, r | vvvv | pp]);
+        } else {
+            let b = if rm_code >= 8 { 0 } else { 0x20 };
+            self.bytes.extend([0xC4, r | 0x40 | b | 0x01, if wide { 0x80 } else { 0 } | vvvv | pp]);
+        }
+        self.bytes.push(opcode);
+        // None of the VEX forms emitted take an immediate
+        self.modrm(reg, rm, 0);
+    }
+
+    /// Emit the ModRM byte, with its SIB byte, displacement or relocation
+    fn modrm(&mut self, reg: u8, rm: &Rm, immediate_len: usize) {
+        let reg = (reg & 7) << 3;
+        match rm {
+            Rm::Reg(code) => self.bytes.push(0xC0 | reg | (code & 7)),
+            Rm::Mem { base, disp } => {
+                let base = base & 7;
+                // rbp/r13 have no displacement-free form, so always encode one
+                let (mode, disp_bytes) = match i8::try_from(*disp) {
+                    Ok(0) if base != 5 => (0x00, vec![]),
+                    Ok(byte) => (0x40, vec![byte as u8]),
+                    Err(_) => (0x80, disp.to_le_bytes().to_vec()),
+                };
+                self.bytes.push(mode | reg | base);
+                if base == 4 {
+                    self.bytes.push(0x24); // SIB: rsp/r12 base, no index
+                }
+                self.bytes.extend(disp_bytes);
+            }
+            Rm::Rip(symbol) => {
+                self.bytes.push(reg | 0x05);
+                self.relocations.push(Relocation {
+                    offset: self.bytes.len(),
+                    symbol: symbol.clone(),
+                    kind: RelocationKind::PcRelative,
+                    addend: -4 - immediate_len as i64,
+                });
+                self.bytes.extend([0; 4]);
+            }
+        }
+    }
+
+    fn rm(&self, operand: &Operand) -> Result<Rm, String> {
+        match operand {
+            Operand::Register(reg) => match Self::xmm(*reg) {
+                Some(code) => Ok(Rm::Reg(code)),
+                None => Ok(Rm::Reg(Self::gpr(*reg, None)?)),
+            },
+            Operand::Memory { base, offset } => Ok(Rm::Mem { base: Self::gpr(*base, Some(Size::Qword))?, disp: *offset }),
+            Operand::Label(label) => Ok(Rm::Rip(label.clone())),
+            other => Err(format!("'{}' cannot be used as a register or memory operand", other)),
+        }
+    }
+
+    fn operand_size(operand: &Operand, explicit: Option<Size>) -> Option<Size> {
+        match operand {
+            Operand::Register(reg) if Self::is_gpr(*reg) => Some(reg.size()),
+            Operand::Memory { .. } | Operand::Label(_) => explicit,
+            _ => None,
+        }
+    }
+
+    fn is_gpr(reg: Register) -> bool {
+        Self::xmm(reg).is_none()
+    }
+
+    /// Register number of a general-purpose register, checking its width
+    /// when `expected` is given
+    fn gpr(reg: Register, expected: Option<Size>) -> Result<u8, String> {
+        if let Some(size) = expected
+            && reg.size() != size {
+            return Err(format!("register {} is not {}-sized", reg, size));
+        }
+        match reg.full() {
+            Register::Rax => Ok(0),
+            Register::Rcx => Ok(1),
+            Register::Rdx => Ok(2),
+            Register::Rbx => Ok(3),
+            Register::Rsp => Ok(4),
+            Register::Rbp => Ok(5),
+            Register::Rsi => Ok(6),
+            Register::Rdi => Ok(7),
+            Register::R8 => Ok(8),
+            Register::R9 => Ok(9),
+            Register::R10 => Ok(10),
+            Register::R11 => Ok(11),
+            Register::R12 => Ok(12),
+            Register::R13 => Ok(13),
+            Register::R14 => Ok(14),
+            Register::R15 => Ok(15),
+            other => Err(format!("{} is not a general-purpose register", other)),
+        }
+    }
+
+    fn xmm(reg: Register) -> Option<u8> {
+        match reg {
+            Register::Xmm0 => Some(0),
+            Register::Xmm1 => Some(1),
+            Register::Xmm2 => Some(2),
+            Register::Xmm3 => Some(3),
+            Register::Xmm4 => Some(4),
+            Register::Xmm5 => Some(5),
+            Register::Xmm6 => Some(6),
+            Register::Xmm7 => Some(7),
+            _ => None,
+        }
+    }
+
+    /// `sil`/`dil` are only reachable with a REX prefix; without one the
+    /// same encoding means `dh`/`bh`
+    fn needs_rex_for_byte(operand: &Operand) -> bool {
+        matches!(operand, Operand::Register(Register::Sil | Register::Dil))
+    }
+
+    /// Low nibble shared by the `jcc` and `setcc` opcodes
+    fn condition_code(instruction: Instruction) -> u8 {
+        match instruction {
+            Instruction::Jb | Instruction::Setb => 0x2,
+            Instruction::Jae | Instruction::Setae => 0x3,
+            Instruction::Je | Instruction::Sete => 0x4,
+            Instruction::Jne | Instruction::Setne => 0x5,
+            Instruction::Jl | Instruction::Setl => 0xC,
+            Instruction::Jge | Instruction::Setge => 0xD,
+            Instruction::Jbe | Instruction::Setbe => 0x6,
+            Instruction::Ja | Instruction::Seta => 0x7,
+            Instruction::Jle | Instruction::Setle => 0xE,
+            _ => 0xF, // jg / setg
+        }
+    }
+
+    fn imm8(value: i64) -> Result<u8, String> {
+        if (-128..=255).contains(&value) {
+            Ok(value as u8)
+        } else {
+            Err(format!("immediate {} does not fit in a byte", value))
+        }
+    }
+
+    fn push_imm32(&mut self, value: i64) -> Result<(), String> {
+        if i32::try_from(value).is_err() && u32::try_from(value).is_err() {
+            return Err(format!("immediate {} does not fit in 32 bits", value));
+        }
+        self.bytes.extend((value as i32).to_le_bytes());
+        Ok(())
+    }
+
+    fn push_sized_imm(&mut self, value: i64, size: Size) -> Result<(), String> {
+        match size {
+            Size::Byte => {
+                let byte = Self::imm8(value)?;
+                self.bytes.push(byte);
+                Ok(())
+            }
+            Size::Word => {
+                self.bytes.extend((value as i16).to_le_bytes());
+                Ok(())
+            }
+            Size::Qword if i32::try_from(value).is_err() => {
+                Err(format!("immediate {} does not fit in a sign-extended 32-bit field", value))
+            }
+            _ => self.push_imm32(value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codegen::core::Register;
+
+    #[test]
+    fn test_encode_small_function() {
+        let mut encoder = Encoder::new();
+        encoder.define_label("answer").unwrap();
+        encoder.encode(Instruction::Push, &[Operand::Register(Register::Rbp)], None).unwrap();
+        encoder.encode(Instruction::Mov, &[Operand::Register(Register::Rbp), Operand::Register(Register::Rsp)], None).unwrap();
+        encoder.encode(Instruction::Mov, &[Operand::Register(Register::Eax), Operand::Immediate(42)], None).unwrap();
+        encoder.encode(Instruction::Pop, &[Operand::Register(Register::Rbp)], None).unwrap();
+        encoder.encode(Instruction::Ret, &[], None).unwrap();
+
+        let text = encoder.finish().unwrap();
+        assert_eq!(text.bytes, vec![0x55, 0x48, 0x89, 0xE5, 0xB8, 0x2A, 0x00, 0x00, 0x00, 0x5D, 0xC3]);
+        assert_eq!(text.labels, vec![("answer".to_string(), 0)]);
+        assert!(text.relocations.is_empty());
+    }
+
+    #[test]
+    fn test_external_call_gets_relocation() {
+        let mut encoder = Encoder::new();
+        encoder.encode(Instruction::Call, &[Operand::Label("printf".to_string())], None).unwrap();
+
+        let text = encoder.finish().unwrap();
+        assert_eq!(text.bytes, vec![0xE8, 0, 0, 0, 0]);
+        assert_eq!(text.relocations.len(), 1);
+        assert_eq!(text.relocations[0].symbol, "printf");
+        assert_eq!(text.relocations[0].offset, 1);
+        assert!(matches!(text.relocations[0].kind, RelocationKind::Branch));
+    }
+
+    #[test]
+    fn test_indirect_call_through_stack_slot() {
+        let mut encoder = Encoder::new();
+        encoder.encode(Instruction::Call, &[Operand::Memory { base: Register::Rbp, offset: -8 }], Some(Size::Qword)).unwrap();
+
+        let text = encoder.finish().unwrap();
+        assert_eq!(text.bytes, vec![0xFF, 0x55, 0xF8]);
+        assert!(text.relocations.is_empty());
+    }
+
+    #[test]
+    fn test_unsigned_conditions_encode_below_and_above() {
+        let mut encoder = Encoder::new();
+        encoder.define_label("top").unwrap();
+        encoder.encode(Instruction::Setb, &[Operand::Register(Register::Al)], None).unwrap();
+        encoder.encode(Instruction::Seta, &[Operand::Register(Register::Al)], None).unwrap();
+        encoder.encode(Instruction::Jae, &[Operand::Label("top".to_string())], None).unwrap();
+
+        let text = encoder.finish().unwrap();
+        assert_eq!(&text.bytes[..6], &[0x0F, 0x92, 0xC0, 0x0F, 0x97, 0xC0]);
+        assert_eq!(&text.bytes[6..8], &[0x0F, 0x83]);
+    }
+
+    #[test]
+    fn test_shifts_by_an_immediate_or_cl() {
+        let mut encoder = Encoder::new();
+        encoder.encode(Instruction::Shl, &[Operand::Register(Register::Eax), Operand::Immediate(3)], None).unwrap();
+        encoder.encode(Instruction::Sar, &[Operand::Register(Register::Eax), Operand::Register(Register::Cl)], None).unwrap();
+
+        let text = encoder.finish().unwrap();
+        assert_eq!(text.bytes, vec![0xC1, 0xE0, 0x03, 0xD3, 0xF8]);
+    }
+
+    #[test]
+    fn test_increment_and_decrement() {
+        let mut encoder = Encoder::new();
+        encoder.encode(Instruction::Inc, &[Operand::Register(Register::Eax)], None).unwrap();
+        encoder.encode(Instruction::Dec, &[Operand::Register(Register::Eax)], None).unwrap();
+        encoder.encode(Instruction::Dec, &[Operand::Memory { base: Register::Rbp, offset: -4 }], Some(Size::Dword)).unwrap();
+
+        let text = encoder.finish().unwrap();
+        assert_eq!(text.bytes, vec![0xFF, 0xC0, 0xFF, 0xC8, 0xFF, 0x4D, 0xFC]);
+    }
+
+    #[test]
+    fn test_sign_extension_to_a_qword() {
+        let mut encoder = Encoder::new();
+        encoder.encode(Instruction::Movsxd, &[Operand::Register(Register::Rdx), Operand::Register(Register::Eax)], None).unwrap();
+        encoder.encode(Instruction::Movsxd, &[Operand::Register(Register::R8), Operand::Register(Register::Eax)], None).unwrap();
+        encoder.encode(Instruction::Movsxd, &[Operand::Register(Register::Rax), Operand::Memory { base: Register::Rbp, offset: -4 }], Some(Size::Dword)).unwrap();
+
+        let text = encoder.finish().unwrap();
+        assert_eq!(text.bytes, vec![0x48, 0x63, 0xD0, 0x4C, 0x63, 0xC0, 0x48, 0x63, 0x45, 0xFC]);
+    }
+
+    #[test]
+    fn test_scalar_double_conversions_encode() {
+        let mut encoder = Encoder::new();
+        encoder.encode(Instruction::Cvtsi2sd, &[Operand::Register(Register::Xmm0), Operand::Register(Register::Eax)], None).unwrap();
+        encoder.encode(Instruction::Cvttsd2si, &[Operand::Register(Register::Eax), Operand::Register(Register::Xmm1)], None).unwrap();
+
+        let text = encoder.finish().unwrap();
+        assert_eq!(text.bytes, vec![0xF2, 0x0F, 0x2A, 0xC0, 0xF2, 0x0F, 0x2C, 0xC1]);
+    }
+
+    #[test]
+    fn test_avx_scalar_double_instructions_use_vex_prefixes() {
+        let xmm = |register| Operand::Register(register);
+        let mut encoder = Encoder::new();
+        encoder.encode(Instruction::Vaddsd, &[xmm(Register::Xmm0), xmm(Register::Xmm0), xmm(Register::Xmm1)], None).unwrap();
+        encoder.encode(Instruction::Vmovsd, &[Operand::Memory { base: Register::Rbp, offset: -8 }, xmm(Register::Xmm0)], None).unwrap();
+        encoder.encode(Instruction::Vcvttsd2si, &[Operand::Register(Register::Rax), xmm(Register::Xmm1)], None).unwrap();
+        encoder.encode(Instruction::Vmovq, &[xmm(Register::Xmm0), Operand::Register(Register::Rax)], None).unwrap();
+
+        let text = encoder.finish().unwrap();
+        assert_eq!(text.bytes, vec![
+            0xC5, 0xFB, 0x58, 0xC1,
+            0xC5, 0xFB, 0x11, 0x45, 0xF8,
+            0xC4, 0xE1, 0xFB, 0x2C, 0xC1,
+            0xC4, 0xE1, 0xF9, 0x6E, 0xC0,
+        ]);
+    }
+}
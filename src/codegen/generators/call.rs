@@ -1,5 +1,5 @@
 use crate::ir::{IrValue, IrType};
-use crate::codegen::core::{Instruction, Operand, Register};
+use crate::codegen::core::{Instruction, Operand, Register, Size};
 use crate::codegen::core::{Emitter, CodeEmitterWithComment};
 use crate::codegen::Codegen;
 
@@ -7,54 +7,167 @@ impl Codegen {
     /// Generate function call
     pub fn generate_function_call(&mut self, dest: &Option<IrValue>, func: &str, args: &[IrValue], return_type: &IrType) {
         self.emit_comment(&format!("call {} with {} args", func, args.len()));
-        
-        // For now, simplified function call handling
-        // In a real implementation, you'd handle calling conventions properly
-        
+        self.save_call_clobbered_registers();
+
+        // The IR already evaluated the arguments left to right into
+        // temporaries; here they are only moved into place
+        let floats: Vec<bool> = args.iter().map(|arg| self.is_float_value(arg)).collect();
+        let registers = self.target.calling_convention().argument_registers(&self.target.parameter_registers(), &floats);
+        let stack_args: Vec<(usize, IrValue)> = args.iter().zip(&registers)
+            .enumerate()
+            .filter(|(_, (_, register))| register.is_none())
+            .map(|(i, (arg, _))| (i, arg.clone()))
+            .collect();
+        let stack_bytes = self.push_stack_arguments(&stack_args);
+        for (i, (arg, register)) in args.iter().zip(registers).enumerate() {
+            match (arg, register) {
+                (_, None) => {}
+                (_, Some(reg)) if reg.is_xmm() => self.load_float_arg(reg, arg, i),
+                (IrValue::Temp(_) | IrValue::Local(_), Some(reg)) => self.load_widened_arg(reg, arg, i),
+                (_, Some(reg)) => {
+                    let arg_operand = self.ir_value_to_operand(arg);
+                    self.emit_instruction_with_comment(Instruction::Mov, vec![
+                        Operand::Register(reg),
+                        arg_operand
+                    ], Some(&format!("load arg {}", i)));
+                }
+            }
+        }
+
+        // A local of the callee's name holds a function pointer
+        let local = IrValue::Local(func.to_string());
+        if self.locals.contains_key(func) || self.register_locals.contains_key(func) {
+            let pointer = self.ir_value_to_operand(&local);
+            self.emit_instruction_with_size_and_comment(Instruction::Call, Size::Qword, vec![pointer],
+                Some(&format!("call through {}", func)));
+        } else {
+            for call_instr in self.target.format_function_call(func) {
+                self.emit_target_code(&call_instr, Some(&format!("call {}", func)));
+            }
+        }
+        self.release_stack_arguments(stack_bytes);
+        self.restore_call_clobbered_registers();
+
         if let Some(dest_val) = dest {
             let dest_operand = self.ir_value_to_operand(dest_val);
-            let register = match return_type {
-                IrType::Float => Register::Xmm0,
-                _ => self.target.return_register(),
-            };
-            
             match return_type {
                 IrType::Float => {
-                    self.emit_instruction_with_comment(Instruction::Movsd, vec![
+                    self.emit_float_instruction(Instruction::Movsd, vec![
                         dest_operand,
-                        Operand::Register(register)
+                        Operand::Register(Register::Xmm0)
                     ], Some("store return value"));
                 }
                 _ => {
+                    let size = self.ir_type_to_size(return_type);
                     self.emit_instruction_with_comment(Instruction::Mov, vec![
                         dest_operand,
-                        Operand::Register(register)
+                        Operand::Register(self.target.return_register().sized(size))
                     ], Some("store return value"));
                 }
             }
         }
     }
 
-    /// Generate print call
-    pub fn generate_print_call(&mut self, format_string: &IrValue, args: &[IrValue]) {
+    /// Whether `value` is a float, and so passed in an XMM register
+    fn is_float_value(&self, value: &IrValue) -> bool {
+        match value {
+            IrValue::FloatConstant(_) => true,
+            IrValue::Temp(_) | IrValue::Local(_) => self.value_types.get(value) == Some(&IrType::Float),
+            _ => false,
+        }
+    }
+
+    /// Load a float argument into the XMM argument register `reg`
+    fn load_float_arg(&mut self, reg: Register, arg: &IrValue, index: usize) {
+        if let IrValue::FloatConstant(f) = arg {
+            self.emit_instruction_with_comment(Instruction::Mov, vec![
+                Operand::Register(Register::Rax),
+                Operand::Immediate(f.to_bits() as i64)
+            ], Some(&format!("load float bits for arg {}", index)));
+            self.emit_float_instruction(Instruction::Movq, vec![
+                Operand::Register(reg),
+                Operand::Register(Register::Rax)
+            ], Some(&format!("move to arg register {}", index)));
+        } else {
+            let arg_operand = self.ir_value_to_operand(arg);
+            self.emit_float_instruction(Instruction::Movsd, vec![
+                Operand::Register(reg),
+                arg_operand
+            ], Some(&format!("load arg {} to register", index)));
+        }
+    }
+
+    /// Load a stack value into the 64-bit argument register `reg`. Values
+    /// narrower than a qword go through the matching sub-register of rax
+    /// and are sign-extended explicitly, as printf reads `%d` and `%ld`
+    /// arguments from the full register or stack slot. A value of unknown
+    /// type fails the generation, as its width is unknown too.
+    fn load_widened_arg(&mut self, reg: Register, arg: &IrValue, index: usize) {
+        let Some(arg_type) = self.value_types.get(arg).cloned() else {
+            self.untyped_value(arg);
+            return;
+        };
+        let arg_operand = self.ir_value_to_operand(arg);
+        let size = self.ir_type_to_size(&arg_type);
+        let scratch = Register::Rax.sized(size);
+
+        self.emit_instruction_with_size_and_comment(Instruction::Mov, size, vec![
+            Operand::Register(scratch),
+            arg_operand
+        ], Some(&format!("load arg {} to register", index)));
+
+        match size {
+            // Loading the qword already filled rax
+            Size::Qword if reg.full() == Register::Rax => {}
+            Size::Byte | Size::Word => {
+                // char is signed, so promote it the way C's default promotions do
+                self.emit_instruction_with_comment(Instruction::Movsx, vec![
+                    Operand::Register(reg.full()),
+                    Operand::Register(scratch)
+                ], Some(&format!("sign-extend arg {} to {}", index, reg.full())));
+            }
+            Size::Dword => {
+                // int is signed; a plain 32-bit move would zero the upper half
+                self.emit_instruction_with_comment(Instruction::Movsxd, vec![
+                    Operand::Register(reg.full()),
+                    Operand::Register(scratch)
+                ], Some(&format!("sign-extend arg {} to {}", index, reg.full())));
+            }
+            Size::Qword => {
+                self.emit_instruction_with_comment(Instruction::Mov, vec![
+                    Operand::Register(reg.full()),
+                    Operand::Register(scratch)
+                ], Some(&format!("move to arg register {}", index)));
+            }
+        }
+    }
+
+    /// Generate print call; `newline` selects the copy of the format
+    /// string that ends in a newline
+    pub fn generate_print_call(&mut self, format_string: &IrValue, args: &[IrValue], newline: bool) {
         self.emit_comment("--- print statement ---");
         
         // Handle printf call - simplified implementation
         if let IrValue::StringConstant(label) = format_string {
             let param_regs = self.target.parameter_registers();
+            self.save_call_clobbered_registers();
+            // The format string takes the first register
+            let register_args = param_regs.len().saturating_sub(1);
+            let stack_args: Vec<(usize, IrValue)> = args.iter().cloned()
+                .enumerate()
+                .skip(register_args)
+                .map(|(i, arg)| (i + 1, arg))
+                .collect();
+            let stack_bytes = self.push_stack_arguments(&stack_args);
             if !param_regs.is_empty() {
                 self.emit_instruction_with_comment(Instruction::Lea, vec![
                     Operand::Register(param_regs[0]),
-                    Operand::Label(label.clone())
+                    Operand::Label(if newline { Self::newline_label(label) } else { label.clone() })
                 ], Some("load format string"));
             }
             
             // Load arguments into registers with proper float handling
-            for (i, arg) in args.iter().enumerate() {
-                if i + 1 >= param_regs.len() {
-                    self.emit_comment("Too many arguments for simplified printf");
-                    break;
-                }
+            for (i, arg) in args.iter().enumerate().take(register_args) {
                 let reg = param_regs[i + 1]; // +1 because first param is format string
                 
                 // Handle different argument types
@@ -72,34 +185,7 @@ impl Codegen {
                         ], Some(&format!("move to arg register {}", i)));
                     }
                     IrValue::Temp(_) | IrValue::Local(_) => {
-                        let arg_operand = self.ir_value_to_operand(arg);
-                        // Check if this is a float by looking at the memory location
-                        // For now, assume temp variables that are floats need special handling
-                        if let IrValue::Temp(_temp_id) = arg {
-                            // Check if this temp was created from a float operation
-                            if matches!(arg_operand, Operand::Memory { .. }) {
-                                // For now, load as 64-bit value (could be float or int)
-                                self.emit_instruction_with_comment(Instruction::Mov, vec![
-                                    Operand::Register(Register::Rax),
-                                    arg_operand
-                                ], Some(&format!("load arg {} to register", i)));
-                                self.emit_instruction_with_comment(Instruction::Mov, vec![
-                                    Operand::Register(reg),
-                                    Operand::Register(Register::Rax)
-                                ], Some(&format!("move to arg register {}", i)));
-                            }
-                        } else if let IrValue::Local(_) = arg {
-                            if matches!(arg_operand, Operand::Memory { .. }) {
-                                self.emit_instruction_with_comment(Instruction::Mov, vec![
-                                    Operand::Register(Register::Rax),
-                                    arg_operand
-                                ], Some(&format!("load arg {} to register", i)));
-                                self.emit_instruction_with_comment(Instruction::Mov, vec![
-                                    Operand::Register(reg),
-                                    Operand::Register(Register::Rax)
-                                ], Some(&format!("move to arg register {}", i)));
-                            }
-                        }
+                        self.load_widened_arg(reg, arg, i);
                     }
                     _ => {
                         // Handle other types (int constants, char constants, etc.)
@@ -114,8 +200,85 @@ impl Codegen {
             
             let call_instructions = self.target.format_function_call("printf");
             for call_instr in call_instructions {
-                self.emit_line_with_comment(&format!("    {}", call_instr), Some("call printf"));
+                self.emit_target_code(&call_instr, Some("call printf"));
+            }
+            self.release_stack_arguments(stack_bytes);
+            self.restore_call_clobbered_registers();
+        }
+    }
+
+    /// Push the arguments passed on the stack, given with their position
+    /// in the call, last first, so the first of them ends up right above
+    /// the return address, then reserve the convention's shadow space.
+    /// Returns the bytes to release after the call.
+    fn push_stack_arguments(&mut self, stack_args: &[(usize, IrValue)]) -> i64 {
+        let shadow_space = self.target.calling_convention().shadow_space() as i64;
+        // rsp is 16-byte aligned in the body and has to be again at the call
+        let padding = if stack_args.len() % 2 == 1 { 8 } else { 0 };
+        if padding > 0 {
+            self.emit_instruction_with_comment(Instruction::Sub, vec![
+                Operand::Register(self.target.stack_pointer()),
+                Operand::Immediate(padding)
+            ], Some("keep the stack aligned at the call"));
+        }
+        for (index, arg) in stack_args.iter().rev() {
+            let index = *index;
+            match arg {
+                IrValue::FloatConstant(f) => {
+                    self.emit_instruction_with_comment(Instruction::Mov, vec![
+                        Operand::Register(Register::Rax),
+                        Operand::Immediate(f.to_bits() as i64)
+                    ], Some(&format!("load float bits for arg {}", index)));
+                }
+                IrValue::Temp(_) | IrValue::Local(_) => self.load_widened_arg(Register::Rax, arg, index),
+                _ => {
+                    let arg_operand = self.ir_value_to_operand(arg);
+                    self.emit_instruction_with_comment(Instruction::Mov, vec![
+                        Operand::Register(Register::Rax),
+                        arg_operand
+                    ], Some(&format!("load arg {}", index)));
+                }
             }
+            self.emit_instruction_with_comment(Instruction::Push, vec![
+                Operand::Register(Register::Rax)
+            ], Some(&format!("pass arg {} on the stack", index)));
+        }
+        if shadow_space > 0 {
+            self.emit_instruction_with_comment(Instruction::Sub, vec![
+                Operand::Register(self.target.stack_pointer()),
+                Operand::Immediate(shadow_space)
+            ], Some("shadow space for the register arguments"));
+        }
+        padding + 8 * stack_args.len() as i64 + shadow_space
+    }
+
+    /// Pop what `push_stack_arguments` pushed
+    fn release_stack_arguments(&mut self, bytes: i64) {
+        if bytes > 0 {
+            self.emit_instruction_with_comment(Instruction::Add, vec![
+                Operand::Register(self.target.stack_pointer()),
+                Operand::Immediate(bytes)
+            ], Some("release the stack arguments"));
+        }
+    }
+
+    /// Keep `register` variables in caller-saved registers in their slots
+    /// while a call may overwrite the registers
+    fn save_call_clobbered_registers(&mut self) {
+        for (register, offset) in self.call_saved_registers.clone() {
+            self.emit_instruction_with_comment(Instruction::Mov, vec![
+                Operand::Memory { base: Register::Rbp, offset },
+                Operand::Register(register)
+            ], Some(&format!("save caller-saved {} across the call", register)));
+        }
+    }
+
+    fn restore_call_clobbered_registers(&mut self) {
+        for (register, offset) in self.call_saved_registers.clone() {
+            self.emit_instruction_with_comment(Instruction::Mov, vec![
+                Operand::Register(register),
+                Operand::Memory { base: Register::Rbp, offset }
+            ], Some(&format!("restore caller-saved {}", register)));
         }
     }
 }
\ No newline at end of file
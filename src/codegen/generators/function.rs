@@ -1,5 +1,5 @@
-use crate::ir::{IrFunction};
-use crate::codegen::core::{Instruction, Operand};
+use crate::ir::{IrFunction, IrType, IrValue};
+use crate::codegen::core::{Instruction, Operand, Register};
 use crate::codegen::core::{Emitter, CodeEmitterWithComment};
 use crate::codegen::Codegen;
 
@@ -7,12 +7,18 @@ impl Codegen {
     /// Generate assembly for a single function
     pub fn generate_function(&mut self, function: &IrFunction) {
         self.emit_subsection_header(&format!("FUNCTION: {}", function.name));
-        self.emit_line(self.target.format_function_name(&function.name).as_str());
+        let name = self.target.format_function_name(&function.name);
+        self.emit_target_code(&name, None);
         
         // Reset state for new function
         self.stack_offset = 0;
         self.locals.clear();
         self.temp_locations.clear();
+        self.value_types.clear();
+        self.register_locals.clear();
+        self.saved_registers.clear();
+        self.call_saved_registers.clear();
+        self.epilogue_label = format!("{}.epilogue", function.name);
 
         // Function prologue
         self.emit_subsection_header("Function Prologue");
@@ -23,17 +29,28 @@ impl Codegen {
                 1 => Some("set up frame"),
                 _ => None,
             };
-            self.emit_line_with_comment(&format!("    {}", instr), comment);
+            self.emit_target_code(instr, comment);
         }
 
         // Calculate stack space needed
         let stack_space = self.calculate_stack_space(function);
         if stack_space > 0 {
+            for (i, instr) in self.target.stack_probe(stack_space as usize).iter().enumerate() {
+                let comment = (i == 0).then_some("probe the stack pages of a large frame");
+                self.emit_target_code(instr, comment);
+            }
             self.emit_instruction_with_comment(Instruction::Sub, vec![
                 Operand::Register(self.target.stack_pointer()), 
                 Operand::Immediate(stack_space as i64)
             ], Some(&format!("allocate {} bytes for locals and temps", stack_space)));
         }
+        for (register, offset) in self.saved_registers.clone() {
+            self.emit_instruction_with_comment(Instruction::Mov, vec![
+                Operand::Memory { base: Register::Rbp, offset },
+                Operand::Register(register)
+            ], Some(&format!("save callee-saved {}", register)));
+        }
+        self.spill_parameters(function);
 
         // Generate function body
         self.emit_subsection_header("Function Body");
@@ -44,6 +61,22 @@ impl Codegen {
         // Function epilogue
         self.emit_subsection_header("Function Epilogue");
         self.emit_stack_layout_summary();
+        let epilogue_label = self.epilogue_label.clone();
+        self.emit_label(&epilogue_label);
+
+        // A void main still has to hand the process a defined exit status
+        if function.name == "main" && function.return_type == IrType::Void {
+            self.emit_instruction_with_comment(Instruction::Xor, vec![
+                Operand::Register(Register::Eax),
+                Operand::Register(Register::Eax)
+            ], Some("void main exits with status 0"));
+        }
+        for (register, offset) in self.saved_registers.clone() {
+            self.emit_instruction_with_comment(Instruction::Mov, vec![
+                Operand::Register(register),
+                Operand::Memory { base: Register::Rbp, offset }
+            ], Some(&format!("restore callee-saved {}", register)));
+        }
         
         if stack_space > 0 {
             self.emit_instruction_with_comment(Instruction::Add, vec![
@@ -60,9 +93,46 @@ impl Codegen {
                 2 => Some("return"),
                 _ => None,
             };
-            self.emit_line_with_comment(&format!("    {}", instr), comment);
+            self.emit_target_code(instr, comment);
         }
         
-        self.emit_line(""); // Add spacing after function
+        self.emit_blank_line(); // Add spacing after function
+    }
+
+    /// Store each register argument into its parameter's slot, and copy
+    /// each argument passed on the stack, found above the return address
+    /// and the shadow space, into its own
+    fn spill_parameters(&mut self, function: &IrFunction) {
+        let floats: Vec<bool> = function.parameters.iter().map(|(_, ir_type)| *ir_type == IrType::Float).collect();
+        let registers = self.target.calling_convention().argument_registers(&self.target.parameter_registers(), &floats);
+        let first_stack_arg = 16 + self.target.calling_convention().shadow_space() as i32;
+        let mut stack_index = 0;
+        for ((name, ir_type), register) in function.parameters.iter().zip(registers) {
+            let size = self.ir_type_to_size(ir_type);
+            let slot = self.ir_value_to_operand(&IrValue::Local(name.clone()));
+            let register = match register {
+                Some(register) if register.is_xmm() => {
+                    self.emit_float_instruction(Instruction::Movsd, vec![
+                        slot,
+                        Operand::Register(register)
+                    ], Some(&format!("spill parameter {}", name)));
+                    continue;
+                }
+                Some(register) => register,
+                None => {
+                    let offset = first_stack_arg + 8 * stack_index;
+                    stack_index += 1;
+                    self.emit_instruction_with_size_and_comment(Instruction::Mov, size, vec![
+                        Operand::Register(Register::Rax.sized(size)),
+                        Operand::Memory { base: Register::Rbp, offset }
+                    ], Some(&format!("load stack parameter {}", name)));
+                    Register::Rax
+                }
+            };
+            self.emit_instruction_with_comment(Instruction::Mov, vec![
+                slot,
+                Operand::Register(register.sized(size))
+            ], Some(&format!("spill parameter {}", name)));
+        }
     }
 }
\ No newline at end of file
@@ -10,9 +10,13 @@ impl Codegen {
             IrInstruction::Alloca { var_type, name } => {
                 // Space already allocated in prologue, just add comment
                 let size = self.get_type_size(var_type);
-                let offset = self.locals.get(name).copied().unwrap_or(0);
-                self.emit_comment(&format!("alloca {} {} at [rbp{}] ({} bytes)", 
-                    var_type, name, offset, size));
+                if let Some(register) = self.register_locals.get(name) {
+                    self.emit_comment(&format!("alloca {} {} in {} ({} bytes)", var_type, name, register, size));
+                } else {
+                    let offset = self.locals.get(name).copied().unwrap_or(0);
+                    self.emit_comment(&format!("alloca {} {} at [rbp{}] ({} bytes)", 
+                        var_type, name, offset, size));
+                }
             }
 
             IrInstruction::Load { dest, src, var_type } => {
@@ -47,7 +51,7 @@ impl Codegen {
                 match (value, var_type) {
                     (IrValue::FloatConstant(f), IrType::Float) => {
                         // For float constants, we need to handle them specially
-                        self.emit_comment(&format!("store float constant {} to {}", f, self.ir_value_to_string(dest)));
+                        self.emit_comment(&format!("store float constant {} to {}", self.ir_value_to_string(value), self.ir_value_to_string(dest)));
                         // Move the float bits as integer first, then convert
                         let bits = f.to_bits() as i64;
                         self.emit_instruction_with_comment(Instruction::Mov, vec![
@@ -59,6 +63,17 @@ impl Codegen {
                             Operand::Register(Register::Rax)
                         ], Some("store float"));
                     }
+                    (IrValue::Global(name), _) => {
+                        // A function address: materialize it rip-relative, never call it
+                        self.emit_instruction_with_comment(Instruction::Lea, vec![
+                            Operand::Register(Register::Rax),
+                            Operand::String(format!("[{}]", name))
+                        ], Some(&format!("address of {}", name)));
+                        self.emit_instruction_with_size_and_comment(Instruction::Mov, Size::Qword, vec![
+                            dest_operand,
+                            Operand::Register(Register::Rax)
+                        ], Some(&format!("store to {}", self.ir_value_to_string(dest))));
+                    }
                     _ => {
                         // For other types, get the value operand and use register as intermediate if needed
                         let value_operand = self.ir_value_to_operand(value);
@@ -70,8 +85,7 @@ impl Codegen {
                         };
                         
                         // Check if we need an intermediate register
-                        let needs_intermediate = matches!(value_operand, Operand::Memory { .. }) && 
-                                               matches!(dest_operand, Operand::Memory { .. });
+                        let needs_intermediate = value_operand.is_memory() && dest_operand.is_memory();
                         
                         if needs_intermediate {
                             self.emit_instruction_with_size_and_comment(Instruction::Mov, size, vec![
@@ -107,17 +121,18 @@ impl Codegen {
 
             IrInstruction::Branch { condition, true_label, false_label } => {
                 let condition_operand = self.ir_value_to_operand(condition);
+                let condition_size = self.value_types.get(condition)
+                    .map(|condition_type| self.ir_type_to_size(condition_type))
+                    .unwrap_or(Size::Dword);
+                let reg = Register::Rax.sized(condition_size);
                 
                 // Load condition to register first, then compare
                 self.emit_instruction_with_comment(Instruction::Mov, vec![
-                    Operand::Register(Register::Eax),
+                    Operand::Register(reg),
                     condition_operand
                 ], Some("load condition"));
                 
-                self.emit_instruction_with_comment(Instruction::Cmp, vec![
-                    Operand::Register(Register::Eax),
-                    Operand::Immediate(0)
-                ], Some("test condition"));
+                self.emit_compare(reg, Operand::Immediate(0), Some("test condition"));
                 
                 self.emit_instruction(Instruction::Je, vec![
                     Operand::Label(false_label.clone())
@@ -127,6 +142,10 @@ impl Codegen {
                 ]);
             }
 
+            IrInstruction::CompareBranch { op, left, right, var_type, true_label, false_label } => {
+                self.generate_compare_branch(op, left, right, var_type, true_label, false_label);
+            }
+
             IrInstruction::Jump { label } => {
                 self.emit_instruction(Instruction::Jmp, vec![
                     Operand::Label(label.clone())
@@ -134,41 +153,57 @@ impl Codegen {
             }
 
             IrInstruction::Label { name } => {
-                self.emit_line(&format!("{}:", name));
+                self.emit_label(name);
             }
 
             IrInstruction::Return { value, var_type } => {
-                if let Some(val) = value {
-                    let val_operand = self.ir_value_to_operand(val);
-                    let register = match var_type {
-                        IrType::Float => Register::Xmm0,
-                        _ => Register::Eax,
-                    };
-                    
-                    match var_type {
-                        IrType::Float => {
-                            self.emit_instruction_with_comment(Instruction::Movsd, vec![
-                                Operand::Register(register),
-                                val_operand
-                            ], Some(&format!("return {}", self.ir_value_to_string(val))));
-                        }
-                        _ => {
-                            self.emit_instruction_with_comment(Instruction::Mov, vec![
-                                Operand::Register(register),
-                                val_operand
-                            ], Some(&format!("return {}", self.ir_value_to_string(val))));
-                        }
+                match (value, var_type) {
+                    (Some(IrValue::FloatConstant(f)), IrType::Float) => {
+                        self.emit_instruction_with_comment(Instruction::Mov, vec![
+                            Operand::Register(Register::Rax),
+                            Operand::Immediate(f.to_bits() as i64)
+                        ], Some("load float bits"));
+                        self.emit_float_instruction(Instruction::Movq, vec![
+                            Operand::Register(Register::Xmm0),
+                            Operand::Register(Register::Rax)
+                        ], Some(&format!("return {}", f)));
+                    }
+                    (Some(val), IrType::Float) => {
+                        let val_operand = self.ir_value_to_operand(val);
+                        self.emit_float_instruction(Instruction::Movsd, vec![
+                            Operand::Register(Register::Xmm0),
+                            val_operand
+                        ], Some(&format!("return {}", self.ir_value_to_string(val))));
+                    }
+                    (Some(val @ (IrValue::Local(_) | IrValue::Temp(_))), IrType::Char) => {
+                        let val_operand = self.ir_value_to_operand(val);
+                        self.emit_instruction_with_size_and_comment(Instruction::Movsx, Size::Byte, vec![
+                            Operand::Register(Register::Eax),
+                            val_operand
+                        ], Some(&format!("return {}", self.ir_value_to_string(val))));
+                    }
+                    (Some(val), _) => {
+                        let val_operand = self.ir_value_to_operand(val);
+                        self.emit_instruction_with_comment(Instruction::Mov, vec![
+                            Operand::Register(Register::Eax),
+                            val_operand
+                        ], Some(&format!("return {}", self.ir_value_to_string(val))));
+                    }
+                    (None, _) => {
+                        self.emit_instruction_with_comment(Instruction::Xor, vec![
+                            Operand::Register(Register::Eax),
+                            Operand::Register(Register::Eax)
+                        ], Some("return 0"));
                     }
-                } else {
-                    self.emit_instruction_with_comment(Instruction::Xor, vec![
-                        Operand::Register(Register::Eax),
-                        Operand::Register(Register::Eax)
-                    ], Some("return 0"));
                 }
+
+                self.emit_instruction_with_comment(Instruction::Jmp, vec![
+                    Operand::Label(self.epilogue_label.clone())
+                ], Some("jump to epilogue"));
             }
 
-            IrInstruction::Print { format_string, args } => {
-                self.generate_print_call(format_string, args);
+            IrInstruction::Print { format_string, args, newline } => {
+                self.generate_print_call(format_string, args, *newline);
             }
 
             IrInstruction::Move { dest, src, var_type } => {
@@ -177,8 +212,7 @@ impl Codegen {
                 let size = self.ir_type_to_size(var_type);
                 
                 // Use register as intermediate for memory-to-memory moves
-                let needs_intermediate = matches!(src_operand, Operand::Memory { .. }) && 
-                                       matches!(dest_operand, Operand::Memory { .. });
+                let needs_intermediate = src_operand.is_memory() && dest_operand.is_memory();
                 
                 if needs_intermediate {
                     let reg = match size {
@@ -222,28 +256,156 @@ impl Codegen {
                 
                 // For now, implement basic casting by moving the value
                 match (src_type, dest_type) {
-                    (IrType::Int, IrType::Float) => {
-                        self.emit_instruction(Instruction::Mov, vec![
+                    (IrType::Bool, _) => {
+                        // Bools live in a byte; zero-extend them for wider uses
+                        let dest_size = self.ir_type_to_size(dest_type);
+                        self.emit_instruction_with_comment(Instruction::Mov, vec![
+                            Operand::Register(Register::Al),
                             self.ir_value_to_operand(src),
+                        ], Some("load bool"));
+                        self.emit_instruction_with_comment(Instruction::Movzx, vec![
+                            Operand::Register(Register::Eax),
+                            Operand::Register(Register::Al),
+                        ], Some("zero-extend bool"));
+                        self.emit_instruction_with_size(Instruction::Mov, dest_size, vec![
                             self.ir_value_to_operand(dest),
+                            Operand::Register(Register::Rax.sized(dest_size)),
                         ]);
                     }
-                    (IrType::Float, IrType::Int) => {
-                        // For float to int conversion, use mov for now
-                        self.emit_instruction(Instruction::Mov, vec![
+                    (IrType::Int | IrType::Char, IrType::Float) => {
+                        // Chars in memory are sign-extended; constants load as they are
+                        let (load, src_size) = match src {
+                            IrValue::Local(_) | IrValue::Temp(_) if *src_type == IrType::Char => (Instruction::Movsx, Size::Byte),
+                            _ => (Instruction::Mov, Size::Dword),
+                        };
+                        self.emit_instruction_with_size_and_comment(load, src_size, vec![
+                            Operand::Register(Register::Eax),
                             self.ir_value_to_operand(src),
+                        ], Some("load integer"));
+                        self.emit_float_instruction(Instruction::Cvtsi2sd, vec![
+                            Operand::Register(Register::Xmm0),
+                            Operand::Register(Register::Eax),
+                        ], Some("convert to double"));
+                        self.emit_float_instruction(Instruction::Movsd, vec![
                             self.ir_value_to_operand(dest),
-                        ]);
+                            Operand::Register(Register::Xmm0),
+                        ], Some("store result"));
+                    }
+                    (IrType::Float, IrType::Int | IrType::Char) => {
+                        match src {
+                            IrValue::FloatConstant(f) => {
+                                self.emit_instruction_with_comment(Instruction::Mov, vec![
+                                    Operand::Register(Register::Rax),
+                                    Operand::Immediate(f.to_bits() as i64),
+                                ], Some("load float bits"));
+                                self.emit_float_instruction(Instruction::Movq, vec![
+                                    Operand::Register(Register::Xmm0),
+                                    Operand::Register(Register::Rax),
+                                ], None);
+                            }
+                            _ => {
+                                self.emit_float_instruction(Instruction::Movsd, vec![
+                                    Operand::Register(Register::Xmm0),
+                                    self.ir_value_to_operand(src),
+                                ], Some("load double"));
+                            }
+                        }
+                        // Truncates toward zero, as C conversions do
+                        self.emit_float_instruction(Instruction::Cvttsd2si, vec![
+                            Operand::Register(Register::Eax),
+                            Operand::Register(Register::Xmm0),
+                        ], Some("convert to integer"));
+                        let dest_size = self.ir_type_to_size(dest_type);
+                        self.emit_instruction_with_size_and_comment(Instruction::Mov, dest_size, vec![
+                            self.ir_value_to_operand(dest),
+                            Operand::Register(Register::Rax.sized(dest_size)),
+                        ], Some("store result"));
                     }
                     _ => {
-                        // For other cases, just move the value
-                        self.emit_instruction(Instruction::Mov, vec![
-                            self.ir_value_to_operand(src),
+                        // Integer-class values: copy through rax at the new width
+                        let src_size = self.ir_type_to_size(src_type);
+                        let dest_size = self.ir_type_to_size(dest_type);
+                        let widens_char = *src_type == IrType::Char && matches!(src, IrValue::Local(_) | IrValue::Temp(_));
+                        if widens_char {
+                            self.emit_instruction_with_size_and_comment(Instruction::Movsx, Size::Byte, vec![
+                                Operand::Register(Register::Eax),
+                                self.ir_value_to_operand(src),
+                            ], Some("sign-extend char"));
+                        } else {
+                            self.emit_instruction_with_size_and_comment(Instruction::Mov, src_size, vec![
+                                Operand::Register(Register::Rax.sized(src_size)),
+                                self.ir_value_to_operand(src),
+                            ], Some("load value"));
+                        }
+                        self.emit_instruction_with_size_and_comment(Instruction::Mov, dest_size, vec![
                             self.ir_value_to_operand(dest),
-                        ]);
+                            Operand::Register(Register::Rax.sized(dest_size)),
+                        ], Some("store result"));
                     }
                 }
             }
+            IrInstruction::LoadElement { dest, array, index, var_type } => {
+                let size = self.ir_type_to_size(var_type);
+                let element = self.element_operand(array, index, var_type);
+                let reg = Register::Rax.sized(size);
+                self.emit_instruction_with_size_and_comment(Instruction::Mov, size, vec![
+                    Operand::Register(reg),
+                    element
+                ], Some(&format!("load {}[{}]", self.ir_value_to_string(array), self.ir_value_to_string(index))));
+                self.emit_instruction_with_size_and_comment(Instruction::Mov, size, vec![
+                    self.ir_value_to_operand(dest),
+                    Operand::Register(reg)
+                ], Some("store to destination"));
+            }
+
+            IrInstruction::StoreElement { value, array, index, var_type } => {
+                let size = self.ir_type_to_size(var_type);
+                let element = self.element_operand(array, index, var_type);
+                let comment = format!("store {} to {}[{}]", self.ir_value_to_string(value),
+                    self.ir_value_to_string(array), self.ir_value_to_string(index));
+                let value_operand = match value {
+                    IrValue::FloatConstant(f) => Operand::Immediate(f.to_bits() as i64),
+                    other => self.ir_value_to_operand(other),
+                };
+                if matches!(value_operand, Operand::Immediate(_)) {
+                    self.emit_instruction_with_size_and_comment(Instruction::Mov, size, vec![
+                        element,
+                        value_operand
+                    ], Some(&comment));
+                } else {
+                    // rax and rcx may hold the element's address
+                    let reg = Register::Rdx.sized(size);
+                    self.emit_instruction_with_size_and_comment(Instruction::Mov, size, vec![
+                        Operand::Register(reg),
+                        value_operand
+                    ], Some(&format!("load {} to register", self.ir_value_to_string(value))));
+                    self.emit_instruction_with_size_and_comment(Instruction::Mov, size, vec![
+                        element,
+                        Operand::Register(reg)
+                    ], Some(&comment));
+                }
+            }
+
+            IrInstruction::AddressOf { dest, src, .. } => {
+                // Stack variables live at [rbp+offset]: lea materializes that address
+                self.emit_instruction_with_comment(Instruction::Lea, vec![
+                    Operand::Register(Register::Rax),
+                    self.ir_value_to_operand(src)
+                ], Some(&format!("address of {}", self.ir_value_to_string(src))));
+                self.emit_instruction_with_size_and_comment(Instruction::Mov, Size::Qword, vec![
+                    self.ir_value_to_operand(dest),
+                    Operand::Register(Register::Rax)
+                ], Some("store pointer"));
+            }
+
+            IrInstruction::Phi { .. } => {
+                unreachable!("phis are lowered to moves before code generation");
+            }
+
+            IrInstruction::Trap => {
+                self.emit_instruction_with_comment(Instruction::Ud2, vec![], Some("trap"));
+            }
+
             IrInstruction::Comment { text } => {
                 self.emit_comment(text);
             }
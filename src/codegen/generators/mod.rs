@@ -6,5 +6,5 @@ pub mod operation;
 pub mod call;
 pub mod value;
 
-// These modules contain impl blocks for IrCodegen, not separate structs
+// These modules contain impl blocks for Codegen, not separate structs
 // So we don't export specific types, just make the modules public
\ No newline at end of file
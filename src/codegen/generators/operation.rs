@@ -1,5 +1,5 @@
 use crate::ir::{IrValue, IrType, IrBinaryOp, IrUnaryOp};
-use crate::codegen::core::{Instruction, Operand, Register};
+use crate::codegen::core::{Instruction, Operand, Register, Size};
 use crate::codegen::core::{Emitter, CodeEmitter, CodeEmitterWithComment};
 use crate::codegen::Codegen;
 
@@ -22,14 +22,14 @@ impl Codegen {
                             Operand::Memory { base: Register::Rsp, offset: -8 },
                             Operand::Register(Register::Rax)
                         ], Some("store float to temp memory"));
-                        self.emit_instruction_with_comment(Instruction::Movsd, vec![
+                        self.emit_float_instruction(Instruction::Movsd, vec![
                             Operand::Register(Register::Xmm0),
                             Operand::Memory { base: Register::Rsp, offset: -8 }
                         ], Some("load left operand"));
                     }
                     _ => {
                         let left_operand = self.ir_value_to_operand(left);
-                        self.emit_instruction_with_comment(Instruction::Movsd, vec![
+                        self.emit_float_instruction(Instruction::Movsd, vec![
                             Operand::Register(Register::Xmm0),
                             left_operand
                         ], Some("load left operand"));
@@ -58,30 +58,31 @@ impl Codegen {
                             Operand::Memory { base: Register::Rsp, offset: -16 },
                             Operand::Register(Register::Rax)
                         ], Some("store float to temp memory"));
-                        self.emit_instruction_with_comment(asm_op, vec![
+                        self.emit_float_instruction(asm_op, vec![
                             Operand::Register(Register::Xmm0),
                             Operand::Memory { base: Register::Rsp, offset: -16 }
                         ], Some(&format!("{} operation", op)));
                     }
                     _ => {
                         let right_operand = self.ir_value_to_operand(right);
-                        self.emit_instruction_with_comment(asm_op, vec![
+                        self.emit_float_instruction(asm_op, vec![
                             Operand::Register(Register::Xmm0),
                             right_operand
                         ], Some(&format!("{} operation", op)));
                     }
                 }
                 
-                self.emit_instruction_with_comment(Instruction::Movsd, vec![
+                self.emit_float_instruction(Instruction::Movsd, vec![
                     dest_operand,
                     Operand::Register(Register::Xmm0)
                 ], Some("store result"));
             }
             _ => {
-                // Integer operations
+                // Integer operations; addresses are compared at their full width
+                let left_reg = if var_type.is_unsigned() { Register::Rax } else { Register::Eax };
                 let left_operand = self.ir_value_to_operand(left);
                 self.emit_instruction_with_comment(Instruction::Mov, vec![
-                    Operand::Register(Register::Eax),
+                    Operand::Register(left_reg),
                     left_operand
                 ], Some("load left operand"));
                 
@@ -89,12 +90,49 @@ impl Codegen {
                     IrBinaryOp::Add => Instruction::Add,
                     IrBinaryOp::Sub => Instruction::Sub,
                     IrBinaryOp::Mul => Instruction::Imul,
-                    IrBinaryOp::Div => {
-                        // Division requires special handling
+                    IrBinaryOp::BitAnd => Instruction::And,
+                    IrBinaryOp::BitOr => Instruction::Or,
+                    IrBinaryOp::BitXor => Instruction::Xor,
+                    IrBinaryOp::Shl | IrBinaryOp::Shr => {
+                        // A variable count has to be in cl; int is signed,
+                        // so `>>` shifts copies of the sign bit in
+                        let shift = if *op == IrBinaryOp::Shl { Instruction::Shl } else { Instruction::Sar };
+                        let count = match right {
+                            IrValue::IntConstant(count) => Operand::Immediate(*count),
+                            _ => {
+                                let right_operand = self.ir_value_to_operand(right);
+                                self.emit_instruction_with_comment(Instruction::Mov, vec![
+                                    Operand::Register(Register::Ecx),
+                                    right_operand
+                                ], Some("load shift count"));
+                                Operand::Register(Register::Cl)
+                            }
+                        };
+                        self.emit_instruction_with_comment(shift, vec![
+                            Operand::Register(Register::Eax),
+                            count
+                        ], Some(&format!("{} operation", op)));
+                        self.emit_instruction_with_comment(Instruction::Mov, vec![
+                            dest_operand,
+                            Operand::Register(Register::Eax)
+                        ], Some("store result"));
+                        return;
+                    }
+                    IrBinaryOp::Div | IrBinaryOp::Mod => {
+                        // idiv leaves the quotient in eax and the remainder
+                        // in edx; the divisor goes through ecx as it takes
+                        // no immediate. A constant zero divisor is never
+                        // folded away, so `x / 0` and `x % 0` fault at run
+                        // time as idiv does on any zero divisor.
                         let right_operand = self.ir_value_to_operand(right);
+                        self.emit_instruction_with_comment(Instruction::Mov, vec![
+                            Operand::Register(Register::Ecx),
+                            right_operand
+                        ], Some("load divisor"));
+                        let result = if *op == IrBinaryOp::Div { Register::Eax } else { Register::Edx };
                         self.emit_instruction(Instruction::Cdq, vec![]);
-                        self.emit_instruction(Instruction::Idiv, vec![right_operand]);
-                        self.emit_instruction(Instruction::Mov, vec![dest_operand, Operand::Register(Register::Eax)]);
+                        self.emit_instruction(Instruction::Idiv, vec![Operand::Register(Register::Ecx)]);
+                        self.emit_instruction(Instruction::Mov, vec![dest_operand, Operand::Register(result)]);
                         return;
                     }
                     IrBinaryOp::Eq | IrBinaryOp::Ne | IrBinaryOp::Lt | 
@@ -114,29 +152,27 @@ impl Codegen {
                             }
                             _ => {
                                 let right_operand = self.ir_value_to_operand(right);
-                                self.emit_instruction(Instruction::Cmp, vec![
-                                    Operand::Register(Register::Eax),
-                                    right_operand
-                                ]);
+                                self.emit_compare(left_reg, right_operand, None);
                             }
                         }
                         
-                        let set_op = match op {
-                            IrBinaryOp::Eq => Instruction::Sete,
-                            IrBinaryOp::Ne => Instruction::Setne,
-                            IrBinaryOp::Lt => Instruction::Setl,
-                            IrBinaryOp::Le => Instruction::Setle,
-                            IrBinaryOp::Gt => Instruction::Setg,
-                            IrBinaryOp::Ge => Instruction::Setge,
+                        let set_op = match (op, var_type.is_unsigned()) {
+                            (IrBinaryOp::Eq, _) => Instruction::Sete,
+                            (IrBinaryOp::Ne, _) => Instruction::Setne,
+                            (IrBinaryOp::Lt, false) => Instruction::Setl,
+                            (IrBinaryOp::Le, false) => Instruction::Setle,
+                            (IrBinaryOp::Gt, false) => Instruction::Setg,
+                            (IrBinaryOp::Ge, false) => Instruction::Setge,
+                            (IrBinaryOp::Lt, true) => Instruction::Setb,
+                            (IrBinaryOp::Le, true) => Instruction::Setbe,
+                            (IrBinaryOp::Gt, true) => Instruction::Seta,
+                            (IrBinaryOp::Ge, true) => Instruction::Setae,
                             _ => unreachable!(),
                         };
                         
+                        // The result is a Bool: keep it in a byte, callers widen it
                         self.emit_instruction(set_op, vec![Operand::Register(Register::Al)]);
-                        self.emit_instruction(Instruction::Movzx, vec![
-                            Operand::Register(Register::Eax),
-                            Operand::Register(Register::Al)
-                        ]);
-                        self.emit_instruction(Instruction::Mov, vec![dest_operand, Operand::Register(Register::Eax)]);
+                        self.emit_instruction_with_size(Instruction::Mov, Size::Byte, vec![dest_operand, Operand::Register(Register::Al)]);
                         return;
                     }
                     _ => {
@@ -145,11 +181,27 @@ impl Codegen {
                     }
                 };
                 
-                let right_operand = self.ir_value_to_operand(right);
-                self.emit_instruction_with_comment(asm_op, vec![
-                    Operand::Register(Register::Eax),
-                    right_operand
-                ], Some(&format!("{} operation", op)));
+                // Stepping by one is written inc/dec. They leave the carry
+                // flag as it was where add/sub would set it, which is safe
+                // as no instruction this backend emits reads the flags an
+                // arithmetic instruction sets: every branch and setcc
+                // follows a cmp or test of its own.
+                let step = match (op, right) {
+                    (IrBinaryOp::Add, IrValue::IntConstant(1)) | (IrBinaryOp::Sub, IrValue::IntConstant(-1)) => Some(Instruction::Inc),
+                    (IrBinaryOp::Add, IrValue::IntConstant(-1)) | (IrBinaryOp::Sub, IrValue::IntConstant(1)) => Some(Instruction::Dec),
+                    _ => None,
+                };
+                if let Some(step) = step {
+                    self.emit_instruction_with_comment(step, vec![
+                        Operand::Register(Register::Eax)
+                    ], Some(&format!("{} operation", op)));
+                } else {
+                    let right_operand = self.ir_value_to_operand(right);
+                    self.emit_instruction_with_comment(asm_op, vec![
+                        Operand::Register(Register::Eax),
+                        right_operand
+                    ], Some(&format!("{} operation", op)));
+                }
                 
                 self.emit_instruction_with_comment(Instruction::Mov, vec![
                     dest_operand,
@@ -159,16 +211,98 @@ impl Codegen {
         }
     }
 
+    /// Compare `reg` with `operand`. A comparison with 0 is written
+    /// `test reg, reg`, which sets the flags exactly as `cmp reg, 0` does
+    /// in fewer bytes.
+    pub fn emit_compare(&mut self, reg: Register, operand: Operand, comment: Option<&str>) {
+        let (instruction, operand) = match operand {
+            Operand::Immediate(0) => (Instruction::Test, Operand::Register(reg)),
+            operand => (Instruction::Cmp, operand),
+        };
+        self.emit_instruction_with_comment(instruction, vec![Operand::Register(reg), operand], comment);
+    }
+
+    /// Generate a fused integer compare-and-branch: one `cmp` and a jump on
+    /// the inverted condition to the false label
+    pub fn generate_compare_branch(&mut self, op: &IrBinaryOp, left: &IrValue, right: &IrValue, var_type: &IrType, true_label: &str, false_label: &str) {
+        let size = self.ir_type_to_size(var_type);
+        let reg = Register::Rax.sized(size);
+
+        let left_operand = self.ir_value_to_operand(left);
+        self.emit_instruction_with_size_and_comment(Instruction::Mov, size, vec![
+            Operand::Register(reg),
+            left_operand
+        ], Some("load left operand"));
+
+        let right_operand = self.ir_value_to_operand(right);
+        self.emit_compare(reg, right_operand, Some(&format!("{} comparison", op)));
+
+        let jump_if_false = match (op, var_type.is_unsigned()) {
+            (IrBinaryOp::Eq, _) => Instruction::Jne,
+            (IrBinaryOp::Ne, _) => Instruction::Je,
+            (IrBinaryOp::Lt, false) => Instruction::Jge,
+            (IrBinaryOp::Le, false) => Instruction::Jg,
+            (IrBinaryOp::Gt, false) => Instruction::Jle,
+            (IrBinaryOp::Ge, false) => Instruction::Jl,
+            (IrBinaryOp::Lt, true) => Instruction::Jae,
+            (IrBinaryOp::Le, true) => Instruction::Ja,
+            (IrBinaryOp::Gt, true) => Instruction::Jbe,
+            (IrBinaryOp::Ge, true) => Instruction::Jb,
+            _ => unreachable!("compare-and-branch requires a comparison operator"),
+        };
+
+        self.emit_instruction(jump_if_false, vec![Operand::Label(false_label.to_string())]);
+        self.emit_instruction(Instruction::Jmp, vec![Operand::Label(true_label.to_string())]);
+    }
+
     /// Generate unary operation
-    pub fn generate_unary_op(&mut self, dest: &IrValue, op: &IrUnaryOp, operand: &IrValue, _var_type: &IrType) {
-        let operand_op = self.ir_value_to_operand(operand);
+    pub fn generate_unary_op(&mut self, dest: &IrValue, op: &IrUnaryOp, operand: &IrValue, var_type: &IrType) {
         let dest_operand = self.ir_value_to_operand(dest);
         
         match op {
+            IrUnaryOp::Neg if *var_type == IrType::Float => {
+                // Negate as 0.0 - x, since integer `neg` would not touch the sign bit
+                match operand {
+                    IrValue::FloatConstant(f) => {
+                        let float_bits = f.to_bits() as i64;
+                        self.emit_instruction_with_comment(Instruction::Mov, vec![
+                            Operand::Register(Register::Rax),
+                            Operand::Immediate(float_bits)
+                        ], Some("load float bits"));
+                        self.emit_float_instruction(Instruction::Movq, vec![
+                            Operand::Register(Register::Xmm1),
+                            Operand::Register(Register::Rax)
+                        ], Some("load operand"));
+                    }
+                    _ => {
+                        self.emit_float_instruction(Instruction::Movsd, vec![
+                            Operand::Register(Register::Xmm1),
+                            self.ir_value_to_operand(operand)
+                        ], Some("load operand"));
+                    }
+                }
+
+                self.emit_instruction_with_comment(Instruction::Mov, vec![
+                    Operand::Register(Register::Rax),
+                    Operand::Immediate(0)
+                ], Some("load 0.0 bits"));
+                self.emit_float_instruction(Instruction::Movq, vec![
+                    Operand::Register(Register::Xmm0),
+                    Operand::Register(Register::Rax)
+                ], None);
+                self.emit_float_instruction(Instruction::Subsd, vec![
+                    Operand::Register(Register::Xmm0),
+                    Operand::Register(Register::Xmm1)
+                ], Some("negate"));
+                self.emit_float_instruction(Instruction::Movsd, vec![
+                    dest_operand,
+                    Operand::Register(Register::Xmm0)
+                ], Some("store result"));
+            }
             IrUnaryOp::Neg => {
                 self.emit_instruction_with_comment(Instruction::Mov, vec![
                     Operand::Register(Register::Eax),
-                    operand_op
+                    self.ir_value_to_operand(operand)
                 ], Some("load operand"));
                 
                 self.emit_instruction_with_comment(Instruction::Neg, vec![
@@ -181,25 +315,22 @@ impl Codegen {
                 ], Some("store result"));
             }
             IrUnaryOp::Not => {
+                let operand_size = self.value_types.get(operand)
+                    .map(|operand_type| self.ir_type_to_size(operand_type))
+                    .unwrap_or(Size::Dword);
+                let reg = Register::Rax.sized(operand_size);
+
                 self.emit_instruction_with_comment(Instruction::Mov, vec![
-                    Operand::Register(Register::Eax),
-                    operand_op
+                    Operand::Register(reg),
+                    self.ir_value_to_operand(operand)
                 ], Some("load operand"));
                 
-                self.emit_instruction_with_comment(Instruction::Cmp, vec![
-                    Operand::Register(Register::Eax),
-                    Operand::Immediate(0)
-                ], Some("test for zero"));
+                self.emit_compare(reg, Operand::Immediate(0), Some("test for zero"));
                 
                 self.emit_instruction(Instruction::Sete, vec![Operand::Register(Register::Al)]);
-                self.emit_instruction(Instruction::Movzx, vec![
-                    Operand::Register(Register::Eax),
-                    Operand::Register(Register::Al)
-                ]);
-                
-                self.emit_instruction_with_comment(Instruction::Mov, vec![
+                self.emit_instruction_with_size_and_comment(Instruction::Mov, Size::Byte, vec![
                     dest_operand,
-                    Operand::Register(Register::Eax)
+                    Operand::Register(Register::Al)
                 ], Some("store result"));
             }
         }
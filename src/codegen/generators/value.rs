@@ -1,5 +1,5 @@
 use crate::ir::{IrValue, IrType};
-use crate::codegen::core::{Operand, Register, Size};
+use crate::codegen::core::{CodeEmitterWithComment, Instruction, Operand, Register, Size};
 use crate::codegen::Codegen;
 
 impl Codegen {
@@ -13,31 +13,74 @@ impl Codegen {
             IrValue::CharConstant(c) => Operand::Immediate(*c as i64),
             IrValue::StringConstant(label) => Operand::Label(label.clone()),
             IrValue::Local(name) => {
-                let offset = self.locals.get(name).copied().unwrap_or(0);
+                if let Some(register) = self.register_locals.get(name) {
+                    let size = self.value_types.get(value).map_or(Size::Qword, |ir_type| self.ir_type_to_size(ir_type));
+                    return Operand::Register(register.sized(size));
+                }
+                let offset = self.locals.get(name).copied().unwrap_or_else(|| self.unplaced_value(value));
                 Operand::Memory { base: Register::Rbp, offset }
             }
             IrValue::Temp(id) => {
-                let offset = self.temp_locations.get(id).copied().unwrap_or(0);
+                let offset = self.temp_locations.get(id).copied().unwrap_or_else(|| self.unplaced_value(value));
                 Operand::Memory { base: Register::Rbp, offset }
             }
-            IrValue::Parameter(_name) => {
-                // Parameters would be at positive offsets from RBP
-                let offset = 16; // Simplified - would need proper parameter handling
+            IrValue::Parameter(name) => {
+                // Spilled into its own slot by the prologue
+                let offset = self.locals.get(name).copied().unwrap_or_else(|| self.unplaced_value(value));
                 Operand::Memory { base: Register::Rbp, offset }
             }
+            // A global variable is read and written in place; any other
+            // global is a function, named by its label
+            IrValue::Global(name) if self.global_vars.contains(name) => Operand::String(format!("[{}]", name)),
             IrValue::Global(name) => Operand::Label(name.clone()),
         }
     }
 
+    /// Memory operand of `array[index]` for elements of `element_type`. A
+    /// constant index is folded into the displacement; any other index is
+    /// scaled in rax and the element's address computed into rcx.
+    pub fn element_operand(&mut self, array: &IrValue, index: &IrValue, element_type: &IrType) -> Operand {
+        let element_size = self.get_type_size(element_type);
+        match (self.ir_value_to_operand(array), index) {
+            (Operand::Memory { base, offset }, IrValue::IntConstant(index)) => {
+                Operand::Memory { base, offset: offset + *index as i32 * element_size }
+            }
+            (array_operand, _) => {
+                self.emit_instruction_with_size_and_comment(Instruction::Mov, Size::Dword, vec![
+                    Operand::Register(Register::Eax),
+                    self.ir_value_to_operand(index)
+                ], Some(&format!("index {}", self.ir_value_to_string(index))));
+                if element_size > 1 {
+                    self.emit_instruction_with_comment(Instruction::Imul, vec![
+                        Operand::Register(Register::Rax),
+                        Operand::Immediate(element_size as i64)
+                    ], Some("scale by element size"));
+                }
+                self.emit_instruction_with_comment(Instruction::Lea, vec![
+                    Operand::Register(Register::Rcx),
+                    array_operand
+                ], Some(&format!("address of {}", self.ir_value_to_string(array))));
+                self.emit_instruction_with_comment(Instruction::Add, vec![
+                    Operand::Register(Register::Rcx),
+                    Operand::Register(Register::Rax)
+                ], Some("address of element"));
+                Operand::Memory { base: Register::Rcx, offset: 0 }
+            }
+        }
+    }
+
     /// Convert IR type to assembly size
     pub fn ir_type_to_size(&self, ir_type: &IrType) -> Size {
         match ir_type {
             IrType::Int => Size::Dword,
             IrType::Float => Size::Qword,
             IrType::Char => Size::Byte,
+            IrType::Bool => Size::Byte,
             IrType::String => Size::Qword,
             IrType::Void => Size::Qword,
             IrType::Pointer(_) => Size::Qword,
+            IrType::Function(_) => Size::Qword,
+            IrType::Array(..) | IrType::Struct(_) => Size::Qword, // Only ever used through their address
         }
     }
 
@@ -45,8 +88,8 @@ impl Codegen {
     pub fn ir_value_to_string(&self, value: &IrValue) -> String {
         match value {
             IrValue::IntConstant(i) => i.to_string(),
-            IrValue::FloatConstant(f) => f.to_string(),
-            IrValue::CharConstant(c) => format!("'{}'", c),
+            IrValue::FloatConstant(_) => value.to_string(),
+            IrValue::CharConstant(c) => format!("{:?}", c),
             IrValue::StringConstant(label) => format!("@{}", label),
             IrValue::Local(name) => format!("%{}", name),
             IrValue::Temp(id) => format!("%t{}", id),
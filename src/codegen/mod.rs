@@ -8,10 +8,13 @@ pub mod utils;
 pub mod generators;
 mod codegen;
 pub mod targets;
+pub mod encoder;
+pub mod stack_usage;
 
 // Re-export commonly used items
-pub use core::{CodeEmitter, CodeEmitterWithComment, Emitter, Instruction, Operand, Register, Size};
+pub use core::{render_lines, AsmLine, AsmLineKind, CodeEmitter, CodeEmitterWithComment, Emitter, IndentStyle, Instruction, Operand, Register, Size};
 
 pub use utils::{InstructionFormatter, RegisterAllocator, StackManager};
 
-pub use codegen::Codegen;
+pub use codegen::{Codegen, DEFAULT_FLOAT_PRECISION};
+pub use stack_usage::{check_recursive_frames, RecursionWarning};
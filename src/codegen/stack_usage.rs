@@ -0,0 +1,88 @@
+use std::fmt;
+
+use crate::codegen::Codegen;
+use crate::codegen::targets::TargetPlatform;
+use crate::ir::{IrFunction, IrInstruction, IrProgram};
+
+/// Bytes every call pushes besides the frame itself: the return address
+/// and the caller's rbp
+const CALL_OVERHEAD: usize = 16;
+
+/// A directly recursive function whose every call takes a large frame,
+/// so deep recursion may overflow the stack
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecursionWarning {
+    pub function: String,
+    /// Stack bytes one call uses, frame and call overhead together
+    pub bytes_per_call: usize,
+}
+
+impl fmt::Display for RecursionWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Function '{}' calls itself and uses {} bytes of stack per call; deep recursion may overflow the stack",
+            self.function, self.bytes_per_call
+        )
+    }
+}
+
+/// Warn about every function that calls itself directly and uses more
+/// than `limit` bytes of stack per call. Frames are sized as the backend
+/// for `target_platform` lays them out. This is a heuristic: recursion
+/// through other functions is not followed, and nothing is known of how
+/// deep the recursion goes.
+pub fn check_recursive_frames(program: &IrProgram, target_platform: TargetPlatform, limit: usize) -> Vec<RecursionWarning> {
+    program.functions.iter()
+        .filter(|function| calls_itself(function))
+        .filter_map(|function| {
+            let frame = Codegen::new_with_target(target_platform).calculate_stack_space(function);
+            let bytes_per_call = usize::try_from(frame).unwrap_or(0) + CALL_OVERHEAD;
+            (bytes_per_call > limit).then(|| RecursionWarning { function: function.name.clone(), bytes_per_call })
+        })
+        .collect()
+}
+
+/// Whether `function` calls itself by name. A local or parameter of the
+/// same name hides the function, so calls through it do not count.
+fn calls_itself(function: &IrFunction) -> bool {
+    let shadowed = function.parameters.iter()
+        .chain(&function.local_vars)
+        .any(|(name, _)| *name == function.name);
+    !shadowed && function.instructions.iter().any(|instruction| {
+        matches!(instruction, IrInstruction::Call { func, .. } if *func == function.name)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ir::IrGenerator;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    fn warnings(source: &str, limit: usize) -> Vec<RecursionWarning> {
+        let tokens = Lexer::new(source).tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let ast = parser.parse();
+        assert!(parser.get_errors().is_empty(), "{:?}", parser.get_errors());
+        let program = IrGenerator::new().generate(&ast).unwrap();
+        check_recursive_frames(&program, TargetPlatform::LinuxX64, limit)
+    }
+
+    #[test]
+    fn test_recursive_function_with_large_frame_is_reported() {
+        let source = "int walk(int n) { int buffer[200]; if (n > 0) { return walk(n - 1); } return buffer[0]; }
+            int small(int n) { int m = n + 1; return m; }
+            int main() { return walk(3) + small(1); }";
+        let found = warnings(source, 512);
+        assert_eq!(found.len(), 1, "{:?}", found);
+        assert_eq!(found[0].function, "walk");
+        assert!(found[0].bytes_per_call > 800, "{:?}", found);
+        assert!(found[0].to_string().starts_with("Function 'walk' calls itself"));
+
+        // The same recursion with a small frame, and a large frame without recursion, pass
+        assert!(warnings("int count(int n) { if (n > 0) { return count(n - 1); } return 0; } int main() { return count(3); }", 512).is_empty());
+        assert!(warnings("int main() { int buffer[200]; return buffer[0]; }", 512).is_empty());
+    }
+}
@@ -1,12 +1,15 @@
 use crate::codegen::Register;
 
 /// Represents different target platforms
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum TargetPlatform {
     WindowsX64,
     LinuxX64,
     MacOSX64,
     MacOSArm64,
+    FreeBSDX64,
+    /// A target registered by a downstream user, identified by name
+    Custom(&'static str),
 }
 
 /// Represents different calling conventions
@@ -18,6 +21,106 @@ pub enum CallingConvention {
     AppleArm64,
 }
 
+impl CallingConvention {
+    /// General-purpose registers a callee must hand back unchanged,
+    /// besides the stack and frame pointers
+    pub fn callee_saved_registers(&self) -> Vec<Register> {
+        match self {
+            CallingConvention::MicrosoftX64 => vec![
+                Register::Rbx, Register::R12, Register::R13, Register::R14, Register::R15, Register::Rdi, Register::Rsi,
+            ],
+            CallingConvention::SystemV | CallingConvention::AppleX64 | CallingConvention::AppleArm64 => vec![
+                Register::Rbx, Register::R12, Register::R13, Register::R14, Register::R15,
+            ],
+        }
+    }
+
+    /// Bytes a caller reserves just below the stack arguments, for the
+    /// callee to spill its register arguments into
+    pub fn shadow_space(&self) -> usize {
+        match self {
+            CallingConvention::MicrosoftX64 => 32,
+            CallingConvention::SystemV | CallingConvention::AppleX64 | CallingConvention::AppleArm64 => 0,
+        }
+    }
+
+    /// The register each argument of a call is passed in, or None for
+    /// the stack, given which arguments are floats and the convention's
+    /// integer argument registers. Microsoft x64 gives each argument
+    /// position one register of either kind; System V hands out integer
+    /// and float registers in separate sequences.
+    pub fn argument_registers(&self, integer_registers: &[Register], floats: &[bool]) -> Vec<Option<Register>> {
+        let float_registers = [
+            Register::Xmm0, Register::Xmm1, Register::Xmm2, Register::Xmm3,
+            Register::Xmm4, Register::Xmm5, Register::Xmm6, Register::Xmm7,
+        ];
+        match self {
+            CallingConvention::MicrosoftX64 => floats.iter()
+                .enumerate()
+                .map(|(index, &float)| if float {
+                    float_registers[..4].get(index).copied()
+                } else {
+                    integer_registers.get(index).copied()
+                })
+                .collect(),
+            CallingConvention::SystemV | CallingConvention::AppleX64 | CallingConvention::AppleArm64 => {
+                let mut integers = integer_registers.iter().copied();
+                let mut floats_left = float_registers.into_iter();
+                floats.iter()
+                    .map(|&float| if float { floats_left.next() } else { integers.next() })
+                    .collect()
+            }
+        }
+    }
+
+    /// General-purpose registers a call may overwrite, which the caller
+    /// has to save itself if it needs their values afterwards
+    pub fn caller_saved_registers(&self) -> Vec<Register> {
+        match self {
+            CallingConvention::MicrosoftX64 => vec![
+                Register::Rax, Register::Rcx, Register::Rdx, Register::R8, Register::R9, Register::R10, Register::R11,
+            ],
+            CallingConvention::SystemV | CallingConvention::AppleX64 | CallingConvention::AppleArm64 => vec![
+                Register::Rax, Register::Rcx, Register::Rdx, Register::Rsi, Register::Rdi,
+                Register::R8, Register::R9, Register::R10, Register::R11,
+            ],
+        }
+    }
+}
+
+/// Optional instruction set extensions the generated code may use, as
+/// given to `--target-features`. SSE2 is part of x86-64 and always on;
+/// without other features scalar floats use its instructions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TargetFeatures {
+    /// Emit the VEX-encoded AVX forms of scalar float instructions
+    pub avx: bool,
+}
+
+impl std::str::FromStr for TargetFeatures {
+    type Err = String;
+
+    /// Parse a comma-separated list such as `+avx` or `+avx,-avx`, where
+    /// later entries override earlier ones
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let mut features = TargetFeatures::default();
+        for entry in spec.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+            let (enabled, name) = match entry.split_at(1) {
+                ("+", name) => (true, name),
+                ("-", name) => (false, name),
+                _ => return Err(format!("target feature '{}' must start with '+' or '-'", entry)),
+            };
+            match name {
+                "avx" => features.avx = enabled,
+                "sse2" if enabled => {}
+                "sse2" => return Err("sse2 cannot be disabled: x86-64 float code requires it".to_string()),
+                _ => return Err(format!("unknown target feature '{}' (expected avx or sse2)", name)),
+            }
+        }
+        Ok(features)
+    }
+}
+
 /// Target-specific configuration and behavior
 pub trait Target {
     /// Get the target platform
@@ -81,6 +184,56 @@ pub trait Target {
     /// Get the size and alignment for a data type
     fn type_info(&self, type_name: &str) -> (usize, usize); // (size, alignment)
     
-    /// Generate platform-specific startup code if needed
+    /// Entry point for programs linked without the C runtime: its label,
+    /// then code calling `main` and exiting with the value it returns.
+    /// Empty when the target has none.
     fn startup_code(&self) -> Vec<String>;
-}
\ No newline at end of file
+
+    /// Instructions to run before reserving a `frame_size`-byte frame, for
+    /// platforms that must touch each new stack page in order
+    fn stack_probe(&self, _frame_size: usize) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+/// Operands of a NASM `db` directive spelling out `content` as a
+/// NUL-terminated string. Newlines are written as `10` bytes, since NASM
+/// takes double-quoted text verbatim.
+pub fn db_string_operands(content: &str) -> String {
+    let mut operands: Vec<String> = Vec::new();
+    for (index, segment) in content.split('\n').enumerate() {
+        if index > 0 {
+            operands.push("10".to_string());
+        }
+        if !segment.is_empty() {
+            operands.push(format!("\"{}\"", segment));
+        }
+    }
+    operands.push("0".to_string());
+    operands.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_float_arguments_take_xmm_registers() {
+        let integers = [Register::Rdi, Register::Rsi];
+        let floats = [false, true, false, true, false];
+        assert_eq!(CallingConvention::SystemV.argument_registers(&integers, &floats), vec![
+            Some(Register::Rdi), Some(Register::Xmm0), Some(Register::Rsi), Some(Register::Xmm1), None,
+        ]);
+        let integers = [Register::Rcx, Register::Rdx, Register::R8, Register::R9];
+        assert_eq!(CallingConvention::MicrosoftX64.argument_registers(&integers, &floats), vec![
+            Some(Register::Rcx), Some(Register::Xmm1), Some(Register::R8), Some(Register::Xmm3), None,
+        ]);
+    }
+
+    #[test]
+    fn test_db_string_operands_spell_newlines_as_bytes() {
+        assert_eq!(db_string_operands("x"), "\"x\", 0");
+        assert_eq!(db_string_operands("x\n"), "\"x\", 10, 0");
+        assert_eq!(db_string_operands("a\nb"), "\"a\", 10, \"b\", 0");
+    }
+}
@@ -0,0 +1,8 @@
+use super::base::TargetPlatform;
+use super::registry::TargetRegistry;
+use super::SystemVX64Target;
+
+/// Add the FreeBSD target to `registry` through the public registration API
+pub fn register(registry: &mut TargetRegistry) {
+    registry.register(TargetPlatform::FreeBSDX64, &["freebsd", "freebsd-x64", "freebsd64"], || Box::new(SystemVX64Target::freebsd()));
+}
@@ -1,4 +1,4 @@
-use super::base::{Target, TargetPlatform, CallingConvention};
+use super::base::{db_string_operands, Target, TargetPlatform, CallingConvention};
 use crate::codegen::Register;
 
 /// macOS ARM64 target implementation
@@ -85,8 +85,7 @@ impl Target for MacOSArm64Target {
     }
     
     fn format_string_literal(&self, label: &str, content: &str) -> String {
-        let formatted_content = content.replace('\n', "").replace("%f", "%.2f");
-        format!("    {}: db \"{}\", 10, 0", label, formatted_content)
+        format!("{}: db {}", label, db_string_operands(content))
     }
     
     fn format_function_call(&self, function_name: &str) -> Vec<String> {
@@ -5,35 +5,36 @@
 
 mod base;
 mod windows;
-mod linux;
+mod system_v;
 mod macos;
 mod macos_arm64;
+mod freebsd;
+mod registry;
 
-pub use base::{Target, TargetPlatform, CallingConvention};
+pub use base::{db_string_operands, Target, TargetFeatures, TargetPlatform, CallingConvention};
 pub use windows::WindowsX64Target;
-pub use linux::LinuxX64Target;
+pub use system_v::SystemVX64Target;
 pub use macos::MacOSX64Target;
 pub use macos_arm64::MacOSArm64Target;
+pub use registry::{TargetFactory, TargetRegistry};
 
-/// Factory function to create target instances
-pub fn create_target(platform: TargetPlatform) -> Box<dyn Target> {
-    match platform {
-        TargetPlatform::WindowsX64 => Box::new(WindowsX64Target),
-        TargetPlatform::LinuxX64 => Box::new(LinuxX64Target),
-        TargetPlatform::MacOSX64 => Box::new(MacOSX64Target),
-        TargetPlatform::MacOSArm64 => Box::new(MacOSArm64Target),
-    }
+/// Factory function to create built-in target instances.
+///
+/// A `TargetPlatform::Custom` target is an error here; create those from
+/// the `TargetRegistry` they were registered in.
+pub fn create_target(platform: TargetPlatform) -> Result<Box<dyn Target>, String> {
+    TargetRegistry::default()
+        .create(platform)
+        .ok_or_else(|| format!("{:?} is not a built-in target; create it from the registry it was registered in", platform))
 }
 
 /// Helper function to parse target platform from string
 pub fn parse_target_platform(target_str: &str) -> Result<TargetPlatform, String> {
     match target_str.to_lowercase().as_str() {
-        "windows" | "win" | "windows-x64" | "win64" => Ok(TargetPlatform::WindowsX64),
-        "linux" | "linux-x64" | "linux64" => Ok(TargetPlatform::LinuxX64),
-        "macos" | "darwin" | "macos-x64" | "darwin-x64" => Ok(TargetPlatform::MacOSX64),
-        "macos-arm64" | "darwin-arm64" | "macos-aarch64" | "darwin-aarch64" => Ok(TargetPlatform::MacOSArm64),
         "macos-auto" | "darwin-auto" => detect_macos_architecture(),
-        _ => Err(format!("Unknown target platform: {}", target_str)),
+        _ => TargetRegistry::default()
+            .platform_for(target_str)
+            .ok_or_else(|| format!("Unknown target platform: {}", target_str)),
     }
 }
 
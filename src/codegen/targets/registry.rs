@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+
+use super::base::{Target, TargetPlatform};
+use super::{freebsd, MacOSArm64Target, MacOSX64Target, SystemVX64Target, WindowsX64Target};
+
+/// Constructor for a target implementation
+pub type TargetFactory = fn() -> Box<dyn Target>;
+
+/// Maps target platforms and their command-line names to target factories.
+///
+/// `TargetRegistry::default()` knows every built-in target; downstream
+/// users can `register` further targets without touching the factory.
+pub struct TargetRegistry {
+    factories: HashMap<TargetPlatform, TargetFactory>,
+    names: HashMap<String, TargetPlatform>,
+}
+
+impl TargetRegistry {
+    /// An empty registry
+    pub fn new() -> Self {
+        Self {
+            factories: HashMap::new(),
+            names: HashMap::new(),
+        }
+    }
+
+    /// Register `factory` for `platform`, selectable by any of `names`.
+    /// Registering a platform or name again replaces the earlier entry.
+    pub fn register(&mut self, platform: TargetPlatform, names: &[&str], factory: TargetFactory) {
+        self.factories.insert(platform, factory);
+        for name in names {
+            self.names.insert(name.to_lowercase(), platform);
+        }
+    }
+
+    /// Look up the platform registered under `name` (case-insensitive)
+    pub fn platform_for(&self, name: &str) -> Option<TargetPlatform> {
+        self.names.get(&name.to_lowercase()).copied()
+    }
+
+    /// Create the target registered for `platform`
+    pub fn create(&self, platform: TargetPlatform) -> Option<Box<dyn Target>> {
+        self.factories.get(&platform).map(|factory| factory())
+    }
+
+    /// Create the target registered under `name`
+    pub fn create_by_name(&self, name: &str) -> Result<Box<dyn Target>, String> {
+        self.platform_for(name)
+            .and_then(|platform| self.create(platform))
+            .ok_or_else(|| format!("Unknown target platform: {}", name))
+    }
+}
+
+impl Default for TargetRegistry {
+    fn default() -> Self {
+        let mut registry = Self::new();
+        registry.register(TargetPlatform::WindowsX64, &["windows", "win", "windows-x64", "win64"], || Box::new(WindowsX64Target));
+        registry.register(TargetPlatform::LinuxX64, &["linux", "linux-x64", "linux64"], || Box::new(SystemVX64Target::linux()));
+        registry.register(TargetPlatform::MacOSX64, &["macos", "darwin", "macos-x64", "darwin-x64"], || Box::new(MacOSX64Target));
+        registry.register(
+            TargetPlatform::MacOSArm64,
+            &["macos-arm64", "darwin-arm64", "macos-aarch64", "darwin-aarch64"],
+            || Box::new(MacOSArm64Target),
+        );
+        freebsd::register(&mut registry);
+        registry
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codegen::targets::CallingConvention;
+    use crate::codegen::Register;
+
+    /// A toy target that borrows everything from Linux but its identity
+    struct ToyTarget;
+
+    impl Target for ToyTarget {
+        fn platform(&self) -> TargetPlatform { TargetPlatform::Custom("toy") }
+        fn calling_convention(&self) -> CallingConvention { CallingConvention::SystemV }
+        fn arch_name(&self) -> &'static str { "toy" }
+        fn calling_convention_name(&self) -> &'static str { SystemVX64Target::linux().calling_convention_name() }
+        fn assembly_directives(&self) -> Vec<String> { SystemVX64Target::linux().assembly_directives() }
+        fn data_section_header(&self) -> String { SystemVX64Target::linux().data_section_header() }
+        fn text_section_header(&self) -> String { SystemVX64Target::linux().text_section_header() }
+        fn external_declarations(&self) -> Vec<String> { SystemVX64Target::linux().external_declarations() }
+        fn global_declarations(&self, symbols: &[&str]) -> Vec<String> { SystemVX64Target::linux().global_declarations(symbols) }
+        fn function_prologue(&self) -> Vec<String> { SystemVX64Target::linux().function_prologue() }
+        fn function_epilogue(&self) -> Vec<String> { SystemVX64Target::linux().function_epilogue() }
+        fn parameter_registers(&self) -> Vec<Register> { SystemVX64Target::linux().parameter_registers() }
+        fn return_register(&self) -> Register { Register::Rax }
+        fn stack_pointer(&self) -> Register { Register::Rsp }
+        fn base_pointer(&self) -> Register { Register::Rbp }
+        fn stack_alignment(&self) -> usize { 16 }
+        fn format_string_literal(&self, label: &str, content: &str) -> String { SystemVX64Target::linux().format_string_literal(label, content) }
+        fn format_function_call(&self, function_name: &str) -> Vec<String> { SystemVX64Target::linux().format_function_call(function_name) }
+        fn format_function_name(&self, function_name: &str) -> String { SystemVX64Target::linux().format_function_name(function_name) }
+        fn type_info(&self, type_name: &str) -> (usize, usize) { SystemVX64Target::linux().type_info(type_name) }
+        fn startup_code(&self) -> Vec<String> { Vec::new() }
+    }
+
+    #[test]
+    fn test_custom_target_created_by_name() {
+        let mut registry = TargetRegistry::default();
+        registry.register(TargetPlatform::Custom("toy"), &["toy", "toy-os"], || Box::new(ToyTarget));
+
+        let target = registry.create_by_name("Toy-OS").unwrap();
+        assert_eq!(target.platform(), TargetPlatform::Custom("toy"));
+        assert_eq!(target.arch_name(), "toy");
+        assert!(registry.create_by_name("linux").is_ok());
+    }
+
+    #[test]
+    fn test_freebsd_registered_by_default() {
+        let registry = TargetRegistry::default();
+        assert_eq!(registry.platform_for("freebsd"), Some(TargetPlatform::FreeBSDX64));
+
+        let target = registry.create(TargetPlatform::FreeBSDX64).unwrap();
+        assert_eq!(target.calling_convention(), CallingConvention::SystemV);
+        assert!(target.startup_code().iter().any(|line| line.starts_with("mov rax, 1 ")));
+        assert_eq!(target.arch_name(), "x86-64 FreeBSD");
+    }
+
+    #[test]
+    fn test_unknown_name_error() {
+        assert!(TargetRegistry::new().create_by_name("linux").is_err());
+    }
+
+    #[test]
+    fn test_custom_platform_is_not_built_in() {
+        assert!(crate::codegen::targets::create_target(TargetPlatform::Custom("toy")).is_err());
+        assert!(crate::codegen::targets::create_target(TargetPlatform::LinuxX64).is_ok());
+    }
+}
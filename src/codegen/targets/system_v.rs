@@ -1,12 +1,29 @@
-use super::base::{Target, TargetPlatform, CallingConvention};
+use super::base::{db_string_operands, Target, TargetPlatform, CallingConvention};
 use crate::codegen::Register;
 
-/// Linux x64 target implementation
-pub struct LinuxX64Target;
+/// x86-64 target for an operating system following the System V ABI.
+/// Linux and FreeBSD share everything but their names and the number of
+/// the exit syscall the entry point makes.
+pub struct SystemVX64Target {
+    platform: TargetPlatform,
+    arch_name: &'static str,
+    os_name: &'static str,
+    exit_syscall: u32,
+}
 
-impl Target for LinuxX64Target {
+impl SystemVX64Target {
+    pub const fn linux() -> Self {
+        Self { platform: TargetPlatform::LinuxX64, arch_name: "x86-64 Linux", os_name: "Linux", exit_syscall: 60 }
+    }
+
+    pub const fn freebsd() -> Self {
+        Self { platform: TargetPlatform::FreeBSDX64, arch_name: "x86-64 FreeBSD", os_name: "FreeBSD", exit_syscall: 1 }
+    }
+}
+
+impl Target for SystemVX64Target {
     fn platform(&self) -> TargetPlatform {
-        TargetPlatform::LinuxX64
+        self.platform
     }
     
     fn calling_convention(&self) -> CallingConvention {
@@ -14,7 +31,7 @@ impl Target for LinuxX64Target {
     }
     
     fn arch_name(&self) -> &'static str {
-        "x86-64 Linux"
+        self.arch_name
     }
     
     fn calling_convention_name(&self) -> &'static str {
@@ -84,8 +101,7 @@ impl Target for LinuxX64Target {
     }
     
     fn format_string_literal(&self, label: &str, content: &str) -> String {
-        let formatted_content = content.replace('\n', "").replace("%f", "%.2f");
-        format!("    {}: db \"{}\", 10, 0", label, formatted_content)
+        format!("{}: db {}", label, db_string_operands(content))
     }
     
     fn format_function_call(&self, function_name: &str) -> Vec<String> {
@@ -110,12 +126,12 @@ impl Target for LinuxX64Target {
     fn startup_code(&self) -> Vec<String> {
         vec![
             "_start:".to_string(),
-            "    ; Linux entry point".to_string(),
-            "    call main".to_string(),
-            "    ; Exit with return value from main".to_string(),
-            "    mov rdi, rax    ; exit code".to_string(),
-            "    mov rax, 60     ; sys_exit".to_string(),
-            "    syscall".to_string(),
+            format!("; {} entry point", self.os_name),
+            "call main".to_string(),
+            "; Exit with return value from main".to_string(),
+            "mov edi, eax    ; exit code".to_string(),
+            format!("mov rax, {:<7}; sys_exit", self.exit_syscall),
+            "syscall".to_string(),
         ]
     }
 }
\ No newline at end of file
@@ -1,6 +1,10 @@
-use super::base::{Target, TargetPlatform, CallingConvention};
+use super::base::{db_string_operands, Target, TargetPlatform, CallingConvention};
 use crate::codegen::Register;
 
+/// Frames larger than one page must be probed, since Windows only grows
+/// the stack when the guard page just below it is touched
+pub const STACK_PROBE_THRESHOLD: usize = 4096;
+
 /// Windows x64 target implementation
 pub struct WindowsX64Target;
 
@@ -40,6 +44,7 @@ impl Target for WindowsX64Target {
         vec![
             "extern printf".to_string(),
             "extern exit".to_string(),
+            "extern __chkstk".to_string(),
         ]
     }
     
@@ -84,8 +89,7 @@ impl Target for WindowsX64Target {
     }
     
     fn format_string_literal(&self, label: &str, content: &str) -> String {
-        let formatted_content = content.replace('\n', "").replace("%f", "%.2f");
-        format!("    {}: db \"{}\", 10, 0", label, formatted_content)
+        format!("{}: db {}", label, db_string_operands(content))
     }
     
     fn format_function_call(&self, function_name: &str) -> Vec<String> {
@@ -110,4 +114,15 @@ impl Target for WindowsX64Target {
     fn startup_code(&self) -> Vec<String> {
         vec![] // Windows doesn't need special startup code for our use case
     }
+
+    fn stack_probe(&self, frame_size: usize) -> Vec<String> {
+        if frame_size <= STACK_PROBE_THRESHOLD {
+            return Vec::new();
+        }
+        // __chkstk takes the size in rax and touches every page of it
+        vec![
+            format!("mov rax, {}", frame_size),
+            "call __chkstk".to_string(),
+        ]
+    }
 }
\ No newline at end of file
@@ -18,24 +18,24 @@ impl InstructionFormatter {
         }
     }
     
-    /// Format an instruction with size and operands
+    /// Format an instruction with size and operands. NASM takes the size
+    /// on the memory operand it describes rather than as a mnemonic
+    /// suffix, so extending moves come out as `movzx eax, byte [rbp-1]`.
     pub fn format_instruction_with_size(instr: &Instruction, size: &Size, operands: &[Operand]) -> String {
         let instr_str = instr.to_string();
-        let size_suffix = match size {
-            Size::Byte => "b",
-            Size::Word => "w", 
-            Size::Dword => "d",
-            Size::Qword => "q",
-        };
-        
         if operands.is_empty() {
-            format!("{}{}", instr_str, size_suffix)
+            instr_str.to_string()
         } else {
             let operands_str = operands.iter()
-                .map(|op| Self::format_operand(op))
+                .enumerate()
+                .map(|(i, op)| if i == instr.sized_operand() && op.is_memory() {
+                    format!("{} {}", size, Self::format_operand(op))
+                } else {
+                    Self::format_operand(op)
+                })
                 .collect::<Vec<_>>()
                 .join(", ");
-            format!("{}{} {}", instr_str, size_suffix, operands_str)
+            format!("{} {}", instr_str, operands_str)
         }
     }
     
@@ -74,4 +74,30 @@ impl InstructionFormatter {
             "ret".to_string(),
         ]
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codegen::core::Register;
+    use crate::codegen::encoder::{parse_assembly, AsmItem, Encoder};
+
+    #[test]
+    fn test_extending_move_sizes_its_source() {
+        let source = Operand::Memory { base: Register::Rbp, offset: -1 };
+        let text = InstructionFormatter::format_instruction_with_size(
+            &Instruction::Movzx, &Size::Byte, &[Operand::Register(Register::Eax), source.clone()]);
+        assert_eq!(text, "movzx eax, byte [rbp-1]");
+
+        // It reads back as a byte-to-dword extension, which the assembler encodes
+        let items = parse_assembly(&text).unwrap();
+        let [AsmItem::Instruction { instruction: Instruction::Movzx, size: Some(Size::Byte), operands }] = items.as_slice() else {
+            panic!("{:?}", items);
+        };
+        assert!(Encoder::new().encode(Instruction::Movzx, operands, Some(Size::Byte)).is_ok());
+
+        let store = InstructionFormatter::format_instruction_with_size(
+            &Instruction::Mov, &Size::Dword, &[source, Operand::Immediate(1)]);
+        assert_eq!(store, "mov dword [rbp-1], 1");
+    }
+}
@@ -1,57 +1,118 @@
 use std::collections::HashMap;
 use crate::codegen::core::Register;
+use crate::codegen::targets::CallingConvention;
 
 /// Simple register allocator for managing register assignments
 pub struct RegisterAllocator {
+    /// Order in which registers are handed out
+    order: Vec<Register>,
+    callee_saved: Vec<Register>,
     available_registers: Vec<Register>,
     allocated_registers: HashMap<String, Register>,
+    /// Callee-saved registers handed out so far, in the order first used
+    used_callee_saved: Vec<Register>,
 }
 
 impl RegisterAllocator {
+    /// Registers the rest of the backend never uses as scratch or for
+    /// passing arguments
+    pub const ALLOCATABLE: [Register; 6] = [
+        Register::Rbx, Register::R12, Register::R13, Register::R14, Register::R15, Register::R10,
+    ];
+
+    /// The callee-saved ones among them, handed out unless another order
+    /// is given, as they survive calls without spilling
+    pub const DEFAULT_ORDER: [Register; 5] = [
+        Register::Rbx, Register::R12, Register::R13, Register::R14, Register::R15,
+    ];
+
     pub fn new() -> Self {
+        Self::for_convention(CallingConvention::SystemV)
+    }
+
+    /// Allocate in the default order under `convention`
+    pub fn for_convention(convention: CallingConvention) -> Self {
+        Self::with_order(convention, Self::DEFAULT_ORDER.to_vec())
+    }
+
+    /// Hand out the registers of `order` first to last. Registers outside
+    /// `ALLOCATABLE` are dropped, as the backend may clobber them at any
+    /// instruction.
+    pub fn with_order(convention: CallingConvention, order: Vec<Register>) -> Self {
+        let mut allocatable = Vec::new();
+        for register in order.into_iter().map(|register| register.full()) {
+            if Self::ALLOCATABLE.contains(&register) && !allocatable.contains(&register) {
+                allocatable.push(register);
+            }
+        }
         Self {
-            available_registers: vec![
-                Register::Rax, Register::Rcx, Register::Rdx, Register::R8, Register::R9,
-            ],
+            available_registers: allocatable.clone(),
+            order: allocatable,
+            callee_saved: convention.callee_saved_registers(),
             allocated_registers: HashMap::new(),
+            used_callee_saved: Vec::new(),
         }
     }
-    
+
     /// Allocate a register for a variable
     pub fn allocate(&mut self, var_name: String) -> Option<Register> {
-        if let Some(reg) = self.available_registers.pop() {
-            self.allocated_registers.insert(var_name, reg);
-            Some(reg)
-        } else {
-            None // Need to spill to memory
+        if self.available_registers.is_empty() {
+            return None; // Need to spill to memory
         }
+        let reg = self.available_registers.remove(0);
+        if self.is_callee_saved(reg) && !self.used_callee_saved.contains(&reg) {
+            self.used_callee_saved.push(reg);
+        }
+        self.allocated_registers.insert(var_name, reg);
+        Some(reg)
     }
-    
+
     /// Free a register from a variable
     pub fn free(&mut self, var_name: &str) -> Option<Register> {
-        if let Some(reg) = self.allocated_registers.remove(var_name) {
-            self.available_registers.push(reg);
-            Some(reg)
-        } else {
-            None
-        }
+        let reg = self.allocated_registers.remove(var_name)?;
+        // Keep the free registers in order of preference
+        let rank = |register: &Register| self.order.iter().position(|candidate| candidate == register);
+        let position = self.available_registers.iter()
+            .position(|register| rank(register) > rank(&reg))
+            .unwrap_or(self.available_registers.len());
+        self.available_registers.insert(position, reg);
+        Some(reg)
     }
-    
+
     /// Get the register assigned to a variable
     pub fn get_register(&self, var_name: &str) -> Option<Register> {
         self.allocated_registers.get(var_name).copied()
     }
-    
+
     /// Check if a register is available
     pub fn is_available(&self, reg: Register) -> bool {
         self.available_registers.contains(&reg)
     }
-    
+
+    /// Whether a callee has to hand `reg` back unchanged
+    pub fn is_callee_saved(&self, reg: Register) -> bool {
+        self.callee_saved.contains(&reg.full())
+    }
+
+    /// Callee-saved registers handed out at any point, which the function
+    /// saves in its prologue and restores in its epilogue
+    pub fn used_callee_saved(&self) -> &[Register] {
+        &self.used_callee_saved
+    }
+
+    /// Allocated registers a call may overwrite, which have to be saved
+    /// around each call while their variables are live
+    pub fn allocated_caller_saved(&self) -> Vec<Register> {
+        self.order.iter().copied()
+            .filter(|reg| !self.is_callee_saved(*reg) && !self.is_available(*reg))
+            .collect()
+    }
+
     /// Get all allocated registers
     pub fn allocated_registers(&self) -> &HashMap<String, Register> {
         &self.allocated_registers
     }
-    
+
     /// Get all available registers
     pub fn available_registers(&self) -> &[Register] {
         &self.available_registers
@@ -62,4 +123,35 @@ impl Default for RegisterAllocator {
     fn default() -> Self {
         Self::new()
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_callee_saved_registers_are_handed_out_first() {
+        let mut allocator = RegisterAllocator::new();
+        assert_eq!(allocator.allocate("a".to_string()), Some(Register::Rbx));
+        assert_eq!(allocator.allocate("b".to_string()), Some(Register::R12));
+        assert_eq!(allocator.used_callee_saved(), &[Register::Rbx, Register::R12]);
+        assert!(allocator.allocated_caller_saved().is_empty());
+
+        allocator.free("a");
+        assert_eq!(allocator.allocate("c".to_string()), Some(Register::Rbx));
+        assert_eq!(allocator.used_callee_saved(), &[Register::Rbx, Register::R12]);
+    }
+
+    #[test]
+    fn test_configured_order_skips_scratch_registers() {
+        let order = vec![Register::R10, Register::Rax, Register::R12, Register::Rdi];
+        let mut allocator = RegisterAllocator::with_order(CallingConvention::MicrosoftX64, order);
+        assert_eq!(allocator.available_registers(), &[Register::R10, Register::R12]);
+
+        assert_eq!(allocator.allocate("a".to_string()), Some(Register::R10));
+        assert_eq!(allocator.allocated_caller_saved(), vec![Register::R10]);
+        assert!(allocator.used_callee_saved().is_empty());
+        assert_eq!(allocator.allocate("b".to_string()), Some(Register::R12));
+        assert_eq!(allocator.allocate("c".to_string()), None);
+    }
+}
@@ -17,26 +17,17 @@ impl StackManager {
         }
     }
     
-    /// Calculate stack offset for a variable type
+    /// Calculate stack offset for a variable type. Every type here is
+    /// aligned to its own size, so the slot's start is rounded down to it.
     pub fn calculate_stack_offset(var_type: &TokenType, current_offset: i32) -> (usize, i32) {
-        match var_type {
-            TokenType::Int => {
-                let new_offset = current_offset - 4;
-                (4, new_offset)
-            },
-            TokenType::FloatType => {
-                let new_offset = current_offset - 8;
-                (8, new_offset)
-            },
-            TokenType::CharType => {
-                let new_offset = current_offset - 1;
-                (1, new_offset)
-            },
-            _ => {
-                let new_offset = current_offset - 8;
-                (8, new_offset)
-            }
-        }
+        let size = match var_type {
+            TokenType::Int => 4,
+            TokenType::CharType | TokenType::BoolType => 1,
+            _ => 8,
+        };
+        let step = size as i32;
+        let new_offset = current_offset - step;
+        (size, new_offset - new_offset.rem_euclid(step))
     }
     
     /// Allocate space for a variable on the stack
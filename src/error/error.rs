@@ -1,5 +1,7 @@
 use std::fmt;
 
+use super::style::Style;
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Span {
     pub start: usize,
@@ -68,7 +70,8 @@ impl Suggestion {
 pub struct CompilerError {
     pub kind: ErrorKind,
     pub span: Span,
-    pub source_context: Option<SourceContext>,
+    // Boxed to keep `Result<_, CompilerError>` small; most errors have none
+    pub source_context: Option<Box<SourceContext>>,
     pub suggestions: Vec<Suggestion>,
 }
 
@@ -163,7 +166,7 @@ impl CompilerError {
     }
     
     pub fn with_context(mut self, context: SourceContext) -> Self {
-        self.source_context = Some(context);
+        self.source_context = Some(Box::new(context));
         self
     }
     
@@ -204,6 +207,17 @@ impl CompilerError {
             Span::dummy()
         )
     }
+
+    /// What went wrong, without the kind of error it is
+    pub fn message(&self) -> String {
+        match &self.kind {
+            ErrorKind::Lexical(err) => err.to_string(),
+            ErrorKind::Syntactic(err) => err.to_string(),
+            ErrorKind::Semantic(err) => err.to_string(),
+            ErrorKind::Codegen(err) => err.to_string(),
+            ErrorKind::Io(msg) => msg.clone(),
+        }
+    }
 }
 
 impl fmt::Display for CompilerError {
@@ -297,16 +311,28 @@ impl ErrorReporter {
     }
     
     pub fn report(&self, error: &CompilerError) -> String {
+        let style = Style::new(self.show_colors);
+        self.render(&style.error("error"), &error.to_string(), error, style)
+    }
+
+    /// Report a problem that does not stop compilation, such as a
+    /// function that may fall off its end
+    pub fn report_warning(&self, warning: &CompilerError) -> String {
+        let style = Style::new(self.show_colors);
+        self.render(&style.warning("warning"), &warning.message(), warning, style)
+    }
+
+    fn render(&self, label: &str, message: &str, error: &CompilerError, style: Style) -> String {
         let mut output = String::new();
         
-        output.push_str(&format!("error: {}\n", error));
+        output.push_str(&format!("{}: {}\n", label, message));
         
         if let Some(context) = &error.source_context {
             output.push_str(&format!("  --> {}:{}:{}\n", 
                 context.filename, error.span.line, error.span.column));
             
             if self.show_context {
-                output.push_str(&self.format_source_context(context, &error.span));
+                output.push_str(&self.format_source_context(context, &error.span, style));
             }
         } else {
             output.push_str(&format!("  at line {}, column {}\n", 
@@ -314,16 +340,16 @@ impl ErrorReporter {
         }
         
         if !error.suggestions.is_empty() {
-            output.push_str("\nhelp:\n");
+            output.push_str(&format!("\n{}:\n", style.help("help")));
             for suggestion in &error.suggestions {
-                output.push_str(&format!("  {}\n", suggestion.message));
+                output.push_str(&format!("  {}\n", style.help(&suggestion.message)));
             }
         }
         
         output
     }
     
-    fn format_source_context(&self, context: &SourceContext, span: &Span) -> String {
+    fn format_source_context(&self, context: &SourceContext, span: &Span, style: Style) -> String {
         let mut output = String::new();
         let context_lines = context.get_context_lines(self.context_lines);
         
@@ -336,9 +362,9 @@ impl ErrorReporter {
                 for _ in 0..span.column.saturating_sub(1) {
                     output.push(' ');
                 }
-                for _ in span.start..span.end.min(span.start + line_content.len()) {
-                    output.push('^');
-                }
+                // Spans without an extent still get a single caret
+                let width = span.end.saturating_sub(span.start).clamp(1, line_content.len().max(1));
+                output.push_str(&style.caret(&"^".repeat(width)));
                 output.push('\n');
             }
         }
@@ -1,3 +1,5 @@
 pub mod error;
+pub mod style;
 
-pub use error::CompilerError;
\ No newline at end of file
+pub use error::CompilerError;
+pub use style::Style;
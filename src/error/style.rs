@@ -0,0 +1,56 @@
+/// ANSI styling for diagnostics. A disabled style returns text unchanged,
+/// so callers never need to branch on whether color is on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Style {
+    enabled: bool,
+}
+
+impl Style {
+    pub fn new(enabled: bool) -> Self {
+        Self { enabled }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Bold red, for error labels
+    pub fn error(&self, text: &str) -> String {
+        self.paint("1;31", text)
+    }
+
+    /// Bold yellow, for warning labels
+    pub fn warning(&self, text: &str) -> String {
+        self.paint("1;33", text)
+    }
+
+    /// Bold cyan, for suggestions
+    pub fn help(&self, text: &str) -> String {
+        self.paint("1;36", text)
+    }
+
+    /// Bold red, for the carets under the offending source
+    pub fn caret(&self, text: &str) -> String {
+        self.paint("1;31", text)
+    }
+
+    fn paint(&self, code: &str, text: &str) -> String {
+        if self.enabled {
+            format!("\x1b[{}m{}\x1b[0m", code, text)
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disabled_style_leaves_text_unchanged() {
+        assert_eq!(Style::new(false).error("error"), "error");
+        assert_eq!(Style::new(true).error("error"), "\x1b[1;31merror\x1b[0m");
+        assert_eq!(Style::new(true).help("help"), "\x1b[1;36mhelp\x1b[0m");
+    }
+}
@@ -1,8 +1,10 @@
 use crate::parser::ast::{Expr, Stmt, Parameter};
+use crate::parser::statement_summary;
 use crate::lexer::TokenType;
-use crate::types::{Type, TypeChecker, TypeConstraint};
+use crate::types::{Type, TypeChecker, TypeConstraint, TargetTypeConfig};
+use crate::semantic::{eval_const_expr, string_literal_char, Builtin, StackFrameManager};
 use super::ir::{IrProgram, IrFunction, IrInstruction, IrValue, IrType, IrBinaryOp, IrUnaryOp};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 #[derive(Debug, Clone)]
 pub enum IrGeneratorError {
@@ -11,6 +13,10 @@ pub enum IrGeneratorError {
     ComplexFunctionCallsNotSupported,
     InvalidBinaryOperator(TokenType),
     UnsupportedConstruct(String),
+    /// A source type with no IR representation
+    UnsupportedType(String),
+    /// A call to a function that is neither defined nor held in a local
+    UndefinedFunction(String),
 }
 
 /// IR Generator - converts AST to IR
@@ -22,12 +28,30 @@ pub struct IrGenerator {
     /// Current function being processed
     current_function: Option<IrFunction>,
     /// Global string constants
-    string_constants: HashMap<String, String>,
+    string_constants: Vec<(String, String)>, // (label, content) in creation order
     /// String label counter
     string_label_counter: usize,
+    /// Labels of the strings used as print formats. They are never shared
+    /// with other strings, as code generation rewrites their conversions.
+    format_labels: HashSet<String>,
+    /// Type of each parameter and local of the function, under the name
+    /// it is stored by: its IR name, or the label of a `static` local
     local_types: HashMap<String, IrType>,
+    /// Return type of every function in the program, for function references
+    function_types: HashMap<String, IrType>,
     type_checker: TypeChecker,
     loop_stack: Vec<LoopContext>,
+    /// Slots of the locals in scope, released when their block ends
+    frame: StackFrameManager,
+    /// Precede each statement's instructions with its source as a comment
+    source_comments: bool,
+    /// Global label of each `static` local in scope
+    static_locals: HashMap<String, String>,
+    /// IR name of each local in scope. A local reusing the name of an
+    /// earlier local or parameter of the function gets a fresh one.
+    local_names: HashMap<String, String>,
+    /// Storage of every `static` local: label, type and initial value
+    global_vars: Vec<(String, IrType, IrValue)>,
 }
 
 #[derive(Debug, Clone)]
@@ -36,42 +60,68 @@ struct LoopContext {
     break_label: String,
 }
 
+/// Names bound outside a scope, restored when it ends
+struct OuterScope {
+    static_locals: HashMap<String, String>,
+    local_names: HashMap<String, String>,
+}
+
 impl IrGenerator {
     pub fn new() -> Self {
         Self {
             temp_counter: 0,
             label_counter: 0,
             current_function: None,
-            string_constants: HashMap::new(),
+            string_constants: Vec::new(),
+            format_labels: HashSet::new(),
             string_label_counter: 0,
             local_types: HashMap::new(),
+            function_types: HashMap::new(),
             type_checker: TypeChecker::new(),
             loop_stack: Vec::new(),
+            frame: StackFrameManager::new(TargetTypeConfig::x86_64()),
+            source_comments: false,
+            static_locals: HashMap::new(),
+            local_names: HashMap::new(),
+            global_vars: Vec::new(),
         }
     }
 
+    /// Emit a `Comment` holding the source of every statement ahead of
+    /// its instructions, so dumped IR and assembly read alongside the code
+    pub fn with_source_comments(mut self, enabled: bool) -> Self {
+        self.source_comments = enabled;
+        self
+    }
+
     /// Generate IR from AST
     pub fn generate(&mut self, ast: &[Stmt]) -> Result<IrProgram, IrGeneratorError> {
-        // First pass: collect variable types for symbol table
-        self.collect_variable_types(ast);
+        // First pass: collect function and variable types for symbol table
+        for stmt in ast {
+            if let Stmt::Function { return_type, name, .. } = stmt {
+                let ir_type = Self::lower_type(return_type)?;
+                self.function_types.insert(name.clone(), ir_type);
+            }
+        }
         
         let mut functions = Vec::new();
 
         for stmt in ast {
-            if let Stmt::Function { return_type, name, type_parameters, parameters, body } = stmt {
-                let ir_function = self.generate_function(return_type, name, type_parameters, parameters, body)?;
+            if let Stmt::Function { is_static, return_type, name, type_parameters, parameters, body, .. } = stmt {
+                let mut ir_function = self.generate_function(return_type, name, type_parameters, parameters, body)?;
+                ir_function.is_static = *is_static;
                 functions.push(ir_function);
             }
         }
 
-        // Convert string constants to global strings
-        let global_strings = self.string_constants.iter()
-            .map(|(label, content)| (label.clone(), content.clone()))
-            .collect();
+        // String constants become global strings, in the order they
+        // were first used, so the same source always yields the same IR
+        let global_strings = std::mem::take(&mut self.string_constants);
 
         Ok(IrProgram {
             functions,
             global_strings,
+            global_vars: std::mem::take(&mut self.global_vars),
         })
     }
 
@@ -91,9 +141,18 @@ impl IrGenerator {
 
     /// Generate a string constant label
     fn get_string_label(&mut self, content: &str) -> String {
+        self.string_label(content, false)
+    }
+
+    /// Label of a string used as a print format
+    fn get_format_label(&mut self, content: &str) -> String {
+        self.string_label(content, true)
+    }
+
+    fn string_label(&mut self, content: &str, is_format: bool) -> String {
         // Check if we already have this string
         for (label, existing_content) in &self.string_constants {
-            if existing_content == content {
+            if existing_content == content && self.format_labels.contains(label) == is_format {
                 return label.clone();
             }
         }
@@ -101,7 +160,10 @@ impl IrGenerator {
         // Create new string label
         let label = format!("str_{}", self.string_label_counter);
         self.string_label_counter += 1;
-        self.string_constants.insert(label.clone(), content.to_string());
+        self.string_constants.push((label.clone(), content.to_string()));
+        if is_format {
+            self.format_labels.insert(label.clone());
+        }
         label
     }
 
@@ -112,75 +174,65 @@ impl IrGenerator {
         }
         
         // Convert parameters to IR format
-        let ir_parameters: Vec<(String, IrType)> = parameters.iter().map(|param| {
-            let ir_type = if let Some(token_type) = param.param_type.to_token_type() {
-                IrType::from(token_type)
-            } else {
-                IrType::Int // Default fallback
-            };
-            self.local_types.insert(param.name.clone(), ir_type.clone());
-            (param.name.clone(), ir_type)
-        }).collect();
+        self.local_names.clear();
+        self.local_types.clear();
+        let mut ir_parameters: Vec<(String, IrType)> = Vec::new();
+        for param in parameters {
+            let ir_type = Self::lower_type(&param.param_type)?;
+            let ir_name = if is_temp_name(&param.name) { format!("{}.1", param.name) } else { param.name.clone() };
+            self.local_types.insert(ir_name.clone(), ir_type.clone());
+            self.local_names.insert(param.name.clone(), ir_name.clone());
+            ir_parameters.push((ir_name, ir_type));
+        }
         
         let function = IrFunction {
             name: name.to_string(),
-            return_type: if let Some(token_type) = return_type.to_token_type() {
-                IrType::from(token_type)
-            } else {
-                IrType::Int // Default fallback
-            },
+            return_type: Self::lower_type(return_type)?,
             parameters: ir_parameters,
             instructions: Vec::new(),
             local_vars: Vec::new(),
+            local_offsets: HashMap::new(),
+            is_static: false,
+            register_hints: Vec::new(),
         };
 
         self.current_function = Some(function.clone());
+        self.frame.reset();
+        self.static_locals.clear();
 
         // Generate instructions for function body
         for stmt in body {
             self.generate_stmt(stmt)?;
         }
 
-        // Ensure function has a return if it doesn't already
-        if let Some(last_instruction) = self.current_function.as_ref().unwrap().instructions.last() {
-            if !matches!(last_instruction, IrInstruction::Return { .. }) {
-                if let Some(token_type) = return_type.to_token_type() {
-                    match token_type {
-                        TokenType::Void => {
-                        self.emit_instruction(IrInstruction::Return {
-                            value: None,
-                            var_type: IrType::Void,
-                        });
-                    }
-                        crate::lexer::TokenType::Int => {
-                        self.emit_instruction(IrInstruction::Return {
-                            value: Some(IrValue::IntConstant(0)),
-                            var_type: IrType::Int,
-                        });
-                    }
-                        _ => {
-                            self.emit_instruction(IrInstruction::Return {
-                                value: None,
-                                var_type: IrType::Int, // Default fallback
-                            });
-                        }
-                    }
-                } else {
-                    self.emit_instruction(IrInstruction::Return {
-                        value: None,
-                        var_type: IrType::Int, // Default fallback
-                    });
-                }
-            }
+        // Ensure function has a return if it doesn't already, including
+        // functions whose body produced no instructions at all
+        let current = self.current_function.as_ref().unwrap();
+        if !matches!(current.instructions.last(), Some(IrInstruction::Return { .. } | IrInstruction::Trap)) {
+            let return_ir_type = current.return_type.clone();
+            self.emit_instruction(IrInstruction::Return {
+                value: Self::default_return_value(&return_ir_type),
+                var_type: return_ir_type,
+            });
         }
 
-        Ok(self.current_function.take().unwrap_or_else(|| IrFunction {
-            name: name.to_string(),
-            return_type: IrType::from(return_type.to_token_type().unwrap_or(TokenType::Void)),
-            parameters: Vec::new(),
-            instructions: Vec::new(),
-            local_vars: Vec::new(),
-        }))
+        Ok(self.current_function.take().expect("function being generated"))
+    }
+
+    /// IR type of a source type, or an error if the IR cannot represent it
+    fn lower_type(source_type: &Type) -> Result<IrType, IrGeneratorError> {
+        IrType::try_from(source_type).map_err(IrGeneratorError::UnsupportedType)
+    }
+
+    /// Zero value returned when control reaches the end of a function
+    /// without an explicit return
+    fn default_return_value(return_type: &IrType) -> Option<IrValue> {
+        match return_type {
+            IrType::Void => None,
+            IrType::Float => Some(IrValue::FloatConstant(0.0)),
+            IrType::Char => Some(IrValue::CharConstant('\0')),
+            _ => Some(IrValue::IntConstant(0)),
+        }
     }
 
     /// Emit an instruction to the current function
@@ -192,40 +244,97 @@ impl IrGenerator {
 
     /// Generate IR for a statement
     fn generate_stmt(&mut self, stmt: &Stmt) -> Result<(), IrGeneratorError> {
+        if self.source_comments && !matches!(stmt, Stmt::Block(_)) {
+            self.emit_instruction(IrInstruction::Comment { text: statement_summary(stmt) });
+        }
         match stmt {
+            Stmt::VarDecl { var_type, name, initializer } if var_type.qualifiers.is_static => {
+                self.declare_static_local(var_type, name, initializer.as_ref())?;
+            }
+
             Stmt::VarDecl { var_type, name, initializer } => {
-                let ir_type = if let Some(token_type) = var_type.to_token_type() {
-                    IrType::from(token_type)
-                } else {
-                    IrType::Int // Default fallback
-                };
+                // A local of the same name hides an outer static one
+                self.static_locals.remove(name);
+                let ir_type = Self::lower_type(var_type)?;
+                if let Some(init_expr) = initializer {
+                    self.check_function_address(name, &ir_type, init_expr)?;
+                }
+                let local_name = self.declare_local_name(name);
+                self.local_types.insert(local_name.clone(), ir_type.clone());
                 
                 // Emit variable allocation
                 self.emit_instruction(IrInstruction::Alloca {
                     var_type: ir_type.clone(),
-                    name: name.clone(),
+                    name: local_name.clone(),
                 });
 
-                // Add to local variables
+                // Add to local variables, in a slot free for the rest of its scope
+                let layout = self.frame.allocate_slot(local_name.clone(), ir_type.size(), ir_type.alignment());
                 if let Some(ref mut function) = self.current_function {
-                    function.local_vars.push((name.clone(), ir_type.clone()));
+                    function.local_vars.push((local_name.clone(), ir_type.clone()));
+                    function.local_offsets.insert(local_name.clone(), layout.offset);
+                    if var_type.qualifiers.is_register {
+                        function.register_hints.push(local_name.clone());
+                    }
                 }
 
                 // Handle initialization
-                if let Some(init_expr) = initializer {
-                    let init_value = self.generate_expr(init_expr);
+                if let IrType::Array(element, count) = &ir_type {
+                    match initializer {
+                        None => {}
+                        Some(Expr::String(text)) if **element == IrType::Char => {
+                            // The bytes, then NUL in every element left over
+                            let bytes = text.bytes().chain(std::iter::repeat(0)).take(*count);
+                            for (index, byte) in bytes.enumerate() {
+                                self.emit_instruction(IrInstruction::StoreElement {
+                                    value: IrValue::CharConstant(byte as char),
+                                    array: IrValue::Local(local_name.clone()),
+                                    index: IrValue::IntConstant(index as i64),
+                                    var_type: IrType::Char,
+                                });
+                            }
+                        }
+                        Some(_) => return Err(IrGeneratorError::UnsupportedConstruct(
+                            format!("array '{}' can only be initialized from a string literal", name)
+                        )),
+                    }
+                } else if let Some(init_expr) = initializer {
+                    let init_type = self.infer_expr_type(init_expr);
+                    let init_value = self.generate_expr(init_expr)?;
+                    let init_value = self.widen_bool(init_value, &init_type, &ir_type);
                     self.emit_instruction(IrInstruction::Store {
                         value: init_value,
-                        dest: IrValue::Local(name.clone()),
+                        dest: self.variable(name),
                         var_type: ir_type,
                     });
                 }
             }
 
-            Stmt::Return(expr_opt) => {
+            Stmt::Return(expr_opt, _) => {
                 if let Some(expr) = expr_opt {
-                    let value = self.generate_expr(expr);
-                    let return_type = self.infer_expr_type(expr);
+                    let value = self.generate_integer_context_expr(expr)?;
+                    let value_type = match self.infer_expr_type(expr) {
+                        IrType::Bool => IrType::Int,
+                        other => other,
+                    };
+                    let declared_type = self.current_function.as_ref()
+                        .map(|function| function.return_type.clone())
+                        .unwrap_or_else(|| value_type.clone());
+                    // Convert to the declared type, so a `float` function
+                    // returning `5` hands back 5.0 in xmm0
+                    let (value, return_type) = if value_type != declared_type
+                        && value_type.is_arithmetic() && declared_type.is_arithmetic() {
+                        let temp = self.new_temp();
+                        self.emit_instruction(IrInstruction::Cast {
+                            dest: temp.clone(),
+                            src: value,
+                            dest_type: declared_type.clone(),
+                            src_type: value_type,
+                        });
+                        (temp, declared_type)
+                    } else {
+                        (value, value_type)
+                    };
                     self.emit_instruction(IrInstruction::Return {
                         value: Some(value),
                         var_type: return_type,
@@ -238,106 +347,48 @@ impl IrGenerator {
                 }
             }
 
-            Stmt::ExprStmt(expr) => {
-                self.generate_expr(expr);
-            }
+            Stmt::ExprStmt(expr) => self.generate_discarded_expr(expr)?,
 
             Stmt::Block(stmts) => {
-                for stmt in stmts {
-                    self.generate_stmt(stmt)?;
-                }
+                self.generate_block(stmts)?;
             }
 
-            Stmt::If { condition, then_branch } => {
-                let condition_value = self.generate_expr(condition);
+            Stmt::If { condition, then_branch, else_branch } => {
                 let then_label = self.new_label("if_then");
                 let end_label = self.new_label("if_end");
+                let else_label = else_branch.as_ref().map(|_| self.new_label("if_else"));
 
                 // Branch based on condition
-                self.emit_instruction(IrInstruction::Branch {
-                    condition: condition_value,
-                    true_label: then_label.clone(),
-                    false_label: end_label.clone(),
-                });
+                self.generate_condition_branch(condition, &then_label, else_label.as_ref().unwrap_or(&end_label))?;
 
                 // Then branch
                 self.emit_instruction(IrInstruction::Label {
                     name: then_label,
                 });
-                for stmt in then_branch {
-                    self.generate_stmt(stmt)?;
-                }
+                self.generate_block(then_branch)?;
                 self.emit_instruction(IrInstruction::Jump {
                     label: end_label.clone(),
                 });
 
+                // Else branch, reached when the condition is false
+                if let (Some(else_label), Some(else_branch)) = (else_label, else_branch) {
+                    self.emit_instruction(IrInstruction::Label {
+                        name: else_label,
+                    });
+                    self.generate_block(else_branch)?;
+                    self.emit_instruction(IrInstruction::Jump {
+                        label: end_label.clone(),
+                    });
+                }
+
                 // End label
                 self.emit_instruction(IrInstruction::Label {
                     name: end_label,
                 });
             }
 
-            Stmt::PrintStmt { format_string, args } => {
-                // Handle format string generation similar to direct AST path
-                if let Expr::String(s) = format_string {
-                    if s.is_empty() && args.len() == 1 {
-                        let arg = &args[0];
-                        let format_str = match arg {
-                            Expr::Integer(_) => "%d\n",
-                            Expr::Float(_) => "%.6f\n", 
-                            Expr::Char(_) => "%c\n",
-                            Expr::Identifier(var_name) => {
-                                // Use type inference for variables
-                                let var_type = self.infer_identifier_type(var_name);
-                                match var_type {
-                                    IrType::Int => "%d\n",
-                                    IrType::Float => "%.6f\n",
-                                    IrType::Char => "%c\n",
-                                    _ => "%d\n", // Default to integer
-                                }
-                            }
-                            _ => "%d\n", // Default to integer format
-                        };
-                        
-                        // Create string constant for the generated format string
-                        let format_label = self.get_string_label(format_str);
-                        let format_value = IrValue::StringConstant(format_label);
-                        
-                        let mut arg_values = Vec::new();
-                        for arg in args {
-                            arg_values.push(self.generate_expr(arg));
-                        }
-
-                        self.emit_instruction(IrInstruction::Print {
-                            format_string: format_value,
-                            args: arg_values,
-                        });
-                    } else {
-                        let format_value = self.generate_expr(format_string);
-                        let mut arg_values = Vec::new();
-                        
-                        for arg in args {
-                            arg_values.push(self.generate_expr(arg));
-                        }
-
-                        self.emit_instruction(IrInstruction::Print {
-                            format_string: format_value,
-                            args: arg_values,
-                        });
-                    }
-                } else {
-                    let format_value = self.generate_expr(format_string);
-                    let mut arg_values = Vec::new();
-                    
-                    for arg in args {
-                        arg_values.push(self.generate_expr(arg));
-                    }
-
-                    self.emit_instruction(IrInstruction::Print {
-                        format_string: format_value,
-                        args: arg_values,
-                    });
-                }
+            Stmt::PrintStmt { format_string, args, newline } => {
+                self.generate_print(format_string, args, *newline)?;
             }
 
             Stmt::Function { .. } => {
@@ -348,7 +399,9 @@ impl IrGenerator {
             Stmt::While { condition, body } => {
                 let loop_start = self.new_label("loop_start");
                 let loop_end = self.new_label("loop_end");
+                let body_label = self.new_label("loop_body");
                 
+                // `continue` goes back to the condition, not to the body
                 self.loop_stack.push(LoopContext {
                     continue_label: loop_start.clone(),
                     break_label: loop_end.clone(),
@@ -357,21 +410,11 @@ impl IrGenerator {
                 self.emit_instruction(IrInstruction::Label { name: loop_start.clone() });
                 
                 // Evaluate condition
-                let condition_val = self.generate_expr(condition);
-                
-                self.emit_instruction(IrInstruction::Branch {
-                    condition: condition_val,
-                    true_label: format!("loop_body_{}", self.label_counter - 1),
-                    false_label: loop_end.clone(),
-                });
-                
-                let body_label = format!("loop_body_{}", self.label_counter - 1);
+                self.generate_loop_condition(condition, &body_label, &loop_end)?;
                 self.emit_instruction(IrInstruction::Label { name: body_label });
                 
                 // Generate body
-                for stmt in body {
-                    self.generate_stmt(stmt)?;
-                }
+                self.generate_block(body)?;
                 
                 self.emit_instruction(IrInstruction::Jump { label: loop_start });
                 
@@ -381,14 +424,19 @@ impl IrGenerator {
             }
             
             Stmt::For { init, condition, update, body } => {
-                // Generate initialization if present
-                if let Some(init_stmt) = init {
+                // A variable declared in the initializer lives until the
+                // loop ends, and hides any outer one of its name until then
+                let outer_scope = self.enter_scope();
+
+                // Generate each initialization in order
+                for init_stmt in init {
                     self.generate_stmt(init_stmt)?;
                 }
                 
                 let loop_start = self.new_label("for_start");
                 let loop_continue = self.new_label("for_continue");
                 let loop_end = self.new_label("for_end");
+                let body_label = self.new_label("for_body");
                 
                 self.loop_stack.push(LoopContext {
                     continue_label: loop_continue.clone(),
@@ -397,28 +445,21 @@ impl IrGenerator {
                 
                 self.emit_instruction(IrInstruction::Label { name: loop_start.clone() });
                 
+                // A missing condition is always true: the body is entered
+                // unconditionally and only `break` leaves the loop
                 if let Some(cond) = condition {
-                    let condition_val = self.generate_expr(cond);
-                    self.emit_instruction(IrInstruction::Branch {
-                        condition: condition_val,
-                        true_label: format!("for_body_{}", self.label_counter - 2),
-                        false_label: loop_end.clone(),
-                    });
-                    
-                    let body_label = format!("for_body_{}", self.label_counter - 2);
+                    self.generate_loop_condition(cond, &body_label, &loop_end)?;
                     self.emit_instruction(IrInstruction::Label { name: body_label });
                 }
                 
                 // Generate body
-                for stmt in body {
-                    self.generate_stmt(stmt)?;
-                }
+                self.generate_block(body)?;
                 
                 self.emit_instruction(IrInstruction::Label { name: loop_continue });
                 
-                // Generate update expression if present
-                if let Some(update_expr) = update {
-                    self.generate_expr(update_expr);
+                // Generate each update expression in order
+                for update_expr in update {
+                    self.generate_discarded_expr(update_expr)?;
                 }
                 
                 self.emit_instruction(IrInstruction::Jump { label: loop_start });
@@ -426,6 +467,7 @@ impl IrGenerator {
                 self.emit_instruction(IrInstruction::Label { name: loop_end });
                 
                 self.loop_stack.pop();
+                self.exit_scope(outer_scope)?;
             }
             
             Stmt::Break => {
@@ -451,20 +493,386 @@ impl IrGenerator {
         Ok(())
     }
 
-    /// Generate IR for an expression, returning the value
-    fn generate_expr(&mut self, expr: &Expr) -> IrValue {
+    /// Generate the statements of a block in their own scope, so the slots
+    /// of its locals can be reused once it ends
+    fn generate_block(&mut self, stmts: &[Stmt]) -> Result<(), IrGeneratorError> {
+        let outer_scope = self.enter_scope();
+        for stmt in stmts {
+            self.generate_stmt(stmt)?;
+        }
+        self.exit_scope(outer_scope)
+    }
+
+    /// Open a scope for locals, returning what the enclosing scope binds
+    /// for `exit_scope` to restore
+    fn enter_scope(&mut self) -> OuterScope {
+        self.frame.enter_scope();
+        OuterScope {
+            static_locals: self.static_locals.clone(),
+            local_names: self.local_names.clone(),
+        }
+    }
+
+    /// Close the innermost scope, releasing the slots of its locals and
+    /// bringing back the names they hid
+    fn exit_scope(&mut self, outer: OuterScope) -> Result<(), IrGeneratorError> {
+        self.static_locals = outer.static_locals;
+        self.local_names = outer.local_names;
+        self.frame.exit_scope().map_err(IrGeneratorError::UnsupportedConstruct)?;
+        Ok(())
+    }
+
+    /// Generate an expression evaluated for its effect alone, as a
+    /// statement or a `for` update. Only there may builtins that produce
+    /// no value, such as `println` or `trap`, be called.
+    fn generate_discarded_expr(&mut self, expr: &Expr) -> Result<(), IrGeneratorError> {
+        match expr {
+            Expr::Call { callee, arguments, .. } if let Some(builtin) = Builtin::of_callee(callee)
+                && builtin != Builtin::Strlen => {
+                if builtin == Builtin::Trap {
+                    self.emit_instruction(IrInstruction::Trap);
+                } else {
+                    let (format_string, args) = Builtin::format_arguments(arguments);
+                    self.generate_print(&format_string, args, builtin.appends_newline())?;
+                }
+            }
+            _ => {
+                self.generate_expr(expr)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Only a function pointer variable may hold the address of a
+    /// function; storing one in `name`, of type `var_type`, is an error
+    /// otherwise
+    fn check_function_address(&self, name: &str, var_type: &IrType, value: &Expr) -> Result<(), IrGeneratorError> {
+        match self.function_reference(value) {
+            Some(function) if !var_type.is_function_pointer() => Err(IrGeneratorError::UnsupportedConstruct(format!(
+                "'{}' is declared '{}', so it cannot hold the address of function '{}'; declare it as '{} (*{})()'",
+                name, var_type.c_name(), function, self.function_types[function].c_name(), name
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Storage a variable name refers to: the global behind a `static`
+    /// local in scope, or the function's own slot
+    fn variable(&self, name: &str) -> IrValue {
+        match self.static_locals.get(name) {
+            Some(label) => IrValue::Global(label.clone()),
+            None => IrValue::Local(self.local_names.get(name).map_or(name, String::as_str).to_string()),
+        }
+    }
+
+    /// IR name for a new local called `name`: the name itself the first
+    /// time the function uses it, then `name.1`, `name.2` and so on, so
+    /// every declaration has a slot of its own even when it shadows or
+    /// follows another of the same name. Names spelled like a temp are
+    /// always suffixed, as the two would print alike.
+    fn declare_local_name(&mut self, name: &str) -> String {
+        let function = self.current_function.as_ref().expect("locals are declared inside a function");
+        let taken = |candidate: &str| is_temp_name(candidate) || function.parameters.iter()
+            .chain(&function.local_vars)
+            .any(|(existing, _)| existing == candidate);
+        let local_name = if taken(name) {
+            (1..).map(|n| format!("{}.{}", name, n)).find(|candidate| !taken(candidate)).expect("an unused name")
+        } else {
+            name.to_string()
+        };
+        self.local_names.insert(name.to_string(), local_name.clone());
+        local_name
+    }
+
+    /// Give a `static` local its own global, `function.static.name`, so
+    /// statics of the same name in different functions stay apart and
+    /// none can collide with the function's own labels. The
+    /// initializer is applied once, in the data section, so it must be a
+    /// constant; no instruction is emitted at the declaration.
+    fn declare_static_local(&mut self, var_type: &Type, name: &str, initializer: Option<&Expr>) -> Result<(), IrGeneratorError> {
+        let ir_type = Self::lower_type(var_type)?;
+        if matches!(ir_type, IrType::Array(..)) {
+            return Err(IrGeneratorError::UnsupportedConstruct(format!("static array '{}'", name)));
+        }
+        let initial_value = match initializer {
+            None => Self::default_return_value(&ir_type).unwrap_or(IrValue::IntConstant(0)),
+            Some(expr) => self.constant_initializer(expr).ok_or_else(|| IrGeneratorError::UnsupportedConstruct(
+                format!("static variable '{}' must be initialized with a constant", name)
+            ))?,
+        };
+        let initial_value = match (initial_value, &ir_type) {
+            (IrValue::IntConstant(value), IrType::Float) => IrValue::FloatConstant(value as f64),
+            (IrValue::FloatConstant(value), IrType::Int | IrType::Char | IrType::Bool) => IrValue::IntConstant(value as i64),
+            (value, _) => value,
+        };
+
+        let function_name = self.current_function.as_ref().map_or("", |function| function.name.as_str());
+        let base = format!("{}.static.{}", function_name, name);
+        let mut label = base.clone();
+        let mut suffix = 1;
+        while self.global_vars.iter().any(|(existing, _, _)| *existing == label) {
+            label = format!("{}.{}", base, suffix);
+            suffix += 1;
+        }
+
+        self.local_types.insert(label.clone(), ir_type.clone());
+        self.global_vars.push((label.clone(), ir_type, initial_value));
+        self.static_locals.insert(name.to_string(), label);
+        Ok(())
+    }
+
+    /// Value of an integer constant expression. The parser has replaced
+    /// the ones naming `const` locals by their values where a constant is
+    /// required.
+    fn constant_value(&self, expr: &Expr) -> Option<i64> {
+        eval_const_expr(expr)
+    }
+
+    /// Value of a constant initializer: an integer constant expression or
+    /// a float literal, negated or not
+    fn constant_initializer(&self, expr: &Expr) -> Option<IrValue> {
+        if let Some(value) = self.constant_value(expr) {
+            return Some(IrValue::IntConstant(value));
+        }
         match expr {
+            Expr::Integer(value) => Some(IrValue::IntConstant(*value)),
+            Expr::Float(value) => Some(IrValue::FloatConstant(*value)),
+            Expr::Char(value) => Some(IrValue::IntConstant(*value as i64)),
+            Expr::Bool(value) => Some(IrValue::IntConstant(*value as i64)),
+            Expr::Unary { operator: TokenType::Minus, operand } => match self.constant_initializer(operand)? {
+                IrValue::IntConstant(value) => Some(IrValue::IntConstant(value.wrapping_neg())),
+                IrValue::FloatConstant(value) => Some(IrValue::FloatConstant(-value)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Emit a branch on `condition`. Integer comparisons become a single
+    /// compare-and-branch instead of materializing a 0/1 value first.
+    /// `&&` and `||` branch on each operand in turn, so no boolean is
+    /// materialized for them.
+    fn generate_condition_branch(&mut self, condition: &Expr, true_label: &str, false_label: &str) -> Result<(), IrGeneratorError> {
+        if let Expr::Binary { left, operator: operator @ (TokenType::LogicalAnd | TokenType::LogicalOr), right } = condition {
+            let right_label = if *operator == TokenType::LogicalAnd {
+                let right_label = self.new_label("and_eval_right");
+                self.generate_condition_branch(left, &right_label, false_label)?;
+                right_label
+            } else {
+                let right_label = self.new_label("or_eval_right");
+                self.generate_condition_branch(left, true_label, &right_label)?;
+                right_label
+            };
+            self.emit_instruction(IrInstruction::Label { name: right_label });
+            return self.generate_condition_branch(right, true_label, false_label);
+        }
+
+        if let Expr::Binary { left, operator, right } = condition {
+            let operand_type = self.infer_expr_type(left);
+            let is_integer_comparison = IrBinaryOp::try_from_token(operator.clone())
+                .is_ok_and(|op| op.is_comparison())
+                && operand_type != IrType::Float
+                && self.infer_expr_type(right) != IrType::Float;
+
+            if is_integer_comparison {
+                let left_value = self.generate_integer_context_expr(left)?;
+                let right_value = self.generate_integer_context_expr(right)?;
+                self.emit_instruction(IrInstruction::CompareBranch {
+                    op: IrBinaryOp::from(operator.clone()),
+                    left: left_value,
+                    right: right_value,
+                    var_type: if operand_type == IrType::Bool { IrType::Int } else { operand_type },
+                    true_label: true_label.to_string(),
+                    false_label: false_label.to_string(),
+                });
+                return Ok(());
+            }
+        }
+
+        let condition_value = self.generate_expr(condition)?;
+        self.emit_instruction(IrInstruction::Branch {
+            condition: condition_value,
+            true_label: true_label.to_string(),
+            false_label: false_label.to_string(),
+        });
+        Ok(())
+    }
+
+    /// Branch to the body or the end of a loop. Conditions built from `&&`
+    /// and `||` branch directly; others are materialized and tested.
+    fn generate_loop_condition(&mut self, condition: &Expr, body_label: &str, end_label: &str) -> Result<(), IrGeneratorError> {
+        if matches!(condition, Expr::Binary { operator: TokenType::LogicalAnd | TokenType::LogicalOr, .. }) {
+            return self.generate_condition_branch(condition, body_label, end_label);
+        }
+
+        let condition_value = self.generate_expr(condition)?;
+        self.emit_instruction(IrInstruction::Branch {
+            condition: condition_value,
+            true_label: body_label.to_string(),
+            false_label: end_label.to_string(),
+        });
+        Ok(())
+    }
+
+    /// Generate `expr` for use where an integer is expected, widening a
+    /// `Bool` result to `Int`
+    fn generate_integer_context_expr(&mut self, expr: &Expr) -> Result<IrValue, IrGeneratorError> {
+        let expr_type = self.infer_expr_type(expr);
+        let value = self.generate_expr(expr)?;
+        Ok(self.widen_bool(value, &expr_type, &IrType::Int))
+    }
+
+    /// Emit a `Print`, for both print statements and calls to the print
+    /// builtins. An empty format string with a single argument prints it
+    /// with a conversion chosen from its type.
+    fn generate_print(&mut self, format_string: &Expr, args: &[Expr], newline: bool) -> Result<(), IrGeneratorError> {
+        let format_value = match format_string {
+            Expr::String(s) if s.is_empty() && args.len() == 1 => {
+                // Arrays are seen as pointers to their first element here
+                let conversion = match self.infer_expr_type(&args[0]) {
+                    IrType::Int | IrType::Bool => "%d",
+                    IrType::Float => "%f",
+                    IrType::Char => "%c",
+                    IrType::String => "%s",
+                    IrType::Pointer(element) if *element == IrType::Char => "%s",
+                    other => return Err(IrGeneratorError::UnsupportedConstruct(
+                        format!("printing a value of type {} without a format string", other.c_name())
+                    )),
+                };
+                IrValue::StringConstant(self.get_format_label(conversion))
+            }
+            Expr::String(s) => IrValue::StringConstant(self.get_format_label(s)),
+            other => self.generate_expr(other)?,
+        };
+
+        let mut arg_values = Vec::new();
+        for arg in args {
+            arg_values.push(self.generate_integer_context_expr(arg)?);
+        }
+
+        self.emit_instruction(IrInstruction::Print {
+            format_string: format_value,
+            args: arg_values,
+            newline,
+        });
+        Ok(())
+    }
+
+    /// Generate `expr[index]` for an array variable, or the character
+    /// there for a string literal indexed by a constant. Indexing anything
+    /// else is not supported yet.
+    fn generate_index_expr(&mut self, array: &Expr, index: &Expr) -> Result<IrValue, IrGeneratorError> {
+        if let Expr::String(text) = array {
+            // The parser has checked the index and folded it to its value
+            return match self.constant_value(index).and_then(|index| string_literal_char(text, index)) {
+                Some(c) => Ok(IrValue::CharConstant(c as u8 as char)),
+                None => Err(IrGeneratorError::UnsupportedConstruct(
+                    "a string literal indexed outside its bounds or by a non-constant".to_string()
+                )),
+            };
+        }
+        let Expr::Identifier(name) = array else {
+            return Err(IrGeneratorError::UnsupportedConstruct("indexing an expression other than an array variable".to_string()));
+        };
+        let IrType::Array(element, _) = self.infer_identifier_type(name) else {
+            return Err(IrGeneratorError::UnsupportedConstruct(format!("indexing '{}', which is not an array", name)));
+        };
+
+        let index_type = self.infer_expr_type(index);
+        let index = self.generate_expr(index)?;
+        let index = match (index, index_type) {
+            (IrValue::CharConstant(c), _) => IrValue::IntConstant(c as i64),
+            (index, IrType::Int) => index,
+            (index, index_type) => {
+                let temp = self.new_temp();
+                self.emit_instruction(IrInstruction::Cast {
+                    dest: temp.clone(),
+                    src: index,
+                    dest_type: IrType::Int,
+                    src_type: index_type,
+                });
+                temp
+            }
+        };
+
+        let temp = self.new_temp();
+        self.emit_instruction(IrInstruction::LoadElement {
+            dest: temp.clone(),
+            array: self.variable(name),
+            index,
+            var_type: *element,
+        });
+        Ok(temp)
+    }
+
+    /// Generate `expr` as a `Bool`, comparing non-bool values against zero
+    fn generate_bool_expr(&mut self, expr: &Expr) -> Result<IrValue, IrGeneratorError> {
+        let expr_type = self.infer_expr_type(expr);
+        let value = self.generate_expr(expr)?;
+        if expr_type == IrType::Bool {
+            return Ok(value);
+        }
+
+        let temp = self.new_temp();
+        self.emit_instruction(IrInstruction::BinaryOp {
+            dest: temp.clone(),
+            op: IrBinaryOp::Ne,
+            left: value,
+            right: IrValue::IntConstant(0),
+            var_type: expr_type,
+        });
+        Ok(temp)
+    }
+
+    /// Zero-extend a `Bool` value stored into a wider slot; other values
+    /// are returned unchanged
+    fn widen_bool(&mut self, value: IrValue, value_type: &IrType, target_type: &IrType) -> IrValue {
+        if *value_type != IrType::Bool || *target_type == IrType::Bool {
+            return value;
+        }
+
+        let temp = self.new_temp();
+        self.emit_instruction(IrInstruction::Cast {
+            dest: temp.clone(),
+            src: value,
+            dest_type: target_type.clone(),
+            src_type: IrType::Bool,
+        });
+        temp
+    }
+
+    /// Generate IR for an expression, returning the value
+    fn generate_expr(&mut self, expr: &Expr) -> Result<IrValue, IrGeneratorError> {
+        Ok(match expr {
             Expr::Integer(i) => IrValue::IntConstant(*i),
             
             Expr::Float(f) => IrValue::FloatConstant(*f),
             
             Expr::Char(c) => IrValue::CharConstant(*c),
+            Expr::Bool(b) => IrValue::IntConstant(*b as i64),
             
             Expr::String(s) => {
                 let label = self.get_string_label(s);
                 IrValue::StringConstant(label)
             }
             
+            Expr::Identifier(name) if self.function_reference(expr).is_some() => {
+                // A function name in value position is its address
+                IrValue::Global(name.clone())
+            }
+            
+            Expr::Identifier(name) if matches!(self.infer_identifier_type(name), IrType::Array(..)) => {
+                // An array stands for the address of its first element
+                let IrType::Array(element, _) = self.infer_identifier_type(name) else { unreachable!() };
+                let temp = self.new_temp();
+                self.emit_instruction(IrInstruction::AddressOf {
+                    dest: temp.clone(),
+                    src: self.variable(name),
+                    var_type: *element,
+                });
+                temp
+            }
+
             Expr::Identifier(name) => {
                 // Load the variable value
                 let temp = self.new_temp();
@@ -472,7 +880,7 @@ impl IrGenerator {
                 
                 self.emit_instruction(IrInstruction::Load {
                     dest: temp.clone(),
-                    src: IrValue::Local(name.clone()),
+                    src: self.variable(name),
                     var_type,
                 });
                 
@@ -487,7 +895,7 @@ impl IrGenerator {
                         let end_label = self.new_label("and_end");
                         
                         // Evaluate left operand
-                        let left_value = self.generate_expr(left);
+                        let left_value = self.generate_expr(left)?;
                         
                         self.emit_instruction(IrInstruction::Branch {
                             condition: left_value,
@@ -498,12 +906,12 @@ impl IrGenerator {
                         // Evaluate right operand
                         let eval_right_label = format!("and_eval_right_{}", self.label_counter - 2);
                         self.emit_instruction(IrInstruction::Label { name: eval_right_label });
-                        let right_value = self.generate_expr(right);
+                        let right_value = self.generate_bool_expr(right)?;
                         
                         self.emit_instruction(IrInstruction::Move {
                             dest: result_temp.clone(),
                             src: right_value,
-                            var_type: IrType::Int,
+                            var_type: IrType::Bool,
                         });
                         self.emit_instruction(IrInstruction::Jump { label: end_label.clone() });
                         
@@ -511,7 +919,7 @@ impl IrGenerator {
                         self.emit_instruction(IrInstruction::Move {
                             dest: result_temp.clone(),
                             src: IrValue::IntConstant(0),
-                            var_type: IrType::Int,
+                            var_type: IrType::Bool,
                         });
                         
                         // End label
@@ -526,7 +934,7 @@ impl IrGenerator {
                         let end_label = self.new_label("or_end");
                         
                         // Evaluate left operand
-                        let left_value = self.generate_expr(left);
+                        let left_value = self.generate_expr(left)?;
                         
                         self.emit_instruction(IrInstruction::Branch {
                             condition: left_value,
@@ -537,12 +945,12 @@ impl IrGenerator {
                         // Evaluate right operand
                         let eval_right_label = format!("or_eval_right_{}", self.label_counter - 2);
                         self.emit_instruction(IrInstruction::Label { name: eval_right_label });
-                        let right_value = self.generate_expr(right);
+                        let right_value = self.generate_bool_expr(right)?;
                         
                         self.emit_instruction(IrInstruction::Move {
                             dest: result_temp.clone(),
                             src: right_value,
-                            var_type: IrType::Int,
+                            var_type: IrType::Bool,
                         });
                         self.emit_instruction(IrInstruction::Jump { label: end_label.clone() });
                         
@@ -550,7 +958,7 @@ impl IrGenerator {
                         self.emit_instruction(IrInstruction::Move {
                             dest: result_temp.clone(),
                             src: IrValue::IntConstant(1),
-                            var_type: IrType::Int,
+                            var_type: IrType::Bool,
                         });
                         
                         // End label
@@ -560,18 +968,33 @@ impl IrGenerator {
                     }
                     
                     _ => {
-                        let left_value = self.generate_expr(left);
-                        let right_value = self.generate_expr(right);
-                        let result_temp = self.new_temp();
+                        // Operands are evaluated left to right, so side
+                        // effects such as calls happen in source order
                         let op = IrBinaryOp::from(operator.clone());
-                        let expr_type = self.infer_expr_type(expr);
+                        if op.is_bitwise() {
+                            let operand_types = [self.infer_expr_type(left), self.infer_expr_type(right)];
+                            if let Some(operand_type) = operand_types.iter().find(|operand_type| !matches!(operand_type, IrType::Int | IrType::Char | IrType::Bool)) {
+                                return Err(IrGeneratorError::UnsupportedConstruct(format!("operator '{}' on a {} operand", operator.operator_symbol().unwrap_or("?"), operand_type.c_name())));
+                            }
+                        }
+                        let left_value = self.generate_integer_context_expr(left)?;
+                        let right_value = self.generate_integer_context_expr(right)?;
+                        let result_temp = self.new_temp();
+                        // Comparisons keep the int type they always used, except
+                        // that addresses stay unsigned; their result is a Bool by
+                        // virtue of the operator
+                        let var_type = if op.is_comparison() {
+                            Some(self.infer_expr_type(left)).filter(IrType::is_unsigned).unwrap_or(IrType::Int)
+                        } else {
+                            self.infer_expr_type(expr)
+                        };
                         
                         self.emit_instruction(IrInstruction::BinaryOp {
                             dest: result_temp.clone(),
                             op,
                             left: left_value,
                             right: right_value,
-                            var_type: expr_type,
+                            var_type,
                         });
                         
                         result_temp
@@ -579,13 +1002,36 @@ impl IrGenerator {
                 }
             }
             
+            Expr::Unary { operator: TokenType::Ampersand, operand } => {
+                match (self.function_reference(operand), operand.as_ref()) {
+                    (Some(name), _) => IrValue::Global(name.to_string()),
+                    (None, Expr::Identifier(name)) => {
+                        let var_type = self.infer_identifier_type(name);
+                        let result_temp = self.new_temp();
+                        self.emit_instruction(IrInstruction::AddressOf {
+                            dest: result_temp.clone(),
+                            src: self.variable(name),
+                            var_type,
+                        });
+                        result_temp
+                    }
+                    // Only variables and functions have an address
+                    _ => return Err(IrGeneratorError::UnsupportedConstruct("taking the address of an expression that is not a variable or a function".to_string())),
+                }
+            }
+            
             Expr::Unary { operator, operand } => {
-                let operand_value = self.generate_expr(operand);
+                // `!` tests its operand against zero at whatever width it has
+                let operand_value = if *operator == TokenType::LogicalNot {
+                    self.generate_expr(operand)?
+                } else {
+                    self.generate_integer_context_expr(operand)?
+                };
                 let result_temp = self.new_temp();
                 let op = match operator {
                     TokenType::Minus => IrUnaryOp::Neg,
                     TokenType::LogicalNot => IrUnaryOp::Not,
-                    _ => return IrValue::IntConstant(0), // Return default value for unsupported operators
+                    _ => return Ok(IrValue::IntConstant(0)), // Return default value for unsupported operators
                 };
                 let expr_type = self.infer_expr_type(expr);
                 
@@ -599,23 +1045,29 @@ impl IrGenerator {
                 result_temp
             }
             
+            Expr::Call { callee, .. } if let Some(builtin) = Builtin::of_callee(callee) => match builtin {
+                Builtin::Strlen => IrValue::IntConstant(self.constant_value(expr).unwrap_or(0)),
+                // The others produce no value, so may only be called for
+                // their effect
+                _ => return Err(IrGeneratorError::UnsupportedConstruct(
+                    format!("'{}' returns no value, so its result cannot be used", builtin.name())
+                )),
+            },
+
             Expr::Call { callee, arguments, .. } => {
                 let func_name = match callee.as_ref() {
                     Expr::Identifier(name) => name.clone(),
-                    _ => return IrValue::IntConstant(0), // Return default value for complex function calls
+                    _ => return Ok(IrValue::IntConstant(0)), // Return default value for complex function calls
                 };
                 
                 let mut arg_values = Vec::new();
                 for arg in arguments {
-                    arg_values.push(self.generate_expr(arg));
+                    arg_values.push(self.generate_integer_context_expr(arg)?);
                 }
                 
+                let return_type = self.call_return_type(callee)
+                    .ok_or_else(|| IrGeneratorError::UndefinedFunction(func_name.clone()))?;
                 let result_temp = self.new_temp();
-                let return_type = match func_name.as_str() {
-                    "printf" | "println" => IrType::Int, // printf returns int (number of chars printed)
-                    "main" => IrType::Int, // main function returns int
-                    _ => IrType::Int, // Default fallback for unknown functions
-                };
                 
                 self.emit_instruction(IrInstruction::Call {
                     dest: Some(result_temp.clone()),
@@ -627,13 +1079,18 @@ impl IrGenerator {
                 result_temp
             }
             
+            Expr::Index { array, index } => self.generate_index_expr(array, index)?,
+
             Expr::Assignment { name, value } => {
-                let value_result = self.generate_expr(value);
+                let value_type = self.infer_expr_type(value);
+                let value_result = self.generate_expr(value)?;
                 let var_type = self.infer_identifier_type(name);
+                self.check_function_address(name, &var_type, value)?;
+                let value_result = self.widen_bool(value_result, &value_type, &var_type);
                 
                 self.emit_instruction(IrInstruction::Store {
                     value: value_result.clone(),
-                    dest: IrValue::Local(name.clone()),
+                    dest: self.variable(name),
                     var_type,
                 });
                 
@@ -641,13 +1098,11 @@ impl IrGenerator {
             }
             
             Expr::TypeCast { expr, target_type } => {
-                let expr_value = self.generate_expr(expr);
+                let expr_value = self.generate_expr(expr)?;
                 let src_type = self.infer_expr_type(expr);
-                let target_ir_type = if let Some(token_type) = target_type.to_token_type() {
-                    IrType::from(token_type)
-                } else {
-                    IrType::Int // Default fallback
-                };
+                // The parser never produces casts, so their types are not
+                // checked up front
+                let target_ir_type = Self::lower_type(target_type)?;
                 
                 let temp = self.new_temp();
                 self.emit_instruction(IrInstruction::Cast {
@@ -659,7 +1114,7 @@ impl IrGenerator {
                 
                 temp
             }
-        }
+        })
     }
 
     /// Infer the type of an expression (simplified type inference)
@@ -668,70 +1123,98 @@ impl IrGenerator {
             Expr::Integer(_) => IrType::Int,
             Expr::Float(_) => IrType::Float,
             Expr::Char(_) => IrType::Char,
+            Expr::Bool(_) => IrType::Bool,
             Expr::String(_) => IrType::String,
-            Expr::Identifier(name) => self.infer_identifier_type(name),
+            Expr::Identifier(name) => match self.function_reference(expr) {
+                Some(func_name) => IrType::function_pointer(self.function_types[func_name].clone()),
+                None => match self.infer_identifier_type(name) {
+                    IrType::Array(element, _) => IrType::Pointer(element),
+                    other => other,
+                },
+            },
+            Expr::Unary { operator: TokenType::Ampersand, operand } => match self.function_reference(operand) {
+                Some(func_name) => IrType::function_pointer(self.function_types[func_name].clone()),
+                None => IrType::Pointer(Box::new(self.infer_expr_type(operand))),
+            },
             Expr::Binary { left, operator, .. } => {
                 match operator {
                     TokenType::Equal | TokenType::NotEqual | 
                     TokenType::LessThan | TokenType::LessEqual |
-                    TokenType::GreaterThan | TokenType::GreaterEqual => IrType::Int, // Boolean as int
-                    _ => self.infer_expr_type(left), // Use left operand type
-                }
-            }
-            Expr::Unary { operand, .. } => self.infer_expr_type(operand),
-            Expr::Call { callee, .. } => {
-                if let Expr::Identifier(func_name) = callee.as_ref() {
-                    match func_name.as_str() {
-                        "printf" | "println" => IrType::Int, // printf returns int
-                        "main" => IrType::Int, // main function returns int
-                        _ => IrType::Int, // Default fallback for unknown functions
-                    }
-                } else {
-                    IrType::Int // Default fallback
+                    TokenType::GreaterThan | TokenType::GreaterEqual |
+                    TokenType::LogicalAnd | TokenType::LogicalOr => IrType::Bool,
+                    // Use left operand type; arithmetic on a Bool is done as int
+                    _ => match self.infer_expr_type(left) {
+                        IrType::Bool => IrType::Int,
+                        other => other,
+                    },
                 }
             }
+            Expr::Unary { operator: TokenType::LogicalNot, .. } => IrType::Bool,
+            Expr::Unary { operand, .. } => match self.infer_expr_type(operand) {
+                IrType::Bool => IrType::Int,
+                other => other,
+            },
+            // Builtins and undefined callees; generating the call reports the latter
+            Expr::Call { callee, .. } => self.call_return_type(callee).unwrap_or(IrType::Int),
+            Expr::Index { array, .. } => match array.as_ref() {
+                Expr::Identifier(name) => match self.infer_identifier_type(name) {
+                    IrType::Array(element, _) => *element,
+                    _ => IrType::Int,
+                },
+                Expr::String(_) => IrType::Char,
+                _ => IrType::Int,
+            },
             Expr::Assignment { name, .. } => self.infer_identifier_type(name),
-            Expr::TypeCast { target_type, .. } => {
-                if let Some(token_type) = target_type.to_token_type() {
-                    IrType::from(token_type)
-                } else {
-                    IrType::Int
-                }
+            Expr::TypeCast { expr, target_type } => {
+                IrType::try_from(target_type).unwrap_or_else(|_| self.infer_expr_type(expr))
             }
         }
     }
 
-    /// Collect variable types from AST for symbol table
-    fn collect_variable_types(&mut self, ast: &[Stmt]) {
-        for stmt in ast {
-            match stmt {
-                Stmt::Function { body, .. } => {
-                    self.collect_variable_types(body);
-                }
-                Stmt::VarDecl { var_type, name, .. } => {
-                    // Store variable type for later use
-                    let ir_type = if let Some(token_type) = var_type.to_token_type() {
-                    IrType::from(token_type)
-                } else {
-                    IrType::Int // Default fallback
-                };
-                    self.local_types.insert(name.clone(), ir_type);
-                }
-                Stmt::If { then_branch, .. } => {
-                    self.collect_variable_types(then_branch);
-                }
-                Stmt::Block(stmts) => {
-                    self.collect_variable_types(stmts);
-                }
-                _ => {}
-            }
+    /// Return type of a call to `callee`: a function of the program, or a
+    /// local holding the address of one
+    fn call_return_type(&self, callee: &Expr) -> Option<IrType> {
+        if let Some(func_name) = self.function_reference(callee) {
+            return self.function_types.get(func_name).cloned();
+        }
+        match callee {
+            Expr::Identifier(name) => match self.local_type(name) {
+                Some(IrType::Pointer(pointee)) => match pointee.as_ref() {
+                    IrType::Function(return_type) => Some(*return_type.clone()),
+                    _ => None,
+                },
+                _ => None,
+            },
+            _ => None,
         }
     }
 
+    /// Name of the function `expr` refers to, if it is a bare function name
+    /// or `&name`. Local variables shadow functions of the same name.
+    fn function_reference<'a>(&self, expr: &'a Expr) -> Option<&'a str> {
+        match expr {
+            Expr::Identifier(name) if self.function_types.contains_key(name)
+                && self.storage_name(name).is_none() => Some(name),
+            Expr::Unary { operator: TokenType::Ampersand, operand } => self.function_reference(operand),
+            _ => None,
+        }
+    }
+
+    /// Name the variable `name` in scope is stored under: the label of a
+    /// `static` local, or the IR name of a parameter or local
+    fn storage_name(&self, name: &str) -> Option<&str> {
+        self.static_locals.get(name).or_else(|| self.local_names.get(name)).map(String::as_str)
+    }
+
+    /// Type of the variable `name` in scope
+    fn local_type(&self, name: &str) -> Option<&IrType> {
+        self.local_types.get(self.storage_name(name)?)
+    }
+
     /// Infer the type of an identifier using symbol table lookup
     fn infer_identifier_type(&self, name: &str) -> IrType {
         // Look up the variable type in the symbol table
-        self.local_types.get(name)
+        self.local_type(name)
             .cloned()
             .unwrap_or_else(|| {
                 // Try to infer from context or use intelligent fallback
@@ -753,3 +1236,9 @@ impl Default for IrGenerator {
         Self::new()
     }
 }
+
+/// Whether `name` is spelled like a temp, `t` and a number, which is how
+/// temps print in the IR text
+fn is_temp_name(name: &str) -> bool {
+    name.strip_prefix('t').is_some_and(|id| !id.is_empty() && id.bytes().all(|b| b.is_ascii_digit()))
+}
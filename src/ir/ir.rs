@@ -1,4 +1,6 @@
 use crate::lexer::TokenType;
+use crate::types::{PrimitiveType, StructType, TargetTypeConfig, Type, TypeKind};
+use std::collections::HashMap;
 use std::fmt;
 
 /// IR Value types - represents the type system in IR
@@ -7,9 +9,17 @@ pub enum IrType {
     Int,
     Float,
     Char,
+    /// 0/1 flag produced by comparisons and logical operators, stored in a byte
+    Bool,
     String,
     Void,
     Pointer(Box<IrType>),
+    /// Function returning the inner type; only ever stored behind a pointer
+    Function(Box<IrType>),
+    /// Fixed number of elements laid out one after another
+    Array(Box<IrType>, usize),
+    /// Fields in declaration order, each at its natural alignment
+    Struct(Vec<IrType>),
 }
 
 impl fmt::Display for IrType {
@@ -18,27 +28,151 @@ impl fmt::Display for IrType {
             IrType::Int => write!(f, "i32"),
             IrType::Float => write!(f, "f64"),
             IrType::Char => write!(f, "i8"),
+            IrType::Bool => write!(f, "i1"),
             IrType::String => write!(f, "str"),
             IrType::Void => write!(f, "void"),
             IrType::Pointer(inner) => write!(f, "{}*", inner),
+            IrType::Function(return_type) => write!(f, "{} ()", return_type),
+            IrType::Array(element, count) => write!(f, "[{} x {}]", count, element),
+            IrType::Struct(fields) => {
+                let fields: Vec<String> = fields.iter().map(IrType::to_string).collect();
+                write!(f, "{{ {} }}", fields.join(", "))
+            }
         }
     }
 }
 
-impl From<TokenType> for IrType {
-    fn from(token_type: TokenType) -> Self {
-        match token_type {
-            TokenType::Int => IrType::Int,
-            TokenType::FloatType => IrType::Float,
-            TokenType::CharType => IrType::Char,
-            TokenType::Void => IrType::Void,
-            _ => IrType::Void, // Default fallback
+impl IrType {
+    /// Pointer to a function returning `return_type`
+    pub fn function_pointer(return_type: IrType) -> Self {
+        IrType::Pointer(Box::new(IrType::Function(Box::new(return_type))))
+    }
+
+    /// Bytes a value of this type occupies in a stack slot, as the source
+    /// type it lowers from is laid out on the target
+    pub fn size(&self) -> usize {
+        Type::from(self).size()
+    }
+
+    /// Bytes a slot of this type is aligned to
+    pub fn alignment(&self) -> usize {
+        Type::from(self).alignment()
+    }
+
+    /// Byte offset of each field of a struct, padded as the target lays
+    /// out structs; empty for other types
+    pub fn field_offsets(&self) -> Vec<usize> {
+        let Type { kind: TypeKind::Struct(struct_type), .. } = Type::from(self) else { return Vec::new() };
+        TargetTypeConfig::default().field_offsets(&struct_type).into_iter()
+            .map(|(offset, _)| offset)
+            .collect()
+    }
+
+    /// Arrays and structs, which live in memory and are only handled
+    /// through their address
+    pub fn is_aggregate(&self) -> bool {
+        matches!(self, IrType::Array(..) | IrType::Struct(_))
+    }
+
+    /// Addresses have no sign, so they are ordered as unsigned values
+    pub fn is_unsigned(&self) -> bool {
+        matches!(self, IrType::String | IrType::Pointer(_) | IrType::Function(_))
+    }
+
+    /// Whether values of this type convert to one another with a `Cast`
+    pub fn is_arithmetic(&self) -> bool {
+        matches!(self, IrType::Int | IrType::Char | IrType::Float)
+    }
+
+    pub fn is_function_pointer(&self) -> bool {
+        matches!(self, IrType::Pointer(inner) if matches!(**inner, IrType::Function(_)))
+    }
+
+    /// The C spelling of the type, for diagnostics, as `Type` displays
+    /// it. `Display` writes the IR text syntax that `parse_ir` reads back.
+    pub fn c_name(&self) -> String {
+        match self {
+            IrType::Int => "int".to_string(),
+            IrType::Float => "float".to_string(),
+            IrType::Char => "char".to_string(),
+            IrType::Bool => "bool".to_string(),
+            IrType::String => "char *".to_string(),
+            IrType::Void => "void".to_string(),
+            IrType::Pointer(inner) => {
+                let inner = inner.c_name();
+                let separator = if inner.ends_with('*') { "" } else { " " };
+                format!("{}{}*", inner, separator)
+            }
+            IrType::Function(return_type) => format!("{} ()", return_type.c_name()),
+            IrType::Array(element, count) => format!("{}[{}]", element.c_name(), count),
+            IrType::Struct(fields) => {
+                let fields: Vec<String> = fields.iter().map(|field| format!("{};", field.c_name())).collect();
+                format!("struct {{ {} }}", fields.join(" "))
+            }
+        }
+    }
+}
+
+/// The IR type a type keyword names, through the source type it names, so
+/// both report a token that is not a type the same way
+impl TryFrom<TokenType> for IrType {
+    type Error = String;
+
+    fn try_from(token_type: TokenType) -> Result<Self, Self::Error> {
+        IrType::try_from(&Type::try_from(token_type)?)
+    }
+}
+
+/// Lower a source type to its IR type. Types the IR cannot represent,
+/// such as `float` widths other than 64 bits, are an error rather than
+/// silently becoming `int`.
+impl TryFrom<&Type> for IrType {
+    type Error = String;
+
+    fn try_from(source_type: &Type) -> Result<Self, Self::Error> {
+        match &source_type.kind {
+            TypeKind::Primitive(PrimitiveType::Void) => Ok(IrType::Void),
+            TypeKind::Primitive(PrimitiveType::Bool) => Ok(IrType::Bool),
+            TypeKind::Primitive(PrimitiveType::Int32) => Ok(IrType::Int),
+            TypeKind::Primitive(PrimitiveType::Float64) => Ok(IrType::Float),
+            TypeKind::Primitive(PrimitiveType::Char) => Ok(IrType::Char),
+            TypeKind::Primitive(PrimitiveType::String) => Ok(IrType::String),
+            TypeKind::Pointer(target) => Ok(IrType::Pointer(Box::new(IrType::try_from(target.as_ref())?))),
+            TypeKind::Array(element, count) => Ok(IrType::Array(Box::new(IrType::try_from(element.as_ref())?), *count)),
+            TypeKind::Function(function) => Ok(IrType::Function(Box::new(IrType::try_from(function.return_type.as_ref())?))),
+            TypeKind::Struct(struct_type) => struct_type.fields.iter()
+                .map(|(_, field_type)| IrType::try_from(field_type))
+                .collect::<Result<_, _>>()
+                .map(IrType::Struct),
+            _ => Err(format!("unsupported type {}", source_type)),
+        }
+    }
+}
+
+/// The source type an IR type lowers from. Struct fields are named by
+/// their position, as the IR does not keep field names.
+impl From<&IrType> for Type {
+    fn from(ir_type: &IrType) -> Self {
+        match ir_type {
+            IrType::Int => Type::primitive(PrimitiveType::Int32),
+            IrType::Float => Type::primitive(PrimitiveType::Float64),
+            IrType::Char => Type::primitive(PrimitiveType::Char),
+            IrType::Bool => Type::primitive(PrimitiveType::Bool),
+            IrType::String => Type::primitive(PrimitiveType::String),
+            IrType::Void => Type::primitive(PrimitiveType::Void),
+            IrType::Pointer(target) => Type::pointer(Type::from(target.as_ref())),
+            IrType::Function(return_type) => Type::function(Type::from(return_type.as_ref()), Vec::new(), false),
+            IrType::Array(element, count) => Type::array(Type::from(element.as_ref()), *count),
+            IrType::Struct(fields) => Type::structure(StructType {
+                name: String::new(),
+                fields: fields.iter().enumerate().map(|(index, field)| (index.to_string(), Type::from(field))).collect(),
+            }),
         }
     }
 }
 
 /// IR Values - represents operands in IR instructions
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub enum IrValue {
     /// Immediate integer constant
     IntConstant(i64),
@@ -62,9 +196,9 @@ impl fmt::Display for IrValue {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             IrValue::IntConstant(i) => write!(f, "{}", i),
-            IrValue::FloatConstant(fl) => write!(f, "{}", fl),
-            IrValue::CharConstant(c) => write!(f, "'{}'", c),
-            IrValue::StringConstant(s) => write!(f, "\"{}\"", s),
+            IrValue::FloatConstant(fl) => write_float(f, *fl),
+            IrValue::CharConstant(c) => write!(f, "{:?}", c),
+            IrValue::StringConstant(s) => write!(f, "{:?}", s),
             IrValue::Local(name) => write!(f, "%{}", name),
             IrValue::Temp(id) => write!(f, "%t{}", id),
             IrValue::Parameter(name) => write!(f, "%{}", name),
@@ -73,6 +207,40 @@ impl fmt::Display for IrValue {
     }
 }
 
+/// A float constant the IR parser reads back bit for bit: finite values
+/// with at least one fractional digit, so `1.0` is not taken for an
+/// integer, infinities as `inf` and `-inf`, and NaNs as `nan`, or with
+/// their bit pattern when it is not the default one
+fn write_float(f: &mut fmt::Formatter<'_>, value: f64) -> fmt::Result {
+    if value.is_nan() && value.to_bits() != f64::NAN.to_bits() {
+        write!(f, "nan(0x{:016x})", value.to_bits())
+    } else if value.is_nan() {
+        write!(f, "nan")
+    } else if value.is_infinite() {
+        write!(f, "{}inf", if value < 0.0 { "-" } else { "" })
+    } else {
+        write!(f, "{:?}", value)
+    }
+}
+
+/// Float constants compare by their bits, like `Hash`, so a NaN equals
+/// itself and 0.0 differs from -0.0
+impl PartialEq for IrValue {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (IrValue::IntConstant(a), IrValue::IntConstant(b)) => a == b,
+            (IrValue::FloatConstant(a), IrValue::FloatConstant(b)) => a.to_bits() == b.to_bits(),
+            (IrValue::CharConstant(a), IrValue::CharConstant(b)) => a == b,
+            (IrValue::StringConstant(a), IrValue::StringConstant(b)) |
+            (IrValue::Local(a), IrValue::Local(b)) |
+            (IrValue::Parameter(a), IrValue::Parameter(b)) |
+            (IrValue::Global(a), IrValue::Global(b)) => a == b,
+            (IrValue::Temp(a), IrValue::Temp(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
 impl Eq for IrValue {}
 
 impl std::hash::Hash for IrValue {
@@ -132,6 +300,12 @@ pub enum IrBinaryOp {
     // Logical operations
     And,
     Or,
+    // Bitwise operations on integers; `Shr` keeps the sign
+    BitAnd,
+    BitOr,
+    BitXor,
+    Shl,
+    Shr,
 }
 
 impl fmt::Display for IrBinaryOp {
@@ -150,11 +324,65 @@ impl fmt::Display for IrBinaryOp {
             IrBinaryOp::Ge => write!(f, "ge"),
             IrBinaryOp::And => write!(f, "and"),
             IrBinaryOp::Or => write!(f, "or"),
+            IrBinaryOp::BitAnd => write!(f, "bitand"),
+            IrBinaryOp::BitOr => write!(f, "bitor"),
+            IrBinaryOp::BitXor => write!(f, "bitxor"),
+            IrBinaryOp::Shl => write!(f, "shl"),
+            IrBinaryOp::Shr => write!(f, "shr"),
         }
     }
 }
 
 impl IrBinaryOp {
+    /// True for the relational operators that produce a 0/1 result
+    pub fn is_comparison(&self) -> bool {
+        matches!(self, IrBinaryOp::Eq | IrBinaryOp::Ne | IrBinaryOp::Lt |
+                       IrBinaryOp::Le | IrBinaryOp::Gt | IrBinaryOp::Ge)
+    }
+
+    /// True for the operators that work on the bits of integer operands
+    pub fn is_bitwise(&self) -> bool {
+        matches!(self, IrBinaryOp::BitAnd | IrBinaryOp::BitOr | IrBinaryOp::BitXor |
+                       IrBinaryOp::Shl | IrBinaryOp::Shr)
+    }
+
+    /// True for the operators whose result is a `Bool` rather than their
+    /// operand type
+    pub fn produces_bool(&self) -> bool {
+        self.is_comparison() || matches!(self, IrBinaryOp::And | IrBinaryOp::Or)
+    }
+
+    /// Compile-time integer result of `left op right`, or `None` when the
+    /// operator cannot be folded (division by zero, overflow, logical
+    /// operators). Ints are 32 bits wide at run time, so operands and
+    /// results outside that range are left for run time rather than folded
+    /// to a value the generated code would not compute.
+    pub fn fold_int(&self, left: i64, right: i64) -> Option<i64> {
+        let (left, right) = (i32::try_from(left).ok()?, i32::try_from(right).ok()?);
+        let result = match self {
+            IrBinaryOp::Add => left.checked_add(right),
+            IrBinaryOp::Sub => left.checked_sub(right),
+            IrBinaryOp::Mul => left.checked_mul(right),
+            IrBinaryOp::Div => left.checked_div(right),
+            IrBinaryOp::Mod => left.checked_rem(right),
+            IrBinaryOp::Eq => Some((left == right) as i32),
+            IrBinaryOp::Ne => Some((left != right) as i32),
+            IrBinaryOp::Lt => Some((left < right) as i32),
+            IrBinaryOp::Le => Some((left <= right) as i32),
+            IrBinaryOp::Gt => Some((left > right) as i32),
+            IrBinaryOp::Ge => Some((left >= right) as i32),
+            IrBinaryOp::BitAnd => Some(left & right),
+            IrBinaryOp::BitOr => Some(left | right),
+            IrBinaryOp::BitXor => Some(left ^ right),
+            // Shifts by the width of an int or more, or of a negative
+            // count, are undefined, and so is shifting bits out of an int
+            IrBinaryOp::Shl if (0..32).contains(&right) => i32::try_from(i64::from(left) << right).ok(),
+            IrBinaryOp::Shr if (0..32).contains(&right) => Some(left >> right),
+            _ => None,
+        };
+        result.map(i64::from)
+    }
+
     pub fn try_from_token(token_type: TokenType) -> Result<Self, String> {
         match token_type {
             TokenType::Plus => Ok(IrBinaryOp::Add),
@@ -170,6 +398,11 @@ impl IrBinaryOp {
             TokenType::GreaterEqual => Ok(IrBinaryOp::Ge),
             TokenType::LogicalAnd => Ok(IrBinaryOp::And),
             TokenType::LogicalOr => Ok(IrBinaryOp::Or),
+            TokenType::Ampersand => Ok(IrBinaryOp::BitAnd),
+            TokenType::BitOr => Ok(IrBinaryOp::BitOr),
+            TokenType::BitXor => Ok(IrBinaryOp::BitXor),
+            TokenType::ShiftLeft => Ok(IrBinaryOp::Shl),
+            TokenType::ShiftRight => Ok(IrBinaryOp::Shr),
             _ => Err(format!("Invalid binary operator: {:?}", token_type)),
         }
     }
@@ -252,6 +485,16 @@ pub enum IrInstruction {
         false_label: String,
     },
     
+    /// Compare and branch: br op type left, right, true_label, false_label
+    CompareBranch {
+        op: IrBinaryOp,
+        left: IrValue,
+        right: IrValue,
+        var_type: IrType,
+        true_label: String,
+        false_label: String,
+    },
+    
     /// Unconditional jump: jmp label
     Jump {
         label: String,
@@ -268,10 +511,12 @@ pub enum IrInstruction {
         var_type: IrType,
     },
     
-    /// Print statement (built-in): print format, args
+    /// Print statement (built-in): print format, args. `newline` ends
+    /// the output with a newline, as `println` does.
     Print {
         format_string: IrValue,
         args: Vec<IrValue>,
+        newline: bool,
     },
     
     /// Move/Copy operation: mov type dest, src
@@ -297,12 +542,133 @@ pub enum IrInstruction {
         src_type: IrType,
     },
     
+    /// Element of an array variable: loadelem type dest, array[index]
+    LoadElement {
+        dest: IrValue,
+        array: IrValue,
+        index: IrValue,
+        var_type: IrType,
+    },
+
+    /// Store to an element of an array variable: storeelem type value, array[index]
+    StoreElement {
+        value: IrValue,
+        array: IrValue,
+        index: IrValue,
+        var_type: IrType,
+    },
+    
+    /// Address of a variable: addressof type dest, src
+    AddressOf {
+        dest: IrValue,
+        src: IrValue,
+        var_type: IrType,
+    },
+    
+    /// SSA join: dest takes the value flowing in from whichever
+    /// predecessor block (named by its label) control came from
+    Phi {
+        dest: IrValue,
+        incoming: Vec<(IrValue, String)>,
+        var_type: IrType,
+    },
+    
+    /// Abort the program on the spot: trap. Control never continues
+    /// past it, so it ends its block like a return.
+    Trap,
+
     /// Comment for debugging
     Comment {
         text: String,
     },
 }
 
+impl IrInstruction {
+    /// Whether the instruction does anything beyond computing its
+    /// destination value: writes memory, performs I/O, calls out or
+    /// transfers control. Such instructions are kept even when their
+    /// result is unused, and passes do not move loads across them. A
+    /// `Store` may still be removed by dead store elimination, which
+    /// proves that nothing reads the stored value before it is
+    /// overwritten; every other such instruction is an observation
+    /// point that pass does not see past.
+    pub fn has_side_effects(&self) -> bool {
+        match self {
+            IrInstruction::Store { .. } |
+            IrInstruction::StoreElement { .. } |
+            IrInstruction::Call { .. } |
+            IrInstruction::Print { .. } |
+            IrInstruction::Alloca { .. } |
+            IrInstruction::Branch { .. } |
+            IrInstruction::CompareBranch { .. } |
+            IrInstruction::Jump { .. } |
+            IrInstruction::Label { .. } |
+            IrInstruction::Return { .. } |
+            IrInstruction::Trap => true,
+            IrInstruction::Load { .. } |
+            IrInstruction::LoadElement { .. } |
+            IrInstruction::BinaryOp { .. } |
+            IrInstruction::UnaryOp { .. } |
+            IrInstruction::Move { .. } |
+            IrInstruction::Convert { .. } |
+            IrInstruction::Cast { .. } |
+            IrInstruction::AddressOf { .. } |
+            IrInstruction::Phi { .. } |
+            IrInstruction::Comment { .. } => false,
+        }
+    }
+
+    /// Whether the instruction only computes its destination from its
+    /// operands: it reads no memory and has no side effects, so a second
+    /// instance with the same operands yields the same value and can reuse
+    /// the first one's result
+    pub fn is_pure(&self) -> bool {
+        matches!(self,
+            IrInstruction::BinaryOp { .. } |
+            IrInstruction::UnaryOp { .. } |
+            IrInstruction::Move { .. } |
+            IrInstruction::Convert { .. } |
+            IrInstruction::Cast { .. } |
+            IrInstruction::AddressOf { .. })
+    }
+
+    /// Value the instruction defines, if any
+    pub fn dest(&self) -> Option<&IrValue> {
+        match self {
+            IrInstruction::Load { dest, .. } |
+            IrInstruction::LoadElement { dest, .. } |
+            IrInstruction::BinaryOp { dest, .. } |
+            IrInstruction::UnaryOp { dest, .. } |
+            IrInstruction::Move { dest, .. } |
+            IrInstruction::Convert { dest, .. } |
+            IrInstruction::Cast { dest, .. } |
+            IrInstruction::AddressOf { dest, .. } |
+            IrInstruction::Phi { dest, .. } => Some(dest),
+            IrInstruction::Call { dest, .. } => dest.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Type of the value written to the destination, if the instruction has one
+    pub fn result_type(&self) -> Option<IrType> {
+        match self {
+            IrInstruction::BinaryOp { op, .. } if op.produces_bool() => Some(IrType::Bool),
+            IrInstruction::UnaryOp { op: IrUnaryOp::Not, .. } => Some(IrType::Bool),
+            IrInstruction::BinaryOp { var_type, .. } |
+            IrInstruction::UnaryOp { var_type, .. } |
+            IrInstruction::Load { var_type, .. } |
+            IrInstruction::LoadElement { var_type, .. } |
+            IrInstruction::Move { var_type, .. } |
+            IrInstruction::Phi { var_type, .. } => Some(var_type.clone()),
+            IrInstruction::Call { dest: Some(_), return_type, .. } => Some(return_type.clone()),
+            IrInstruction::Convert { dest_type, .. } |
+            IrInstruction::Cast { dest_type, .. } => Some(dest_type.clone()),
+            IrInstruction::AddressOf { var_type, .. } => Some(IrType::Pointer(Box::new(var_type.clone()))),
+            _ => None,
+        }
+    }
+}
+
 impl fmt::Display for IrInstruction {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -335,6 +701,9 @@ impl fmt::Display for IrInstruction {
             IrInstruction::Branch { condition, true_label, false_label } => {
                 write!(f, "  br {}, label %{}, label %{}", condition, true_label, false_label)
             }
+            IrInstruction::CompareBranch { op, left, right, var_type, true_label, false_label } => {
+                write!(f, "  br {} {} {}, {}, label %{}, label %{}", op, var_type, left, right, true_label, false_label)
+            }
             IrInstruction::Jump { label } => {
                 write!(f, "  jmp label %{}", label)
             }
@@ -348,12 +717,16 @@ impl fmt::Display for IrInstruction {
                     write!(f, "  ret {}", var_type)
                 }
             }
-            IrInstruction::Print { format_string, args } => {
+            IrInstruction::Print { format_string, args, newline } => {
                 let args_str = args.iter()
                     .map(|arg| format!("{}", arg))
                     .collect::<Vec<_>>()
                     .join(", ");
-                write!(f, "  print {}, [{}]", format_string, args_str)
+                write!(f, "  print {}, [{}]", format_string, args_str)?;
+                if *newline {
+                    write!(f, ", newline")?;
+                }
+                Ok(())
             }
             IrInstruction::Move { dest, src, var_type } => {
                 write!(f, "  {} = mov {} {}", dest, var_type, src)
@@ -364,6 +737,25 @@ impl fmt::Display for IrInstruction {
             IrInstruction::Cast { dest, src, dest_type, src_type } => {
                 write!(f, "  {} = cast {} {} to {}", dest, src_type, src, dest_type)
             }
+            IrInstruction::LoadElement { dest, array, index, var_type } => {
+                write!(f, "  {} = loadelem {}, {}[{}]", dest, var_type, array, index)
+            }
+            IrInstruction::StoreElement { value, array, index, var_type } => {
+                write!(f, "  storeelem {} {}, {}[{}]", var_type, value, array, index)
+            }
+            IrInstruction::AddressOf { dest, src, var_type } => {
+                write!(f, "  {} = addressof {} {}", dest, var_type, src)
+            }
+            IrInstruction::Phi { dest, incoming, var_type } => {
+                let incoming_str = incoming.iter()
+                    .map(|(value, label)| format!("[{}, {}]", value, label))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                write!(f, "  {} = phi {} {}", dest, var_type, incoming_str)
+            }
+            IrInstruction::Trap => {
+                write!(f, "  trap")
+            }
             IrInstruction::Comment { text } => {
                 write!(f, "  ; {}", text)
             }
@@ -372,13 +764,54 @@ impl fmt::Display for IrInstruction {
 }
 
 /// IR Function representation
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct IrFunction {
     pub name: String,
     pub return_type: IrType,
     pub parameters: Vec<(String, IrType)>,
     pub instructions: Vec<IrInstruction>,
     pub local_vars: Vec<(String, IrType)>,
+    /// Offset of each local from the start of the locals area, assigned per
+    /// block scope so locals whose scopes never overlap share a slot. Locals
+    /// missing from it are laid out one after another.
+    pub local_offsets: HashMap<String, i32>,
+    /// Internal linkage: the symbol is not exported from the module
+    pub is_static: bool,
+    /// Locals declared `register`, which the backend keeps in a register
+    /// when one is free
+    pub register_hints: Vec<String>,
+}
+
+impl IrFunction {
+    /// Describe the first place where this function differs from `other`,
+    /// or None when they are equal
+    pub fn first_difference(&self, other: &IrFunction) -> Option<String> {
+        if self == other {
+            return None;
+        }
+        let signature = |function: &IrFunction| function.to_string().lines().next().unwrap_or_default().to_string();
+        if self.name != other.name || self.return_type != other.return_type
+            || self.parameters != other.parameters || self.is_static != other.is_static {
+            return Some(format!("signature `{}` vs `{}`", signature(self), signature(other)));
+        }
+
+        let length = self.instructions.len().max(other.instructions.len());
+        let describe = |instruction: Option<&IrInstruction>| instruction
+            .map_or("end of function".to_string(), |instruction| format!("`{}`", instruction.to_string().trim()));
+        if let Some(index) = (0..length).find(|&index| self.instructions.get(index) != other.instructions.get(index)) {
+            return Some(format!("function '{}', instruction {}: {} vs {}", self.name, index,
+                describe(self.instructions.get(index)), describe(other.instructions.get(index))));
+        }
+
+        let field = if self.local_vars != other.local_vars {
+            "local variables"
+        } else if self.local_offsets != other.local_offsets {
+            "local offsets"
+        } else {
+            "register hints"
+        };
+        Some(format!("function '{}': {} differ", self.name, field))
+    }
 }
 
 impl fmt::Display for IrFunction {
@@ -389,11 +822,21 @@ impl fmt::Display for IrFunction {
             .collect::<Vec<_>>()
             .join(", ");
         
-        writeln!(f, "define {} @{}({}) {{", self.return_type, self.name, params_str)?;
+        let linkage = if self.is_static { "internal " } else { "" };
+        writeln!(f, "define {}{} @{}({}) {{", linkage, self.return_type, self.name, params_str)?;
         
-        // Instructions
+        // Instructions, with the slot and register hint of each local
         for instruction in &self.instructions {
-            writeln!(f, "{}", instruction)?;
+            write!(f, "{}", instruction)?;
+            if let IrInstruction::Alloca { name, .. } = instruction {
+                if let Some(offset) = self.local_offsets.get(name) {
+                    write!(f, ", offset {}", offset)?;
+                }
+                if self.register_hints.contains(name) {
+                    write!(f, ", register")?;
+                }
+            }
+            writeln!(f)?;
         }
         
         writeln!(f, "}}")?;
@@ -402,10 +845,42 @@ impl fmt::Display for IrFunction {
 }
 
 /// Complete IR Program
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct IrProgram {
     pub functions: Vec<IrFunction>,
     pub global_strings: Vec<(String, String)>, // (label, content)
+    pub global_vars: Vec<(String, IrType, IrValue)>, // (label, type, initial value)
+}
+
+impl IrProgram {
+    /// Check that the program defines the exported `main` the C runtime
+    /// calls, which the linker would otherwise fail to find
+    pub fn check_entry_point(&self) -> Result<(), String> {
+        match self.functions.iter().find(|function| function.name == "main") {
+            None => Err("The program has no 'main' function".to_string()),
+            Some(main) if main.is_static => Err("'main' is declared static, so it is not visible to the linker".to_string()),
+            Some(_) => Ok(()),
+        }
+    }
+
+    /// Describe the first place where this program differs from `other`,
+    /// or None when they are equal. Tests comparing whole programs use it
+    /// to point at the instruction that differs.
+    pub fn first_difference(&self, other: &IrProgram) -> Option<String> {
+        if self.global_strings != other.global_strings {
+            return Some(format!("global strings {:?} vs {:?}", self.global_strings, other.global_strings));
+        }
+        if self.global_vars != other.global_vars {
+            return Some(format!("global variables {:?} vs {:?}", self.global_vars, other.global_vars));
+        }
+        let names = |program: &IrProgram| program.functions.iter().map(|function| function.name.clone()).collect::<Vec<_>>();
+        if self.functions.len() != other.functions.len() {
+            return Some(format!("functions {:?} vs {:?}", names(self), names(other)));
+        }
+        self.functions.iter()
+            .zip(&other.functions)
+            .find_map(|(function, other)| function.first_difference(other))
+    }
 }
 
 impl fmt::Display for IrProgram {
@@ -417,10 +892,19 @@ impl fmt::Display for IrProgram {
         if !self.global_strings.is_empty() {
             writeln!(f, "; Global string constants")?;
             for (label, content) in &self.global_strings {
-                writeln!(f, "@{} = constant str \"{}\"", label, content)?;
+                writeln!(f, "@{} = constant str {:?}", label, content)?;
             }
             writeln!(f, "")?;
         }
+
+        // Global variables
+        if !self.global_vars.is_empty() {
+            writeln!(f, "; Global variables")?;
+            for (label, var_type, value) in &self.global_vars {
+                writeln!(f, "@{} = global {} {}", label, var_type, value)?;
+            }
+            writeln!(f)?;
+        }
         
         // Functions
         for function in &self.functions {
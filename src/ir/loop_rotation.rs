@@ -0,0 +1,238 @@
+use super::ir::{IrFunction, IrInstruction, IrValue};
+use super::optimizer::OptimizationPass;
+use super::ssa::{next_temp_id, operands, operands_mut};
+use std::collections::{HashMap, HashSet};
+
+/// Where a loop that can be rotated sits in the instruction list
+#[derive(Debug, Clone, Copy)]
+struct RotatableLoop {
+    /// `Label` of the header block, which only evaluates the condition
+    header: usize,
+    /// Branch closing the header, into the body or out of the loop
+    test: usize,
+    /// `Jump` back to the header, placed right before the exit label
+    latch: usize,
+}
+
+/// Rotate every `while` and `for` loop of `function` so the condition is
+/// tested at the bottom, after the body.
+///
+/// A loop laid out as
+///
+/// ```text
+/// header: <condition>; br cond, body, exit
+/// body:   ...; jmp header
+/// exit:
+/// ```
+///
+/// becomes a copy of the condition guarding the entry, then the body,
+/// then the header falling through from the body:
+///
+/// ```text
+///         <condition>; br cond, body, exit
+/// body:   ...
+/// header: <condition>; br cond, body, exit
+/// exit:
+/// ```
+///
+/// Each iteration then takes one branch instead of a branch and a jump.
+/// Jumps to the header, such as `continue`, still evaluate the condition.
+/// Functions in SSA form are left alone, since the copied test would add
+/// a predecessor no phi knows about. Returns true if any loop was rotated.
+pub fn rotate_loops(function: &mut IrFunction) -> bool {
+    if function.instructions.iter().any(|instruction| matches!(instruction, IrInstruction::Phi { .. })) {
+        return false;
+    }
+
+    let mut changed = false;
+    // A rotated loop no longer matches, so this ends once all are done
+    while let Some(found) = find_rotatable_loop(&function.instructions) {
+        rotate(function, found);
+        changed = true;
+    }
+    changed
+}
+
+/// Labels a block-ending branch goes to, true target first
+fn branch_targets(instruction: &IrInstruction) -> Option<(&str, &str)> {
+    match instruction {
+        IrInstruction::Branch { true_label, false_label, .. } |
+        IrInstruction::CompareBranch { true_label, false_label, .. } => Some((true_label, false_label)),
+        _ => None,
+    }
+}
+
+fn ends_block(instruction: &IrInstruction) -> bool {
+    matches!(instruction,
+        IrInstruction::Label { .. } |
+        IrInstruction::Jump { .. } |
+        IrInstruction::Branch { .. } |
+        IrInstruction::CompareBranch { .. } |
+        IrInstruction::Return { .. } |
+        IrInstruction::Trap)
+}
+
+fn is_label(instruction: Option<&IrInstruction>, label: &str) -> bool {
+    matches!(instruction, Some(IrInstruction::Label { name }) if name == label)
+}
+
+fn find_rotatable_loop(instructions: &[IrInstruction]) -> Option<RotatableLoop> {
+    instructions.iter().enumerate().find_map(|(header, instruction)| {
+        let IrInstruction::Label { name: header_label } = instruction else {
+            return None;
+        };
+        let test = (header + 1..instructions.len()).find(|&index| ends_block(&instructions[index]))?;
+        let (body_label, exit_label) = branch_targets(&instructions[test])?;
+        if body_label == exit_label || !is_label(instructions.get(test + 1), body_label) {
+            return None;
+        }
+
+        let exit = (test + 2..instructions.len()).find(|&index| is_label(instructions.get(index), exit_label))?;
+        let latch = exit - 1;
+        if !matches!(&instructions[latch], IrInstruction::Jump { label } if label == header_label) {
+            return None;
+        }
+
+        // The copied condition gets fresh temporaries, so nothing past the
+        // header may read the ones it defines
+        let defined: HashSet<&IrValue> = instructions[header + 1..test].iter()
+            .filter_map(|instruction| instruction.dest())
+            .collect();
+        let read_elsewhere = instructions[..=header].iter()
+            .chain(&instructions[test + 1..])
+            .flat_map(operands)
+            .any(|value| defined.contains(value));
+        (!read_elsewhere).then_some(RotatableLoop { header, test, latch })
+    })
+}
+
+fn rotate(function: &mut IrFunction, found: RotatableLoop) {
+    let RotatableLoop { header, test, latch } = found;
+
+    let mut next_temp = next_temp_id(function);
+    let mut renamed: HashMap<usize, usize> = HashMap::new();
+    let guard: Vec<IrInstruction> = function.instructions[header + 1..=test].iter()
+        .cloned()
+        .map(|mut instruction| {
+            if let Some(IrValue::Temp(id)) = instruction.dest() {
+                renamed.insert(*id, next_temp);
+                next_temp += 1;
+            }
+            for value in operands_mut(&mut instruction) {
+                if let IrValue::Temp(id) = value
+                    && let Some(&fresh) = renamed.get(id) {
+                    *value = IrValue::Temp(fresh);
+                }
+            }
+            instruction
+        })
+        .collect();
+
+    let mut rest = std::mem::take(&mut function.instructions).into_iter();
+    let before: Vec<IrInstruction> = rest.by_ref().take(header).collect();
+    let header_block: Vec<IrInstruction> = rest.by_ref().take(test - header + 1).collect();
+    let body: Vec<IrInstruction> = rest.by_ref().take(latch - test - 1).collect();
+    // The jump back is replaced by falling through into the header
+    rest.next();
+
+    function.instructions = before.into_iter()
+        .chain(guard)
+        .chain(body)
+        .chain(header_block)
+        .chain(rest)
+        .collect();
+}
+
+/// Optimization pass wrapper around `rotate_loops`
+pub struct LoopRotationPass;
+
+impl LoopRotationPass {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl OptimizationPass for LoopRotationPass {
+    fn name(&self) -> &str {
+        "loop_rotation"
+    }
+
+    fn dependencies(&self) -> Vec<&str> {
+        vec![]
+    }
+
+    fn run(&mut self, function: &mut IrFunction) -> bool {
+        rotate_loops(function)
+    }
+}
+
+impl Default for LoopRotationPass {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ir::IrGenerator;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    fn main_function(source: &str) -> IrFunction {
+        let tokens = Lexer::new(source).tokenize().unwrap();
+        let ast = Parser::new(tokens).parse();
+        let program = IrGenerator::new().generate(&ast).unwrap();
+        program.functions.into_iter().find(|function| function.name == "main").unwrap()
+    }
+
+    fn position(function: &IrFunction, matches: impl Fn(&IrInstruction) -> bool) -> Vec<usize> {
+        function.instructions.iter()
+            .enumerate()
+            .filter_map(|(index, instruction)| matches(instruction).then_some(index))
+            .collect()
+    }
+
+    #[test]
+    fn test_rotated_loop_tests_condition_after_body() {
+        let mut function = main_function("int main() { int i = 0; while (i < 10) { i = i + 1; } return i; }");
+        assert!(rotate_loops(&mut function));
+        assert!(!rotate_loops(&mut function), "rotation is applied once\n{}", function);
+
+        let branches = position(&function, |instruction| branch_targets(instruction).is_some());
+        let body = position(&function, |instruction| matches!(instruction, IrInstruction::Label { name } if name.starts_with("loop_body")));
+        let header = position(&function, |instruction| matches!(instruction, IrInstruction::Label { name } if name.starts_with("loop_start")));
+        let increment = position(&function, |instruction| matches!(instruction, IrInstruction::Store { .. }))[1];
+
+        // One guarding test before the body, the loop's own test after it
+        assert_eq!(branches.len(), 2, "{}", function);
+        assert!(branches[0] < body[0] && body[0] < increment, "{}", function);
+        assert!(increment < header[0] && header[0] < branches[1], "{}", function);
+        assert!(!function.instructions.iter().any(|instruction| matches!(instruction, IrInstruction::Jump { .. })), "{}", function);
+
+        // The guard reads its own copies of the condition temporaries
+        let IrInstruction::Branch { condition: guard, .. } = &function.instructions[branches[0]] else { panic!() };
+        let IrInstruction::Branch { condition: test, .. } = &function.instructions[branches[1]] else { panic!() };
+        assert_ne!(guard, test);
+    }
+
+    #[test]
+    fn test_continue_still_reaches_the_condition() {
+        let mut function = main_function(
+            "int main() { int i = 0; for (int j = 0; j < 4; j = j + 1) { if (j == 2) { continue; } i = i + j; } return i; }",
+        );
+        assert!(rotate_loops(&mut function));
+
+        let labels: HashSet<&String> = function.instructions.iter()
+            .filter_map(|instruction| match instruction {
+                IrInstruction::Label { name } => Some(name),
+                _ => None,
+            })
+            .collect();
+        for instruction in &function.instructions {
+            if let IrInstruction::Jump { label } = instruction {
+                assert!(labels.contains(label), "{}", function);
+            }
+        }
+    }
+}
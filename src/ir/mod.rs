@@ -1,7 +1,13 @@
 pub mod ir;
 pub mod generator;
 pub mod optimizer;
+pub mod ssa;
+pub mod loop_rotation;
+pub mod parser;
 
 pub use ir::*;
 pub use generator::*;
-pub use optimizer::*;
\ No newline at end of file
+pub use optimizer::*;
+pub use ssa::*;
+pub use loop_rotation::*;
+pub use parser::*;
\ No newline at end of file
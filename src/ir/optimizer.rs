@@ -1,5 +1,8 @@
-use super::ir::{IrProgram, IrFunction, IrInstruction, IrValue, IrBinaryOp};
+use super::ir::{IrProgram, IrFunction, IrInstruction, IrValue, IrType, IrBinaryOp};
+use super::ssa::SsaConstructionPass;
+use super::loop_rotation::LoopRotationPass;
 use std::collections::HashMap;
+use std::time::{Duration, Instant};
 
 pub trait OptimizationPass {
     fn name(&self) -> &str;
@@ -7,39 +10,112 @@ pub trait OptimizationPass {
     fn dependencies(&self) -> Vec<&str>; // Pass dependencies
 }
 
+/// Iterations of the pass pipeline run before it stops unless
+/// `set_max_iterations` picks another limit
+pub const DEFAULT_MAX_ITERATIONS: usize = 10;
+
+/// How the pass pipeline fared on one function
+#[derive(Debug, Clone, PartialEq)]
+pub struct Convergence {
+    pub function: String,
+    /// Runs of the whole pipeline, including the last one, which changed
+    /// nothing when a fixpoint was reached
+    pub iterations: usize,
+    pub reached_fixpoint: bool,
+}
+
 pub struct OptimizationManager {
     passes: Vec<Box<dyn OptimizationPass>>,
     max_iterations: usize,
+    warnings: Vec<String>,
+    /// One entry per function optimized, in order
+    convergence: Vec<Convergence>,
+    /// Time spent in each pass, by name, when timing is enabled
+    pass_times: Option<HashMap<String, Duration>>,
 }
 
 impl OptimizationManager {
     pub fn new() -> Self {
         Self {
             passes: Vec::new(),
-            max_iterations: 10, // Prevent infinite loops
+            max_iterations: DEFAULT_MAX_ITERATIONS, // Prevent infinite loops
+            warnings: Vec::new(),
+            convergence: Vec::new(),
+            pass_times: None,
         }
     }
     
     pub fn add_pass<P: OptimizationPass + 'static>(&mut self, pass: P) {
         self.passes.push(Box::new(pass));
     }
+
+    pub fn set_max_iterations(&mut self, max_iterations: usize) {
+        self.max_iterations = max_iterations;
+    }
+
+    pub fn max_iterations(&self) -> usize {
+        self.max_iterations
+    }
+
+    /// Diagnostics collected while running passes, such as functions that
+    /// never reached a fixpoint
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    /// How many iterations each function took, and whether it settled
+    pub fn convergence(&self) -> &[Convergence] {
+        &self.convergence
+    }
+
+    /// Record how long every pass runs, summed over all functions and
+    /// iterations
+    pub fn set_time_passes(&mut self, enabled: bool) {
+        self.pass_times = enabled.then(HashMap::new);
+    }
+
+    /// Time spent in each pass so far, or None when timing is disabled
+    pub fn pass_times(&self) -> Option<&HashMap<String, Duration>> {
+        self.pass_times.as_ref()
+    }
     
     pub fn run_passes(&mut self, function: &mut IrFunction) {
-        for _iteration in 0..self.max_iterations {
-            let mut changed = false;
+        let mut still_changing = Vec::new();
+
+        for iteration in 1..=self.max_iterations {
+            still_changing.clear();
             
             let sorted_passes = self.sort_passes_by_dependencies();
             
             for pass_index in sorted_passes {
-                if self.passes[pass_index].run(function) {
-                    changed = true;
+                let start = Instant::now();
+                let changed = self.passes[pass_index].run(function);
+                if let Some(pass_times) = &mut self.pass_times {
+                    *pass_times.entry(self.passes[pass_index].name().to_string()).or_default() += start.elapsed();
+                }
+                if changed {
+                    still_changing.push(self.passes[pass_index].name().to_string());
                 }
             }
             
-            if !changed {
-                break; // Reached fixpoint
+            if still_changing.is_empty() {
+                // Reached fixpoint
+                self.convergence.push(Convergence { function: function.name.clone(), iterations: iteration, reached_fixpoint: true });
+                return;
             }
         }
+        self.convergence.push(Convergence {
+            function: function.name.clone(),
+            iterations: self.max_iterations,
+            reached_fixpoint: false,
+        });
+
+        // Passes still report changes after the last allowed iteration: the
+        // IR may not be stable, which usually points at a pass bug
+        self.warnings.push(format!(
+            "optimizer did not reach a fixpoint for function '{}' after {} iterations (still changing: {})",
+            function.name, self.max_iterations, still_changing.join(", ")
+        ));
     }
     
     fn sort_passes_by_dependencies(&self) -> Vec<usize> {
@@ -64,7 +140,10 @@ impl IrOptimizer {
         
         manager.add_pass(ConstantFoldingPass::new());
         manager.add_pass(CopyPropagationPass::new());
+        manager.add_pass(CommonSubexpressionEliminationPass::new());
+        manager.add_pass(DeadStoreEliminationPass::new());
         manager.add_pass(DeadCodeEliminationPass::new());
+        manager.add_pass(LoopRotationPass::new());
         
         Self { manager }
     }
@@ -77,6 +156,48 @@ impl IrOptimizer {
         Self { manager }
     }
 
+    /// Build an optimizer running only the passes named in `names`, in
+    /// that order, matched against `OptimizationPass::name()`
+    pub fn with_pass_names<S: AsRef<str>>(names: &[S]) -> Result<Self, String> {
+        let mut passes = Vec::new();
+        for name in names {
+            let name = name.as_ref();
+            let pass = available_passes().into_iter()
+                .find(|pass| pass.name() == name)
+                .ok_or_else(|| {
+                    let available: Vec<String> = available_passes().iter().map(|pass| pass.name().to_string()).collect();
+                    format!("Unknown optimization pass '{}' (available passes: {})", name, available.join(", "))
+                })?;
+            passes.push(pass);
+        }
+        Ok(Self::with_custom_passes(passes))
+    }
+
+    /// Limit how many times the pass pipeline is re-run per function while
+    /// looking for a fixpoint
+    pub fn with_max_iterations(mut self, max_iterations: usize) -> Self {
+        self.manager.set_max_iterations(max_iterations);
+        self
+    }
+
+    pub fn warnings(&self) -> &[String] {
+        self.manager.warnings()
+    }
+
+    pub fn convergence(&self) -> &[Convergence] {
+        self.manager.convergence()
+    }
+
+    /// Record the time spent in each pass, read back with `pass_times`
+    pub fn with_pass_timing(mut self, enabled: bool) -> Self {
+        self.manager.set_time_passes(enabled);
+        self
+    }
+
+    pub fn pass_times(&self) -> Option<&HashMap<String, Duration>> {
+        self.manager.pass_times()
+    }
+
     /// Optimize an IR program
     pub fn optimize(&mut self, mut program: IrProgram) -> IrProgram {
         // Apply optimizations to each function
@@ -88,6 +209,19 @@ impl IrOptimizer {
     }
 }
 
+/// Every pass that can be selected by name
+pub fn available_passes() -> Vec<Box<dyn OptimizationPass>> {
+    vec![
+        Box::new(ConstantFoldingPass::new()),
+        Box::new(CopyPropagationPass::new()),
+        Box::new(CommonSubexpressionEliminationPass::new()),
+        Box::new(DeadStoreEliminationPass::new()),
+        Box::new(DeadCodeEliminationPass::new()),
+        Box::new(SsaConstructionPass::new()),
+        Box::new(LoopRotationPass::new()),
+    ]
+}
+
 /// Constant folding optimization pass
 pub struct ConstantFoldingPass;
 
@@ -114,30 +248,17 @@ impl OptimizationPass for ConstantFoldingPass {
                 IrInstruction::BinaryOp { dest, op, left, right, var_type } => {
                     // Try to fold constants
                     if let (IrValue::IntConstant(l), IrValue::IntConstant(r)) = (left, right) {
-                        let result = match op {
-                            IrBinaryOp::Add => l + r,
-                            IrBinaryOp::Sub => l - r,
-                            IrBinaryOp::Mul => l * r,
-                            IrBinaryOp::Div if *r != 0 => l / r,
-                            IrBinaryOp::Mod if *r != 0 => l % r,
-                            IrBinaryOp::Eq => if l == r { 1 } else { 0 },
-                            IrBinaryOp::Ne => if l != r { 1 } else { 0 },
-                            IrBinaryOp::Lt => if l < r { 1 } else { 0 },
-                            IrBinaryOp::Le => if l <= r { 1 } else { 0 },
-                            IrBinaryOp::Gt => if l > r { 1 } else { 0 },
-                            IrBinaryOp::Ge => if l >= r { 1 } else { 0 },
-                            _ => {
-                                // Can't fold this operation, keep original
-                                optimized_instructions.push(instruction.clone());
-                                continue;
-                            }
+                        let Some(result) = op.fold_int(*l, *r) else {
+                            // Can't fold this operation, keep original
+                            optimized_instructions.push(instruction.clone());
+                            continue;
                         };
                         
                         // Replace with a move of the constant result
                         optimized_instructions.push(IrInstruction::Move {
                             dest: dest.clone(),
                             src: IrValue::IntConstant(result),
-                            var_type: var_type.clone(),
+                            var_type: if op.produces_bool() { IrType::Bool } else { var_type.clone() },
                         });
                     } else if let (IrValue::FloatConstant(l), IrValue::FloatConstant(r)) = (left, right) {
                         let result = match op {
@@ -157,6 +278,14 @@ impl OptimizationPass for ConstantFoldingPass {
                             }
                         };
                         
+                        if op.produces_bool() {
+                            optimized_instructions.push(IrInstruction::Move {
+                                dest: dest.clone(),
+                                src: IrValue::IntConstant(result as i64),
+                                var_type: IrType::Bool,
+                            });
+                            continue;
+                        }
                         optimized_instructions.push(IrInstruction::Move {
                             dest: dest.clone(),
                             src: IrValue::FloatConstant(result),
@@ -214,6 +343,26 @@ impl OptimizationPass for ConstantFoldingPass {
                         }
                     }
                 }
+                // A branch on a known condition always goes the same way, so
+                // the untaken side is left for dead code elimination
+                IrInstruction::Branch { condition: IrValue::IntConstant(condition), true_label, false_label } => {
+                    let label = if *condition != 0 { true_label } else { false_label };
+                    optimized_instructions.push(IrInstruction::Jump { label: label.clone() });
+                }
+                IrInstruction::CompareBranch { op, left: IrValue::IntConstant(l), right: IrValue::IntConstant(r), true_label, false_label, .. }
+                    if op.fold_int(*l, *r).is_some() => {
+                    let taken = op.fold_int(*l, *r) != Some(0);
+                    let label = if taken { true_label } else { false_label };
+                    optimized_instructions.push(IrInstruction::Jump { label: label.clone() });
+                }
+                // A constant bool is already 0 or 1, so widening it is a plain move
+                IrInstruction::Cast { dest, src: IrValue::IntConstant(value), dest_type, src_type: IrType::Bool } => {
+                    optimized_instructions.push(IrInstruction::Move {
+                        dest: dest.clone(),
+                        src: IrValue::IntConstant(*value),
+                        var_type: dest_type.clone(),
+                    });
+                }
                 _ => {
                     optimized_instructions.push(instruction.clone());
                 }
@@ -254,6 +403,22 @@ impl CopyPropagationPass {
                     var_type: var_type.clone(),
                 }
             }
+            IrInstruction::LoadElement { dest, array, index, var_type } => {
+                IrInstruction::LoadElement {
+                    dest: dest.clone(),
+                    array: array.clone(),
+                    index: self.substitute_value(index, copy_map),
+                    var_type: var_type.clone(),
+                }
+            }
+            IrInstruction::StoreElement { value, array, index, var_type } => {
+                IrInstruction::StoreElement {
+                    value: self.substitute_value(value, copy_map),
+                    array: array.clone(),
+                    index: self.substitute_value(index, copy_map),
+                    var_type: var_type.clone(),
+                }
+            }
             IrInstruction::BinaryOp { dest, op, left, right, var_type } => {
                 IrInstruction::BinaryOp {
                     dest: dest.clone(),
@@ -277,6 +442,14 @@ impl CopyPropagationPass {
                     var_type: var_type.clone(),
                 }
             }
+            IrInstruction::Cast { dest, src, dest_type, src_type } => {
+                IrInstruction::Cast {
+                    dest: dest.clone(),
+                    src: self.substitute_value(src, copy_map),
+                    dest_type: dest_type.clone(),
+                    src_type: src_type.clone(),
+                }
+            }
             IrInstruction::Branch { condition, true_label, false_label } => {
                 IrInstruction::Branch {
                     condition: self.substitute_value(condition, copy_map),
@@ -284,6 +457,16 @@ impl CopyPropagationPass {
                     false_label: false_label.clone(),
                 }
             }
+            IrInstruction::CompareBranch { op, left, right, var_type, true_label, false_label } => {
+                IrInstruction::CompareBranch {
+                    op: op.clone(),
+                    left: self.substitute_value(left, copy_map),
+                    right: self.substitute_value(right, copy_map),
+                    var_type: var_type.clone(),
+                    true_label: true_label.clone(),
+                    false_label: false_label.clone(),
+                }
+            }
             _ => instruction.clone(),
         }
     }
@@ -342,6 +525,95 @@ impl Default for CopyPropagationPass {
     }
 }
 
+/// Common subexpression elimination pass
+///
+/// Within a basic block, a pure instruction (`IrInstruction::is_pure`) or
+/// a load that repeats an earlier one with the same operands becomes a
+/// move from the earlier result. Loads are only shared until the next
+/// instruction with side effects, which may write the memory they read.
+/// Calls are never shared, as each may have effects of its own.
+pub struct CommonSubexpressionEliminationPass;
+
+impl CommonSubexpressionEliminationPass {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// The instruction with its destination blanked, for comparing what
+    /// two instructions compute
+    fn computation(instruction: &IrInstruction) -> IrInstruction {
+        let mut computation = instruction.clone();
+        match &mut computation {
+            IrInstruction::Load { dest, .. } |
+            IrInstruction::BinaryOp { dest, .. } |
+            IrInstruction::UnaryOp { dest, .. } |
+            IrInstruction::Convert { dest, .. } |
+            IrInstruction::Cast { dest, .. } |
+            IrInstruction::AddressOf { dest, .. } => *dest = IrValue::IntConstant(0),
+            _ => {}
+        }
+        computation
+    }
+}
+
+impl OptimizationPass for CommonSubexpressionEliminationPass {
+    fn name(&self) -> &str {
+        "common_subexpression_elimination"
+    }
+
+    fn dependencies(&self) -> Vec<&str> {
+        vec!["copy_propagation"] // Copies replaced by their source compare equal
+    }
+
+    fn run(&mut self, function: &mut IrFunction) -> bool {
+        // Computations available in the current block, with their result
+        let mut available: Vec<(IrInstruction, IrValue)> = Vec::new();
+        // Results replaced by an earlier one, for matching later uses
+        let mut replaced: HashMap<IrValue, IrValue> = HashMap::new();
+        let mut changed = false;
+
+        for instruction in &mut function.instructions {
+            if matches!(instruction, IrInstruction::Label { .. }) || super::ssa::is_terminator(instruction) {
+                available.clear();
+                replaced.clear();
+                continue;
+            }
+            if instruction.has_side_effects() {
+                available.retain(|(computation, _)| !matches!(computation, IrInstruction::Load { .. }));
+            }
+            let Some(dest) = instruction.dest().cloned() else { continue };
+
+            // The old value of `dest` no longer stands for anything
+            available.retain(|(computation, result)| {
+                *result != dest && !DeadStoreEliminationPass::read_values(computation).contains(&&dest)
+            });
+            replaced.retain(|value, result| *value != dest && *result != dest);
+
+            let shareable = instruction.is_pure() || matches!(instruction, IrInstruction::Load { .. });
+            if !shareable || matches!(instruction, IrInstruction::Move { .. }) {
+                continue;
+            }
+            let computation = Self::computation(&CopyPropagationPass.substitute_instruction(instruction, &replaced));
+            match available.iter().find(|(earlier, _)| *earlier == computation) {
+                Some((_, result)) => {
+                    let var_type = instruction.result_type().expect("a pure instruction has a result");
+                    replaced.insert(dest.clone(), result.clone());
+                    *instruction = IrInstruction::Move { dest, src: result.clone(), var_type };
+                    changed = true;
+                }
+                None => available.push((computation, dest)),
+            }
+        }
+        changed
+    }
+}
+
+impl Default for CommonSubexpressionEliminationPass {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Dead code elimination optimization pass
 pub struct DeadCodeEliminationPass;
 
@@ -369,10 +641,18 @@ impl OptimizationPass for DeadCodeEliminationPass {
                 IrInstruction::Store { value, .. } => {
                     used_values.insert(value.clone());
                 }
-                IrInstruction::BinaryOp { left, right, .. } => {
+                IrInstruction::BinaryOp { left, right, .. } |
+                IrInstruction::CompareBranch { left, right, .. } => {
                     used_values.insert(left.clone());
                     used_values.insert(right.clone());
                 }
+                IrInstruction::LoadElement { index, .. } => {
+                    used_values.insert(index.clone());
+                }
+                IrInstruction::StoreElement { value, index, .. } => {
+                    used_values.insert(value.clone());
+                    used_values.insert(index.clone());
+                }
                 IrInstruction::UnaryOp { operand, .. } => {
                     used_values.insert(operand.clone());
                 }
@@ -387,32 +667,32 @@ impl OptimizationPass for DeadCodeEliminationPass {
                         used_values.insert(arg.clone());
                     }
                 }
-                IrInstruction::Print { format_string, args } => {
+                IrInstruction::Print { format_string, args, .. } => {
                     used_values.insert(format_string.clone());
                     for arg in args {
                         used_values.insert(arg.clone());
                     }
                 }
-                IrInstruction::Move { src, .. } => {
+                IrInstruction::Move { src, .. } |
+                IrInstruction::Convert { src, .. } |
+                IrInstruction::Cast { src, .. } => {
                     used_values.insert(src.clone());
                 }
+                IrInstruction::Phi { incoming, .. } => {
+                    for (value, _) in incoming {
+                        used_values.insert(value.clone());
+                    }
+                }
                 _ => {}
             }
         }
         
-        // Second pass: remove instructions that define unused values
+        // Second pass: remove side-effect free instructions that define unused values
         let mut optimized_instructions = Vec::new();
         for instruction in &function.instructions {
-            let should_keep = match instruction {
-                IrInstruction::BinaryOp { dest, .. } |
-                IrInstruction::UnaryOp { dest, .. } |
-                IrInstruction::Move { dest, .. } => {
-                    used_values.contains(dest)
-                }
-                IrInstruction::Load { dest, .. } => {
-                    used_values.contains(dest)
-                }
-                _ => true, // Keep all other instructions (they have side effects)
+            let should_keep = instruction.has_side_effects() || match instruction.dest() {
+                Some(dest) => used_values.contains(dest),
+                None => true,
             };
             
             if should_keep {
@@ -432,8 +712,497 @@ impl Default for DeadCodeEliminationPass {
     }
 }
 
+/// Dead store elimination optimization pass
+///
+/// Removes a `Store` whose location is overwritten by a later `Store` in the
+/// same basic block with no read of that location in between. Calls and
+/// prints are treated as reads of every location, so stores before them are
+/// always kept.
+pub struct DeadStoreEliminationPass;
+
+impl DeadStoreEliminationPass {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Values read by an instruction, not counting the destination of a store
+    fn read_values(instruction: &IrInstruction) -> Vec<&IrValue> {
+        match instruction {
+            IrInstruction::Load { src, .. } => vec![src],
+            IrInstruction::Store { value, .. } => vec![value],
+            IrInstruction::LoadElement { array, index, .. } => vec![array, index],
+            IrInstruction::BinaryOp { left, right, .. } => vec![left, right],
+            IrInstruction::UnaryOp { operand, .. } => vec![operand],
+            IrInstruction::Return { value, .. } => value.iter().collect(),
+            IrInstruction::Branch { condition, .. } => vec![condition],
+            IrInstruction::Move { src, .. } |
+            IrInstruction::Convert { src, .. } |
+            IrInstruction::Cast { src, .. } => vec![src],
+            // The variable may be read through the pointer later on
+            IrInstruction::AddressOf { src, .. } => vec![src],
+            IrInstruction::Phi { incoming, .. } => incoming.iter().map(|(value, _)| value).collect(),
+            _ => vec![],
+        }
+    }
+}
+
+impl OptimizationPass for DeadStoreEliminationPass {
+    fn name(&self) -> &str {
+        "dead_store_elimination"
+    }
+
+    fn dependencies(&self) -> Vec<&str> {
+        vec!["copy_propagation"] // Fewer intervening copies means more killed stores
+    }
+
+    fn run(&mut self, function: &mut IrFunction) -> bool {
+        // Location -> index of the last store to it that has not been read yet
+        let mut pending_stores: HashMap<IrValue, usize> = HashMap::new();
+        let mut dead = vec![false; function.instructions.len()];
+
+        for (index, instruction) in function.instructions.iter().enumerate() {
+            match instruction {
+                IrInstruction::Store { value, dest, .. } => {
+                    pending_stores.remove(value);
+                    if let Some(previous) = pending_stores.insert(dest.clone(), index) {
+                        dead[previous] = true;
+                    }
+                }
+                // Block boundaries, calls and prints: stores may be read on
+                // another path or observed by the callee
+                other if other.has_side_effects() => {
+                    pending_stores.clear();
+                }
+                _ => {
+                    for value in Self::read_values(instruction) {
+                        pending_stores.remove(value);
+                    }
+                }
+            }
+        }
+
+        if !dead.contains(&true) {
+            return false;
+        }
+
+        let instructions = std::mem::take(&mut function.instructions);
+        function.instructions = instructions.into_iter()
+            .zip(dead)
+            .filter_map(|(instruction, is_dead)| (!is_dead).then_some(instruction))
+            .collect();
+        true
+    }
+}
+
+impl Default for DeadStoreEliminationPass {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Default for IrOptimizer {
     fn default() -> Self {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn function_with(instructions: Vec<IrInstruction>) -> IrFunction {
+        IrFunction {
+            name: "main".to_string(),
+            return_type: IrType::Int,
+            parameters: Vec::new(),
+            instructions,
+            local_vars: vec![("x".to_string(), IrType::Int)],
+            local_offsets: HashMap::new(),
+            is_static: false,
+            register_hints: Vec::new(),
+        }
+    }
+
+    fn store_x(value: i64) -> IrInstruction {
+        IrInstruction::Store {
+            value: IrValue::IntConstant(value),
+            dest: IrValue::Local("x".to_string()),
+            var_type: IrType::Int,
+        }
+    }
+
+    fn load_x(temp: usize) -> IrInstruction {
+        IrInstruction::Load {
+            dest: IrValue::Temp(temp),
+            src: IrValue::Local("x".to_string()),
+            var_type: IrType::Int,
+        }
+    }
+
+    /// Claims to change the function on every run without ever converging
+    struct NeverConvergingPass;
+
+    impl OptimizationPass for NeverConvergingPass {
+        fn name(&self) -> &str {
+            "never_converging"
+        }
+
+        fn dependencies(&self) -> Vec<&str> {
+            vec![]
+        }
+
+        fn run(&mut self, _function: &mut IrFunction) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn test_iteration_cap_without_fixpoint_warns() {
+        let mut optimizer = IrOptimizer::with_custom_passes(vec![Box::new(NeverConvergingPass)])
+            .with_max_iterations(3);
+        let program = IrProgram {
+            functions: vec![function_with(vec![store_x(1)])],
+            global_strings: Vec::new(),
+            global_vars: Vec::new(),
+        };
+
+        optimizer.optimize(program);
+
+        assert_eq!(optimizer.warnings().len(), 1);
+        assert!(optimizer.warnings()[0].contains("'main' after 3 iterations"));
+        assert!(optimizer.warnings()[0].contains("never_converging"));
+    }
+
+    #[test]
+    fn test_converging_pipeline_does_not_warn() {
+        let mut optimizer = IrOptimizer::new();
+        let program = IrProgram {
+            functions: vec![function_with(vec![store_x(1), store_x(2)])],
+            global_strings: Vec::new(),
+            global_vars: Vec::new(),
+        };
+
+        optimizer.optimize(program);
+
+        assert!(optimizer.warnings().is_empty());
+    }
+
+    #[test]
+    fn test_iterations_until_fixpoint_are_reported() {
+        // The first iteration removes the dead store, the second changes nothing
+        let program = || IrProgram {
+            functions: vec![function_with(vec![store_x(1), store_x(2)])],
+            global_strings: Vec::new(),
+            global_vars: Vec::new(),
+        };
+
+        let mut optimizer = IrOptimizer::with_pass_names(&["dead_store_elimination"]).unwrap();
+        optimizer.optimize(program());
+        assert_eq!(optimizer.convergence(), &[Convergence { function: "main".to_string(), iterations: 2, reached_fixpoint: true }]);
+
+        let mut optimizer = IrOptimizer::with_pass_names(&["dead_store_elimination"]).unwrap().with_max_iterations(1);
+        let optimized = optimizer.optimize(program());
+        assert_eq!(optimizer.convergence(), &[Convergence { function: "main".to_string(), iterations: 1, reached_fixpoint: false }]);
+        assert_eq!(optimized.functions[0].instructions, vec![store_x(2)]);
+        assert_eq!(optimizer.warnings().len(), 1);
+    }
+
+    #[test]
+    fn test_pass_timing_has_an_entry_for_every_pass() {
+        let program = || IrProgram {
+            functions: vec![function_with(vec![store_x(1), store_x(2)])],
+            global_strings: Vec::new(),
+            global_vars: Vec::new(),
+        };
+
+        let mut untimed = IrOptimizer::new();
+        untimed.optimize(program());
+        assert!(untimed.pass_times().is_none());
+
+        let mut optimizer = IrOptimizer::new().with_pass_timing(true);
+        optimizer.optimize(program());
+        let mut timed: Vec<&str> = optimizer.pass_times().unwrap().keys().map(String::as_str).collect();
+        timed.sort_unstable();
+        let mut registered: Vec<&str> = optimizer.manager.passes.iter().map(|pass| pass.name()).collect();
+        registered.sort_unstable();
+        assert_eq!(timed, registered);
+    }
+
+    #[test]
+    fn test_single_named_pass_does_not_propagate_copies() {
+        // t0 = 1 + 2; t1 = t0; return t1
+        let program = IrProgram {
+            functions: vec![function_with(vec![
+                IrInstruction::BinaryOp {
+                    dest: IrValue::Temp(0),
+                    op: IrBinaryOp::Add,
+                    left: IrValue::IntConstant(1),
+                    right: IrValue::IntConstant(2),
+                    var_type: IrType::Int,
+                },
+                IrInstruction::Move { dest: IrValue::Temp(1), src: IrValue::Temp(0), var_type: IrType::Int },
+                IrInstruction::Return { value: Some(IrValue::Temp(1)), var_type: IrType::Int },
+            ])],
+            global_strings: Vec::new(),
+            global_vars: Vec::new(),
+        };
+
+        let optimized = IrOptimizer::with_pass_names(&["constant_folding"]).unwrap().optimize(program);
+        let instructions = &optimized.functions[0].instructions;
+
+        assert_eq!(instructions[0], IrInstruction::Move { dest: IrValue::Temp(0), src: IrValue::IntConstant(3), var_type: IrType::Int });
+        assert_eq!(instructions[1], IrInstruction::Move { dest: IrValue::Temp(1), src: IrValue::Temp(0), var_type: IrType::Int });
+    }
+
+    #[test]
+    fn test_unknown_pass_name_lists_available_passes() {
+        let error = IrOptimizer::with_pass_names(&["loop_unrolling"]).err().unwrap();
+        assert!(error.contains("loop_unrolling"));
+        assert!(error.contains("constant_folding, copy_propagation"), "{}", error);
+    }
+
+    fn mul(dest: usize, left: usize, right: usize) -> IrInstruction {
+        IrInstruction::BinaryOp {
+            dest: IrValue::Temp(dest),
+            op: IrBinaryOp::Mul,
+            left: IrValue::Temp(left),
+            right: IrValue::Temp(right),
+            var_type: IrType::Int,
+        }
+    }
+
+    fn call_f(dest: usize) -> IrInstruction {
+        IrInstruction::Call { dest: Some(IrValue::Temp(dest)), func: "f".to_string(), args: vec![], return_type: IrType::Int }
+    }
+
+    #[test]
+    fn test_repeated_product_is_computed_once_but_calls_are_not() {
+        // a*b + a*b and f() + f(), with a and b loaded from x
+        let mut function = function_with(vec![
+            load_x(0),
+            load_x(1),
+            mul(2, 0, 1),
+            mul(3, 0, 1),
+            call_f(4),
+            call_f(5),
+        ]);
+
+        assert!(CommonSubexpressionEliminationPass::new().run(&mut function));
+        let instructions = &function.instructions;
+        assert_eq!(instructions[1], IrInstruction::Move { dest: IrValue::Temp(1), src: IrValue::Temp(0), var_type: IrType::Int });
+        assert_eq!(instructions[2], mul(2, 0, 1));
+        assert_eq!(instructions[3], IrInstruction::Move { dest: IrValue::Temp(3), src: IrValue::Temp(2), var_type: IrType::Int });
+        assert_eq!(instructions[4], call_f(4));
+        assert_eq!(instructions[5], call_f(5));
+    }
+
+    #[test]
+    fn test_repeats_in_source_share_their_pure_parts_only() {
+        let source = "int f() { return 1; } int main() { int a = 2; int b = 3; int x = a * b + a * b; int y = f() + f(); a = b; return x + y + a * b; }";
+        let tokens = crate::lexer::Lexer::new(source).tokenize().unwrap();
+        let ast = crate::parser::Parser::new(tokens).parse();
+        let mut program = crate::ir::IrGenerator::new().generate(&ast).unwrap();
+        let function = &mut program.functions[1];
+
+        assert!(CommonSubexpressionEliminationPass::new().run(function));
+        let count = |matches: &dyn Fn(&IrInstruction) -> bool| function.instructions.iter().filter(|instruction| matches(instruction)).count();
+        // The product after the assignment to `a` is computed again
+        assert_eq!(count(&|instruction| matches!(instruction, IrInstruction::BinaryOp { op: IrBinaryOp::Mul, .. })), 2);
+        assert_eq!(count(&|instruction| matches!(instruction, IrInstruction::Call { .. })), 2);
+    }
+
+    #[test]
+    fn test_loads_are_not_shared_across_a_store_or_a_label() {
+        let mut function = function_with(vec![
+            load_x(0),
+            store_x(1),
+            load_x(1),
+            IrInstruction::Label { name: "next".to_string() },
+            load_x(2),
+        ]);
+
+        assert!(!CommonSubexpressionEliminationPass::new().run(&mut function));
+    }
+
+    #[test]
+    fn test_dead_store_is_eliminated() {
+        // x = 1; x = 2; return x;
+        let mut function = function_with(vec![
+            store_x(1),
+            store_x(2),
+            load_x(0),
+            IrInstruction::Return { value: Some(IrValue::Temp(0)), var_type: IrType::Int },
+        ]);
+
+        assert!(DeadStoreEliminationPass::new().run(&mut function));
+        assert_eq!(function.instructions[0], store_x(2));
+        assert_eq!(function.instructions.len(), 3);
+    }
+
+    #[test]
+    fn test_store_read_before_overwrite_is_kept() {
+        let mut function = function_with(vec![store_x(1), load_x(0), store_x(2)]);
+
+        assert!(!DeadStoreEliminationPass::new().run(&mut function));
+        assert_eq!(function.instructions.len(), 3);
+    }
+
+    #[test]
+    fn test_call_counts_as_read() {
+        let mut function = function_with(vec![
+            store_x(1),
+            IrInstruction::Call { dest: None, func: "helper".to_string(), args: vec![], return_type: IrType::Void },
+            store_x(2),
+        ]);
+
+        assert!(!DeadStoreEliminationPass::new().run(&mut function));
+        assert_eq!(function.instructions.len(), 3);
+    }
+
+    #[test]
+    fn test_side_effects_survive_dead_code_elimination() {
+        // Neither the print nor the call's result is used by anything
+        let mut function = function_with(vec![
+            IrInstruction::Print {
+                format_string: IrValue::StringConstant("str_0".to_string()),
+                args: vec![IrValue::IntConstant(1)],
+                newline: true,
+            },
+            IrInstruction::Call { dest: Some(IrValue::Temp(0)), func: "helper".to_string(), args: vec![], return_type: IrType::Int },
+            IrInstruction::Move { dest: IrValue::Temp(1), src: IrValue::IntConstant(2), var_type: IrType::Int },
+        ]);
+
+        assert!(DeadCodeEliminationPass::new().run(&mut function));
+        assert_eq!(function.instructions.len(), 2);
+        assert!(function.instructions.iter().all(IrInstruction::has_side_effects));
+    }
+
+    #[test]
+    fn test_stores_across_blocks_are_kept() {
+        let mut function = function_with(vec![
+            store_x(1),
+            IrInstruction::Label { name: "next".to_string() },
+            store_x(2),
+        ]);
+
+        assert!(!DeadStoreEliminationPass::new().run(&mut function));
+        assert_eq!(function.instructions.len(), 3);
+    }
+
+    #[test]
+    fn test_folded_comparison_stays_bool_until_widened() {
+        let mut function = function_with(vec![
+            IrInstruction::BinaryOp {
+                dest: IrValue::Temp(0),
+                op: IrBinaryOp::Gt,
+                left: IrValue::IntConstant(3),
+                right: IrValue::IntConstant(2),
+                var_type: IrType::Int,
+            },
+            IrInstruction::Cast {
+                dest: IrValue::Temp(1),
+                src: IrValue::Temp(0),
+                dest_type: IrType::Int,
+                src_type: IrType::Bool,
+            },
+            IrInstruction::Return { value: Some(IrValue::Temp(1)), var_type: IrType::Int },
+        ]);
+
+        ConstantFoldingPass::new().run(&mut function);
+        assert_eq!(function.instructions[0], IrInstruction::Move {
+            dest: IrValue::Temp(0),
+            src: IrValue::IntConstant(1),
+            var_type: IrType::Bool,
+        });
+
+        CopyPropagationPass::new().run(&mut function);
+        ConstantFoldingPass::new().run(&mut function);
+        assert_eq!(function.instructions[1], IrInstruction::Move {
+            dest: IrValue::Temp(1),
+            src: IrValue::IntConstant(1),
+            var_type: IrType::Int,
+        });
+    }
+
+    #[test]
+    fn test_branch_on_constant_condition_becomes_jump() {
+        let label = |name: &str| IrInstruction::Label { name: name.to_string() };
+        let mut function = function_with(vec![
+            IrInstruction::Branch {
+                condition: IrValue::IntConstant(1),
+                true_label: "then".to_string(),
+                false_label: "else".to_string(),
+            },
+            label("then"),
+            IrInstruction::CompareBranch {
+                op: IrBinaryOp::Lt,
+                left: IrValue::IntConstant(5),
+                right: IrValue::IntConstant(2),
+                var_type: IrType::Int,
+                true_label: "then".to_string(),
+                false_label: "else".to_string(),
+            },
+            label("else"),
+            IrInstruction::Return { value: Some(IrValue::IntConstant(0)), var_type: IrType::Int },
+        ]);
+
+        assert!(ConstantFoldingPass::new().run(&mut function));
+        assert_eq!(function.instructions[0], IrInstruction::Jump { label: "then".to_string() });
+        assert_eq!(function.instructions[2], IrInstruction::Jump { label: "else".to_string() });
+    }
+
+    #[test]
+    fn test_overflowing_operations_are_not_folded() {
+        let binary = |op: IrBinaryOp, left: i64, right: i64, dest: usize| IrInstruction::BinaryOp {
+            dest: IrValue::Temp(dest),
+            op,
+            left: IrValue::IntConstant(left),
+            right: IrValue::IntConstant(right),
+            var_type: IrType::Int,
+        };
+        let (max, min) = (i64::from(i32::MAX), i64::from(i32::MIN));
+        let overflowing = vec![
+            binary(IrBinaryOp::Add, max, 1, 0),
+            binary(IrBinaryOp::Sub, min, 1, 1),
+            binary(IrBinaryOp::Mul, max, 2, 2),
+            binary(IrBinaryOp::Div, min, -1, 3),
+            binary(IrBinaryOp::Mod, min, -1, 4),
+            binary(IrBinaryOp::Eq, max + 1, min, 5),
+        ];
+        let mut function = function_with(overflowing.clone());
+
+        assert!(!ConstantFoldingPass::new().run(&mut function));
+        assert_eq!(function.instructions, overflowing);
+
+        let mut function = function_with(vec![binary(IrBinaryOp::Add, max - 1, 1, 0)]);
+        assert!(ConstantFoldingPass::new().run(&mut function));
+        assert!(matches!(&function.instructions[0], IrInstruction::Move { src: IrValue::IntConstant(value), .. } if *value == max));
+    }
+
+    #[test]
+    fn test_folding_agrees_with_run_time_ints_at_the_overflow_boundary() {
+        // What the generated code computes on 32-bit ints
+        let run_time = |op: IrBinaryOp, left: i32, right: i32| -> Option<i32> {
+            match op {
+                IrBinaryOp::Add => Some(left.wrapping_add(right)),
+                IrBinaryOp::Sub => Some(left.wrapping_sub(right)),
+                IrBinaryOp::Mul => Some(left.wrapping_mul(right)),
+                IrBinaryOp::Lt => Some((left < right) as i32),
+                IrBinaryOp::Gt => Some((left > right) as i32),
+                IrBinaryOp::BitXor => Some(left ^ right),
+                _ => None,
+            }
+        };
+        let boundary = [i32::MIN, i32::MIN + 1, -1, 0, 1, i32::MAX - 1, i32::MAX];
+        let ops = [IrBinaryOp::Add, IrBinaryOp::Sub, IrBinaryOp::Mul, IrBinaryOp::Lt, IrBinaryOp::Gt, IrBinaryOp::BitXor];
+        for op in ops {
+            for left in boundary {
+                for right in boundary {
+                    if let Some(folded) = op.fold_int(i64::from(left), i64::from(right)) {
+                        assert_eq!(Some(folded), run_time(op.clone(), left, right).map(i64::from), "{} {} {}", left, op, right);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,613 @@
+use super::ir::*;
+use std::collections::{HashMap, HashSet};
+
+/// Read back the text `IrProgram`'s `Display` writes. Temps and locals
+/// print alike as `%name`, so a name is a local when the function declares
+/// it, as a parameter or with an `alloca`, and a temp otherwise.
+pub fn parse_ir(text: &str) -> Result<IrProgram, String> {
+    let mut program = IrProgram { functions: Vec::new(), global_strings: Vec::new(), global_vars: Vec::new() };
+    let mut lines = text.lines().enumerate().map(|(index, line)| (index + 1, line));
+
+    while let Some((number, line)) = lines.next() {
+        if line.is_empty() || line.starts_with(';') {
+            continue;
+        }
+        let at_line = |message: String| format!("line {}: {}", number, message);
+        let mut cursor = Cursor::new(line);
+        if cursor.eat("define ") {
+            let function = parse_function(number, cursor, &mut lines)?;
+            program.functions.push(function);
+        } else {
+            cursor.expect("@").map_err(at_line)?;
+            let label = cursor.name().map_err(at_line)?.to_string();
+            if cursor.eat(" = constant str ") {
+                let content = cursor.string().map_err(at_line)?;
+                program.global_strings.push((label, content));
+            } else {
+                cursor.expect(" = global ").map_err(at_line)?;
+                let var_type = cursor.ir_type().map_err(at_line)?;
+                cursor.expect(" ").map_err(at_line)?;
+                let value = cursor.value(&HashSet::new()).map_err(at_line)?;
+                program.global_vars.push((label, var_type, value));
+            }
+            cursor.end().map_err(at_line)?;
+        }
+    }
+    Ok(program)
+}
+
+/// Print `program` as text and read it back, returning the program read.
+/// Any difference between the two is an error naming where they part,
+/// as the text format and its parser have drifted apart.
+pub fn verify_round_trip(program: &IrProgram) -> Result<IrProgram, String> {
+    let reparsed = parse_ir(&program.to_string())
+        .map_err(|e| format!("IR text does not parse back: {}", e))?;
+    match program.first_difference(&reparsed) {
+        Some(difference) => Err(format!("IR text does not read back as the same program: {}", difference)),
+        None => Ok(reparsed),
+    }
+}
+
+/// The function whose `define` line is `header`, up to its closing brace
+fn parse_function<'a>(
+    header_line: usize,
+    header: Cursor<'a>,
+    lines: &mut impl Iterator<Item = (usize, &'a str)>,
+) -> Result<IrFunction, String> {
+    let mut function = parse_signature(header)
+        .map_err(|e| format!("line {}: {}", header_line, e))?;
+
+    let mut body = Vec::new();
+    for (number, line) in lines.by_ref() {
+        if line == "}" {
+            break;
+        }
+        body.push((number, line));
+    }
+
+    // Every name the function declares, wherever the declaration is
+    let mut locals: HashSet<String> = function.parameters.iter().map(|(name, _)| name.clone()).collect();
+    locals.extend(body.iter().filter_map(|(_, line)| {
+        let rest = line.strip_prefix("  %")?;
+        let (name, opcode) = rest.split_once(" = ")?;
+        opcode.starts_with("alloca ").then(|| name.to_string())
+    }));
+
+    for (number, line) in body {
+        parse_line(line, &locals, &mut function).map_err(|e| format!("line {}: {}", number, e))?;
+    }
+    Ok(function)
+}
+
+/// The function with the linkage, return type, name and parameters after
+/// `define `, and no body yet
+fn parse_signature(mut header: Cursor) -> Result<IrFunction, String> {
+    let is_static = header.eat("internal ");
+    let return_type = header.ir_type()?;
+    header.expect(" @")?;
+    let name = header.name()?.to_string();
+    header.expect("(")?;
+    let mut parameters = Vec::new();
+    while !header.eat(")") {
+        if !parameters.is_empty() {
+            header.expect(", ")?;
+        }
+        let parameter_type = header.ir_type()?;
+        header.expect(" %")?;
+        parameters.push((header.name()?.to_string(), parameter_type));
+    }
+    header.expect(" {")?;
+    header.end()?;
+    Ok(IrFunction {
+        name,
+        return_type,
+        parameters,
+        instructions: Vec::new(),
+        local_vars: Vec::new(),
+        local_offsets: HashMap::new(),
+        is_static,
+        register_hints: Vec::new(),
+    })
+}
+
+/// Append the instruction on `line` to `function`, declaring the local of
+/// an `alloca` along with the slot and register hint it is annotated with
+fn parse_line(line: &str, locals: &HashSet<String>, function: &mut IrFunction) -> Result<(), String> {
+    let Some(text) = line.strip_prefix("  ") else {
+        let name = line.strip_suffix(':').ok_or_else(|| format!("expected a label or an instruction at `{}`", line))?;
+        function.instructions.push(IrInstruction::Label { name: name.to_string() });
+        return Ok(());
+    };
+    let mut cursor = Cursor::new(text);
+
+    if let Some(text) = text.strip_prefix(";") {
+        let text = text.strip_prefix(' ').unwrap_or(text);
+        function.instructions.push(IrInstruction::Comment { text: text.to_string() });
+        return Ok(());
+    }
+    if let Some((name, var_type)) = cursor.alloca()? {
+        if cursor.eat(", offset ") {
+            function.local_offsets.insert(name.clone(), cursor.integer()? as i32);
+        }
+        if cursor.eat(", register") {
+            function.register_hints.push(name.clone());
+        }
+        cursor.end()?;
+        function.local_vars.push((name.clone(), var_type.clone()));
+        function.instructions.push(IrInstruction::Alloca { var_type, name });
+        return Ok(());
+    }
+
+    let instruction = cursor.instruction(locals)?;
+    cursor.end()?;
+    function.instructions.push(instruction);
+    Ok(())
+}
+
+const BINARY_OPS: [IrBinaryOp; 18] = [
+    IrBinaryOp::Add, IrBinaryOp::Sub, IrBinaryOp::Mul, IrBinaryOp::Div, IrBinaryOp::Mod,
+    IrBinaryOp::Eq, IrBinaryOp::Ne, IrBinaryOp::Lt, IrBinaryOp::Le, IrBinaryOp::Gt, IrBinaryOp::Ge,
+    IrBinaryOp::And, IrBinaryOp::Or,
+    IrBinaryOp::BitAnd, IrBinaryOp::BitOr, IrBinaryOp::BitXor, IrBinaryOp::Shl, IrBinaryOp::Shr,
+];
+
+/// The unread rest of one line of IR text
+struct Cursor<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(line: &'a str) -> Self {
+        Cursor { rest: line }
+    }
+
+    /// Consume `expected` if the text continues with it
+    fn eat(&mut self, expected: &str) -> bool {
+        match self.rest.strip_prefix(expected) {
+            Some(rest) => {
+                self.rest = rest;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), String> {
+        if self.eat(expected) {
+            Ok(())
+        } else {
+            Err(format!("expected `{}` at `{}`", expected, self.rest))
+        }
+    }
+
+    fn end(&self) -> Result<(), String> {
+        if self.rest.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("unexpected `{}`", self.rest))
+        }
+    }
+
+    /// Consume the longest prefix made of characters matching `accept`
+    fn take_while(&mut self, accept: impl Fn(char) -> bool) -> &'a str {
+        let end = self.rest.find(|c: char| !accept(c)).unwrap_or(self.rest.len());
+        let (taken, rest) = self.rest.split_at(end);
+        self.rest = rest;
+        taken
+    }
+
+    /// A function, variable or label name
+    fn name(&mut self) -> Result<&'a str, String> {
+        match self.take_while(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.') {
+            "" => Err(format!("expected a name at `{}`", self.rest)),
+            name => Ok(name),
+        }
+    }
+
+    /// An opcode or operator keyword followed by a space
+    fn keyword(&mut self, keyword: &str) -> bool {
+        let followed = self.rest.strip_prefix(keyword).is_some_and(|rest| rest.starts_with(' '));
+        followed && self.eat(keyword) && self.eat(" ")
+    }
+
+    fn integer(&mut self) -> Result<i64, String> {
+        let text = self.take_while(|c| c.is_ascii_digit() || c == '-');
+        text.parse().map_err(|_| format!("expected an integer at `{}{}`", text, self.rest))
+    }
+
+    fn ir_type(&mut self) -> Result<IrType, String> {
+        let mut ir_type = if self.eat("i32") {
+            IrType::Int
+        } else if self.eat("f64") {
+            IrType::Float
+        } else if self.eat("i8") {
+            IrType::Char
+        } else if self.eat("i1") {
+            IrType::Bool
+        } else if self.eat("str") {
+            IrType::String
+        } else if self.eat("void") {
+            IrType::Void
+        } else if self.eat("[") {
+            let count = self.integer()? as usize;
+            self.expect(" x ")?;
+            let element = self.ir_type()?;
+            self.expect("]")?;
+            IrType::Array(Box::new(element), count)
+        } else if self.eat("{ ") {
+            let mut fields = Vec::new();
+            while !self.eat(" }") {
+                if !fields.is_empty() {
+                    self.expect(", ")?;
+                }
+                fields.push(self.ir_type()?);
+            }
+            IrType::Struct(fields)
+        } else {
+            return Err(format!("expected a type at `{}`", self.rest));
+        };
+
+        loop {
+            if self.eat("*") {
+                ir_type = IrType::Pointer(Box::new(ir_type));
+            } else if self.eat(" ()") {
+                ir_type = IrType::Function(Box::new(ir_type));
+            } else {
+                return Ok(ir_type);
+            }
+        }
+    }
+
+    /// An operand; `%name` is a local when `locals` holds the name
+    fn value(&mut self, locals: &HashSet<String>) -> Result<IrValue, String> {
+        if self.eat("%") {
+            let name = self.name()?;
+            let temp = name.strip_prefix('t').and_then(|id| id.parse().ok());
+            return Ok(match temp {
+                Some(id) if !locals.contains(name) => IrValue::Temp(id),
+                _ => IrValue::Local(name.to_string()),
+            });
+        }
+        if self.eat("@") {
+            return Ok(IrValue::Global(self.name()?.to_string()));
+        }
+        if self.rest.starts_with('"') {
+            return Ok(IrValue::StringConstant(self.string()?));
+        }
+        if self.rest.starts_with('\'') {
+            return Ok(IrValue::CharConstant(self.character()?));
+        }
+
+        let text = self.take_while(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '+' | '.'));
+        if text == "nan" && self.eat("(0x") {
+            let bits = self.take_while(|c| c.is_ascii_hexdigit());
+            let bits = u64::from_str_radix(bits, 16).map_err(|_| format!("invalid NaN bits `{}`", bits))?;
+            self.expect(")")?;
+            return Ok(IrValue::FloatConstant(f64::from_bits(bits)));
+        }
+        if text == "nan" {
+            return Ok(IrValue::FloatConstant(f64::NAN));
+        }
+        if let Ok(integer) = text.parse() {
+            Ok(IrValue::IntConstant(integer))
+        } else if let Ok(float) = text.parse() {
+            Ok(IrValue::FloatConstant(float))
+        } else {
+            Err(format!("expected a value at `{}{}`", text, self.rest))
+        }
+    }
+
+    /// Values separated by `, ` up to `close`
+    fn values(&mut self, locals: &HashSet<String>, close: &str) -> Result<Vec<IrValue>, String> {
+        let mut values = Vec::new();
+        while !self.eat(close) {
+            if !values.is_empty() {
+                self.expect(", ")?;
+            }
+            values.push(self.value(locals)?);
+        }
+        Ok(values)
+    }
+
+    /// `"text"` with the escapes `{:?}` writes
+    fn string(&mut self) -> Result<String, String> {
+        self.expect("\"")?;
+        let mut content = String::new();
+        loop {
+            match self.next_char()? {
+                '"' => return Ok(content),
+                '\\' => content.push(self.escape()?),
+                c => content.push(c),
+            }
+        }
+    }
+
+    fn character(&mut self) -> Result<char, String> {
+        self.expect("'")?;
+        let c = match self.next_char()? {
+            '\\' => self.escape()?,
+            c => c,
+        };
+        self.expect("'")?;
+        Ok(c)
+    }
+
+    fn next_char(&mut self) -> Result<char, String> {
+        let mut chars = self.rest.chars();
+        let c = chars.next().ok_or("unterminated literal")?;
+        self.rest = chars.as_str();
+        Ok(c)
+    }
+
+    /// The character an escape sequence stands for, after its backslash
+    fn escape(&mut self) -> Result<char, String> {
+        Ok(match self.next_char()? {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            '0' => '\0',
+            'u' => {
+                self.expect("{")?;
+                let digits = self.take_while(|c| c.is_ascii_hexdigit());
+                self.expect("}")?;
+                u32::from_str_radix(digits, 16).ok().and_then(char::from_u32)
+                    .ok_or_else(|| format!("invalid escape `\\u{{{}}}`", digits))?
+            }
+            c @ ('\\' | '"' | '\'') => c,
+            c => return Err(format!("invalid escape `\\{}`", c)),
+        })
+    }
+
+    /// `label %name`
+    fn label(&mut self) -> Result<String, String> {
+        self.expect("label %")?;
+        Ok(self.name()?.to_string())
+    }
+
+    fn binary_op(&mut self) -> Option<IrBinaryOp> {
+        BINARY_OPS.into_iter().find(|op| self.keyword(&op.to_string()))
+    }
+
+    /// `%name = alloca type`, up to any annotations
+    fn alloca(&mut self) -> Result<Option<(String, IrType)>, String> {
+        let Some((name, rest)) = self.rest.strip_prefix('%').and_then(|rest| rest.split_once(" = alloca ")) else {
+            return Ok(None);
+        };
+        self.rest = rest;
+        Ok(Some((name.to_string(), self.ir_type()?)))
+    }
+
+    fn instruction(&mut self, locals: &HashSet<String>) -> Result<IrInstruction, String> {
+        if self.rest == "trap" {
+            self.rest = "";
+            return Ok(IrInstruction::Trap);
+        }
+        if self.keyword("store") {
+            let var_type = self.ir_type()?;
+            self.expect(" ")?;
+            let value = self.value(locals)?;
+            self.expect(", ")?;
+            return Ok(IrInstruction::Store { value, dest: self.value(locals)?, var_type });
+        }
+        if self.keyword("storeelem") {
+            let var_type = self.ir_type()?;
+            self.expect(" ")?;
+            let value = self.value(locals)?;
+            self.expect(", ")?;
+            let (array, index) = self.element(locals)?;
+            return Ok(IrInstruction::StoreElement { value, array, index, var_type });
+        }
+        if self.keyword("br") {
+            if let Some(op) = self.binary_op() {
+                let var_type = self.ir_type()?;
+                self.expect(" ")?;
+                let left = self.value(locals)?;
+                self.expect(", ")?;
+                let right = self.value(locals)?;
+                self.expect(", ")?;
+                let true_label = self.label()?;
+                self.expect(", ")?;
+                let false_label = self.label()?;
+                return Ok(IrInstruction::CompareBranch { op, left, right, var_type, true_label, false_label });
+            }
+            let condition = self.value(locals)?;
+            self.expect(", ")?;
+            let true_label = self.label()?;
+            self.expect(", ")?;
+            let false_label = self.label()?;
+            return Ok(IrInstruction::Branch { condition, true_label, false_label });
+        }
+        if self.keyword("jmp") {
+            return Ok(IrInstruction::Jump { label: self.label()? });
+        }
+        if self.keyword("ret") {
+            let var_type = self.ir_type()?;
+            let value = if self.eat(" ") { Some(self.value(locals)?) } else { None };
+            return Ok(IrInstruction::Return { value, var_type });
+        }
+        if self.keyword("print") {
+            let format_string = self.value(locals)?;
+            self.expect(", [")?;
+            let args = self.values(locals, "]")?;
+            let newline = self.eat(", newline");
+            return Ok(IrInstruction::Print { format_string, args, newline });
+        }
+        if self.keyword("call") {
+            return self.call(None, locals);
+        }
+
+        let dest = self.value(locals)?;
+        self.expect(" = ")?;
+        if self.keyword("call") {
+            return self.call(Some(dest), locals);
+        }
+        if let Some(op) = self.binary_op() {
+            let var_type = self.ir_type()?;
+            self.expect(" ")?;
+            let left = self.value(locals)?;
+            self.expect(", ")?;
+            let right = self.value(locals)?;
+            return Ok(IrInstruction::BinaryOp { dest, op, left, right, var_type });
+        }
+        for op in [IrUnaryOp::Neg, IrUnaryOp::Not] {
+            if self.keyword(&op.to_string()) {
+                let var_type = self.ir_type()?;
+                self.expect(" ")?;
+                return Ok(IrInstruction::UnaryOp { dest, op, operand: self.value(locals)?, var_type });
+            }
+        }
+        if self.keyword("load") {
+            let var_type = self.ir_type()?;
+            self.expect(", ")?;
+            return Ok(IrInstruction::Load { dest, src: self.value(locals)?, var_type });
+        }
+        if self.keyword("loadelem") {
+            let var_type = self.ir_type()?;
+            self.expect(", ")?;
+            let (array, index) = self.element(locals)?;
+            return Ok(IrInstruction::LoadElement { dest, array, index, var_type });
+        }
+        if self.keyword("mov") {
+            let var_type = self.ir_type()?;
+            self.expect(" ")?;
+            return Ok(IrInstruction::Move { dest, src: self.value(locals)?, var_type });
+        }
+        if self.keyword("addressof") {
+            let var_type = self.ir_type()?;
+            self.expect(" ")?;
+            return Ok(IrInstruction::AddressOf { dest, src: self.value(locals)?, var_type });
+        }
+        for (opcode, is_cast) in [("convert", false), ("cast", true)] {
+            if self.keyword(opcode) {
+                let src_type = self.ir_type()?;
+                self.expect(" ")?;
+                let src = self.value(locals)?;
+                self.expect(" to ")?;
+                let dest_type = self.ir_type()?;
+                return Ok(if is_cast {
+                    IrInstruction::Cast { dest, src, dest_type, src_type }
+                } else {
+                    IrInstruction::Convert { dest, dest_type, src, src_type }
+                });
+            }
+        }
+        if self.keyword("phi") {
+            let var_type = self.ir_type()?;
+            let mut incoming = Vec::new();
+            while self.eat(if incoming.is_empty() { " [" } else { ", [" }) {
+                let value = self.value(locals)?;
+                self.expect(", ")?;
+                let label = self.name()?.to_string();
+                self.expect("]")?;
+                incoming.push((value, label));
+            }
+            return Ok(IrInstruction::Phi { dest, incoming, var_type });
+        }
+        Err(format!("unknown instruction `{}`", self.rest))
+    }
+
+    /// `type func(args)`, after `call `
+    fn call(&mut self, dest: Option<IrValue>, locals: &HashSet<String>) -> Result<IrInstruction, String> {
+        let return_type = self.ir_type()?;
+        self.expect(" ")?;
+        let func = self.name()?.to_string();
+        self.expect("(")?;
+        let args = self.values(locals, ")")?;
+        Ok(IrInstruction::Call { dest, func, args, return_type })
+    }
+
+    /// `array[index]`
+    fn element(&mut self, locals: &HashSet<String>) -> Result<(IrValue, IrValue), String> {
+        let array = self.value(locals)?;
+        self.expect("[")?;
+        let index = self.value(locals)?;
+        self.expect("]")?;
+        Ok((array, index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ir::IrGenerator;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    #[test]
+    fn test_generated_ir_reads_back_unchanged() {
+        let source = r#"
+int twice(int t0) {
+    return t0 * 2;
+}
+
+int main() {
+    register int count = 0;
+    int values[3];
+    float ratio = 1.0;
+    char letter = '\n';
+    int doubled = twice(count);
+    if (count < 3) {
+        println("ratio %f, letter %c", ratio / 2.0, letter);
+    }
+    print("quote \" and tab\t");
+    return values[1] + doubled - -count;
+}
+"#;
+        let tokens = Lexer::new(source).tokenize().expect("Tokenization failed");
+        let mut parser = Parser::new(tokens);
+        let ast = parser.parse();
+        assert!(parser.get_errors().is_empty(), "{:?}", parser.get_errors());
+        let program = IrGenerator::new().with_source_comments(true).generate(&ast).expect("IR generation failed");
+
+        let reparsed = verify_round_trip(&program).unwrap_or_else(|e| panic!("{}\n{}", e, program));
+        assert_eq!(reparsed.to_string(), program.to_string());
+        let main = &reparsed.functions[1];
+        assert_eq!(main.register_hints, vec!["count".to_string()]);
+        assert!(main.local_offsets.contains_key("values"));
+    }
+
+    #[test]
+    fn test_special_float_constants_read_back_bit_for_bit() {
+        let signaling_nan = f64::from_bits(0xfff0_0000_0000_0001);
+        let constants = [f64::INFINITY, f64::NEG_INFINITY, f64::NAN, signaling_nan, -0.0, 1.0, 1e300, 2.5e-8];
+        let instructions = constants.iter().enumerate()
+            .map(|(id, value)| IrInstruction::Move { dest: IrValue::Temp(id), src: IrValue::FloatConstant(*value), var_type: IrType::Float })
+            .chain([IrInstruction::Return { value: Some(IrValue::IntConstant(0)), var_type: IrType::Int }])
+            .collect();
+        let program = IrProgram {
+            functions: vec![IrFunction {
+                name: "main".to_string(),
+                return_type: IrType::Int,
+                parameters: Vec::new(),
+                instructions,
+                local_vars: Vec::new(),
+                local_offsets: HashMap::new(),
+                is_static: false,
+                register_hints: Vec::new(),
+            }],
+            global_strings: Vec::new(),
+            global_vars: vec![("limit".to_string(), IrType::Float, IrValue::FloatConstant(f64::INFINITY))],
+        };
+
+        let text = program.to_string();
+        assert!(text.contains("%t0 = mov f64 inf"), "{}", text);
+        assert!(text.contains("%t1 = mov f64 -inf"), "{}", text);
+        assert!(text.contains("%t2 = mov f64 nan\n"), "{}", text);
+        assert!(text.contains("%t3 = mov f64 nan(0xfff0000000000001)"), "{}", text);
+        assert!(text.contains("%t5 = mov f64 1.0"), "{}", text);
+        verify_round_trip(&program).unwrap_or_else(|e| panic!("{}\n{}", e, text));
+
+        // The same from source, through the lexer's `inf` and `nan`
+        let tokens = Lexer::new("int main() { float big = inf; float odd = nan; return 0; }").tokenize().unwrap();
+        let ast = Parser::new(tokens).parse();
+        let program = IrGenerator::new().generate(&ast).expect("IR generation failed");
+        verify_round_trip(&program).unwrap_or_else(|e| panic!("{}\n{}", e, program));
+    }
+
+    #[test]
+    fn test_malformed_ir_names_the_line() {
+        let text = "define i32 @main() {\n  %t0 = frobnicate i32 1\n}\n";
+        let error = parse_ir(text).unwrap_err();
+        assert!(error.starts_with("line 2:"), "{}", error);
+    }
+}
@@ -0,0 +1,704 @@
+use super::ir::{IrFunction, IrInstruction, IrType, IrValue};
+use super::optimizer::OptimizationPass;
+use std::collections::{HashMap, HashSet};
+
+/// A straight-line run of instructions entered only at its label
+#[derive(Debug, Clone)]
+struct Block {
+    label: String,
+    instructions: Vec<IrInstruction>,
+    successors: Vec<usize>,
+    predecessors: Vec<usize>,
+}
+
+/// Control flow graph of one function, blocks in program order
+struct Cfg {
+    blocks: Vec<Block>,
+}
+
+impl Cfg {
+    /// Split `function` into basic blocks. Blocks that do not start with a
+    /// label get a generated one, so every block can be named by a phi.
+    /// The entry block is always a block of its own with no predecessors.
+    fn build(function: &IrFunction) -> Self {
+        let mut blocks: Vec<Block> = Vec::new();
+        let mut current = Some(Block::new(format!("{}_entry", function.name)));
+
+        for instruction in &function.instructions {
+            if let IrInstruction::Label { name } = instruction {
+                // A previous build already named the entry block
+                if blocks.is_empty() && current.as_ref().is_some_and(|block| block.label == *name && block.instructions.is_empty()) {
+                    continue;
+                }
+                blocks.extend(current.take());
+                current = Some(Block::new(name.clone()));
+                continue;
+            }
+
+            let block = current.get_or_insert_with(|| {
+                Block::new(format!("{}_block_{}", function.name, blocks.len()))
+            });
+            block.instructions.push(instruction.clone());
+
+            if is_terminator(instruction) {
+                blocks.extend(current.take());
+            }
+        }
+        blocks.extend(current);
+
+        let index_of: HashMap<String, usize> = blocks.iter()
+            .enumerate()
+            .map(|(index, block)| (block.label.clone(), index))
+            .collect();
+
+        for index in 0..blocks.len() {
+            let targets: Vec<usize> = match blocks[index].instructions.last() {
+                Some(IrInstruction::Jump { label }) => vec![index_of[label]],
+                Some(IrInstruction::Branch { true_label, false_label, .. }) |
+                Some(IrInstruction::CompareBranch { true_label, false_label, .. }) => {
+                    vec![index_of[true_label], index_of[false_label]]
+                }
+                Some(IrInstruction::Return { .. } | IrInstruction::Trap) => vec![],
+                // Falls through into the next block
+                _ => (index + 1 < blocks.len()).then_some(index + 1).into_iter().collect(),
+            };
+            for target in targets {
+                if !blocks[index].successors.contains(&target) {
+                    blocks[index].successors.push(target);
+                    blocks[target].predecessors.push(index);
+                }
+            }
+        }
+
+        Self { blocks }
+    }
+
+    /// Blocks reachable from the entry, in reverse postorder
+    fn reverse_postorder(&self) -> Vec<usize> {
+        let mut visited = vec![false; self.blocks.len()];
+        let mut postorder = Vec::new();
+        // (block, index of the next successor to visit)
+        let mut stack = vec![(0, 0)];
+        if self.blocks.is_empty() {
+            return postorder;
+        }
+        visited[0] = true;
+
+        while let Some((block, next)) = stack.pop() {
+            if let Some(&successor) = self.blocks[block].successors.get(next) {
+                stack.push((block, next + 1));
+                if !visited[successor] {
+                    visited[successor] = true;
+                    stack.push((successor, 0));
+                }
+            } else {
+                postorder.push(block);
+            }
+        }
+
+        postorder.reverse();
+        postorder
+    }
+
+    /// Immediate dominator of every reachable block (the entry is its own),
+    /// using the iterative algorithm of Cooper, Harvey and Kennedy
+    fn immediate_dominators(&self, order: &[usize]) -> Vec<Option<usize>> {
+        let mut rank = vec![usize::MAX; self.blocks.len()];
+        for (position, &block) in order.iter().enumerate() {
+            rank[block] = position;
+        }
+
+        let mut idom: Vec<Option<usize>> = vec![None; self.blocks.len()];
+        if let Some(&entry) = order.first() {
+            idom[entry] = Some(entry);
+        }
+
+        let intersect = |idom: &[Option<usize>], mut a: usize, mut b: usize| {
+            while a != b {
+                while rank[a] > rank[b] {
+                    a = idom[a].expect("processed block has a dominator");
+                }
+                while rank[b] > rank[a] {
+                    b = idom[b].expect("processed block has a dominator");
+                }
+            }
+            a
+        };
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &block in order.iter().skip(1) {
+                let mut new_idom = None;
+                for &predecessor in &self.blocks[block].predecessors {
+                    if idom[predecessor].is_none() {
+                        continue;
+                    }
+                    new_idom = Some(match new_idom {
+                        None => predecessor,
+                        Some(current) => intersect(&idom, predecessor, current),
+                    });
+                }
+                if new_idom.is_some() && idom[block] != new_idom {
+                    idom[block] = new_idom;
+                    changed = true;
+                }
+            }
+        }
+
+        idom
+    }
+
+    /// Dominance frontier of every block
+    fn dominance_frontiers(&self, idom: &[Option<usize>]) -> Vec<HashSet<usize>> {
+        let mut frontiers = vec![HashSet::new(); self.blocks.len()];
+        for (block, data) in self.blocks.iter().enumerate() {
+            let Some(block_idom) = idom[block] else { continue };
+            if data.predecessors.len() < 2 {
+                continue;
+            }
+            for &predecessor in &data.predecessors {
+                let mut runner = predecessor;
+                while idom[runner].is_some() && runner != block_idom {
+                    frontiers[runner].insert(block);
+                    runner = idom[runner].unwrap();
+                }
+            }
+        }
+        frontiers
+    }
+
+    /// Flatten the blocks back into an instruction list
+    fn into_instructions(self) -> Vec<IrInstruction> {
+        self.blocks.into_iter()
+            .flat_map(|block| {
+                std::iter::once(IrInstruction::Label { name: block.label }).chain(block.instructions)
+            })
+            .collect()
+    }
+}
+
+impl Block {
+    fn new(label: String) -> Self {
+        Self {
+            label,
+            instructions: Vec::new(),
+            successors: Vec::new(),
+            predecessors: Vec::new(),
+        }
+    }
+
+    /// Index of the block's terminator, if it ends in a jump or branch
+    fn terminator_index(&self) -> Option<usize> {
+        self.instructions.last()
+            .filter(|instruction| is_terminator(instruction))
+            .map(|_| self.instructions.len() - 1)
+    }
+}
+
+/// Plain-text listing of the control flow graph of `function`: every
+/// basic block with its id and label, the ids of the blocks it can go to
+/// next, and its instructions
+pub fn format_cfg(function: &IrFunction) -> String {
+    let cfg = Cfg::build(function);
+    let mut text = format!("function {}:\n", function.name);
+    for (id, block) in cfg.blocks.iter().enumerate() {
+        let successors = if block.successors.is_empty() {
+            "exit".to_string()
+        } else {
+            block.successors.iter().map(|successor| format!("bb{}", successor)).collect::<Vec<_>>().join(", ")
+        };
+        text.push_str(&format!("  bb{} ({}) -> {}\n", id, block.label, successors));
+        for instruction in &block.instructions {
+            text.push_str(&format!("      {}\n", instruction.to_string().trim()));
+        }
+    }
+    text
+}
+
+pub(super) fn is_terminator(instruction: &IrInstruction) -> bool {
+    matches!(instruction,
+        IrInstruction::Jump { .. } |
+        IrInstruction::Branch { .. } |
+        IrInstruction::CompareBranch { .. } |
+        IrInstruction::Return { .. } |
+        IrInstruction::Trap)
+}
+
+/// Every value an instruction reads or writes
+pub(super) fn operands(instruction: &IrInstruction) -> Vec<&IrValue> {
+    match instruction {
+        IrInstruction::Load { dest, src, .. } |
+        IrInstruction::Move { dest, src, .. } |
+        IrInstruction::Convert { dest, src, .. } |
+        IrInstruction::Cast { dest, src, .. } |
+        IrInstruction::AddressOf { dest, src, .. } => vec![dest, src],
+        IrInstruction::Store { value, dest, .. } => vec![value, dest],
+        IrInstruction::LoadElement { dest, array, index, .. } => vec![dest, array, index],
+        IrInstruction::StoreElement { value, array, index, .. } => vec![value, array, index],
+        IrInstruction::BinaryOp { dest, left, right, .. } => vec![dest, left, right],
+        IrInstruction::UnaryOp { dest, operand, .. } => vec![dest, operand],
+        IrInstruction::Call { dest, args, .. } => dest.iter().chain(args).collect(),
+        IrInstruction::Print { format_string, args, .. } => std::iter::once(format_string).chain(args).collect(),
+        IrInstruction::Phi { dest, incoming, .. } => {
+            std::iter::once(dest).chain(incoming.iter().map(|(value, _)| value)).collect()
+        }
+        IrInstruction::Branch { condition, .. } => vec![condition],
+        IrInstruction::CompareBranch { left, right, .. } => vec![left, right],
+        IrInstruction::Return { value, .. } => value.iter().collect(),
+        IrInstruction::Alloca { .. } |
+        IrInstruction::Jump { .. } |
+        IrInstruction::Label { .. } |
+        IrInstruction::Trap |
+        IrInstruction::Comment { .. } => vec![],
+    }
+}
+
+/// Mutable view of every value an instruction reads or writes, in the
+/// order of `operands`
+pub(super) fn operands_mut(instruction: &mut IrInstruction) -> Vec<&mut IrValue> {
+    match instruction {
+        IrInstruction::Load { dest, src, .. } |
+        IrInstruction::Move { dest, src, .. } |
+        IrInstruction::Convert { dest, src, .. } |
+        IrInstruction::Cast { dest, src, .. } |
+        IrInstruction::AddressOf { dest, src, .. } => vec![dest, src],
+        IrInstruction::Store { value, dest, .. } => vec![value, dest],
+        IrInstruction::LoadElement { dest, array, index, .. } => vec![dest, array, index],
+        IrInstruction::StoreElement { value, array, index, .. } => vec![value, array, index],
+        IrInstruction::BinaryOp { dest, left, right, .. } => vec![dest, left, right],
+        IrInstruction::UnaryOp { dest, operand, .. } => vec![dest, operand],
+        IrInstruction::Call { dest, args, .. } => dest.iter_mut().chain(args).collect(),
+        IrInstruction::Print { format_string, args, .. } => std::iter::once(format_string).chain(args).collect(),
+        IrInstruction::Phi { dest, incoming, .. } => {
+            std::iter::once(dest).chain(incoming.iter_mut().map(|(value, _)| value)).collect()
+        }
+        IrInstruction::Branch { condition, .. } => vec![condition],
+        IrInstruction::CompareBranch { left, right, .. } => vec![left, right],
+        IrInstruction::Return { value, .. } => value.iter_mut().collect(),
+        IrInstruction::Alloca { .. } |
+        IrInstruction::Jump { .. } |
+        IrInstruction::Label { .. } |
+        IrInstruction::Trap |
+        IrInstruction::Comment { .. } => vec![],
+    }
+}
+
+/// Next unused temporary id in `function`
+pub(super) fn next_temp_id(function: &IrFunction) -> usize {
+    function.instructions.iter()
+        .flat_map(operands)
+        .filter_map(|value| match value {
+            IrValue::Temp(id) => Some(id + 1),
+            _ => None,
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// Value a promoted variable holds before its first store
+fn undefined_value(var_type: &IrType) -> IrValue {
+    match var_type {
+        IrType::Float => IrValue::FloatConstant(0.0),
+        IrType::Char => IrValue::CharConstant('\0'),
+        _ => IrValue::IntConstant(0),
+    }
+}
+
+/// Put `function` into SSA form.
+///
+/// Every local that is only ever loaded and stored (its address is never
+/// taken) stops living in memory: each store defines a fresh versioned
+/// temporary, loads read the version reaching them, and a `Phi` merges
+/// the versions at every join point where they differ. Unreachable blocks
+/// are dropped. Returns true if any local was promoted.
+pub fn construct_ssa(function: &mut IrFunction) -> bool {
+    // Locals whose every use is a plain load or store
+    let mut promotable: HashMap<String, IrType> = function.instructions.iter()
+        .filter_map(|instruction| match instruction {
+            IrInstruction::Alloca { name, var_type } => Some((name.clone(), var_type.clone())),
+            _ => None,
+        })
+        .collect();
+    for instruction in &function.instructions {
+        let escaping: Vec<&IrValue> = match instruction {
+            IrInstruction::Load { .. } | IrInstruction::Alloca { .. } => vec![],
+            IrInstruction::Store { value, .. } => vec![value],
+            other => operands(other),
+        };
+        for value in escaping {
+            if let IrValue::Local(name) = value {
+                promotable.remove(name);
+            }
+        }
+    }
+    if promotable.is_empty() {
+        return false;
+    }
+
+    let mut next_temp = next_temp_id(function);
+    let mut cfg = Cfg::build(function);
+    let order = cfg.reverse_postorder();
+    let idom = cfg.immediate_dominators(&order);
+    let frontiers = cfg.dominance_frontiers(&idom);
+
+    // Place phis at the iterated dominance frontier of each variable's stores
+    let mut phis: Vec<Vec<(String, usize)>> = vec![Vec::new(); cfg.blocks.len()];
+    let mut names: Vec<&String> = promotable.keys().collect();
+    names.sort();
+    for name in names {
+        let mut worklist: Vec<usize> = order.iter().copied()
+            .filter(|&block| cfg.blocks[block].instructions.iter().any(|instruction| {
+                matches!(instruction, IrInstruction::Store { dest: IrValue::Local(dest), .. } if dest == name)
+            }))
+            .collect();
+        let mut has_phi = HashSet::new();
+        while let Some(block) = worklist.pop() {
+            for &frontier in &frontiers[block] {
+                if has_phi.insert(frontier) {
+                    phis[frontier].push((name.clone(), next_temp));
+                    next_temp += 1;
+                    worklist.push(frontier);
+                }
+            }
+        }
+    }
+
+    // Rename along the dominator tree
+    let mut children: Vec<Vec<usize>> = vec![Vec::new(); cfg.blocks.len()];
+    for &block in order.iter().skip(1) {
+        if let Some(parent) = idom[block] {
+            children[parent].push(block);
+        }
+    }
+
+    let mut incoming: Vec<Vec<Vec<(IrValue, String)>>> = phis.iter()
+        .map(|block_phis| vec![Vec::new(); block_phis.len()])
+        .collect();
+    let mut versions: HashMap<String, Vec<IrValue>> = HashMap::new();
+    // (block, true once its children have been pushed)
+    let mut stack = vec![(0, false)];
+    let mut pushed: Vec<Vec<String>> = vec![Vec::new(); cfg.blocks.len()];
+
+    while let Some((block, done)) = stack.pop() {
+        if done {
+            for name in pushed[block].drain(..) {
+                versions.get_mut(&name).unwrap().pop();
+            }
+            continue;
+        }
+
+        for (name, temp) in &phis[block] {
+            versions.entry(name.clone()).or_default().push(IrValue::Temp(*temp));
+            pushed[block].push(name.clone());
+        }
+
+        let instructions = std::mem::take(&mut cfg.blocks[block].instructions);
+        for instruction in instructions {
+            let renamed = match instruction {
+                IrInstruction::Alloca { ref name, .. } if promotable.contains_key(name) => continue,
+                IrInstruction::Load { dest, src: IrValue::Local(name), var_type } if promotable.contains_key(&name) => {
+                    let current = versions.get(&name).and_then(|stack| stack.last()).cloned()
+                        .unwrap_or_else(|| undefined_value(&promotable[&name]));
+                    IrInstruction::Move { dest, src: current, var_type }
+                }
+                IrInstruction::Store { value, dest: IrValue::Local(name), var_type } if promotable.contains_key(&name) => {
+                    let version = IrValue::Temp(next_temp);
+                    next_temp += 1;
+                    versions.entry(name.clone()).or_default().push(version.clone());
+                    pushed[block].push(name);
+                    IrInstruction::Move { dest: version, src: value, var_type }
+                }
+                other => other,
+            };
+            cfg.blocks[block].instructions.push(renamed);
+        }
+
+        let label = cfg.blocks[block].label.clone();
+        for &successor in &cfg.blocks[block].successors {
+            for (index, (name, _)) in phis[successor].iter().enumerate() {
+                let current = versions.get(name).and_then(|stack| stack.last()).cloned()
+                    .unwrap_or_else(|| undefined_value(&promotable[name]));
+                incoming[successor][index].push((current, label.clone()));
+            }
+        }
+
+        stack.push((block, true));
+        for &child in children[block].iter().rev() {
+            stack.push((child, false));
+        }
+    }
+
+    // Insert the phis and drop unreachable blocks
+    let reachable: HashSet<usize> = order.iter().copied().collect();
+    for (block, block_phis) in phis.into_iter().enumerate() {
+        let phi_instructions = block_phis.into_iter()
+            .zip(std::mem::take(&mut incoming[block]))
+            .map(|((name, temp), incoming)| IrInstruction::Phi {
+                dest: IrValue::Temp(temp),
+                incoming,
+                var_type: promotable[&name].clone(),
+            });
+        let body = std::mem::take(&mut cfg.blocks[block].instructions);
+        cfg.blocks[block].instructions = phi_instructions.chain(body).collect();
+    }
+    let mut index = 0;
+    cfg.blocks.retain(|_| {
+        index += 1;
+        reachable.contains(&(index - 1))
+    });
+
+    function.instructions = cfg.into_instructions();
+    function.local_vars.retain(|(name, _)| !promotable.contains_key(name));
+    function.local_offsets.retain(|name, _| !promotable.contains_key(name));
+    function.register_hints.retain(|name| !promotable.contains_key(name));
+    true
+}
+
+/// A phi's copy along one edge: (dest, src, type)
+type EdgeCopy = (IrValue, IrValue, IrType);
+
+/// Lower every `Phi` of `function` to moves at the end of its predecessor
+/// blocks. Edges from a block with several successors into a block with
+/// several predecessors are split first, so a move never runs on a path
+/// that does not lead to its phi.
+pub fn eliminate_phis(function: &mut IrFunction) {
+    let mut next_temp = next_temp_id(function);
+    let mut cfg = Cfg::build(function);
+    let index_of: HashMap<String, usize> = cfg.blocks.iter()
+        .enumerate()
+        .map(|(index, block)| (block.label.clone(), index))
+        .collect();
+
+    // Copies to perform on each (predecessor, block) edge
+    let mut copies: Vec<((usize, usize), Vec<EdgeCopy>)> = Vec::new();
+    for (block, data) in cfg.blocks.iter_mut().enumerate() {
+        let instructions = std::mem::take(&mut data.instructions);
+        for instruction in instructions {
+            match instruction {
+                IrInstruction::Phi { dest, incoming, var_type } => {
+                    for (value, label) in incoming {
+                        let edge = (index_of[&label], block);
+                        let copy = (dest.clone(), value, var_type.clone());
+                        match copies.iter_mut().find(|(existing, _)| *existing == edge) {
+                            Some((_, edge_copies)) => edge_copies.push(copy),
+                            None => copies.push((edge, vec![copy])),
+                        }
+                    }
+                }
+                other => data.instructions.push(other),
+            }
+        }
+    }
+
+    for ((predecessor, block), edge_copies) in copies {
+        let moves = sequentialize(edge_copies, &mut next_temp);
+        let critical = cfg.blocks[predecessor].successors.len() > 1 && cfg.blocks[block].predecessors.len() > 1;
+
+        if critical {
+            let target = cfg.blocks[block].label.clone();
+            let mut split = Block::new(format!("{}_to_{}", cfg.blocks[predecessor].label, target));
+            split.instructions.extend(moves);
+            split.instructions.push(IrInstruction::Jump { label: target.clone() });
+
+            if let Some(terminator) = cfg.blocks[predecessor].instructions.last_mut() {
+                retarget(terminator, &target, &split.label);
+            }
+            // Split blocks end in a jump, so they can go after everything else
+            cfg.blocks.push(split);
+        } else {
+            let predecessor = &mut cfg.blocks[predecessor];
+            let at = predecessor.terminator_index().unwrap_or(predecessor.instructions.len());
+            predecessor.instructions.splice(at..at, moves);
+        }
+    }
+
+    function.instructions = cfg.into_instructions();
+}
+
+/// Order the parallel copies of one edge as moves. When a copy would
+/// overwrite a value another copy still reads, everything goes through
+/// fresh temporaries first.
+fn sequentialize(copies: Vec<EdgeCopy>, next_temp: &mut usize) -> Vec<IrInstruction> {
+    let conflicts = copies.iter().any(|(dest, _, _)| {
+        copies.iter().any(|(other_dest, src, _)| src == dest && other_dest != dest)
+    });
+    if !conflicts {
+        return copies.into_iter()
+            .map(|(dest, src, var_type)| IrInstruction::Move { dest, src, var_type })
+            .collect();
+    }
+
+    let mut moves = Vec::new();
+    let mut staged = Vec::new();
+    for (dest, src, var_type) in copies {
+        let temp = IrValue::Temp(*next_temp);
+        *next_temp += 1;
+        moves.push(IrInstruction::Move { dest: temp.clone(), src, var_type: var_type.clone() });
+        staged.push((dest, temp, var_type));
+    }
+    moves.extend(staged.into_iter().map(|(dest, src, var_type)| IrInstruction::Move { dest, src, var_type }));
+    moves
+}
+
+fn retarget(terminator: &mut IrInstruction, from: &str, to: &str) {
+    let labels: Vec<&mut String> = match terminator {
+        IrInstruction::Jump { label } => vec![label],
+        IrInstruction::Branch { true_label, false_label, .. } |
+        IrInstruction::CompareBranch { true_label, false_label, .. } => vec![true_label, false_label],
+        _ => vec![],
+    };
+    for label in labels {
+        if label == from {
+            *label = to.to_string();
+        }
+    }
+}
+
+/// Optimization pass wrapper around `construct_ssa`, for pipelines that
+/// want to work on SSA form
+pub struct SsaConstructionPass;
+
+impl SsaConstructionPass {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl OptimizationPass for SsaConstructionPass {
+    fn name(&self) -> &str {
+        "ssa_construction"
+    }
+
+    fn dependencies(&self) -> Vec<&str> {
+        vec![]
+    }
+
+    fn run(&mut self, function: &mut IrFunction) -> bool {
+        construct_ssa(function)
+    }
+}
+
+impl Default for SsaConstructionPass {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ir::IrGenerator;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    fn main_function(source: &str) -> IrFunction {
+        let tokens = Lexer::new(source).tokenize().unwrap();
+        let ast = Parser::new(tokens).parse();
+        let program = IrGenerator::new().generate(&ast).unwrap();
+        program.functions.into_iter().find(|function| function.name == "main").unwrap()
+    }
+
+    fn phis(function: &IrFunction) -> Vec<&IrInstruction> {
+        function.instructions.iter()
+            .filter(|instruction| matches!(instruction, IrInstruction::Phi { .. }))
+            .collect()
+    }
+
+    #[test]
+    fn test_conditional_assignment_gets_phi_at_merge() {
+        let mut function = main_function("int main() { int x = 0; int c = 5; if (c > 3) { x = 1; } return x; }");
+        assert!(construct_ssa(&mut function));
+
+        let phis = phis(&function);
+        assert_eq!(phis.len(), 1, "{}", function);
+        let IrInstruction::Phi { dest, incoming, var_type } = phis[0] else { unreachable!() };
+        assert_eq!(*var_type, IrType::Int);
+        assert_eq!(incoming.len(), 2);
+
+        // Each incoming value is the version stored on that path
+        let stored: Vec<&IrValue> = function.instructions.iter()
+            .filter_map(|instruction| match instruction {
+                IrInstruction::Move { dest, src: IrValue::IntConstant(0 | 1), .. } => Some(dest),
+                _ => None,
+            })
+            .collect();
+        assert!(incoming.iter().all(|(value, _)| stored.contains(&value)), "{}", function);
+
+        // The return reads the merged value; x no longer lives in memory
+        assert!(function.instructions.iter().any(|instruction| {
+            matches!(instruction, IrInstruction::Move { src, .. } if src == dest)
+        }));
+        assert!(!function.instructions.iter().any(|instruction| {
+            matches!(instruction, IrInstruction::Load { .. } | IrInstruction::Store { .. } | IrInstruction::Alloca { .. })
+        }), "{}", function);
+        assert!(function.local_vars.is_empty());
+    }
+
+    #[test]
+    fn test_cfg_listing_shows_blocks_and_successors() {
+        // The two returns stand in for the arms of an if/else
+        let function = main_function("int main() { int c = 5; if (c > 3) { return 1; } return 2; }");
+        let text = format_cfg(&function);
+        let headers: Vec<&str> = text.lines().filter(|line| line.starts_with("  bb")).map(str::trim).collect();
+        assert_eq!(headers, vec![
+            "bb0 (main_entry) -> bb1, bb3",
+            "bb1 (if_then_0) -> exit",
+            "bb2 (main_block_2) -> bb3",
+            "bb3 (if_end_1) -> exit",
+        ], "{}", text);
+        assert!(text.starts_with("function main:\n"), "{}", text);
+        assert!(text.contains("\n      br gt i32 %t0, 3, label %if_then_0, label %if_end_1\n  bb1"), "{}", text);
+        assert!(text.contains("\n      ret i32 2\n"), "{}", text);
+    }
+
+    #[test]
+    fn test_address_taken_local_stays_in_memory() {
+        let mut function = main_function("int main() { int x = 0; int p = &x; return x; }");
+        construct_ssa(&mut function);
+        assert!(function.local_vars.iter().any(|(name, _)| name == "x"));
+    }
+
+    #[test]
+    fn test_phi_elimination_splits_critical_edges() {
+        let mut function = main_function(
+            "int main() { int i = 0; while (i < 10) { if (i > 5) { break; } i = i + 1; } return i; }",
+        );
+        construct_ssa(&mut function);
+        assert!(!phis(&function).is_empty());
+
+        eliminate_phis(&mut function);
+        assert!(phis(&function).is_empty(), "{}", function);
+
+        // Every jump and branch target is still defined
+        let labels: HashSet<&String> = function.instructions.iter()
+            .filter_map(|instruction| match instruction {
+                IrInstruction::Label { name } => Some(name),
+                _ => None,
+            })
+            .collect();
+        for instruction in &function.instructions {
+            match instruction {
+                IrInstruction::Jump { label } => assert!(labels.contains(label)),
+                IrInstruction::Branch { true_label, false_label, .. } |
+                IrInstruction::CompareBranch { true_label, false_label, .. } => {
+                    assert!(labels.contains(true_label) && labels.contains(false_label));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    fn test_swapped_phis_use_temporaries() {
+        let a = IrValue::Temp(0);
+        let b = IrValue::Temp(1);
+        let mut next_temp = 2;
+        let moves = sequentialize(
+            vec![(a.clone(), b.clone(), IrType::Int), (b.clone(), a.clone(), IrType::Int)],
+            &mut next_temp,
+        );
+        assert_eq!(moves.len(), 4);
+        assert_eq!(next_temp, 4);
+    }
+}
@@ -1,4 +1,4 @@
-use super::token::{Token, TokenType};
+use super::token::{Token, TokenType, Trivia, TriviaKind, TriviaToken};
 use crate::error::CompilerError;
 use crate::Result;
 
@@ -66,6 +66,75 @@ impl Lexer {
         Ok(tokens)
     }
 
+    /// Tokenizes the complete input keeping whitespace and comments as
+    /// trivia, so the source can be rebuilt from the tokens. The last
+    /// token is `Eof`, leading whatever trivia ends the input.
+    pub fn tokenize_with_trivia(&mut self) -> Result<Vec<TriviaToken>> {
+        let mut tokens = Vec::new();
+
+        loop {
+            let leading = self.scan_trivia(false)?;
+            self.start = self.current;
+            let start_line = self.line;
+            let start_column = self.column;
+
+            if self.is_at_end() {
+                let token = Token::new(TokenType::Eof, String::new(), start_line, start_column);
+                tokens.push(TriviaToken { token, leading, trailing: Vec::new() });
+                return Ok(tokens);
+            }
+
+            match self.scan_token() {
+                Ok(Some(token_type)) => {
+                    let token = Token::new(token_type, self.get_lexeme(), start_line, start_column);
+                    let trailing = self.scan_trivia(true)?;
+                    tokens.push(TriviaToken { token, leading, trailing });
+                }
+                // Comments were taken as trivia before reaching here
+                Ok(None) => unreachable!("comment after trivia"),
+                Err(message) => {
+                    return Err(CompilerError::lex_error(message, start_line, start_column));
+                }
+            }
+        }
+    }
+
+    /// Whitespace and comments ahead, in order. Stops before a line break
+    /// when `same_line` is set.
+    fn scan_trivia(&mut self, same_line: bool) -> Result<Vec<Trivia>> {
+        let mut trivia = Vec::new();
+
+        while !self.is_at_end() {
+            self.start = self.current;
+            let start_line = self.line;
+            let start_column = self.column;
+            let is_space = |c: char| matches!(c, ' ' | '\r' | '\t') || (c == '\n' && !same_line);
+            let kind = if is_space(self.peek()) {
+                while !self.is_at_end() && is_space(self.peek()) {
+                    if self.advance() == '\n' {
+                        self.line += 1;
+                        self.column = 1;
+                    }
+                }
+                TriviaKind::Whitespace
+            } else if self.peek() == '/' && self.peek_next() == '/' {
+                self.skip_line_comment();
+                TriviaKind::LineComment
+            } else if self.peek() == '/' && self.peek_next() == '*' {
+                self.advance();
+                self.advance();
+                self.skip_block_comment()
+                    .map_err(|message| CompilerError::lex_error(message, start_line, start_column))?;
+                TriviaKind::BlockComment
+            } else {
+                break;
+            };
+            trivia.push(Trivia { kind, text: self.get_lexeme() });
+        }
+
+        Ok(trivia)
+    }
+
     fn scan_token(&mut self) -> std::result::Result<Option<TokenType>, String> {
         let c = self.advance();
 
@@ -78,6 +147,13 @@ impl Lexer {
             ']' => Ok(Some(TokenType::RightBracket)),
             ';' => Ok(Some(TokenType::Semicolon)),
             ',' => Ok(Some(TokenType::Comma)),
+            ':' => {
+                if self.match_char(':') {
+                    Ok(Some(TokenType::ColonColon))
+                } else {
+                    Ok(Some(TokenType::Colon))
+                }
+            }
             '+' => Ok(Some(TokenType::Plus)),
             '-' => Ok(Some(TokenType::Minus)),
             '*' => Ok(Some(TokenType::Multiply)),
@@ -111,6 +187,8 @@ impl Lexer {
             '<' => {
                 if self.match_char('=') {
                     Ok(Some(TokenType::LessEqual))
+                } else if self.match_char('<') {
+                    Ok(Some(TokenType::ShiftLeft))
                 } else {
                     Ok(Some(TokenType::LessThan))
                 }
@@ -118,6 +196,8 @@ impl Lexer {
             '>' => {
                 if self.match_char('=') {
                     Ok(Some(TokenType::GreaterEqual))
+                } else if self.match_char('>') {
+                    Ok(Some(TokenType::ShiftRight))
                 } else {
                     Ok(Some(TokenType::GreaterThan))
                 }
@@ -126,16 +206,23 @@ impl Lexer {
                 if self.match_char('&') {
                     Ok(Some(TokenType::LogicalAnd))
                 } else {
-                    Err("Unexpected character '&'".to_string())
+                    Ok(Some(TokenType::Ampersand))
                 }
             }
             '|' => {
                 if self.match_char('|') {
                     Ok(Some(TokenType::LogicalOr))
                 } else {
-                    Err("Unexpected character '|'".to_string())
+                    Ok(Some(TokenType::BitOr))
                 }
             }
+            '^' => Ok(Some(TokenType::BitXor)),
+
+            '.' if self.peek() == '.' && self.peek_next() == '.' => {
+                self.advance();
+                self.advance();
+                Ok(Some(TokenType::Ellipsis))
+            }
 
             '"' => Ok(Some(self.string()?)),
 
@@ -214,6 +301,11 @@ impl Lexer {
     }
 
     fn number(&mut self) -> std::result::Result<TokenType, String> {
+        if self.get_lexeme() == "0" && matches!(self.peek(), 'x' | 'X') {
+            self.advance();
+            return self.hex_number();
+        }
+
         while self.peek().is_ascii_digit() {
             self.advance();
         }
@@ -235,6 +327,80 @@ impl Lexer {
         }
     }
 
+    /// A number after its `0x`: a hexadecimal integer, or a hexadecimal
+    /// float such as `0x1.8p3` (1.5 * 2^3), whose binary exponent is
+    /// required as in C
+    fn hex_number(&mut self) -> std::result::Result<TokenType, String> {
+        // The leading 64 bits of the digits, exactly; `sticky` records
+        // whether any digit past them is nonzero, and `dropped_digits` how
+        // many integer digits they leave out
+        let mut mantissa = 0u64;
+        let mut sticky = false;
+        let mut dropped_digits: i64 = 0;
+        let mut digits = 0;
+        let mut fraction_digits: i64 = 0;
+        let mut seen_point = false;
+        loop {
+            let c = self.peek();
+            if let Some(digit) = c.to_digit(16) {
+                digits += 1;
+                if mantissa >> 60 == 0 {
+                    mantissa = mantissa * 16 + u64::from(digit);
+                    if seen_point {
+                        fraction_digits += 1;
+                    }
+                } else {
+                    sticky |= digit != 0;
+                    if !seen_point {
+                        dropped_digits += 1;
+                    }
+                }
+            } else if c == '.' && !seen_point {
+                seen_point = true;
+            } else {
+                break;
+            }
+            self.advance();
+        }
+        if digits == 0 {
+            return Err(format!("Malformed hexadecimal number '{}': no digits", self.get_lexeme()));
+        }
+
+        if !matches!(self.peek(), 'p' | 'P') {
+            if seen_point {
+                return Err(format!("Malformed hexadecimal float '{}': missing 'p' exponent", self.get_lexeme()));
+            }
+            let lexeme = self.get_lexeme();
+            let value = i64::from_str_radix(&lexeme[2..], 16)
+                .map_err(|_| format!("Hexadecimal integer '{}' is out of range", lexeme))?;
+            return Ok(TokenType::Integer(value));
+        }
+        self.advance();
+
+        let negative = self.peek() == '-';
+        if matches!(self.peek(), '+' | '-') {
+            self.advance();
+        }
+        let mut exponent: i32 = 0;
+        let mut exponent_digits = 0;
+        while let Some(digit) = self.peek().to_digit(10) {
+            exponent = exponent.saturating_mul(10).saturating_add(digit as i32);
+            exponent_digits += 1;
+            self.advance();
+        }
+        if exponent_digits == 0 {
+            return Err(format!("Malformed hexadecimal float '{}': exponent has no digits", self.get_lexeme()));
+        }
+        if negative {
+            exponent = -exponent;
+        }
+
+        // Each fraction digit is four more bits below the point, and each
+        // dropped integer digit four more above it
+        let scale = i64::from(exponent) + 4 * (dropped_digits - fraction_digits);
+        Ok(TokenType::Float(hex_float_value(mantissa, sticky, scale)))
+    }
+
     fn identifier(&mut self) -> std::result::Result<TokenType, String> {
         while self.peek().is_ascii_alphanumeric() || self.peek() == '_' {
             self.advance();
@@ -246,7 +412,13 @@ impl Lexer {
             "int" => TokenType::Int,
             "float" => TokenType::FloatType,
             "char" => TokenType::CharType,
+            "bool" => TokenType::BoolType,
+            "true" => TokenType::True,
+            "false" => TokenType::False,
             "void" => TokenType::Void,
+            "static" => TokenType::Static,
+            "register" => TokenType::Register,
+            "const" => TokenType::Const,
             "if" => TokenType::If,
             "else" => TokenType::Else,
             "while" => TokenType::While,
@@ -254,7 +426,12 @@ impl Lexer {
             "return" => TokenType::Return,
             "break" => TokenType::Break,
             "continue" => TokenType::Continue,
+            "sizeof" => TokenType::Sizeof,
             "println" => TokenType::Println,
+            "print" => TokenType::Print,
+            "printf" => TokenType::Printf,
+            "inf" => TokenType::Float(f64::INFINITY),
+            "nan" => TokenType::Float(f64::NAN),
             _ => TokenType::Identifier(text),
         };
 
@@ -343,6 +520,46 @@ impl Lexer {
     }
 }
 
+/// `mantissa * 2^exponent` rounded once to the nearest double, ties to
+/// even. `sticky` tells that nonzero bits below the mantissa were cut off,
+/// which makes a tie round up.
+fn hex_float_value(mantissa: u64, sticky: bool, exponent: i64) -> f64 {
+    if mantissa == 0 {
+        return 0.0;
+    }
+    let bits = 64 - i64::from(mantissa.leading_zeros());
+    // The value lies in [2^top, 2^(top + 1))
+    let top = exponent + bits - 1;
+    if top > 1023 {
+        return f64::INFINITY;
+    }
+    // Doubles keep 53 significant bits, subnormals one fewer per binade
+    // below 2^-1022
+    let precision = 53 - (-1022 - top).max(0);
+    if precision < 0 {
+        return 0.0;
+    }
+    let shift = bits - precision;
+    let (kept, scale) = if shift > 0 {
+        let mantissa = u128::from(mantissa);
+        let kept = mantissa >> shift;
+        let dropped = mantissa - (kept << shift);
+        let half = 1u128 << (shift - 1);
+        let round_up = dropped > half || (dropped == half && (sticky || kept & 1 == 1));
+        (kept as u64 + u64::from(round_up), exponent + shift)
+    } else {
+        (mantissa, exponent)
+    };
+    // `kept` has at most 54 bits and `scale` is at least -1074, so both
+    // factors are exact and only an overflow to infinity can round
+    let power_of_two = if scale >= -1022 {
+        f64::from_bits(((scale + 1023) as u64) << 52)
+    } else {
+        f64::from_bits(1u64 << (scale + 1074))
+    };
+    kept as f64 * power_of_two
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -363,6 +580,20 @@ mod tests {
         assert_eq!(tokens[8].token_type, TokenType::Eof);
     }
 
+    #[test]
+    fn test_colons() {
+        let mut lexer = Lexer::new("done: a::b ::: x");
+        let types: Vec<TokenType> = lexer.tokenize().unwrap().into_iter().map(|token| token.token_type).collect();
+
+        let name = |name: &str| TokenType::Identifier(name.to_string());
+        assert_eq!(types, vec![
+            name("done"), TokenType::Colon,
+            name("a"), TokenType::ColonColon, name("b"),
+            TokenType::ColonColon, TokenType::Colon,
+            name("x"), TokenType::Eof,
+        ]);
+    }
+
     #[test]
     fn test_arithmetic_operators() {
         let mut lexer = Lexer::new("+ - * / %");
@@ -413,6 +644,21 @@ mod tests {
         assert_eq!(tokens[4].token_type, TokenType::Eof);
     }
 
+    #[test]
+    fn test_dangling_else_fixture() {
+        let mut lexer = Lexer::new("if (a) if (b) x; else y;");
+        let types: Vec<TokenType> = lexer.tokenize().unwrap().into_iter().map(|token| token.token_type).collect();
+        let name = |n: &str| TokenType::Identifier(n.to_string());
+
+        assert_eq!(types, vec![
+            TokenType::If, TokenType::LeftParen, name("a"), TokenType::RightParen,
+            TokenType::If, TokenType::LeftParen, name("b"), TokenType::RightParen,
+            name("x"), TokenType::Semicolon,
+            TokenType::Else, name("y"), TokenType::Semicolon,
+            TokenType::Eof,
+        ]);
+    }
+
     #[test]
     fn test_keywords() {
         let mut lexer = Lexer::new("int float char void if else while for return break continue");
@@ -432,6 +678,18 @@ mod tests {
         assert_eq!(tokens[11].token_type, TokenType::Eof);
     }
 
+    #[test]
+    fn test_bool_keywords() {
+        let mut lexer = Lexer::new("bool true false truthy");
+        let tokens = lexer.tokenize().unwrap();
+
+        assert_eq!(tokens[0].token_type, TokenType::BoolType);
+        assert_eq!(tokens[1].token_type, TokenType::True);
+        assert_eq!(tokens[2].token_type, TokenType::False);
+        assert_eq!(tokens[3].token_type, TokenType::Identifier("truthy".to_string()));
+        assert_eq!(tokens[4].token_type, TokenType::Eof);
+    }
+
     #[test]
     fn test_identifiers() {
         let mut lexer = Lexer::new("variable_name _private myVar test123 _");
@@ -467,6 +725,36 @@ mod tests {
         assert_eq!(tokens[3].token_type, TokenType::Eof);
     }
 
+    #[test]
+    fn test_hex_floats_and_special_values() {
+        let bits = |source: &str| match Lexer::new(source).tokenize().unwrap()[0].token_type {
+            TokenType::Float(value) => value.to_bits(),
+            ref other => panic!("{} lexed as {:?}", source, other),
+        };
+        assert_eq!(bits("0x1.8p3"), 12.0f64.to_bits());
+        assert_eq!(bits("0X.4P-1"), 0.125f64.to_bits());
+        assert_eq!(bits("0x1p+10"), 1024.0f64.to_bits());
+        // Just above halfway between 1 and the next double: the digits
+        // past the first 64 bits must still round it up
+        assert_eq!(bits("0x1.00000000000008000001p0"), (1.0 + f64::EPSILON).to_bits());
+        assert_eq!(bits("0x100000000000008000001p-80"), (1.0 + f64::EPSILON).to_bits());
+        assert_eq!(bits("0x1.00000000000008p0"), 1.0f64.to_bits());
+        // Subnormals round once, at their own precision
+        assert_eq!(bits("0x1p-1074"), 1);
+        assert_eq!(bits("0x1.8p-1074"), 2);
+        assert_eq!(bits("0x1p-1075"), 0);
+        assert_eq!(bits("0x1.000001p-1075"), 1);
+        assert_eq!(bits("0x1.fffffffffffff8p1023"), f64::INFINITY.to_bits());
+        assert_eq!(bits("0x1.fffffffffffffp1023"), f64::MAX.to_bits());
+        assert_eq!(bits("inf"), f64::INFINITY.to_bits());
+        assert!(f64::from_bits(bits("nan")).is_nan());
+
+        assert_eq!(Lexer::new("0x1F").tokenize().unwrap()[0].token_type, TokenType::Integer(31));
+        for malformed in ["0x", "0x1.8", "0x1p", "0x.p2"] {
+            assert!(Lexer::new(malformed).tokenize().is_err(), "{} should not lex", malformed);
+        }
+    }
+
     #[test]
     fn test_strings() {
         let mut lexer = Lexer::new(r#""hello" "world with spaces" "" "with\nnewline""#);
@@ -662,17 +950,59 @@ mod tests {
     }
 
     #[test]
-    fn test_single_ampersand_error() {
+    fn test_trivia_mode_keeps_comments_and_whitespace() {
+        let source = "int x; // count\n/* doc */ int y;\n";
+        let tokens = Lexer::new(source).tokenize_with_trivia().unwrap();
+
+        let rebuilt: String = tokens.iter().map(TriviaToken::source).collect();
+        assert_eq!(rebuilt, source);
+
+        // The same-line comment trails `;`, the next line leads `int`
+        let semicolon = &tokens[2];
+        assert_eq!(semicolon.token.token_type, TokenType::Semicolon);
+        assert_eq!(semicolon.trailing, vec![
+            Trivia { kind: TriviaKind::Whitespace, text: " ".to_string() },
+            Trivia { kind: TriviaKind::LineComment, text: "// count".to_string() },
+        ]);
+        let int = &tokens[3];
+        assert_eq!((int.token.line, int.token.column), (2, 11));
+        assert_eq!(int.leading.iter().map(|trivia| trivia.kind.clone()).collect::<Vec<_>>(), vec![
+            TriviaKind::Whitespace, TriviaKind::BlockComment, TriviaKind::Whitespace,
+        ]);
+
+        // Without trivia the same source lexes to the same tokens
+        let plain: Vec<TokenType> = Lexer::new(source).tokenize().unwrap().into_iter().map(|token| token.token_type).collect();
+        let kept: Vec<TokenType> = tokens.into_iter().map(|token| token.token.token_type).collect();
+        assert_eq!(plain, kept);
+    }
+
+    #[test]
+    fn test_ellipsis() {
+        let tokens = Lexer::new("int, ...)").tokenize().unwrap();
+        assert_eq!(tokens[2].token_type, TokenType::Ellipsis);
+        assert_eq!(tokens[3].token_type, TokenType::RightParen);
+        assert!(Lexer::new("..").tokenize().is_err());
+    }
+
+    #[test]
+    fn test_single_ampersand() {
         let mut lexer = Lexer::new("&");
-        let result = lexer.tokenize();
-        assert!(result.is_err());
+        let tokens = lexer.tokenize().unwrap();
+        assert_eq!(tokens[0].token_type, TokenType::Ampersand);
     }
 
     #[test]
-    fn test_single_pipe_error() {
-        let mut lexer = Lexer::new("|");
-        let result = lexer.tokenize();
-        assert!(result.is_err());
+    fn test_bitwise_operators() {
+        let types: Vec<TokenType> = Lexer::new("| || ^ & && << <= < >> >= > <<=").tokenize().unwrap()
+            .into_iter().map(|token| token.token_type).collect();
+        assert_eq!(types, vec![
+            TokenType::BitOr, TokenType::LogicalOr, TokenType::BitXor,
+            TokenType::Ampersand, TokenType::LogicalAnd,
+            TokenType::ShiftLeft, TokenType::LessEqual, TokenType::LessThan,
+            TokenType::ShiftRight, TokenType::GreaterEqual, TokenType::GreaterThan,
+            TokenType::ShiftLeft, TokenType::Assign,
+            TokenType::Eof,
+        ]);
     }
 
     #[test]
@@ -1,5 +1,7 @@
 pub mod token;
 pub mod lexer;
+pub mod preprocessor;
 
-pub use token::{Token, TokenType};
-pub use lexer::Lexer;
\ No newline at end of file
+pub use token::{Token, TokenType, Trivia, TriviaKind, TriviaToken};
+pub use lexer::Lexer;
+pub use preprocessor::{preprocess, preprocess_file, preprocess_source, PreprocessedSource, SourceLine};
\ No newline at end of file
@@ -0,0 +1,419 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::CompilerError;
+use crate::Result;
+
+/// Where a line of preprocessed output came from
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceLine {
+    pub file: String,
+    pub line: usize,
+}
+
+/// Preprocessed code plus the origin of each of its lines
+#[derive(Debug, Clone)]
+pub struct PreprocessedSource {
+    pub code: String,
+    line_map: Vec<SourceLine>,
+    dependencies: Vec<String>,
+    /// Name and text of every file read, as written before preprocessing
+    files: Vec<(String, String)>,
+}
+
+impl PreprocessedSource {
+    /// Original file and line of a (1-based) line of the preprocessed code
+    pub fn locate(&self, line: usize) -> Option<&SourceLine> {
+        self.line_map.get(line.checked_sub(1)?)
+    }
+
+    /// Text of a file named by `locate`, as written before preprocessing
+    pub fn file_text(&self, file: &str) -> Option<&str> {
+        self.files.iter().find(|(name, _)| name == file).map(|(_, text)| text.as_str())
+    }
+
+    /// Files read to produce the code: the main file, when compiling one,
+    /// then every included file in the order first included
+    pub fn dependencies(&self) -> &[String] {
+        &self.dependencies
+    }
+
+    /// A `make` rule stating that `target` depends on every file read
+    pub fn make_rule(&self, target: &str) -> String {
+        let prerequisites: Vec<String> = self.dependencies.iter().map(|path| escape_make_path(path)).collect();
+        format!("{}: {}\n", escape_make_path(target), prerequisites.join(" "))
+    }
+}
+
+/// Escape the characters `make` would otherwise split or expand a path on
+fn escape_make_path(path: &str) -> String {
+    let mut escaped = String::with_capacity(path.len());
+    for c in path.chars() {
+        match c {
+            ' ' | '#' => escaped.push('\\'),
+            '$' => escaped.push('$'),
+            _ => {}
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Runs the preprocessing pass over raw source before lexing.
+///
+/// Only object-like `#define NAME value` macros are supported. Directive
+/// lines are replaced by blank lines so token line numbers stay correct.
+/// A macro is never expanded inside its own expansion, as in C, so
+/// self-referencing or mutually recursive macros terminate.
+pub fn preprocess(source: &str) -> Result<String> {
+    let mut preprocessor = Preprocessor::new(Vec::new());
+    preprocessor.process(source, "<input>", None)?;
+    Ok(preprocessor.output)
+}
+
+/// Preprocess a source file, splicing in `#include "file"` directives.
+/// Includes are looked up next to the including file first, then in
+/// each of `include_dirs`.
+pub fn preprocess_file(path: &Path, include_dirs: &[PathBuf]) -> Result<PreprocessedSource> {
+    let source = fs::read_to_string(path)
+        .map_err(|e| CompilerError::io(format!("cannot read '{}': {}", path.display(), e)))?;
+
+    let mut preprocessor = Preprocessor::new(include_dirs.to_vec());
+    preprocessor.include_stack.push(canonical(path));
+    preprocessor.dependencies.push(path.display().to_string());
+    preprocessor.process(&source, &path.display().to_string(), path.parent())?;
+
+    Ok(preprocessor.finish())
+}
+
+/// Preprocess in-memory source named `name` in diagnostics; includes are
+/// looked up in `include_dirs` only
+pub fn preprocess_source(source: &str, name: &str, include_dirs: &[PathBuf]) -> Result<PreprocessedSource> {
+    let mut preprocessor = Preprocessor::new(include_dirs.to_vec());
+    preprocessor.process(source, name, None)?;
+    Ok(preprocessor.finish())
+}
+
+struct Preprocessor {
+    include_dirs: Vec<PathBuf>,
+    macros: HashMap<String, String>,
+    /// Files currently being processed, outermost first, for cycle detection
+    include_stack: Vec<PathBuf>,
+    output: String,
+    line_map: Vec<SourceLine>,
+    /// Every file read so far, without repeats
+    dependencies: Vec<String>,
+    files: Vec<(String, String)>,
+}
+
+impl Preprocessor {
+    fn new(include_dirs: Vec<PathBuf>) -> Self {
+        Self {
+            include_dirs,
+            macros: HashMap::new(),
+            include_stack: Vec::new(),
+            output: String::new(),
+            line_map: Vec::new(),
+            dependencies: Vec::new(),
+            files: Vec::new(),
+        }
+    }
+
+    fn process(&mut self, source: &str, file: &str, dir: Option<&Path>) -> Result<()> {
+        let mut in_comment = false;
+        if !self.files.iter().any(|(name, _)| name == file) {
+            self.files.push((file.to_string(), source.to_string()));
+        }
+
+        for (index, line) in source.lines().enumerate() {
+            let line_number = index + 1;
+            let trimmed = line.trim_start();
+
+            if !in_comment && trimmed.starts_with('#') {
+                let column = line.len() - trimmed.len() + 1;
+                let error = |message: String| CompilerError::lex_error(format!("{}: {}", file, message), line_number, column);
+                let directive = trimmed[1..].trim_start();
+
+                if let Some(rest) = directive.strip_prefix("define").filter(|rest| rest.starts_with(char::is_whitespace)) {
+                    let (name, body) = parse_define(rest).map_err(error)?;
+
+                    if let Some(existing) = self.macros.get(&name)
+                        && normalize(existing) != normalize(&body) {
+                        return Err(error(format!("Macro '{}' redefined with a different value", name)));
+                    }
+                    self.macros.insert(name, body);
+                } else if let Some(rest) = directive.strip_prefix("include") {
+                    let name = strip_line_comment(rest).trim();
+                    let name = name.strip_prefix('"').and_then(|n| n.strip_suffix('"'))
+                        .ok_or_else(|| error("Expected '#include \"file\"'".to_string()))?;
+                    let (path, source) = self.open_include(name, dir).map_err(error)?;
+                    let display = path.display().to_string();
+                    if !self.dependencies.contains(&display) {
+                        self.dependencies.push(display);
+                    }
+
+                    self.include_stack.push(canonical(&path));
+                    self.process(&source, &path.display().to_string(), path.parent())?;
+                    self.include_stack.pop();
+                    continue;
+                } else {
+                    return Err(error(format!("Unsupported preprocessor directive '#{}'", directive)));
+                }
+
+                self.push_line("", file, line_number);
+                continue;
+            }
+
+            let mut active = Vec::new();
+            let expanded = expand(line, &self.macros, &mut active, &mut in_comment);
+            self.push_line(&expanded, file, line_number);
+        }
+
+        Ok(())
+    }
+
+    /// Find an include file and read it, refusing to re-enter a file that
+    /// is already being processed
+    fn open_include(&self, name: &str, dir: Option<&Path>) -> std::result::Result<(PathBuf, String), String> {
+        let path = dir.into_iter()
+            .chain(self.include_dirs.iter().map(PathBuf::as_path))
+            .map(|dir| dir.join(name))
+            .find(|candidate| candidate.is_file())
+            .ok_or_else(|| format!("Cannot find include file '{}'", name))?;
+
+        let key = canonical(&path);
+        if self.include_stack.contains(&key) {
+            let cycle: Vec<String> = self.include_stack.iter()
+                .chain(std::iter::once(&key))
+                .map(|p| p.display().to_string())
+                .collect();
+            return Err(format!("Include cycle: {}", cycle.join(" -> ")));
+        }
+
+        let source = fs::read_to_string(&path)
+            .map_err(|e| format!("Cannot read include file '{}': {}", path.display(), e))?;
+        Ok((path, source))
+    }
+
+    fn finish(self) -> PreprocessedSource {
+        PreprocessedSource { code: self.output, line_map: self.line_map, dependencies: self.dependencies, files: self.files }
+    }
+
+    fn push_line(&mut self, text: &str, file: &str, line: usize) {
+        self.output.push_str(text);
+        self.output.push('\n');
+        self.line_map.push(SourceLine { file: file.to_string(), line });
+    }
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Split the text after `#define` into the macro name and its body
+fn parse_define(rest: &str) -> std::result::Result<(String, String), String> {
+    let rest = rest.trim_start();
+    let name_len = rest
+        .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .unwrap_or(rest.len());
+    let name = &rest[..name_len];
+
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        return Err("Expected a macro name after '#define'".to_string());
+    }
+    if rest[name_len..].starts_with('(') {
+        return Err(format!("Function-like macro '{}' is not supported", name));
+    }
+
+    let body = strip_line_comment(&rest[name_len..]).trim();
+    Ok((name.to_string(), body.to_string()))
+}
+
+/// Substitute macro names in `text`, skipping string and character
+/// literals and comments. `active` holds the macros currently being
+/// expanded, which are left untouched.
+fn expand(text: &str, macros: &HashMap<String, String>, active: &mut Vec<String>, in_comment: &mut bool) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut output = String::with_capacity(text.len());
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        if *in_comment {
+            output.push(c);
+            if c == '*' && chars.get(i + 1) == Some(&'/') {
+                output.push('/');
+                i += 1;
+                *in_comment = false;
+            }
+            i += 1;
+            continue;
+        }
+
+        match c {
+            '/' if chars.get(i + 1) == Some(&'/') => {
+                output.extend(&chars[i..]);
+                break;
+            }
+            '/' if chars.get(i + 1) == Some(&'*') => {
+                output.push_str("/*");
+                *in_comment = true;
+                i += 2;
+            }
+            '"' | '\'' => {
+                let quote = c;
+                output.push(c);
+                i += 1;
+                while i < chars.len() {
+                    output.push(chars[i]);
+                    if chars[i] == '\\' && i + 1 < chars.len() {
+                        output.push(chars[i + 1]);
+                        i += 1;
+                    } else if chars[i] == quote {
+                        i += 1;
+                        break;
+                    }
+                    i += 1;
+                }
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+
+                match macros.get(&word) {
+                    Some(body) if !active.contains(&word) => {
+                        active.push(word);
+                        output.push_str(&expand(body, macros, active, &mut false));
+                        active.pop();
+                    }
+                    _ => output.push_str(&word),
+                }
+            }
+            c if c.is_ascii_digit() => {
+                // Keep number suffixes like `1e5` from being read as identifiers
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '.' || chars[i] == '_') {
+                    output.push(chars[i]);
+                    i += 1;
+                }
+            }
+            _ => {
+                output.push(c);
+                i += 1;
+            }
+        }
+    }
+
+    output
+}
+
+fn strip_line_comment(text: &str) -> &str {
+    let mut quote = None;
+    let mut previous = '\0';
+    for (index, c) in text.char_indices() {
+        match quote {
+            Some(q) if c == q && previous != '\\' => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == '/' && text[index + 1..].starts_with(['/', '*']) => return &text[..index],
+            None => {}
+        }
+        previous = c;
+    }
+    text
+}
+
+fn normalize(body: &str) -> String {
+    body.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::{Lexer, TokenType};
+
+    fn token_types(source: &str) -> Vec<TokenType> {
+        Lexer::new(source).tokenize().unwrap().into_iter().map(|t| t.token_type).collect()
+    }
+
+    #[test]
+    fn test_define_substitutes_value() {
+        let output = preprocess("#define MAX 100\nint a = MAX;").unwrap();
+        assert_eq!(token_types(&output), token_types("int a = 100;"));
+    }
+
+    #[test]
+    fn test_define_keeps_line_numbers() {
+        let output = preprocess("#define MAX 100\nint a = MAX;").unwrap();
+        let tokens = Lexer::new(&output).tokenize().unwrap();
+        assert_eq!(tokens[0].line, 2);
+    }
+
+    #[test]
+    fn test_macros_not_expanded_in_strings_or_comments() {
+        let output = preprocess("#define N 1\nprintln(\"N\"); // N\nint x = N;").unwrap();
+        assert!(output.contains("println(\"N\"); // N"));
+        assert!(output.contains("int x = 1;"));
+    }
+
+    #[test]
+    fn test_nested_and_recursive_macros() {
+        let output = preprocess("#define A B + 1\n#define B A\nint x = A;").unwrap();
+        assert!(output.contains("int x = A + 1;"));
+    }
+
+    #[test]
+    fn test_identical_redefinition_allowed() {
+        assert!(preprocess("#define N 1\n#define N  1\n").is_ok());
+    }
+
+    #[test]
+    fn test_conflicting_redefinition_error() {
+        assert!(preprocess("#define N 1\n#define N 2\n").is_err());
+    }
+
+    #[test]
+    fn test_function_like_macro_error() {
+        assert!(preprocess("#define SQ(x) x * x\n").is_err());
+    }
+
+    #[test]
+    fn test_include_cycle_error() {
+        let dir = std::env::temp_dir().join(format!("minic_cycle_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.minic"), "#include \"b.minic\"\n").unwrap();
+        fs::write(dir.join("b.minic"), "#include \"a.minic\"\n").unwrap();
+
+        let error = preprocess_file(&dir.join("a.minic"), &[]).unwrap_err();
+        assert!(error.to_string().contains("Include cycle"), "{}", error);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_dependencies_list_main_file_and_every_include() {
+        let dir = std::env::temp_dir().join(format!("minic_deps_{}", std::process::id()));
+        fs::create_dir_all(dir.join("lib")).unwrap();
+        fs::write(dir.join("main.minic"), "#include \"a.minic\"\n#include \"lib/b.minic\"\nint main() { return A + B; }\n").unwrap();
+        fs::write(dir.join("a.minic"), "#include \"lib/b.minic\"\n#define A 1\n").unwrap();
+        fs::write(dir.join("lib/b.minic"), "#define B 2\n").unwrap();
+
+        let source = preprocess_file(&dir.join("main.minic"), &[]).unwrap();
+        let expected: Vec<String> = ["main.minic", "a.minic", "lib/b.minic"].iter()
+            .map(|name| dir.join(name).display().to_string())
+            .collect();
+        assert_eq!(source.dependencies(), expected);
+        assert_eq!(source.make_rule("out dir/output.o"), format!("out\\ dir/output.o: {}\n", expected.join(" ")));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_missing_include_error() {
+        assert!(preprocess("#include \"does_not_exist.minic\"\n").is_err());
+    }
+}
@@ -13,7 +13,13 @@ pub enum TokenType {
     Int,
     FloatType,
     CharType,
+    BoolType,
+    True,
+    False,
     Void,
+    Static,
+    Register,
+    Const,
     If,
     Else,
     While,
@@ -21,7 +27,11 @@ pub enum TokenType {
     Return,
     Break,
     Continue,
+    /// `sizeof(type)`: the size of a type in bytes, a compile-time constant
+    Sizeof,
     Println,
+    Print,
+    Printf,
 
     Plus,
     Minus,
@@ -39,6 +49,12 @@ pub enum TokenType {
     LogicalAnd,
     LogicalOr,
     LogicalNot,
+    /// `&`: address-of as a prefix, bitwise and between operands
+    Ampersand,
+    BitOr,
+    BitXor,
+    ShiftLeft,
+    ShiftRight,
 
     Assign,
 
@@ -50,10 +66,44 @@ pub enum TokenType {
     RightBracket,
     Semicolon,
     Comma,
+    Colon,
+    /// `...`, only recognized to report variadic parameter lists
+    Ellipsis,
+    /// `::`, reserved for scope resolution
+    ColonColon,
 
     Eof,
 }
 
+impl TokenType {
+    /// How an operator is written in source, or None for other tokens
+    pub fn operator_symbol(&self) -> Option<&'static str> {
+        Some(match self {
+            TokenType::Plus => "+",
+            TokenType::Minus => "-",
+            TokenType::Multiply => "*",
+            TokenType::Divide => "/",
+            TokenType::Modulo => "%",
+            TokenType::Equal => "==",
+            TokenType::NotEqual => "!=",
+            TokenType::LessThan => "<",
+            TokenType::LessEqual => "<=",
+            TokenType::GreaterThan => ">",
+            TokenType::GreaterEqual => ">=",
+            TokenType::LogicalAnd => "&&",
+            TokenType::LogicalOr => "||",
+            TokenType::LogicalNot => "!",
+            TokenType::Ampersand => "&",
+            TokenType::BitOr => "|",
+            TokenType::BitXor => "^",
+            TokenType::ShiftLeft => "<<",
+            TokenType::ShiftRight => ">>",
+            TokenType::Assign => "=",
+            _ => return None,
+        })
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Token {
     pub token_type: TokenType,
@@ -73,6 +123,41 @@ impl Token {
     }
 }
 
+/// Source text between tokens, which the parser never sees
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TriviaKind {
+    /// Spaces, tabs and line breaks
+    Whitespace,
+    /// `// ...` up to, not including, the end of the line
+    LineComment,
+    /// `/* ... */`, possibly over several lines
+    BlockComment,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub text: String,
+}
+
+/// A token with the trivia around it. Trailing trivia runs to the end of
+/// the token's line; everything from the line break on leads the next
+/// token, so a comment on a line of its own belongs to the code below it.
+#[derive(Debug, Clone)]
+pub struct TriviaToken {
+    pub token: Token,
+    pub leading: Vec<Trivia>,
+    pub trailing: Vec<Trivia>,
+}
+
+impl TriviaToken {
+    /// The exact source the token and its trivia were read from
+    pub fn source(&self) -> String {
+        let text = |trivia: &[Trivia]| trivia.iter().map(|piece| piece.text.as_str()).collect::<String>();
+        format!("{}{}{}", text(&self.leading), self.token.lexeme, text(&self.trailing))
+    }
+}
+
 impl fmt::Display for Token {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{:?} '{}' at {}:{}", self.token_type, self.lexeme, self.line, self.column)
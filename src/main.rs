@@ -1,14 +1,20 @@
 use std::fs;
-use std::path::PathBuf;
+use std::io::{BufWriter, IsTerminal};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
 use std::process;
 
-use clap::Parser;
-use compiler_minic::codegen::{Codegen};
-use compiler_minic::codegen::targets::{parse_target_platform, TargetPlatform};
-use compiler_minic::lexer::Lexer;
-use compiler_minic::parser::Parser as MiniCParser;
-use compiler_minic::ir::{IrGenerator, IrOptimizer};
-use compiler_minic::semantic::{MemorySafetyChecker, MemorySafetySeverity};
+use clap::{Parser, ValueEnum};
+use compiler_minic::codegen::{check_recursive_frames, render_lines, Codegen, DEFAULT_FLOAT_PRECISION};
+use compiler_minic::codegen::encoder::assemble_object;
+use compiler_minic::codegen::targets::{create_target, parse_target_platform, TargetFeatures, TargetPlatform};
+use compiler_minic::error::{CompilerError, Style};
+use compiler_minic::error::error::{ErrorReporter, SourceContext};
+use compiler_minic::lexer::{preprocess_file, preprocess_source, Lexer, PreprocessedSource};
+use compiler_minic::parser::{ast_to_json, Parser as MiniCParser};
+use compiler_minic::parser::ast::Stmt;
+use compiler_minic::ir::{format_cfg, verify_round_trip, IrGenerator, IrOptimizer, DEFAULT_MAX_ITERATIONS};
+use compiler_minic::semantic::{check_calls, check_returns, format_symbols, MemorySafetyChecker, MemorySafetySeverity, ReturnError, SymbolCollector};
 
 /// MiniC Compiler - A simple C-like language compiler
 #[derive(Parser)]
@@ -24,10 +30,22 @@ struct Cli {
     #[arg(short, long, default_value = "auto", )]
     target: String,
 
-    /// Output directory for generated files
-    #[arg(short, long, default_value = "build")]
+    /// Additional directory to search for `#include` files (repeatable)
+    #[arg(short = 'I', long = "include-dir", value_name = "DIR")]
+    include_dirs: Vec<PathBuf>,
+
+    /// Output directory for generated files, named `output.asm`,
+    /// `output.ir` and so on, when `--output` is not given
+    #[arg(long, default_value = "build")]
     output_dir: PathBuf,
 
+    /// Path of the assembly output, or of the object with `--emit obj`.
+    /// Other outputs share its name with their own extension, so `-o foo`
+    /// writes `foo.asm` and `foo.ir`, and `-o foo.s` writes `foo.s` and
+    /// `foo.ir`.
+    #[arg(short, long, value_name = "PATH")]
+    output: Option<PathBuf>,
+
     /// Enable verbose output
     #[arg(short, long)]
     verbose: bool,
@@ -39,13 +57,151 @@ struct Cli {
     /// Skip IR optimization
     #[arg(long)]
     skip_optimization: bool,
+
+    /// Run only the named optimization pass instead of the default
+    /// pipeline (repeatable, passes run in the order given)
+    #[arg(long = "optimize-pass", value_name = "NAME")]
+    optimize_passes: Vec<String>,
+
+    /// Stop re-running the optimization passes on a function after N
+    /// iterations, even if they still change it
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_MAX_ITERATIONS,
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    max_optimize_iterations: usize,
+
+    /// Print the symbol table built during semantic analysis
+    #[arg(long)]
+    print_symbols: bool,
+
+    /// Report the time spent in each optimization pass
+    #[arg(long)]
+    time_passes: bool,
+
+    /// Print the basic blocks of each function, with their successors,
+    /// after optimization
+    #[arg(long)]
+    dump_cfg_text: bool,
+
+    /// Warn about functions that call themselves and use more than BYTES
+    /// of stack per call
+    #[arg(long, value_name = "BYTES")]
+    warn_stack_usage: Option<usize>,
+
+    /// Print a `make` rule listing the files the compilation reads, the
+    /// input plus everything it includes, instead of compiling
+    #[arg(long)]
+    emit_deps: bool,
+
+    /// Only check that the input parses: exit 0 if it does and 1 on a
+    /// syntax error, writing no output files
+    #[arg(long)]
+    parse_only: bool,
+
+    /// Stop after the IR: check that its text reads back as the same
+    /// program, and that both compile to the same assembly, writing no
+    /// assembly
+    #[arg(long)]
+    ir_only_no_codegen: bool,
+
+    /// Link without the C runtime: emit a `_start` entry point that calls
+    /// `main` and exits with its return value
+    #[arg(long)]
+    no_crt: bool,
+
+    /// Compile a library: do not require a `main` function
+    #[arg(long, conflicts_with = "no_crt")]
+    no_main: bool,
+
+    /// Kind of output to produce
+    #[arg(long, value_enum, default_value_t = Emit::Asm)]
+    emit: Emit,
+
+    /// Digits printed after the decimal point for `%f` and `println(float)`
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_FLOAT_PRECISION)]
+    float_precision: usize,
+
+    /// Instruction set extensions for float code, as a comma-separated
+    /// list such as `+avx`; SSE2 is used by default
+    #[arg(long, value_name = "FEATURES", default_value = "+sse2")]
+    target_features: TargetFeatures,
+
+    /// When to color diagnostics
+    #[arg(long, value_enum, value_name = "WHEN", default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
+}
+
+impl Cli {
+    /// Where the output ending in `suffix`, such as `.ir`, is written:
+    /// next to the `--output` path, or in the output directory
+    fn artifact_path(&self, suffix: &str) -> PathBuf {
+        let stem = match &self.output {
+            // `-o foo.s` names the same outputs as `-o foo`
+            Some(path) => path.with_extension(""),
+            None => self.output_dir.join("output"),
+        };
+        let mut path = OsString::from(stem);
+        path.push(suffix);
+        PathBuf::from(path)
+    }
+
+    /// The backend, configured by the code generation options
+    fn codegen(&self, target_platform: TargetPlatform) -> Result<Codegen, String> {
+        Ok(Codegen::with_target(create_target(target_platform)?)
+            .with_float_precision(self.float_precision)
+            .with_target_features(self.target_features)
+            .with_no_crt(self.no_crt))
+    }
+
+    /// Path of the final artifact `--emit` asks for: the `--output` path
+    /// itself when it has an extension
+    fn final_artifact_path(&self) -> PathBuf {
+        match &self.output {
+            Some(path) if path.extension().is_some() => path.clone(),
+            _ => self.artifact_path(if self.emit == Emit::Obj { ".o" } else { ".asm" }),
+        }
+    }
+}
+
+/// Whether diagnostics written to stderr are colored
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ColorChoice {
+    /// Color when stderr is a terminal
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    fn style(self) -> Style {
+        Style::new(match self {
+            ColorChoice::Auto => std::io::stderr().is_terminal(),
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        })
+    }
+}
+
+/// Final artifact written to the output directory
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Emit {
+    /// NASM assembly (.asm)
+    Asm,
+    /// Relocatable object file assembled in-process (.o)
+    Obj,
+    /// Token stream, one token per line (.tokens)
+    Tokens,
+    /// Syntax tree as JSON (.ast.json)
+    Ast,
+    /// Tokens, AST, IR, optimized IR and assembly in one run
+    All,
 }
 
 fn main() {
     let cli = Cli::parse();
+    let style = cli.color.style();
 
     if let Err(e) = run_compiler(cli) {
-        eprintln!("Compilation failed: {}", e);
+        eprintln!("{} {}", style.error("Compilation failed:"), e);
         process::exit(1);
     }
 }
@@ -58,35 +214,72 @@ fn run_compiler(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
         parse_target_platform(&cli.target)
             .map_err(|_| format!("Invalid target platform: {}", cli.target))?
     };
+    if cli.no_crt && create_target(target_platform)?.startup_code().is_empty() {
+        return Err(format!("--no-crt is not supported for target {:?}", target_platform).into());
+    }
 
     if cli.verbose {
         println!("Target platform: {:?}", target_platform);
-        println!("Output directory: {:?}", cli.output_dir);
+        println!("Output: {:?}", cli.final_artifact_path());
     }
 
-    // Read source code
-    let code = read_source_code(&cli)?;
+    // Read and preprocess source code
+    let source = read_source_code(&cli)?;
+
+    if cli.emit_deps {
+        print!("{}", source.make_rule(&cli.final_artifact_path().display().to_string()));
+        return Ok(());
+    }
 
     // Compile the code
-    compile_code(&code, target_platform, &cli)
+    compile_code(&source, target_platform, &cli)
 }
 
-fn read_source_code(cli: &Cli) -> Result<String, Box<dyn std::error::Error>> {
-    match &cli.input {
+fn read_source_code(cli: &Cli) -> Result<PreprocessedSource, Box<dyn std::error::Error>> {
+    let source = match &cli.input {
         Some(filename) => {
             if cli.verbose {
                 println!("Compiling file: {:?}", filename);
             }
-            fs::read_to_string(filename)
-                .map_err(|e| format!("Error reading file '{:?}': {}", filename, e).into())
+            preprocess_file(filename, &cli.include_dirs)
         }
         None => {
             if cli.verbose {
                 println!("No file provided, using default code...");
             }
-            Ok(get_default_code())
+            preprocess_source(&get_default_code(), "<default>", &cli.include_dirs)
         }
-    }
+    };
+
+    source.map_err(|e| format!("Preprocessing error: {}", e).into())
+}
+
+/// Render an error with the source lines around it, at the file and line
+/// it came from before preprocessing
+fn render_error(error: &CompilerError, source: &PreprocessedSource, style: Style) -> String {
+    let reporter = ErrorReporter { show_colors: style.is_enabled(), ..ErrorReporter::default() };
+    reporter.report(&in_original_source(error, source))
+}
+
+/// Render a warning as `render_error` renders an error
+fn render_warning(warning: &CompilerError, source: &PreprocessedSource, style: Style) -> String {
+    let reporter = ErrorReporter { show_colors: style.is_enabled(), ..ErrorReporter::default() };
+    reporter.report_warning(&in_original_source(warning, source))
+}
+
+/// The error located in the file and at the line it came from, with that
+/// file's text around it
+fn in_original_source(error: &CompilerError, source: &PreprocessedSource) -> CompilerError {
+    let mut error = error.clone();
+    let (filename, text) = match source.locate(error.span.line) {
+        Some(origin) => {
+            error.span.line = origin.line;
+            (origin.file.clone(), source.file_text(&origin.file).unwrap_or_default().to_string())
+        }
+        None => ("<input>".to_string(), source.code.clone()),
+    };
+    let context = SourceContext::new(filename, text, error.span.clone());
+    error.with_context(context)
 }
 
 fn get_default_code() -> String {
@@ -143,20 +336,35 @@ fn get_default_code() -> String {
 }
 
 fn compile_code(
-    code: &str,
+    source: &PreprocessedSource,
     target_platform: TargetPlatform,
     cli: &Cli,
 ) -> Result<(), Box<dyn std::error::Error>> {
 
+    let style = cli.color.style();
+
     // Tokenization
-    let mut lexer = Lexer::new(code);
-    let tokens = lexer.tokenize()
-        .map_err(|e| format!("Lexing error: {}", e))?;
+    let mut lexer = Lexer::new(&source.code);
+    let tokens = match lexer.tokenize() {
+        Ok(tokens) => tokens,
+        Err(error) => {
+            eprint!("{}", render_error(&error, source, style));
+            return Err("Lexing failed".into());
+        }
+    };
 
     if cli.verbose {
         println!("Tokenization completed successfully");
     }
 
+    if matches!(cli.emit, Emit::Tokens | Emit::All) && !cli.parse_only {
+        let listing: String = tokens.iter().map(|token| format!("{}\n", token)).collect();
+        save_text_file(&listing, &cli.artifact_path(".tokens"), "token", cli.verbose)?;
+        if cli.emit == Emit::Tokens {
+            return Ok(());
+        }
+    }
+
     // Parsing
     let mut parser = MiniCParser::new(tokens);
     let ast = parser.parse();
@@ -165,7 +373,7 @@ fn compile_code(
     let parser_errors = parser.get_errors();
     if !parser_errors.is_empty() {
         for error in parser_errors {
-            eprintln!("Parser error: {}", error);
+            eprint!("{}", render_error(error, source, style));
         }
         return Err("Parsing failed with errors".into());
     }
@@ -174,16 +382,67 @@ fn compile_code(
         println!("Parsing completed successfully");
     }
 
+    if cli.parse_only {
+        if cli.verbose {
+            let functions = ast.iter().filter(|stmt| matches!(stmt, Stmt::Function { .. })).count();
+            println!("Parsed {} function{}", functions, if functions == 1 { "" } else { "s" });
+        }
+        return Ok(());
+    }
+
+    if matches!(cli.emit, Emit::Ast | Emit::All) {
+        save_text_file(&ast_to_json(&ast), &cli.artifact_path(".ast.json"), "AST", cli.verbose)?;
+        if cli.emit == Emit::Ast {
+            return Ok(());
+        }
+    }
+
+    // Every call must match the signature of the function it calls
+    let call_errors = check_calls(&ast);
+    if !call_errors.is_empty() {
+        for error in &call_errors {
+            eprint!("{}", render_error(&error.into(), source, style));
+        }
+        return Err("Semantic analysis failed".into());
+    }
+
+    // Functions returning a value must return one on every path
+    let return_errors = check_returns(&ast);
+    for error in &return_errors {
+        if error.is_error() {
+            eprint!("{}", render_error(&error.into(), source, style));
+        } else {
+            eprint!("{}", render_warning(&error.into(), source, style));
+        }
+    }
+    if return_errors.iter().any(ReturnError::is_error) {
+        return Err("Semantic analysis failed".into());
+    }
+
     // Memory safety analysis (if not skipped)
     if !cli.skip_memory_checks {
-        run_memory_safety_analysis(&ast, cli.verbose)?;
+        run_memory_safety_analysis(&ast, style, cli.verbose)?;
+    }
+
+    if cli.print_symbols {
+        let symbols = SymbolCollector::new().collect(&ast)
+            .map_err(|e| format!("Symbol table error: {}", e))?;
+        print!("{}", format_symbols(&symbols));
     }
 
     // IR generation
     let ir_program = generate_ir(&ast, cli.verbose)?;
+    if !cli.no_main {
+        ir_program.check_entry_point()
+            .map_err(|e| format!("{}; pass --no-main to compile a library", e))?;
+    }
 
     // Save IR to file
-    save_ir_to_file(&ir_program, &cli.output_dir, "output.ir", cli.verbose)?;
+    save_ir_to_file(&ir_program, &cli.artifact_path(".ir"), cli.verbose)?;
+
+    if cli.ir_only_no_codegen {
+        verify_ir_round_trip(&ir_program, target_platform, cli)?;
+    }
 
     // IR optimization (if not skipped)
     let final_ir = if cli.skip_optimization {
@@ -192,13 +451,33 @@ fn compile_code(
         }
         ir_program
     } else {
-        let optimized_ir = optimize_ir(ir_program, cli.verbose)?;
-        save_ir_to_file(&optimized_ir, &cli.output_dir, "output_optimized.ir", cli.verbose)?;
+        let optimized_ir = optimize_ir(ir_program, &cli.optimize_passes, cli.max_optimize_iterations, cli.time_passes, style, cli.verbose)?;
+        save_ir_to_file(&optimized_ir, &cli.artifact_path("_optimized.ir"), cli.verbose)?;
         optimized_ir
     };
 
+    if cli.dump_cfg_text {
+        for function in &final_ir.functions {
+            print!("{}", format_cfg(function));
+        }
+    }
+
+    if let Some(limit) = cli.warn_stack_usage {
+        for warning in check_recursive_frames(&final_ir, target_platform, limit) {
+            eprintln!("{} {}", style.warning("Stack usage warning:"), warning);
+        }
+    }
+
+    if cli.ir_only_no_codegen {
+        verify_ir_round_trip(&final_ir, target_platform, cli)?;
+        if cli.verbose {
+            println!("IR round trip verified");
+        }
+        return Ok(());
+    }
+
     // Code generation
-    generate_assembly(&final_ir, target_platform, &cli.output_dir, cli.verbose)?;
+    generate_assembly(&final_ir, cli.codegen(target_platform)?, target_platform, &cli.final_artifact_path(), cli.emit, cli.verbose)?;
 
     if cli.verbose {
         println!("Compilation completed successfully!");
@@ -209,6 +488,7 @@ fn compile_code(
 
 fn run_memory_safety_analysis(
     ast: &[compiler_minic::parser::ast::Stmt],
+    style: Style,
     verbose: bool,
 ) -> Result<(), Box<dyn std::error::Error>> {
     if verbose {
@@ -222,10 +502,10 @@ fn run_memory_safety_analysis(
     for warning in warnings {
         match warning.severity() {
             MemorySafetySeverity::Error => {
-                eprintln!("Memory safety error: {}", warning.message());
+                eprintln!("{} {}", style.error("Memory safety error:"), warning.message());
             }
             MemorySafetySeverity::Warning => {
-                println!("Memory safety warning: {}", warning.message());
+                println!("{} {}", style.warning("Memory safety warning:"), warning.message());
             }
             MemorySafetySeverity::Info => {
                 if verbose {
@@ -246,39 +526,125 @@ fn generate_ir(
         println!("Generating IR...");
     }
 
-    let mut ir_generator = IrGenerator::new();
+    let mut ir_generator = IrGenerator::new().with_source_comments(true);
     ir_generator.generate(ast)
         .map_err(|e| format!("IR generation failed: {e:?}").into())
 }
 
 fn optimize_ir(
     ir_program: compiler_minic::ir::IrProgram,
+    pass_names: &[String],
+    max_iterations: usize,
+    time_passes: bool,
+    style: Style,
     verbose: bool,
 ) -> Result<compiler_minic::ir::IrProgram, Box<dyn std::error::Error>> {
     if verbose {
         println!("Optimizing IR...");
     }
 
-    let mut optimizer = IrOptimizer::new();
-    Ok(optimizer.optimize(ir_program))
+    let mut optimizer = if pass_names.is_empty() {
+        IrOptimizer::new()
+    } else {
+        IrOptimizer::with_pass_names(pass_names)?
+    }.with_max_iterations(max_iterations).with_pass_timing(time_passes);
+    let optimized = optimizer.optimize(ir_program);
+
+    if verbose {
+        for convergence in optimizer.convergence() {
+            let outcome = if convergence.reached_fixpoint { "reached a fixpoint" } else { "stopped without a fixpoint" };
+            println!("  '{}' {} after {} iteration(s)", convergence.function, outcome, convergence.iterations);
+        }
+    }
+
+    if let Some(pass_times) = optimizer.pass_times() {
+        // Slowest first, so the pass dominating compile time leads
+        let mut pass_times: Vec<_> = pass_times.iter().collect();
+        pass_times.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+        let total: std::time::Duration = pass_times.iter().map(|(_, time)| **time).sum();
+        println!("Pass timing:");
+        for (name, time) in pass_times {
+            println!("  {:<24} {:>10.3} ms", name, time.as_secs_f64() * 1000.0);
+        }
+        println!("  {:<24} {:>10.3} ms", "total", total.as_secs_f64() * 1000.0);
+    }
+
+    for warning in optimizer.warnings() {
+        eprintln!("{} {}", style.warning("Optimizer warning:"), warning);
+    }
+
+    Ok(optimized)
 }
 
 fn save_ir_to_file(
     ir_program: &compiler_minic::ir::IrProgram,
-    output_dir: &PathBuf,
-    filename: &str,
+    output_path: &Path,
     verbose: bool,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    // Create output directory if it doesn't exist
-    fs::create_dir_all(output_dir)
-        .map_err(|e| format!("Error creating output directory '{output_dir:?}': {e}"))?;
+    save_text_file(&format!("{ir_program}"), output_path, "IR", verbose)
+}
 
-    let output_path = output_dir.join(filename);
-    fs::write(&output_path, format!("{ir_program}"))
-        .map_err(|e| format!("Error writing IR file '{output_path:?}': {e}"))?;
+/// Read the text of `ir_program` back and compile both the original and
+/// the program read, failing where either differs
+fn verify_ir_round_trip(
+    ir_program: &compiler_minic::ir::IrProgram,
+    target_platform: TargetPlatform,
+    cli: &Cli,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let reparsed = verify_round_trip(ir_program)?;
+
+    let original_asm = round_trip_assembly(ir_program, target_platform, cli)?;
+    let reparsed_asm = round_trip_assembly(&reparsed, target_platform, cli)?;
+    let mut original_lines = original_asm.lines();
+    let mut reparsed_lines = reparsed_asm.lines();
+    for line in 1.. {
+        match (original_lines.next(), reparsed_lines.next()) {
+            (None, None) => break,
+            (original, reparsed) if original != reparsed => {
+                return Err(format!("Assembly differs after the IR round trip at line {}: `{}` vs `{}`", line,
+                    original.unwrap_or("end of output"), reparsed.unwrap_or("end of output")).into());
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Assembly text of `ir_program`, for comparing the two sides of the
+/// round trip
+fn round_trip_assembly(
+    ir_program: &compiler_minic::ir::IrProgram,
+    target_platform: TargetPlatform,
+    cli: &Cli,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let lines = cli.codegen(target_platform)?.generate_lines(ir_program)
+        .map_err(|e| CompilerError::codegen_error(e.to_string()))?;
+    Ok(render_lines(&lines))
+}
+
+/// Create the directory `output_path` is written to, if it doesn't exist
+fn create_output_dir(output_path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    match output_path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        Some(dir) => fs::create_dir_all(dir)
+            .map_err(|e| format!("Error creating output directory '{dir:?}': {e}").into()),
+        None => Ok(()),
+    }
+}
+
+/// Write `contents` to `output_path`; `kind` names the artifact in messages
+fn save_text_file(
+    contents: &str,
+    output_path: &Path,
+    kind: &str,
+    verbose: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    create_output_dir(output_path)?;
+
+    fs::write(output_path, contents)
+        .map_err(|e| format!("Error writing {kind} file '{output_path:?}': {e}"))?;
 
     if verbose {
-        println!("IR code saved to {output_path:?}");
+        println!("{kind} output saved to {output_path:?}");
     }
 
     Ok(())
@@ -286,24 +652,36 @@ fn save_ir_to_file(
 
 fn generate_assembly(
     ir_program: &compiler_minic::ir::IrProgram,
+    ir_codegen: Codegen,
     target_platform: TargetPlatform,
-    output_dir: &PathBuf,
+    output_path: &Path,
+    emit: Emit,
     verbose: bool,
 ) -> Result<(), Box<dyn std::error::Error>> {
     if verbose {
         println!("Generating assembly code...");
     }
 
-    // Create output directory if it doesn't exist
-    fs::create_dir_all(output_dir)
-        .map_err(|e| format!("Error creating output directory '{output_dir:?}': {e}"))?;
+    create_output_dir(output_path)?;
 
-    let ir_codegen = Codegen::new_with_target(target_platform);
-    let asm_code = ir_codegen.generate(ir_program);
+    if emit == Emit::Obj {
+        let lines = ir_codegen.generate_lines(ir_program)
+            .map_err(|e| format!("Error generating code for object file '{output_path:?}': {e}"))?;
+        let object = assemble_object(&lines, target_platform)?;
+        fs::write(output_path, object)
+            .map_err(|e| format!("Error writing object file '{output_path:?}': {e}"))?;
+
+        if verbose {
+            println!("Object file saved to {output_path:?}");
+        }
+        return Ok(());
+    }
 
-    let output_path = output_dir.join("output.asm");
-    fs::write(&output_path, asm_code)
-        .map_err(|e| format!("Error writing assembly file '{output_path:?}': {e}"))?;
+    // Stream the assembly to disk rather than building it in memory first
+    let file = fs::File::create(output_path)
+        .map_err(|e| format!("Error creating assembly file '{output_path:?}': {e}"))?;
+    ir_codegen.generate_to(ir_program, &mut BufWriter::new(file))
+        .map_err(|e| format!("Error generating assembly file '{output_path:?}': {e}"))?;
 
     if verbose {
         println!("Assembly code saved to {output_path:?}");
@@ -317,6 +695,7 @@ fn detect_current_platform() -> Result<TargetPlatform, Box<dyn std::error::Error
     match std::env::consts::OS {
         "windows" => Ok(TargetPlatform::WindowsX64),
         "linux" => Ok(TargetPlatform::LinuxX64),
+        "freebsd" => Ok(TargetPlatform::FreeBSDX64),
         "macos" => {
             match std::env::consts::ARCH {
                 "aarch64" => Ok(TargetPlatform::MacOSArm64),
@@ -1,12 +1,26 @@
 use crate::lexer::TokenType;
 use crate::types::Type;
 
+/// Where a node starts in the source, for diagnostics
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Location {
+    pub fn new(line: usize, column: usize) -> Self {
+        Location { line, column }
+    }
+}
+
 // AST definitions
 #[derive(Debug, Clone, PartialEq)]
 pub enum Expr {
     Integer(i64),
     Float(f64),
     Char(char),
+    Bool(bool),
     String(String),
     Identifier(String),
     Binary {
@@ -22,6 +36,11 @@ pub enum Expr {
         callee: Box<Expr>,
         arguments: Vec<Expr>,
         type_arguments: Vec<Type>, // For generic function calls like func<T>(args)
+        location: Location,        // Of the callee
+    },
+    Index {
+        array: Box<Expr>,
+        index: Box<Expr>,
     },
     Assignment {
         name: String,
@@ -41,37 +60,117 @@ pub enum Stmt {
         name: String,
         initializer: Option<Expr>,
     },
-    Return(Option<Expr>),
+    Return(Option<Expr>, Location), // Of the `return` keyword
     If {
         condition: Expr,
         then_branch: Vec<Stmt>,
+        else_branch: Option<Vec<Stmt>>, // `else if` is an else holding one `If`
     },
     While {
         condition: Expr,
         body: Vec<Stmt>,
     },
     For {
-        init: Option<Box<Stmt>>,
+        init: Vec<Stmt>,              // declarations or expressions, in order
         condition: Option<Expr>,
-        update: Option<Expr>,
+        update: Vec<Expr>,            // evaluated left to right
         body: Vec<Stmt>,
     },
     Break,
     Continue,
     Block(Vec<Stmt>),
     Function {
+        is_static: bool,              // `static`: internal linkage, not exported
         return_type: Type,
         name: String,
         type_parameters: Vec<String>, // Generic type parameters like <T, U>
         parameters: Vec<Parameter>,   // Function parameters
         body: Vec<Stmt>,
+        location: Location,           // Of the function's name
     },
+    /// A statement calling `print`, `println` or `printf`. Print calls
+    /// anywhere else parse as an `Expr::Call` to the builtin, lowered the
+    /// same way; folding this node into that one is still to do.
     PrintStmt {
         format_string: Expr,
         args: Vec<Expr>,
+        newline: bool,                // `println` appends a newline, `print`/`printf` do not
     },
 }
 
+impl Expr {
+    /// Whether evaluating the expression has no effect beyond producing
+    /// its value: it calls nothing and assigns nothing, so two evaluations
+    /// with no store in between agree and one may stand for both
+    pub fn is_pure(&self) -> bool {
+        match self {
+            Expr::Integer(_) | Expr::Float(_) | Expr::Char(_) | Expr::Bool(_) |
+            Expr::String(_) | Expr::Identifier(_) => true,
+            Expr::Binary { left, right, .. } => left.is_pure() && right.is_pure(),
+            Expr::Unary { operand, .. } => operand.is_pure(),
+            Expr::Index { array, index } => array.is_pure() && index.is_pure(),
+            Expr::TypeCast { expr, .. } => expr.is_pure(),
+            Expr::Call { .. } | Expr::Assignment { .. } => false,
+        }
+    }
+
+    /// Reset every location in the expression to the default, to compare
+    /// trees by their structure alone
+    pub fn clear_locations(&mut self) {
+        match self {
+            Expr::Call { callee, arguments, location, .. } => {
+                *location = Location::default();
+                callee.clear_locations();
+                arguments.iter_mut().for_each(Expr::clear_locations);
+            }
+            Expr::Binary { left, right, .. } | Expr::Index { array: left, index: right } => {
+                left.clear_locations();
+                right.clear_locations();
+            }
+            Expr::Unary { operand: expr, .. } | Expr::Assignment { value: expr, .. } | Expr::TypeCast { expr, .. } => {
+                expr.clear_locations();
+            }
+            Expr::Integer(_) | Expr::Float(_) | Expr::Char(_) | Expr::Bool(_) | Expr::String(_) | Expr::Identifier(_) => {}
+        }
+    }
+}
+
+impl Stmt {
+    /// Reset every location in the statement to the default, to compare
+    /// trees by their structure alone
+    pub fn clear_locations(&mut self) {
+        match self {
+            Stmt::ExprStmt(expr) => expr.clear_locations(),
+            Stmt::VarDecl { initializer, .. } => initializer.iter_mut().for_each(Expr::clear_locations),
+            Stmt::Return(value, location) => {
+                *location = Location::default();
+                value.iter_mut().for_each(Expr::clear_locations);
+            }
+            Stmt::If { condition, then_branch, else_branch } => {
+                condition.clear_locations();
+                then_branch.iter_mut().chain(else_branch.iter_mut().flatten()).for_each(Stmt::clear_locations);
+            }
+            Stmt::While { condition, body } => {
+                condition.clear_locations();
+                body.iter_mut().for_each(Stmt::clear_locations);
+            }
+            Stmt::For { init, condition, update, body } => {
+                init.iter_mut().chain(body.iter_mut()).for_each(Stmt::clear_locations);
+                condition.iter_mut().chain(update.iter_mut()).for_each(Expr::clear_locations);
+            }
+            Stmt::Block(body) => body.iter_mut().for_each(Stmt::clear_locations),
+            Stmt::Function { body, location, .. } => {
+                *location = Location::default();
+                body.iter_mut().for_each(Stmt::clear_locations);
+            }
+            Stmt::PrintStmt { format_string, args, .. } => {
+                std::iter::once(format_string).chain(args.iter_mut()).for_each(Expr::clear_locations);
+            }
+            Stmt::Break | Stmt::Continue => {}
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Parameter {
     pub name: String,
@@ -0,0 +1,197 @@
+use super::ast::{Expr, Parameter, Stmt};
+
+/// Render a program as an indented JSON array of its top-level statements,
+/// one object per node tagged with its `"node"` kind
+pub fn ast_to_json(program: &[Stmt]) -> String {
+    let mut output = String::new();
+    Json::Array(program.iter().map(stmt_json).collect()).write(&mut output, 0);
+    output.push('\n');
+    output
+}
+
+enum Json {
+    Null,
+    Bool(bool),
+    Number(String),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(&'static str, Json)>),
+}
+
+impl Json {
+    fn node(kind: &str, mut fields: Vec<(&'static str, Json)>) -> Self {
+        fields.insert(0, ("node", Json::String(kind.to_string())));
+        Json::Object(fields)
+    }
+
+    fn string(text: &str) -> Self {
+        Json::String(text.to_string())
+    }
+
+    fn optional<T>(value: Option<T>, render: impl Fn(T) -> Json) -> Self {
+        value.map(render).unwrap_or(Json::Null)
+    }
+
+    fn write(&self, output: &mut String, depth: usize) {
+        let indent = |output: &mut String, depth: usize| output.push_str(&"  ".repeat(depth));
+        match self {
+            Json::Null => output.push_str("null"),
+            Json::Bool(value) => output.push_str(&value.to_string()),
+            Json::Number(value) => output.push_str(value),
+            Json::String(value) => write_string(output, value),
+            Json::Array(items) if items.is_empty() => output.push_str("[]"),
+            Json::Array(items) => {
+                output.push_str("[\n");
+                for (index, item) in items.iter().enumerate() {
+                    indent(output, depth + 1);
+                    item.write(output, depth + 1);
+                    output.push_str(if index + 1 < items.len() { ",\n" } else { "\n" });
+                }
+                indent(output, depth);
+                output.push(']');
+            }
+            Json::Object(fields) => {
+                output.push_str("{\n");
+                for (index, (key, value)) in fields.iter().enumerate() {
+                    indent(output, depth + 1);
+                    write_string(output, key);
+                    output.push_str(": ");
+                    value.write(output, depth + 1);
+                    output.push_str(if index + 1 < fields.len() { ",\n" } else { "\n" });
+                }
+                indent(output, depth);
+                output.push('}');
+            }
+        }
+    }
+}
+
+fn write_string(output: &mut String, text: &str) {
+    output.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\t' => output.push_str("\\t"),
+            '\r' => output.push_str("\\r"),
+            c if (c as u32) < 0x20 => output.push_str(&format!("\\u{:04x}", c as u32)),
+            c => output.push(c),
+        }
+    }
+    output.push('"');
+}
+
+fn stmts_json(stmts: &[Stmt]) -> Json {
+    Json::Array(stmts.iter().map(stmt_json).collect())
+}
+
+fn stmt_json(stmt: &Stmt) -> Json {
+    match stmt {
+        Stmt::ExprStmt(expr) => Json::node("ExprStmt", vec![("expr", expr_json(expr))]),
+        Stmt::VarDecl { var_type, name, initializer } => Json::node("VarDecl", vec![
+            ("var_type", Json::String(var_type.to_string())),
+            ("name", Json::string(name)),
+            ("initializer", Json::optional(initializer.as_ref(), expr_json)),
+        ]),
+        Stmt::Return(value, _) => Json::node("Return", vec![("value", Json::optional(value.as_ref(), expr_json))]),
+        Stmt::If { condition, then_branch, else_branch } => Json::node("If", vec![
+            ("condition", expr_json(condition)),
+            ("then_branch", stmts_json(then_branch)),
+            ("else_branch", Json::optional(else_branch.as_deref(), stmts_json)),
+        ]),
+        Stmt::While { condition, body } => Json::node("While", vec![
+            ("condition", expr_json(condition)),
+            ("body", stmts_json(body)),
+        ]),
+        Stmt::For { init, condition, update, body } => Json::node("For", vec![
+            ("init", stmts_json(init)),
+            ("condition", Json::optional(condition.as_ref(), expr_json)),
+            ("update", Json::Array(update.iter().map(expr_json).collect())),
+            ("body", stmts_json(body)),
+        ]),
+        Stmt::Break => Json::node("Break", Vec::new()),
+        Stmt::Continue => Json::node("Continue", Vec::new()),
+        Stmt::Block(body) => Json::node("Block", vec![("body", stmts_json(body))]),
+        Stmt::Function { is_static, return_type, name, type_parameters, parameters, body, .. } => Json::node("Function", vec![
+            ("is_static", Json::Bool(*is_static)),
+            ("return_type", Json::String(return_type.to_string())),
+            ("name", Json::string(name)),
+            ("type_parameters", Json::Array(type_parameters.iter().map(|name| Json::string(name)).collect())),
+            ("parameters", Json::Array(parameters.iter().map(parameter_json).collect())),
+            ("body", stmts_json(body)),
+        ]),
+        Stmt::PrintStmt { format_string, args, newline } => Json::node("PrintStmt", vec![
+            ("format_string", expr_json(format_string)),
+            ("args", Json::Array(args.iter().map(expr_json).collect())),
+            ("newline", Json::Bool(*newline)),
+        ]),
+    }
+}
+
+fn parameter_json(parameter: &Parameter) -> Json {
+    Json::Object(vec![
+        ("name", Json::string(&parameter.name)),
+        ("param_type", Json::String(parameter.param_type.to_string())),
+        ("is_mutable", Json::Bool(parameter.is_mutable)),
+    ])
+}
+
+fn expr_json(expr: &Expr) -> Json {
+    match expr {
+        Expr::Integer(value) => Json::node("Integer", vec![("value", Json::Number(value.to_string()))]),
+        // JSON has no infinities or NaN, so those are kept as strings
+        Expr::Float(value) if value.is_finite() => Json::node("Float", vec![("value", Json::Number(format!("{:?}", value)))]),
+        Expr::Float(value) => Json::node("Float", vec![("value", Json::String(value.to_string()))]),
+        Expr::Char(value) => Json::node("Char", vec![("value", Json::String(value.to_string()))]),
+        Expr::Bool(value) => Json::node("Bool", vec![("value", Json::Bool(*value))]),
+        Expr::String(value) => Json::node("String", vec![("value", Json::string(value))]),
+        Expr::Identifier(name) => Json::node("Identifier", vec![("name", Json::string(name))]),
+        Expr::Binary { left, operator, right } => Json::node("Binary", vec![
+            ("operator", Json::string(operator.operator_symbol().unwrap_or("?"))),
+            ("left", expr_json(left)),
+            ("right", expr_json(right)),
+        ]),
+        Expr::Unary { operator, operand } => Json::node("Unary", vec![
+            ("operator", Json::string(operator.operator_symbol().unwrap_or("?"))),
+            ("operand", expr_json(operand)),
+        ]),
+        Expr::Call { callee, arguments, type_arguments, .. } => Json::node("Call", vec![
+            ("callee", expr_json(callee)),
+            ("arguments", Json::Array(arguments.iter().map(expr_json).collect())),
+            ("type_arguments", Json::Array(type_arguments.iter().map(|t| Json::String(t.to_string())).collect())),
+        ]),
+        Expr::Index { array, index } => Json::node("Index", vec![
+            ("array", expr_json(array)),
+            ("index", expr_json(index)),
+        ]),
+        Expr::Assignment { name, value } => Json::node("Assignment", vec![
+            ("name", Json::string(name)),
+            ("value", expr_json(value)),
+        ]),
+        Expr::TypeCast { expr, target_type } => Json::node("TypeCast", vec![
+            ("target_type", Json::String(target_type.to_string())),
+            ("expr", expr_json(expr)),
+        ]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    #[test]
+    fn test_ast_json_nests_nodes_and_escapes_strings() {
+        let tokens = Lexer::new("int main() { int x = 1 + 2; println(\"a \\\"b\\\"\"); return x; }").tokenize().unwrap();
+        let ast = Parser::new(tokens).parse();
+        let json = ast_to_json(&ast);
+
+        assert!(json.starts_with("[\n  {\n    \"node\": \"Function\""), "{}", json);
+        assert!(json.contains("\"var_type\": \"int\""), "{}", json);
+        assert!(json.contains("\"operator\": \"+\""), "{}", json);
+        assert!(json.contains("\"value\": \"a \\\"b\\\"\""), "{}", json);
+        assert_eq!(json.matches('{').count(), json.matches('}').count());
+    }
+}
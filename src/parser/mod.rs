@@ -1,4 +1,8 @@
 mod parser;
+mod json;
+mod pretty;
 pub mod ast;
 
-pub use parser::Parser;
\ No newline at end of file
+pub use parser::Parser;
+pub use json::ast_to_json;
+pub use pretty::{pretty_print, pretty_print_expr, statement_summary};
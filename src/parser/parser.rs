@@ -1,12 +1,17 @@
 use crate::lexer::{Token, TokenType};
-use crate::parser::ast::{Expr, Stmt, Parameter};
+use crate::parser::ast::{Expr, Location, Stmt, Parameter};
 use crate::types::Type;
-use crate::error::error::CompilerError;
+use crate::semantic::{eval_const_expr_in, string_literal_char, Builtin, ConstantTable};
+use crate::semantic::symbol_table::{Mutability, Symbol, Visibility};
+use crate::error::error::{CompilerError, Suggestion};
 
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
     errors: Vec<CompilerError>,
+    /// Variables in scope in the function being parsed, holding the value
+    /// of each integer constant
+    constants: ConstantTable,
 }
 
 impl Parser {
@@ -15,7 +20,7 @@ impl Parser {
         if tokens.is_empty() || tokens.last().unwrap().token_type != TokenType::Eof {
             tokens.push(Token::new(TokenType::Eof, String::new(), 1, 1));
         }
-        Parser { tokens, current: 0, errors: Vec::new() }
+        Parser { tokens, current: 0, errors: Vec::new(), constants: ConstantTable::new() }
     }
     
     pub fn get_errors(&self) -> &[CompilerError] {
@@ -43,24 +48,49 @@ impl Parser {
     }
 
     fn function(&mut self) -> Option<Stmt> {
+        let is_static = self.match_token(&TokenType::Static);
         let return_type = self.consume_type()?;
+        let location = Location::new(self.peek().line, self.peek().column);
         let name = self.consume_identifier()?;
         self.consume(TokenType::LeftParen)?;
         
         // Parse function parameters
+        self.constants = ConstantTable::new();
         let mut parameters = Vec::new();
         if !self.check(&TokenType::RightParen) {
             loop {
+                if self.check(&TokenType::Ellipsis) {
+                    let token = self.peek().clone();
+                    self.report_error(
+                        &format!("Function '{}' cannot take a variable number of arguments", name),
+                        Some("Only printf is variadic; declare each parameter with its type"),
+                        token.line,
+                        token.column
+                    );
+                    return None;
+                }
                 let param_type = self.consume_type()?;
                 let param_name = self.consume_identifier()?;
+                let param_type = self.source_type(param_type)?;
+                self.declare_variable(&param_name, &param_type, false, None);
                 parameters.push(Parameter {
                     name: param_name,
-                    param_type: Type::from(param_type),
+                    param_type,
                     is_mutable: false,
                 });
                 if !self.match_token(&TokenType::Comma) {
                     break;
                 }
+                if self.check(&TokenType::RightParen) {
+                    let token = self.previous().clone();
+                    self.report_error(
+                        "Trailing comma in parameter list",
+                        Some("Remove the ',' after the last parameter"),
+                        token.line,
+                        token.column
+                    );
+                    return None;
+                }
             }
         }
         
@@ -79,34 +109,44 @@ impl Parser {
         self.consume(TokenType::RightBrace)?;
 
         Some(Stmt::Function {
-            return_type: Type::from(return_type),
+            is_static,
+            return_type: self.source_type(return_type)?,
             name,
             type_parameters: Vec::new(), // TODO: Parse generic type parameters
             parameters,
             body,
+            location,
         })
     }
 
     fn statement(&mut self) -> Option<Stmt> {
+        if self.is_function_definition_ahead() {
+            return self.nested_function();
+        }
+
         if self.match_token(&TokenType::Return) {
+            let location = Location::new(self.previous().line, self.previous().column);
             let expr = if !self.check(&TokenType::Semicolon) {
                 Some(self.expression()?)
             } else {
                 None
             };
             self.consume(TokenType::Semicolon)?;
-            return Some(Stmt::Return(expr));
+            return Some(Stmt::Return(expr, location));
         }
 
         if self.match_token(&TokenType::LeftBrace) {
-            let mut statements = Vec::new();
-            while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
-                if let Some(stmt) = self.statement() {
-                    statements.push(stmt);
-                } else {
-                    self.synchronize();
+            let statements = self.in_scope(|parser| {
+                let mut statements = Vec::new();
+                while !parser.check(&TokenType::RightBrace) && !parser.is_at_end() {
+                    if let Some(stmt) = parser.statement() {
+                        statements.push(stmt);
+                    } else {
+                        parser.synchronize();
+                    }
                 }
-            }
+                Some(statements)
+            })?;
             self.consume(TokenType::RightBrace)?;
             return Some(Stmt::Block(statements));
         }
@@ -115,60 +155,71 @@ impl Parser {
             self.consume(TokenType::LeftParen)?;
             let condition = self.expression()?;
             self.consume(TokenType::RightParen)?;
-            self.consume(TokenType::LeftBrace)?;
-            let mut then_branch = Vec::new();
-            while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
-                then_branch.push(self.statement()?);
-            }
-            self.consume(TokenType::RightBrace)?;
-            return Some(Stmt::If { condition, then_branch });
+            let then_branch = self.body()?;
+            // An `else` belongs to the nearest `if` without one, so
+            // `else if` is an else whose body is the next `if`
+            let else_branch = if self.match_token(&TokenType::Else) {
+                Some(self.body()?)
+            } else {
+                None
+            };
+            return Some(Stmt::If { condition, then_branch, else_branch });
         }
 
         if self.match_token(&TokenType::While) {
             self.consume(TokenType::LeftParen)?;
             let condition = self.expression()?;
             self.consume(TokenType::RightParen)?;
-            self.consume(TokenType::LeftBrace)?;
-            let mut body = Vec::new();
-            while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
-                body.push(self.statement()?);
-            }
-            self.consume(TokenType::RightBrace)?;
+            let body = self.body()?;
             return Some(Stmt::While { condition, body });
         }
 
         if self.match_token(&TokenType::For) {
             self.consume(TokenType::LeftParen)?;
-            let init = if self.check(&TokenType::Semicolon) {
-                None
-            } else {
-                Some(Box::new(self.statement()?))
-            };
-            if init.is_none() {
-                self.consume(TokenType::Semicolon)?;
-            }
-            
-            let condition = if self.check(&TokenType::Semicolon) {
-                None
-            } else {
-                Some(self.expression()?)
-            };
-            self.consume(TokenType::Semicolon)?;
-            
-            let update = if self.check(&TokenType::RightParen) {
-                None
-            } else {
-                Some(self.expression()?)
-            };
-            self.consume(TokenType::RightParen)?;
-            
-            self.consume(TokenType::LeftBrace)?;
-            let mut body = Vec::new();
-            while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
-                body.push(self.statement()?);
-            }
-            self.consume(TokenType::RightBrace)?;
-            return Some(Stmt::For { init, condition, update, body });
+            return self.in_scope(|parser| {
+                // `int i = 0, j = 10` declares each counter in turn
+                let mut init = Vec::new();
+                if let Some(var_type) = parser.match_any_type() {
+                    loop {
+                        init.push(parser.declarator(var_type.clone(), false, false, false)?);
+                        if !parser.match_token(&TokenType::Comma) {
+                            break;
+                        }
+                    }
+                    parser.consume(TokenType::Semicolon)?;
+                } else if !parser.match_token(&TokenType::Semicolon) {
+                    // `i = 0, j = 10` sets each counter in turn
+                    loop {
+                        init.push(Stmt::ExprStmt(parser.expression()?));
+                        if !parser.match_token(&TokenType::Comma) {
+                            break;
+                        }
+                    }
+                    parser.consume(TokenType::Semicolon)?;
+                }
+
+                let condition = if parser.check(&TokenType::Semicolon) {
+                    None
+                } else {
+                    Some(parser.expression()?)
+                };
+                parser.consume(TokenType::Semicolon)?;
+
+                // `i++, j--` updates each counter in turn
+                let mut update = Vec::new();
+                if !parser.check(&TokenType::RightParen) {
+                    loop {
+                        update.push(parser.expression()?);
+                        if !parser.match_token(&TokenType::Comma) {
+                            break;
+                        }
+                    }
+                }
+                parser.consume(TokenType::RightParen)?;
+
+                let body = parser.body()?;
+                Some(Stmt::For { init, condition, update, body })
+            });
         }
 
         if self.match_token(&TokenType::Break) {
@@ -181,7 +232,8 @@ impl Parser {
             return Some(Stmt::Continue);
         }
 
-        if self.match_token(&TokenType::Println) {
+        if let Some(builtin) = self.match_any(&[TokenType::Println, TokenType::Print, TokenType::Printf]) {
+            let newline = builtin == TokenType::Println;
             self.consume(TokenType::LeftParen)?;
 
             // Parse the first expression
@@ -224,7 +276,17 @@ impl Parser {
 
                     self.consume(TokenType::RightParen)?;
                     self.consume(TokenType::Semicolon)?;
-                    return Some(Stmt::PrintStmt { format_string: first_expr, args });
+                    return Some(Stmt::PrintStmt { format_string: first_expr, args, newline });
+                }
+                _ if builtin == TokenType::Printf => {
+                    let token = self.previous();
+                    self.report_error(
+                        "printf requires a format string",
+                        Some("Pass a string literal as the first argument"),
+                        token.line,
+                        token.column
+                    );
+                    return None;
                 }
                 _ => {
                     // Simple expression case: println(expr)
@@ -232,7 +294,7 @@ impl Parser {
                     if self.check(&TokenType::Comma) {
                         let token = self.peek();
                         self.report_error(
-                            "Simple println cannot have additional arguments",
+                            "Simple print cannot have additional arguments",
                             Some("Use format string for multiple arguments"),
                             token.line,
                             token.column
@@ -247,21 +309,41 @@ impl Parser {
                     // We'll use an empty string as format_string to indicate this is a simple print
                     return Some(Stmt::PrintStmt { 
                         format_string: Expr::String(String::new()), 
-                        args: vec![first_expr] 
+                        args: vec![first_expr],
+                        newline,
                     });
                 }
             }
         }
 
-        if let Some(var_type) = self.match_any_type() {
-            let name = self.consume_identifier()?;
-            let initializer = if self.match_token(&TokenType::Assign) {
-                Some(self.expression()?)
+        // `const` may come before or after the storage class
+        let mut is_const = self.match_token(&TokenType::Const);
+        let is_static = self.match_token(&TokenType::Static);
+        let is_register = !is_static && self.match_token(&TokenType::Register);
+        is_const |= self.match_token(&TokenType::Const);
+        let declared_type = self.match_any_type();
+        if (is_static || is_register || is_const) && declared_type.is_none() {
+            let token = self.peek().clone();
+            let (message, suggestion) = if is_static {
+                ("Expected a type after 'static'", "Declare a variable, as in 'static int count = 0;'")
+            } else if is_register {
+                ("Expected a type after 'register'", "Declare a variable, as in 'register int i;'")
             } else {
-                None
+                ("Expected a type after 'const'", "Declare a constant, as in 'const int N = 4;'")
             };
+            self.report_error(
+                message,
+                Some(suggestion),
+                token.line,
+                token.column
+            );
+            return None;
+        }
+
+        if let Some(var_type) = declared_type {
+            let declaration = self.declarator(var_type, is_static, is_register, is_const)?;
             self.consume(TokenType::Semicolon)?;
-            return Some(Stmt::VarDecl { var_type: Type::from(var_type), name, initializer });
+            return Some(declaration);
         }
 
         let expr = self.expression()?;
@@ -269,6 +351,162 @@ impl Parser {
         Some(Stmt::ExprStmt(expr))
     }
 
+    /// The body of an `if`, `else` or loop: a braced block, or a single
+    /// statement, in a scope of its own either way
+    fn body(&mut self) -> Option<Vec<Stmt>> {
+        self.in_scope(|parser| {
+            if parser.match_token(&TokenType::LeftBrace) {
+                let mut body = Vec::new();
+                while !parser.check(&TokenType::RightBrace) && !parser.is_at_end() {
+                    body.push(parser.statement()?);
+                }
+                parser.consume(TokenType::RightBrace)?;
+                Some(body)
+            } else {
+                Some(vec![parser.statement()?])
+            }
+        })
+    }
+
+    /// Run `parse` in a new scope for constants, which ends with it even
+    /// when `parse` gives up part way
+    fn in_scope<T>(&mut self, parse: impl FnOnce(&mut Self) -> Option<T>) -> Option<T> {
+        self.constants.enter_scope();
+        let parsed = parse(self);
+        let _ = self.constants.exit_scope();
+        parsed
+    }
+
+    /// One declared name of type `var_type`, with its array size and
+    /// initializer, up to the `,` or `;` that follows it. `(*name)()`
+    /// declares a pointer to a function returning `var_type`.
+    fn declarator(&mut self, var_type: TokenType, is_static: bool, is_register: bool, is_const: bool) -> Option<Stmt> {
+        let is_function_pointer = self.match_token(&TokenType::LeftParen);
+        if is_function_pointer {
+            self.consume(TokenType::Multiply)?;
+        }
+        let name = self.consume_identifier()?;
+        if is_function_pointer {
+            self.consume(TokenType::RightParen)?;
+            self.consume(TokenType::LeftParen)?;
+            self.consume(TokenType::RightParen)?;
+        }
+        // `[]` takes its size from a string literal initializer
+        let array_size = if !is_function_pointer && self.match_token(&TokenType::LeftBracket) {
+            if self.match_token(&TokenType::RightBracket) {
+                Some(None)
+            } else {
+                Some(Some(self.array_size()?))
+            }
+        } else {
+            None
+        };
+        let (line, column) = (self.peek().line, self.peek().column);
+        let initializer = if self.match_token(&TokenType::Assign) {
+            Some(self.expression()?)
+        } else {
+            None
+        };
+        let mut var_type = match (array_size, &initializer) {
+            (None, _) if is_function_pointer => Type::pointer(Type::function(self.source_type(var_type)?, Vec::new(), false)),
+            (None, _) => self.source_type(var_type)?,
+            (Some(size), Some(Expr::String(text))) if var_type == TokenType::CharType => {
+                // Room for the bytes and the NUL terminator
+                let needed = text.len() + 1;
+                let size = size.unwrap_or(needed);
+                if text.len() > size {
+                    self.report_error(
+                        &format!("Initializer string for '{}' is {} characters long, but the array holds {}", name, text.len(), size),
+                        Some("Make the array larger, or leave the size out as in 'char msg[] = \"hi\";'"),
+                        line,
+                        column
+                    );
+                    return None;
+                }
+                Type::array(self.source_type(var_type)?, size)
+            }
+            (Some(Some(size)), _) => Type::array(self.source_type(var_type)?, size),
+            (Some(None), _) => {
+                self.report_error(
+                    &format!("Array '{}' needs a size", name),
+                    Some("Give the size, as in 'int a[4];', or initialize a char array from a string literal"),
+                    line,
+                    column
+                );
+                return None;
+            }
+        };
+        var_type.qualifiers.is_register = is_register;
+        var_type.qualifiers.is_static = is_static;
+        var_type.qualifiers.is_const = is_const;
+
+        let value = initializer.as_ref().and_then(|value| eval_const_expr_in(value, &self.constants));
+        match &initializer {
+            None if is_const => {
+                self.report_error(
+                    &format!("Constant '{}' must be initialized", name),
+                    Some(&format!("Give it a value, as in 'const int {} = 4;'", name)),
+                    line,
+                    column
+                );
+                return None;
+            }
+            // A static is initialized once, before the program runs
+            Some(initializer) if is_static && value.is_none() && !is_float_literal(initializer) => {
+                self.report_error(
+                    &format!("Initializer of static '{}' must be a constant expression", name),
+                    Some("Use literals, arithmetic on literals and earlier constants"),
+                    line,
+                    column
+                );
+                return None;
+            }
+            _ => {}
+        }
+        self.declare_variable(&name, &var_type, is_const, value.filter(|_| is_const));
+        // Constants are only known here, so a static gets the value its
+        // initializer names
+        let initializer = match value {
+            Some(value) if is_static => Some(Expr::Integer(value)),
+            _ => initializer,
+        };
+
+        Some(Stmt::VarDecl { var_type, name, initializer })
+    }
+
+    /// Whether the next tokens start a function definition:
+    /// an optional `static`, a type, a name and an opening parenthesis
+    fn is_function_definition_ahead(&self) -> bool {
+        let mut index = self.current;
+        if self.tokens.get(index).is_some_and(|token| token.token_type == TokenType::Static) {
+            index += 1;
+        }
+        matches!(
+            (self.tokens.get(index), self.tokens.get(index + 1), self.tokens.get(index + 2)),
+            (Some(ty), Some(name), Some(paren))
+                if matches!(ty.token_type, TokenType::Int | TokenType::FloatType | TokenType::CharType | TokenType::BoolType | TokenType::Void)
+                    && matches!(name.token_type, TokenType::Identifier(_))
+                    && paren.token_type == TokenType::LeftParen
+        )
+    }
+
+    /// Reject a function defined inside another one. The definition is
+    /// still parsed in full so the statements after it are not reported
+    /// as errors of their own.
+    fn nested_function(&mut self) -> Option<Stmt> {
+        let start = self.peek().clone();
+        let function = self.function()?;
+        if let Stmt::Function { name, .. } = &function {
+            self.report_error(
+                &format!("Function '{}' cannot be defined inside another function", name),
+                Some(&format!("Move '{}' to the top level of the file", name)),
+                start.line,
+                start.column
+            );
+        }
+        Some(function)
+    }
+
     fn expression(&mut self) -> Option<Expr> {
         self.assignment()
     }
@@ -279,6 +517,10 @@ impl Parser {
         // Check if this is an assignment (identifier = expression)
         if let Expr::Identifier(name) = expr {
             if self.match_token(&TokenType::Assign) {
+                if self.constants.lookup(&name).is_some_and(|symbol| !symbol.can_modify()) {
+                    let token = self.previous().clone();
+                    self.report_error(&format!("Cannot assign to constant '{}'", name), None, token.line, token.column);
+                }
                 let value = self.assignment()?; // Right-associative
                 return Some(Expr::Assignment {
                     name,
@@ -306,8 +548,49 @@ impl Parser {
     }
 
     fn logical_and(&mut self) -> Option<Expr> {
-        let mut expr = self.equality()?;
+        let mut expr = self.bitwise_or()?;
         while let Some(op) = self.match_any(&[TokenType::LogicalAnd]) {
+            let right = self.bitwise_or()?;
+            expr = Expr::Binary {
+                left: Box::new(expr),
+                operator: op,
+                right: Box::new(right),
+            };
+        }
+        Some(expr)
+    }
+
+    // As in C, the bitwise operators bind more loosely than comparisons,
+    // so `x & 1 == 0` is `x & (1 == 0)`
+    fn bitwise_or(&mut self) -> Option<Expr> {
+        let mut expr = self.bitwise_xor()?;
+        while let Some(op) = self.match_any(&[TokenType::BitOr]) {
+            let right = self.bitwise_xor()?;
+            expr = Expr::Binary {
+                left: Box::new(expr),
+                operator: op,
+                right: Box::new(right),
+            };
+        }
+        Some(expr)
+    }
+
+    fn bitwise_xor(&mut self) -> Option<Expr> {
+        let mut expr = self.bitwise_and()?;
+        while let Some(op) = self.match_any(&[TokenType::BitXor]) {
+            let right = self.bitwise_and()?;
+            expr = Expr::Binary {
+                left: Box::new(expr),
+                operator: op,
+                right: Box::new(right),
+            };
+        }
+        Some(expr)
+    }
+
+    fn bitwise_and(&mut self) -> Option<Expr> {
+        let mut expr = self.equality()?;
+        while let Some(op) = self.match_any(&[TokenType::Ampersand]) {
             let right = self.equality()?;
             expr = Expr::Binary {
                 left: Box::new(expr),
@@ -332,13 +615,26 @@ impl Parser {
     }
 
     fn comparison(&mut self) -> Option<Expr> {
-        let mut expr = self.term()?;
+        let mut expr = self.shift()?;
         while let Some(op) = self.match_any(&[
             TokenType::LessThan,
             TokenType::LessEqual,
             TokenType::GreaterThan,
             TokenType::GreaterEqual,
         ]) {
+            let right = self.shift()?;
+            expr = Expr::Binary {
+                left: Box::new(expr),
+                operator: op,
+                right: Box::new(right),
+            };
+        }
+        Some(expr)
+    }
+
+    fn shift(&mut self) -> Option<Expr> {
+        let mut expr = self.term()?;
+        while let Some(op) = self.match_any(&[TokenType::ShiftLeft, TokenType::ShiftRight]) {
             let right = self.term()?;
             expr = Expr::Binary {
                 left: Box::new(expr),
@@ -376,8 +672,9 @@ impl Parser {
     }
 
     fn unary(&mut self) -> Option<Expr> {
-        // Handle unary operators: '!' for logical negation and '-' for arithmetic negation
-        if let Some(op) = self.match_any(&[TokenType::LogicalNot, TokenType::Minus]) {
+        // Handle unary operators: '!' for logical negation, '-' for arithmetic negation
+        // and '&' for address-of
+        if let Some(op) = self.match_any(&[TokenType::LogicalNot, TokenType::Minus, TokenType::Ampersand]) {
             let operand = self.unary()?; // Recursive to handle !!x or -(-x)
             return Some(Expr::Unary {
                 operator: op,
@@ -388,6 +685,7 @@ impl Parser {
     }
 
     fn call(&mut self) -> Option<Expr> {
+        let location = Location::new(self.peek().line, self.peek().column);
         let mut expr = self.primary()?;
 
         loop {
@@ -402,11 +700,33 @@ impl Parser {
                         }
                     }
                 }
-                self.consume(TokenType::RightParen)?;
+                let close = self.consume(TokenType::RightParen)?;
+                if Builtin::of_callee(&expr) == Some(Builtin::Strlen)
+                    && matches!(arguments.as_slice(), [argument] if !matches!(argument, Expr::String(_))) {
+                    self.report_error(
+                        "__builtin_strlen takes a string literal",
+                        Some("Its length is computed at compile time"),
+                        close.line,
+                        close.column
+                    );
+                }
                 expr = Expr::Call {
                     callee: Box::new(expr),
                     arguments,
                     type_arguments: Vec::new(), // TODO: Parse generic type arguments
+                    location,
+                };
+            } else if self.match_token(&TokenType::LeftBracket) {
+                let (line, column) = (self.peek().line, self.peek().column);
+                let mut index = self.expression()?;
+                self.consume(TokenType::RightBracket)?;
+                if let Expr::String(text) = &expr
+                    && let Some(value) = self.string_literal_index(text, &index, line, column) {
+                    index = Expr::Integer(value);
+                }
+                expr = Expr::Index {
+                    array: Box::new(expr),
+                    index: Box::new(index),
                 };
             } else {
                 break;
@@ -421,8 +741,15 @@ impl Parser {
             TokenType::Integer(i) => Some(Expr::Integer(*i)),
             TokenType::Float(f) => Some(Expr::Float(*f)),
             TokenType::Char(c) => Some(Expr::Char(*c)),
+            TokenType::True => Some(Expr::Bool(true)),
+            TokenType::False => Some(Expr::Bool(false)),
             TokenType::String(s) => Some(Expr::String(s.clone())),
             TokenType::Identifier(name) => Some(Expr::Identifier(name.clone())),
+            // Inside an expression a print is an ordinary call to a builtin
+            TokenType::Println => Some(Expr::Identifier("println".to_string())),
+            TokenType::Print => Some(Expr::Identifier("print".to_string())),
+            TokenType::Printf => Some(Expr::Identifier("printf".to_string())),
+            TokenType::Sizeof => self.size_of(),
             TokenType::LeftParen => {
                 let expr = self.expression()?;
                 self.consume(TokenType::RightParen)?;
@@ -440,6 +767,25 @@ impl Parser {
         }
     }
 
+    /// Parse `(type)` after `sizeof`, folding it to the size of the type,
+    /// so it is an integer constant wherever one is required
+    fn size_of(&mut self) -> Option<Expr> {
+        self.consume(TokenType::LeftParen)?;
+        let Some(type_token) = self.match_any_type() else {
+            let token = self.peek();
+            self.report_error(
+                &format!("Expected a type after 'sizeof(', found {:?}", token.token_type),
+                Some("Name a type, as in 'sizeof(int)'"),
+                token.line,
+                token.column
+            );
+            return None;
+        };
+        let size = self.source_type(type_token)?.size();
+        self.consume(TokenType::RightParen)?;
+        Some(Expr::Integer(size as i64))
+    }
+
     fn consume(&mut self, expected: TokenType) -> Option<Token> {
         if self.check(&expected) {
             Some(self.advance())
@@ -457,14 +803,65 @@ impl Parser {
         }
     }
 
+    /// Parse the size of an array declarator after `[`; it must be a
+    /// positive integer constant expression
+    fn array_size(&mut self) -> Option<usize> {
+        let (line, column) = (self.peek().line, self.peek().column);
+        let size_expr = self.expression()?;
+        self.consume(TokenType::RightBracket)?;
+
+        match eval_const_expr_in(&size_expr, &self.constants) {
+            Some(size) if size > 0 => Some(size as usize),
+            Some(size) => {
+                self.report_error(&format!("Array size must be positive, found {}", size), None, line, column);
+                None
+            }
+            None => {
+                self.report_error(
+                    "Array size must be an integer constant expression",
+                    Some("Use literals, arithmetic on literals and constants, e.g. 'int a[2 * N];' after 'const int N = 4;'"),
+                    line,
+                    column
+                );
+                None
+            }
+        }
+    }
+
+    /// Value of an index into a string literal, which is folded to the
+    /// character there and so must be a constant within the literal
+    fn string_literal_index(&mut self, text: &str, index: &Expr, line: usize, column: usize) -> Option<i64> {
+        match eval_const_expr_in(index, &self.constants) {
+            Some(index) if string_literal_char(text, index).is_none() => {
+                self.report_error(
+                    &format!("Index {} is out of range for a string literal of length {}", index, text.len()),
+                    None,
+                    line,
+                    column
+                );
+                None
+            }
+            Some(index) => Some(index),
+            None => {
+                self.report_error(
+                    "Index into a string literal must be an integer constant expression",
+                    None,
+                    line,
+                    column
+                );
+                None
+            }
+        }
+    }
+
     fn consume_type(&mut self) -> Option<TokenType> {
-        if let Some(token_type) = self.match_any(&[TokenType::Int, TokenType::FloatType, TokenType::CharType, TokenType::Void]) {
+        if let Some(token_type) = self.match_any(&[TokenType::Int, TokenType::FloatType, TokenType::CharType, TokenType::BoolType, TokenType::Void]) {
             Some(token_type)
         } else {
             let current_token = self.peek();
             self.report_error(
                 &format!("Expected type, found {:?}", current_token.token_type),
-                Some("Expected a type like 'int', 'float', 'char', or 'void'"),
+                Some("Expected a type like 'int', 'float', 'char', 'bool', or 'void'"),
                 current_token.line,
                 current_token.column
             );
@@ -472,6 +869,31 @@ impl Parser {
         }
     }
 
+    /// Bring a variable into scope for constant expressions. It hides any
+    /// outer variable of the same name; `value` is its value when it is an
+    /// integer constant.
+    fn declare_variable(&mut self, name: &str, var_type: &Type, is_const: bool, value: Option<i64>) {
+        let mutability = if is_const { Mutability::Immutable } else { Mutability::Mutable };
+        let level = self.constants.current_scope_level();
+        let token = self.previous();
+        let symbol = Symbol::new(name.to_string(), var_type.clone(), value, Visibility::Public, mutability, level, token.line, token.column);
+        // A redeclaration in the same scope is reported by semantic analysis
+        self.constants.remove(name);
+        let _ = self.constants.insert(symbol);
+    }
+
+    /// The type a keyword returned by `consume_type` names
+    fn source_type(&mut self, token_type: TokenType) -> Option<Type> {
+        match Type::try_from(token_type) {
+            Ok(source_type) => Some(source_type),
+            Err(message) => {
+                let (line, column) = (self.previous().line, self.previous().column);
+                self.report_error(&message, None, line, column);
+                None
+            }
+        }
+    }
+
     fn consume_identifier(&mut self) -> Option<String> {
         let token = self.peek();
         if let TokenType::Identifier(name) = &token.token_type {
@@ -508,7 +930,7 @@ impl Parser {
     }
 
     fn match_any_type(&mut self) -> Option<TokenType> {
-        self.match_any(&[TokenType::Int, TokenType::FloatType, TokenType::CharType])
+        self.match_any(&[TokenType::Int, TokenType::FloatType, TokenType::CharType, TokenType::BoolType])
     }
 
     fn check(&self, token_type: &TokenType) -> bool {
@@ -548,8 +970,9 @@ impl Parser {
             
             match self.peek().token_type {
                 TokenType::If | TokenType::Return | TokenType::Int | 
-                TokenType::FloatType | TokenType::CharType | TokenType::Void |
-                TokenType::Println | TokenType::LeftBrace | TokenType::RightBrace => {
+                TokenType::FloatType | TokenType::CharType | TokenType::BoolType | TokenType::Void |
+                TokenType::Println | TokenType::Print | TokenType::Printf |
+                TokenType::LeftBrace | TokenType::RightBrace => {
                     return;
                 }
                 _ => {
@@ -568,12 +991,11 @@ impl Parser {
     }
     
     fn report_error(&mut self, message: &str, suggestion: Option<&str>, line: usize, column: usize) {
-        let error = CompilerError::parse_error(message.to_string(), line, column);
-        self.errors.push(error);
-        eprintln!("Parse Error at {}:{}: {}", line, column, message);
+        let mut error = CompilerError::parse_error(message.to_string(), line, column);
         if let Some(suggestion) = suggestion {
-            eprintln!("  Suggestion: {}", suggestion);
+            error = error.with_suggestion(Suggestion::new(suggestion.to_string()));
         }
+        self.errors.push(error);
     }
     
     fn suggest_fix_for_token(&self, expected: &TokenType) -> String {
@@ -589,11 +1011,22 @@ impl Parser {
     }
 }
 
+/// `1.5` or `-1.5`: the float initializers a static accepts besides
+/// integer constant expressions
+fn is_float_literal(expr: &Expr) -> bool {
+    match expr {
+        Expr::Float(_) => true,
+        Expr::Unary { operator: TokenType::Minus, operand } => is_float_literal(operand),
+        _ => false,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::lexer::{Token, TokenType};
     use crate::parser::ast::{Expr, Stmt};
+    use crate::types::TypeKind;
 
     fn create_token(token_type: TokenType, lexeme: &str) -> Token {
         Token::new(token_type, lexeme.to_string(), 1, 1)
@@ -618,7 +1051,7 @@ mod tests {
         assert_eq!(result.len(), 1);
         match &result[0] {
             Stmt::Function { return_type, name, body, .. } => {
-                assert_eq!(*return_type, Type::from(TokenType::Int));
+                assert_eq!(*return_type, Type::try_from(TokenType::Int).unwrap());
                 assert_eq!(*name, "main");
                 assert!(body.is_empty());
             }
@@ -648,11 +1081,11 @@ mod tests {
         assert_eq!(result.len(), 1);
         match &result[0] {
             Stmt::Function { return_type, name, body, .. } => {
-                assert_eq!(*return_type, Type::from(TokenType::Int));
+                assert_eq!(*return_type, Type::try_from(TokenType::Int).unwrap());
                 assert_eq!(*name, "test");
                 assert_eq!(body.len(), 1);
                 match &body[0] {
-                    Stmt::Return(Some(expr)) => {
+                    Stmt::Return(Some(expr), _) => {
                         assert_eq!(*expr, Expr::Integer(42));
                     }
                     _ => panic!("Expected return statement"),
@@ -692,7 +1125,7 @@ mod tests {
         if let Some(stmt) = parser.statement() {
             match stmt {
                 Stmt::VarDecl { var_type, name, initializer } => {
-                    assert_eq!(var_type, Type::from(TokenType::Int));
+                    assert_eq!(var_type, Type::try_from(TokenType::Int).unwrap());
                     assert_eq!(name, "x");
                     assert_eq!(initializer, Some(Expr::Integer(10)));
                 }
@@ -716,7 +1149,7 @@ mod tests {
         let mut parser = Parser::new(tokens);
         if let Some(stmt) = parser.statement() {
             match stmt {
-                Stmt::Return(Some(expr)) => {
+                Stmt::Return(Some(expr), _) => {
                     assert_eq!(expr, Expr::Integer(5));
                 }
                 _ => panic!("Expected return statement with value"),
@@ -747,7 +1180,7 @@ mod tests {
         let mut parser = Parser::new(tokens);
         if let Some(stmt) = parser.statement() {
             match stmt {
-                Stmt::If { condition, then_branch } => {
+                Stmt::If { condition, then_branch, else_branch: None } => {
                     // Check condition: x == 5
                     match condition {
                         Expr::Binary { left, operator, right } => {
@@ -760,7 +1193,7 @@ mod tests {
                     // Check then branch
                     assert_eq!(then_branch.len(), 1);
                     match &then_branch[0] {
-                        Stmt::Return(Some(expr)) => {
+                        Stmt::Return(Some(expr), _) => {
                             assert_eq!(*expr, Expr::Integer(1));
                         }
                         _ => panic!("Expected return statement in then branch"),
@@ -790,8 +1223,9 @@ mod tests {
         let mut parser = Parser::new(tokens);
         if let Some(stmt) = parser.statement() {
             match stmt {
-                Stmt::PrintStmt { format_string, args } => {
+                Stmt::PrintStmt { format_string, args, newline } => {
                     assert_eq!(format_string, Expr::String("Hello %d".to_string()));
+                    assert!(newline);
                     assert_eq!(args.len(), 1);
                     assert_eq!(args[0], Expr::Integer(42));
                 }
@@ -802,6 +1236,21 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_printf_requires_format_string() {
+        // Test parsing: "printf(42);"
+        let tokens = vec![
+            create_token(TokenType::Printf, "printf"),
+            create_token(TokenType::LeftParen, "("),
+            create_token(TokenType::Integer(42), "42"),
+            create_token(TokenType::RightParen, ")"),
+            create_token(TokenType::Semicolon, ";"),
+            create_token(TokenType::Eof, ""),
+        ];
+
+        assert!(Parser::new(tokens).statement().is_none());
+    }
+
     #[test]
     fn test_parse_binary_expressions() {
         // Test parsing: "5 + 3 * 2"
@@ -966,15 +1415,15 @@ mod tests {
         assert_eq!(result.len(), 1);
         match &result[0] {
             Stmt::Function { return_type, name, parameters, body, .. } => {
-                assert_eq!(*return_type, Type::from(TokenType::Int));
+                assert_eq!(*return_type, Type::try_from(TokenType::Int).unwrap());
                 assert_eq!(*name, "add");
                 assert_eq!(parameters.len(), 2);
                 
                 assert_eq!(parameters[0].name, "a");
-                assert_eq!(parameters[0].param_type, Type::from(TokenType::Int));
+                assert_eq!(parameters[0].param_type, Type::try_from(TokenType::Int).unwrap());
                 
                 assert_eq!(parameters[1].name, "b");
-                assert_eq!(parameters[1].param_type, Type::from(TokenType::FloatType));
+                assert_eq!(parameters[1].param_type, Type::try_from(TokenType::FloatType).unwrap());
                 
                 assert_eq!(body.len(), 1);
             }
@@ -1155,10 +1604,10 @@ mod tests {
             match stmt {
                 Stmt::For { init, condition, update, body } => {
                     // Check init: int i = 0
-                    assert!(init.is_some());
-                    match init.unwrap().as_ref() {
+                    assert_eq!(init.len(), 1);
+                    match &init[0] {
                         Stmt::VarDecl { var_type, name, initializer } => {
-                            assert_eq!(*var_type, Type::from(TokenType::Int));
+                            assert_eq!(*var_type, Type::try_from(TokenType::Int).unwrap());
                             assert_eq!(name, "i");
                             assert_eq!(*initializer, Some(Expr::Integer(0)));
                         }
@@ -1176,8 +1625,8 @@ mod tests {
                         _ => panic!("Expected binary expression in for condition"),
                     }
                     
-                    assert!(update.is_some());
-                    match update.unwrap() {
+                    assert_eq!(update.len(), 1);
+                    match &update[0] {
                         Expr::Assignment { name, value } => {
                             assert_eq!(name, "i");
                             match value.as_ref() {
@@ -1193,7 +1642,7 @@ mod tests {
                     
                     assert_eq!(body.len(), 1);
                     match &body[0] {
-                        Stmt::PrintStmt { format_string, args } => {
+                        Stmt::PrintStmt { format_string, args, .. } => {
                             assert_eq!(*format_string, Expr::String("Hello".to_string()));
                             assert!(args.is_empty());
                         }
@@ -1248,4 +1697,214 @@ mod tests {
             panic!("Failed to parse continue statement");
         }
     }
+
+    #[test]
+    fn test_parse_array_size_constant_expression() {
+        // Test parsing: "int a[2*4];"
+        let tokens = crate::lexer::Lexer::new("int a[2*4];").tokenize().unwrap();
+
+        let mut parser = Parser::new(tokens);
+        match parser.statement() {
+            Some(Stmt::VarDecl { var_type, name, .. }) => {
+                assert_eq!(name, "a");
+                assert_eq!(var_type, Type::array(Type::try_from(TokenType::Int).unwrap(), 8));
+                assert_eq!(var_type.size(), 8 * 4);
+            }
+            other => panic!("Expected array declaration, got {:?}", other),
+        }
+        assert!(parser.get_errors().is_empty());
+    }
+
+    #[test]
+    fn test_sizeof_is_an_array_size_constant() {
+        let tokens = crate::lexer::Lexer::new("char buffer[sizeof(int) * 2 + sizeof(float)];").tokenize().unwrap();
+
+        let mut parser = Parser::new(tokens);
+        match parser.statement() {
+            Some(Stmt::VarDecl { var_type, .. }) => {
+                assert_eq!(var_type, Type::array(Type::try_from(TokenType::CharType).unwrap(), 16));
+            }
+            other => panic!("Expected array declaration, got {:?}", other),
+        }
+        assert!(parser.get_errors().is_empty());
+
+        let tokens = crate::lexer::Lexer::new("int main() { return sizeof(x); }").tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        parser.parse();
+        assert!(parser.get_errors()[0].to_string().contains("Expected a type after 'sizeof('"), "{:?}", parser.get_errors());
+    }
+
+    #[test]
+    fn test_nested_function_is_rejected_with_its_location() {
+        let source = "int main() {\n    int x = 1;\n    int helper() { return 2; }\n    return x;\n}";
+        let tokens = crate::lexer::Lexer::new(source).tokenize().unwrap();
+
+        let mut parser = Parser::new(tokens);
+        parser.parse();
+        let errors = parser.get_errors();
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert!(errors[0].to_string().contains("'helper' cannot be defined inside another function"), "{}", errors[0]);
+        assert_eq!((errors[0].span.line, errors[0].span.column), (3, 5));
+    }
+
+    #[test]
+    fn test_parse_array_size_non_constant_error() {
+        // Test parsing: "int a[n];"
+        let tokens = crate::lexer::Lexer::new("int a[n];").tokenize().unwrap();
+
+        let mut parser = Parser::new(tokens);
+        assert!(parser.statement().is_none());
+        assert_eq!(parser.get_errors().len(), 1);
+    }
+
+    fn parse_errors(source: &str) -> (Vec<Stmt>, Vec<String>) {
+        let tokens = crate::lexer::Lexer::new(source).tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let ast = parser.parse();
+        let errors = parser.get_errors().iter().map(|error| error.to_string()).collect();
+        (ast, errors)
+    }
+
+    #[test]
+    fn test_const_names_an_array_size() {
+        let (ast, errors) = parse_errors("int main() { const int N = 4; int a[N * 2]; return 0; }");
+        assert!(errors.is_empty(), "{:?}", errors);
+        let Stmt::Function { body, .. } = &ast[0] else { panic!() };
+        let Stmt::VarDecl { var_type, .. } = &body[1] else { panic!() };
+        assert_eq!(var_type.kind, TypeKind::Array(Box::new(Type::try_from(TokenType::Int).unwrap()), 8));
+
+        let (_, errors) = parse_errors("int main() { int x = 4; int a[x]; return 0; }");
+        assert!(errors.iter().any(|error| error.contains("integer constant expression")), "{:?}", errors);
+
+        // A variable in an inner block hides the constant
+        let (_, errors) = parse_errors("int main() { const int N = 4; { int N = 2; int a[N]; } int b[N]; return 0; }");
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+    }
+
+    #[test]
+    fn test_constant_required_positions_reject_variables() {
+        let (_, errors) = parse_errors("int main() { int x = 1; static int s = x; return s; }");
+        assert!(errors.iter().any(|error| error.contains("Initializer of static 's'")), "{:?}", errors);
+
+        let (_, errors) = parse_errors("int main() { const int N = 2; static int s = N + 1; static float f = -1.5; return s; }");
+        assert!(errors.is_empty(), "{:?}", errors);
+
+        let (_, errors) = parse_errors("int main() { const int N = 2; N = 3; const int M; return N; }");
+        assert!(errors.iter().any(|error| error.contains("Cannot assign to constant 'N'")), "{:?}", errors);
+        assert!(errors.iter().any(|error| error.contains("Constant 'M' must be initialized")), "{:?}", errors);
+    }
+
+    #[test]
+    fn test_scopes_end_when_their_statement_fails_to_parse() {
+        let (ast, errors) = parse_errors("int main() { const int N = 1; while (1) const int N = 2, M = ; N; int a[N]; return 0; }");
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        let Stmt::Function { body, .. } = &ast[0] else { panic!("{:?}", ast) };
+        let sizes: Vec<_> = body.iter().filter_map(|stmt| match stmt {
+            Stmt::VarDecl { name, var_type, .. } if name == "a" => Some(var_type.size()),
+            _ => None,
+        }).collect();
+        assert_eq!(sizes, [4]);
+    }
+
+    #[test]
+    fn test_constants_are_resolved_where_the_generator_needs_them() {
+        let (ast, errors) = parse_errors("int main() { const int N = 2; static int s = N + 1; char c = \"abc\"[N - 1]; return s; }");
+        assert!(errors.is_empty(), "{:?}", errors);
+        let Stmt::Function { body, .. } = &ast[0] else { panic!("{:?}", ast) };
+        assert!(matches!(&body[1], Stmt::VarDecl { initializer: Some(Expr::Integer(3)), .. }), "{:?}", body[1]);
+        assert!(matches!(&body[2], Stmt::VarDecl { initializer: Some(Expr::Index { index, .. }), .. } if **index == Expr::Integer(1)), "{:?}", body[2]);
+    }
+
+    #[test]
+    fn test_calls_and_assignments_are_not_pure() {
+        let (ast, errors) = parse_errors("int f() { return 1; } int main() { int a = 2; int b = 3; a * b + -a; f() + 1; a = b; return (a[b]); }");
+        assert!(errors.is_empty(), "{:?}", errors);
+        let Stmt::Function { body, .. } = &ast[1] else { panic!() };
+        let expr = |index: usize| match &body[index] {
+            Stmt::ExprStmt(expr) | Stmt::Return(Some(expr), _) => expr,
+            other => panic!("{:?}", other),
+        };
+        assert!(expr(2).is_pure());
+        assert!(!expr(3).is_pure());
+        assert!(!expr(4).is_pure());
+        assert!(expr(5).is_pure());
+    }
+
+    #[test]
+    fn test_parameter_lists() {
+        let (ast, errors) = parse_errors("int add(int a, char b) { return a + b; } int main() { return add(1, 2); }");
+        assert!(errors.is_empty(), "{:?}", errors);
+        let Stmt::Function { parameters, .. } = &ast[0] else { panic!() };
+        let names: Vec<&str> = parameters.iter().map(|parameter| parameter.name.as_str()).collect();
+        assert_eq!(names, vec!["a", "b"]);
+        assert_eq!(parameters[1].param_type, Type::try_from(TokenType::CharType).unwrap());
+        let Stmt::Function { parameters, .. } = &ast[1] else { panic!() };
+        assert!(parameters.is_empty());
+
+        let (_, errors) = parse_errors("int add(int a, int b,) { return a; }");
+        assert!(errors.iter().any(|error| error.contains("Trailing comma in parameter list")), "{:?}", errors);
+
+        let (_, errors) = parse_errors("int sum(int count, ...) { return count; }");
+        assert!(errors.iter().any(|error| error.contains("'sum' cannot take a variable number of arguments")), "{:?}", errors);
+    }
+
+    #[test]
+    fn test_string_literal_indexes_are_checked() {
+        let (_, errors) = parse_errors("int main() { const int N = 2; char c = \"abc\"[N]; char nul = \"abc\"[3]; return __builtin_strlen(\"abc\"); }");
+        assert!(errors.is_empty(), "{:?}", errors);
+
+        let (_, errors) = parse_errors("int main() { char c = \"abc\"[4]; return 0; }");
+        assert!(errors.iter().any(|error| error.contains("Index 4 is out of range for a string literal of length 3")), "{:?}", errors);
+
+        let (_, errors) = parse_errors("int main() { int i = 0; char c = \"abc\"[i]; char d = \"abc\"[-1]; return 0; }");
+        assert!(errors.iter().any(|error| error.contains("must be an integer constant expression")), "{:?}", errors);
+        assert!(errors.iter().any(|error| error.contains("Index -1 is out of range")), "{:?}", errors);
+
+        let (_, errors) = parse_errors("int main() { int n = 1; return __builtin_strlen(n); }");
+        assert!(errors.iter().any(|error| error.contains("__builtin_strlen takes a string literal")), "{:?}", errors);
+    }
+
+    #[test]
+    fn test_else_if_chains_nest_in_the_else_branch() {
+        let (ast, errors) = parse_errors("int main() { int x = 1; if (x) { x = 2; } else if (x > 1) { x = 3; } else { x = 4; } return x; }");
+        assert!(errors.is_empty(), "{:?}", errors);
+        let Stmt::Function { body, .. } = &ast[0] else { panic!() };
+        let Stmt::If { then_branch, else_branch: Some(else_branch), .. } = &body[1] else { panic!("{:?}", body[1]) };
+        assert_eq!(then_branch.len(), 1);
+        let [Stmt::If { condition, else_branch: Some(last), .. }] = else_branch.as_slice() else { panic!("{:?}", else_branch) };
+        assert!(matches!(condition, Expr::Binary { operator: TokenType::GreaterThan, .. }));
+        assert_eq!(last.len(), 1);
+    }
+
+    #[test]
+    fn test_bodies_may_be_a_single_statement() {
+        let (ast, errors) = parse_errors("int main() { int x = 0; while (x < 3) x = x + 1; for (;;) break; if (x) return 1; else if (x > 1) return 2; else return 3; }");
+        assert!(errors.is_empty(), "{:?}", errors);
+        let Stmt::Function { body, .. } = &ast[0] else { panic!() };
+        assert!(matches!(&body[1], Stmt::While { body, .. } if matches!(body.as_slice(), [Stmt::ExprStmt(Expr::Assignment { .. })])));
+        assert!(matches!(&body[2], Stmt::For { init, condition: None, update, body } if init.is_empty() && update.is_empty() && body == &[Stmt::Break]));
+        let Stmt::If { then_branch, else_branch: Some(else_branch), .. } = &body[3] else { panic!("{:?}", body[3]) };
+        assert!(matches!(then_branch.as_slice(), [Stmt::Return(Some(Expr::Integer(1)), _)]));
+        assert!(matches!(else_branch.as_slice(), [Stmt::If { else_branch: Some(_), .. }]));
+    }
+
+    #[test]
+    fn test_dangling_else_belongs_to_the_nearest_if() {
+        let (ast, errors) = parse_errors("int main() { int a = 1; int b = 0; int x = 0; if (a) if (b) x; else x = 2; return x; }");
+        assert!(errors.is_empty(), "{:?}", errors);
+        let Stmt::Function { body, .. } = &ast[0] else { panic!() };
+        let Stmt::If { condition: Expr::Identifier(outer), then_branch, else_branch: None } = &body[3] else { panic!("{:?}", body[3]) };
+        assert_eq!(outer, "a");
+        let [Stmt::If { condition: Expr::Identifier(inner), else_branch: Some(else_branch), .. }] = then_branch.as_slice() else { panic!("{:?}", then_branch) };
+        assert_eq!(inner, "b");
+        assert!(matches!(else_branch.as_slice(), [Stmt::ExprStmt(Expr::Assignment { .. })]));
+
+        // Each `else` takes the nearest `if` still without one
+        let (ast, errors) = parse_errors("int main() { int x = 0; if (x) if (x) if (x) x = 1; else x = 2; else x = 3; return x; }");
+        assert!(errors.is_empty(), "{:?}", errors);
+        let Stmt::Function { body, .. } = &ast[0] else { panic!() };
+        let Stmt::If { then_branch: middle, else_branch: None, .. } = &body[1] else { panic!("{:?}", body[1]) };
+        let [Stmt::If { then_branch: inner, else_branch: Some(_), .. }] = middle.as_slice() else { panic!("{:?}", middle) };
+        assert!(matches!(inner.as_slice(), [Stmt::If { else_branch: Some(_), .. }]));
+    }
 }
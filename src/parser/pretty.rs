@@ -0,0 +1,404 @@
+use crate::lexer::TokenType;
+use crate::types::{Type, TypeKind, TypeQualifiers};
+use super::ast::{Expr, Parameter, Stmt};
+
+const INDENT: &str = "    ";
+
+/// Render a program back to MiniC source, one statement per line with
+/// four-space indentation. Parentheses are only written where operator
+/// precedence needs them, so parsing the output yields the same AST.
+pub fn pretty_print(program: &[Stmt]) -> String {
+    let mut printer = PrettyPrinter { output: String::new(), depth: 0 };
+    for (index, stmt) in program.iter().enumerate() {
+        if index > 0 {
+            printer.output.push('\n');
+        }
+        printer.stmt(stmt);
+    }
+    printer.output
+}
+
+/// Render a single expression as MiniC source
+pub fn pretty_print_expr(expr: &Expr) -> String {
+    expr_source(expr, Precedence::Assignment)
+}
+
+/// One line of source summarizing a statement: the whole statement when it
+/// fits on a line, otherwise its header such as `while (i < 3)`
+pub fn statement_summary(stmt: &Stmt) -> String {
+    stmt_header(stmt).unwrap_or_else(|| simple_stmt_source(stmt))
+}
+
+struct PrettyPrinter {
+    output: String,
+    depth: usize,
+}
+
+impl PrettyPrinter {
+    fn line(&mut self, text: &str) {
+        self.output.push_str(&INDENT.repeat(self.depth));
+        self.output.push_str(text);
+        self.output.push('\n');
+    }
+
+    /// Statements of a braced body, one level deeper, then the closing brace
+    fn body(&mut self, stmts: &[Stmt]) {
+        self.depth += 1;
+        for stmt in stmts {
+            self.stmt(stmt);
+        }
+        self.depth -= 1;
+        self.line("}");
+    }
+
+    /// The branches of an `if` after its header line, chaining `else if`
+    fn branches(&mut self, then_branch: &[Stmt], else_branch: Option<&[Stmt]>) {
+        self.depth += 1;
+        for stmt in then_branch {
+            self.stmt(stmt);
+        }
+        self.depth -= 1;
+        match else_branch {
+            None => self.line("}"),
+            Some([Stmt::If { condition, then_branch, else_branch }]) => {
+                self.line(&format!("}} else if ({}) {{", pretty_print_expr(condition)));
+                self.branches(then_branch, else_branch.as_deref());
+            }
+            Some(else_branch) => {
+                self.line("} else {");
+                self.body(else_branch);
+            }
+        }
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match (stmt_header(stmt), stmt) {
+            (Some(header), Stmt::If { then_branch, else_branch, .. }) => {
+                self.line(&format!("{} {{", header));
+                self.branches(then_branch, else_branch.as_deref());
+            }
+            (Some(header), Stmt::While { body, .. } |
+                Stmt::For { body, .. } | Stmt::Block(body) | Stmt::Function { body, .. }) => {
+                self.line(format!("{} {{", header).trim_start());
+                self.body(body);
+            }
+            (_, simple) => {
+                let text = simple_stmt_source(simple);
+                self.line(&text);
+            }
+        }
+    }
+}
+
+/// Source before the opening brace of a statement with a body
+fn stmt_header(stmt: &Stmt) -> Option<String> {
+    match stmt {
+        Stmt::If { condition, .. } => Some(format!("if ({})", pretty_print_expr(condition))),
+        Stmt::While { condition, .. } => Some(format!("while ({})", pretty_print_expr(condition))),
+        Stmt::For { init, condition, update, .. } => {
+            // The initializer carries its own semicolon; empty clauses
+            // print as `for (;;)`
+            let init = for_init_source(init);
+            let condition = condition.as_ref().map(|c| format!(" {}", pretty_print_expr(c))).unwrap_or_default();
+            let update: Vec<String> = update.iter().map(pretty_print_expr).collect();
+            let update = if update.is_empty() { String::new() } else { format!(" {}", update.join(", ")) };
+            Some(format!("for ({}{};{})", init, condition, update))
+        }
+        Stmt::Block(_) => Some(String::new()),
+        Stmt::Function { is_static, return_type, name, parameters, .. } => {
+            let parameters: Vec<String> = parameters.iter().map(parameter_source).collect();
+            let storage = if *is_static { "static " } else { "" };
+            Some(format!("{}{} {}({})", storage, type_name(return_type), name, parameters.join(", ")))
+        }
+        _ => None,
+    }
+}
+
+/// Source of a statement that fits on one line, including its semicolon
+fn simple_stmt_source(stmt: &Stmt) -> String {
+    match stmt {
+        Stmt::ExprStmt(expr) => format!("{};", pretty_print_expr(expr)),
+        Stmt::VarDecl { var_type, name, initializer } => {
+            let storage = if var_type.qualifiers.is_static {
+                "static "
+            } else if var_type.qualifiers.is_register {
+                "register "
+            } else {
+                ""
+            };
+            let constness = if var_type.qualifiers.is_const { "const " } else { "" };
+            let declaration = format!("{}{}{}", storage, constness, declarator_source(var_type, name));
+            match initializer {
+                Some(value) => format!("{} = {};", declaration, pretty_print_expr(value)),
+                None => format!("{};", declaration),
+            }
+        }
+        Stmt::Return(Some(value), _) => format!("return {};", pretty_print_expr(value)),
+        Stmt::Return(None, _) => "return;".to_string(),
+        Stmt::Break => "break;".to_string(),
+        Stmt::Continue => "continue;".to_string(),
+        Stmt::PrintStmt { format_string, args, newline } => {
+            let builtin = if *newline { "println" } else { "print" };
+            match (format_string, &args[..]) {
+                // A bare `println(x)` is stored with an empty format string
+                (Expr::String(format), [arg]) if format.is_empty() => format!("{}({});", builtin, pretty_print_expr(arg)),
+                _ => {
+                    let operands: Vec<String> = std::iter::once(format_string).chain(args).map(pretty_print_expr).collect();
+                    format!("{}({});", builtin, operands.join(", "))
+                }
+            }
+        }
+        compound => {
+            let mut printer = PrettyPrinter { output: String::new(), depth: 0 };
+            printer.stmt(compound);
+            printer.output.trim_end().to_string()
+        }
+    }
+}
+
+/// `int x` or, for arrays, `int x[4]`
+fn declarator_source(var_type: &Type, name: &str) -> String {
+    match &var_type.kind {
+        TypeKind::Array(element, size) => format!("{}[{}]", declarator_source(element, name), size),
+        TypeKind::Pointer(target) if let TypeKind::Function(function) = &target.kind => {
+            format!("{} {}", type_name(&function.return_type), declared_name(var_type, name))
+        }
+        _ => format!("{} {}", type_name(var_type), name),
+    }
+}
+
+/// The name of a declaration with its array sizes but not its type
+fn declared_name(var_type: &Type, name: &str) -> String {
+    match &var_type.kind {
+        TypeKind::Array(element, size) => format!("{}[{}]", declared_name(element, name), size),
+        TypeKind::Pointer(target) if matches!(target.kind, TypeKind::Function(_)) => format!("(*{})()", name),
+        _ => name.to_string(),
+    }
+}
+
+/// Source of the initializer clause of a `for`, with later declarations
+/// sharing the type of the first, as in `int i = 0, j = 10;`
+fn for_init_source(init: &[Stmt]) -> String {
+    let Some((first, rest)) = init.split_first() else {
+        return ";".to_string();
+    };
+    let mut source = simple_stmt_source(first);
+    source.pop();
+    for stmt in rest {
+        match stmt {
+            Stmt::VarDecl { var_type, name, initializer } => {
+                source.push_str(&format!(", {}", declared_name(var_type, name)));
+                if let Some(value) = initializer {
+                    source.push_str(&format!(" = {}", pretty_print_expr(value)));
+                }
+            }
+            Stmt::ExprStmt(expr) => source.push_str(&format!(", {}", pretty_print_expr(expr))),
+            _ => {}
+        }
+    }
+    source.push(';');
+    source
+}
+
+fn parameter_source(parameter: &Parameter) -> String {
+    format!("{} {}", type_name(&parameter.param_type), parameter.name)
+}
+
+/// A type without its qualifiers, which are written with the declaration
+fn type_name(source_type: &Type) -> String {
+    Type { qualifiers: TypeQualifiers::default(), ..source_type.clone() }.to_string()
+}
+
+/// Binding strength of each expression level, loosest first, mirroring
+/// the parser's recursive descent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Precedence {
+    Assignment,
+    LogicalOr,
+    LogicalAnd,
+    BitwiseOr,
+    BitwiseXor,
+    BitwiseAnd,
+    Equality,
+    Comparison,
+    Shift,
+    Term,
+    Factor,
+    Unary,
+    Call,
+}
+
+fn binary_precedence(operator: &TokenType) -> Precedence {
+    match operator {
+        TokenType::LogicalOr => Precedence::LogicalOr,
+        TokenType::LogicalAnd => Precedence::LogicalAnd,
+        TokenType::BitOr => Precedence::BitwiseOr,
+        TokenType::BitXor => Precedence::BitwiseXor,
+        TokenType::Ampersand => Precedence::BitwiseAnd,
+        TokenType::Equal | TokenType::NotEqual => Precedence::Equality,
+        TokenType::LessThan | TokenType::LessEqual | TokenType::GreaterThan | TokenType::GreaterEqual => Precedence::Comparison,
+        TokenType::ShiftLeft | TokenType::ShiftRight => Precedence::Shift,
+        TokenType::Plus | TokenType::Minus => Precedence::Term,
+        _ => Precedence::Factor,
+    }
+}
+
+fn expr_precedence(expr: &Expr) -> Precedence {
+    match expr {
+        Expr::Assignment { .. } => Precedence::Assignment,
+        Expr::Binary { operator, .. } => binary_precedence(operator),
+        Expr::Unary { .. } | Expr::TypeCast { .. } => Precedence::Unary,
+        _ => Precedence::Call,
+    }
+}
+
+/// Source of `expr` where the grammar expects at least `minimum` binding
+/// strength, parenthesized when it binds more loosely
+fn expr_source(expr: &Expr, minimum: Precedence) -> String {
+    let text = match expr {
+        Expr::Integer(value) => value.to_string(),
+        Expr::Float(value) => float_source(*value),
+        Expr::Char(value) => format!("'{}'", escape(*value, '\'')),
+        Expr::Bool(value) => value.to_string(),
+        Expr::String(value) => format!("\"{}\"", value.chars().map(|c| escape(c, '"')).collect::<String>()),
+        Expr::Identifier(name) => name.clone(),
+        Expr::Binary { left, operator, right } => {
+            // Operators associate to the left, so only a right operand at
+            // the same level needs parentheses
+            let precedence = binary_precedence(operator);
+            format!("{} {} {}",
+                expr_source(left, precedence),
+                operator.operator_symbol().unwrap_or("?"),
+                expr_source(right, next_level(precedence)))
+        }
+        Expr::Unary { operator, operand } => {
+            let operand = expr_source(operand, Precedence::Unary);
+            let operator = operator.operator_symbol().unwrap_or("?");
+            // Keep `- -x` and `& &x` from lexing as other tokens
+            let separator = if operand.starts_with(operator) { " " } else { "" };
+            format!("{}{}{}", operator, separator, operand)
+        }
+        Expr::Call { callee, arguments, type_arguments, .. } => {
+            let arguments: Vec<String> = arguments.iter().map(pretty_print_expr).collect();
+            let type_arguments = if type_arguments.is_empty() {
+                String::new()
+            } else {
+                format!("<{}>", type_arguments.iter().map(type_name).collect::<Vec<_>>().join(", "))
+            };
+            format!("{}{}({})", expr_source(callee, Precedence::Call), type_arguments, arguments.join(", "))
+        }
+        Expr::Index { array, index } => format!("{}[{}]", expr_source(array, Precedence::Call), pretty_print_expr(index)),
+        Expr::Assignment { name, value } => format!("{} = {}", name, expr_source(value, Precedence::Assignment)),
+        Expr::TypeCast { expr, target_type } => format!("({}){}", type_name(target_type), expr_source(expr, Precedence::Unary)),
+    };
+
+    if expr_precedence(expr) < minimum {
+        format!("({})", text)
+    } else {
+        text
+    }
+}
+
+fn next_level(precedence: Precedence) -> Precedence {
+    match precedence {
+        Precedence::Assignment => Precedence::LogicalOr,
+        Precedence::LogicalOr => Precedence::LogicalAnd,
+        Precedence::LogicalAnd => Precedence::BitwiseOr,
+        Precedence::BitwiseOr => Precedence::BitwiseXor,
+        Precedence::BitwiseXor => Precedence::BitwiseAnd,
+        Precedence::BitwiseAnd => Precedence::Equality,
+        Precedence::Equality => Precedence::Comparison,
+        Precedence::Comparison => Precedence::Shift,
+        Precedence::Shift => Precedence::Term,
+        Precedence::Term => Precedence::Factor,
+        Precedence::Factor => Precedence::Unary,
+        Precedence::Unary | Precedence::Call => Precedence::Call,
+    }
+}
+
+/// Float literals are always written with a fractional part, without an
+/// exponent, since the lexer reads neither `1` nor `1e5` as a float
+fn float_source(value: f64) -> String {
+    let text = value.to_string();
+    if text.contains('.') || !value.is_finite() {
+        text
+    } else {
+        format!("{}.0", text)
+    }
+}
+
+fn escape(c: char, quote: char) -> String {
+    match c {
+        '\n' => "\\n".to_string(),
+        '\t' => "\\t".to_string(),
+        '\r' => "\\r".to_string(),
+        '\\' => "\\\\".to_string(),
+        c if c == quote => format!("\\{}", c),
+        c => c.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    fn parse(source: &str) -> Vec<Stmt> {
+        let tokens = Lexer::new(source).tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let ast = parser.parse();
+        assert!(parser.get_errors().is_empty(), "{:?}\n{}", parser.get_errors(), source);
+        ast
+    }
+
+    #[test]
+    fn test_pretty_printed_program_reparses_to_the_same_ast() {
+        let source = r#"
+            static int helper(int a, float b) { return a * (b > 1.5); }
+            int main() {
+                int x = (1 + 2) * -(3 - 4) / 5 % 6;
+                int y = x - (x - 1) - -x;
+                int bits = (x | y) & 3 ^ y << 2 >> (x & 1) | &x == &y;
+                bool ok = !(x < y || y >= 2) && (x == y || x != 3);
+                char c = '\n';
+                int arr[4];
+                register int hot = 0;
+                const int limit = 4;
+                static const char tag = 'x';
+                int (*pick)() = &helper;
+                x = y = 7;
+                { int z = &x == &y; }
+                if (ok) { println("a \"quoted\"\t%d", x); }
+                if (x > 1) { x = 1; } else if (x < 0) { x = 0; } else { if (ok) { x = 2; } else { x = 3; } }
+                while (x > 0) { x = x - 1; if (x == 2) { break; } continue; }
+                for (int i = 0; i < 3; i = i + 1) { print(i); }
+                for (int i = 0, j = 3; i < j; i = i + 1, j = j - 1) { print(j); }
+                for (x = 0, y = 3; x < y; x = x + 1) { print(y); }
+                for (;;) { break; }
+                println(helper(x, 2.0));
+                return (x + y) * 2;
+            }
+        "#;
+        let mut ast = parse(source);
+        let printed = pretty_print(&ast);
+        // Only the layout differs, and with it the locations
+        let mut reparsed = parse(&printed);
+        ast.iter_mut().chain(reparsed.iter_mut()).for_each(Stmt::clear_locations);
+        assert_eq!(reparsed, ast, "{}", printed);
+        assert_eq!(pretty_print(&reparsed), printed);
+    }
+
+    #[test]
+    fn test_parentheses_only_where_precedence_needs_them() {
+        let ast = parse("int main() { return (a * b) + (c - (d - e)) * (f + g); }");
+        let Stmt::Function { body, .. } = &ast[0] else { panic!() };
+        let Stmt::Return(Some(value), _) = &body[0] else { panic!() };
+        assert_eq!(pretty_print_expr(value), "a * b + (c - (d - e)) * (f + g)");
+
+        let ast = parse("int main() { return (a | b) & ((c ^ d) ^ (e << 1)) == (f & g); }");
+        let Stmt::Function { body, .. } = &ast[0] else { panic!() };
+        let Stmt::Return(Some(value), _) = &body[0] else { panic!() };
+        assert_eq!(pretty_print_expr(value), "(a | b) & (c ^ d ^ e << 1) == (f & g)");
+    }
+}
@@ -0,0 +1,81 @@
+use crate::parser::ast::Expr;
+
+/// Functions the compiler provides itself. Calls to them are parsed like
+/// any other `Expr::Call` and lowered to `IrInstruction::Print`,
+/// `IrInstruction::Trap` for `trap()`, or a constant for
+/// `__builtin_strlen`, instead of a call by name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Builtin {
+    Print,
+    Println,
+    Printf,
+    /// `trap()`: abort the program, for exercising runtime checks
+    Trap,
+    /// `__builtin_strlen("...")`: the length of a string literal, folded
+    /// at compile time
+    Strlen,
+}
+
+impl Builtin {
+    /// Every builtin, for registering their names
+    pub const ALL: [Builtin; 5] = [Builtin::Print, Builtin::Println, Builtin::Printf, Builtin::Trap, Builtin::Strlen];
+
+    /// The builtin called `name`, if any
+    pub fn lookup(name: &str) -> Option<Builtin> {
+        Self::ALL.into_iter().find(|builtin| builtin.name() == name)
+    }
+
+    /// The builtin a call goes to, when its callee names one
+    pub fn of_callee(callee: &Expr) -> Option<Builtin> {
+        match callee {
+            Expr::Identifier(name) => Self::lookup(name),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Builtin::Print => "print",
+            Builtin::Println => "println",
+            Builtin::Printf => "printf",
+            Builtin::Trap => "trap",
+            Builtin::Strlen => "__builtin_strlen",
+        }
+    }
+
+    /// Whether control never returns from a call to it
+    pub fn diverges(self) -> bool {
+        self == Builtin::Trap
+    }
+
+    /// Whether the output ends with a newline
+    pub fn appends_newline(self) -> bool {
+        self == Builtin::Println
+    }
+
+    /// Split call arguments into a format string and the values it
+    /// formats. A call whose first argument is not a string literal, such
+    /// as `println(x)`, prints its one value with a conversion chosen
+    /// from its type, marked by an empty format string.
+    pub fn format_arguments(arguments: &[Expr]) -> (Expr, &[Expr]) {
+        match arguments.split_first() {
+            Some((format @ Expr::String(_), rest)) => (format.clone(), rest),
+            _ => (Expr::String(String::new()), arguments),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup_by_name() {
+        assert_eq!(Builtin::lookup("println"), Some(Builtin::Println));
+        assert_eq!(Builtin::lookup("printf"), Some(Builtin::Printf));
+        assert_eq!(Builtin::lookup("trap"), Some(Builtin::Trap));
+        assert_eq!(Builtin::lookup("__builtin_strlen"), Some(Builtin::Strlen));
+        assert_eq!(Builtin::lookup("puts"), None);
+        assert!(Builtin::ALL.iter().all(|builtin| Builtin::lookup(builtin.name()) == Some(*builtin)));
+    }
+}
@@ -0,0 +1,321 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::error::CompilerError;
+use crate::parser::ast::{Expr, Location, Stmt};
+use crate::semantic::Builtin;
+use crate::types::{FunctionType, PrimitiveType, Type, TypeKind};
+
+/// A call that does not match the signature of the function it calls
+#[derive(Debug, Clone, PartialEq)]
+pub enum CallError {
+    ArgumentCount {
+        function: String,
+        expected: usize,
+        found: usize,
+        location: Location,
+    },
+    ArgumentType {
+        function: String,
+        parameter: String,
+        expected: String,
+        found: String,
+        location: Location,
+    },
+    /// A function definition that takes the name of a builtin
+    BuiltinRedefined {
+        function: String,
+        location: Location,
+    },
+}
+
+impl CallError {
+    /// Where the call, or the redefining function, starts
+    pub fn location(&self) -> Location {
+        match self {
+            CallError::ArgumentCount { location, .. }
+            | CallError::ArgumentType { location, .. }
+            | CallError::BuiltinRedefined { location, .. } => *location,
+        }
+    }
+}
+
+impl fmt::Display for CallError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CallError::ArgumentCount { function, expected, found, .. } => write!(
+                f,
+                "Function '{}' expects {} argument{} but {} {} given",
+                function, expected, if *expected == 1 { "" } else { "s" },
+                found, if *found == 1 { "was" } else { "were" }
+            ),
+            CallError::ArgumentType { function, parameter, expected, found, .. } => write!(
+                f,
+                "Argument '{}' of function '{}' expects {} but a {} was given",
+                parameter, function, expected, found
+            ),
+            CallError::BuiltinRedefined { function, .. } => write!(
+                f,
+                "Function '{}' is provided by the compiler and cannot be redefined",
+                function
+            ),
+        }
+    }
+}
+
+impl From<&CallError> for CompilerError {
+    fn from(error: &CallError) -> Self {
+        let location = error.location();
+        CompilerError::semantic_error(error.to_string(), location.line, location.column)
+    }
+}
+
+/// Check every call to a function defined in `program` against its
+/// signature: the number of arguments always, and their types where the
+/// argument's type is known (literals, variables and parameters).
+/// Arithmetic types convert into one another, as in C. Variadic
+/// functions only check their fixed parameters, and calls to functions
+/// the program does not define are not checked, apart from `trap()`,
+/// which takes no arguments, and `__builtin_strlen`, which takes a string.
+/// Builtin names are reserved: a program defining a function of the same
+/// name is an error, since its calls would go to the builtin.
+pub fn check_calls(program: &[Stmt]) -> Vec<CallError> {
+    let mut checker = CallChecker::default();
+    checker.functions.insert(
+        Builtin::Trap.name().to_string(),
+        (signature(Type::primitive(PrimitiveType::Void), vec![]), vec![]),
+    );
+    checker.functions.insert(
+        Builtin::Strlen.name().to_string(),
+        (
+            signature(Type::primitive(PrimitiveType::Int32), vec![Type::pointer(Type::primitive(PrimitiveType::Char))]),
+            vec!["s".to_string()],
+        ),
+    );
+    for stmt in program {
+        if let Stmt::Function { name, return_type, parameters, location, .. } = stmt {
+            if Builtin::lookup(name).is_some() {
+                // Its calls matched neither signature; one error is enough
+                checker.errors.push(CallError::BuiltinRedefined { function: name.clone(), location: *location });
+                checker.functions.remove(name);
+                continue;
+            }
+            let parameter_types = parameters.iter().map(|parameter| parameter.param_type.clone()).collect();
+            let names = parameters.iter().map(|parameter| parameter.name.clone()).collect();
+            checker.functions.insert(name.clone(), (signature(return_type.clone(), parameter_types), names));
+        }
+    }
+
+    for stmt in program {
+        if let Stmt::Function { parameters, body, .. } = stmt {
+            checker.variables = parameters.iter()
+                .map(|parameter| (parameter.name.clone(), parameter.param_type.clone()))
+                .collect();
+            checker.check_block(body);
+        }
+    }
+    checker.errors
+}
+
+#[derive(Default)]
+struct CallChecker {
+    /// Signature and parameter names of each function
+    functions: HashMap<String, (FunctionType, Vec<String>)>,
+    /// Declared type of each variable in scope
+    variables: HashMap<String, Type>,
+    errors: Vec<CallError>,
+}
+
+impl CallChecker {
+    fn check_block(&mut self, stmts: &[Stmt]) {
+        let outer = self.variables.clone();
+        for stmt in stmts {
+            self.check_stmt(stmt);
+        }
+        self.variables = outer;
+    }
+
+    fn check_stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::VarDecl { var_type, name, initializer } => {
+                if let Some(value) = initializer {
+                    self.check_expr(value);
+                }
+                self.variables.insert(name.clone(), var_type.clone());
+            }
+            Stmt::ExprStmt(expr) | Stmt::Return(Some(expr), _) => self.check_expr(expr),
+            Stmt::If { condition, then_branch, else_branch } => {
+                self.check_expr(condition);
+                self.check_block(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.check_block(else_branch);
+                }
+            }
+            Stmt::While { condition, body } => {
+                self.check_expr(condition);
+                self.check_block(body);
+            }
+            Stmt::For { init, condition, update, body } => {
+                let outer = self.variables.clone();
+                for init in init {
+                    self.check_stmt(init);
+                }
+                condition.iter().chain(update).for_each(|expr| self.check_expr(expr));
+                self.check_block(body);
+                self.variables = outer;
+            }
+            Stmt::Block(body) => self.check_block(body),
+            Stmt::PrintStmt { format_string, args, .. } => {
+                std::iter::once(format_string).chain(args).for_each(|expr| self.check_expr(expr));
+            }
+            Stmt::Return(None, _) | Stmt::Break | Stmt::Continue | Stmt::Function { .. } => {}
+        }
+    }
+
+    fn check_expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Call { callee, arguments, location, .. } => {
+                for argument in arguments {
+                    self.check_expr(argument);
+                }
+                match callee.as_ref() {
+                    // A variable of the same name holds a function pointer
+                    // whose signature is not known
+                    Expr::Identifier(name) if !self.variables.contains_key(name) => self.check_call(name, arguments, *location),
+                    other => self.check_expr(other),
+                }
+            }
+            Expr::Binary { left, right, .. } => {
+                self.check_expr(left);
+                self.check_expr(right);
+            }
+            Expr::Index { array, index } => {
+                self.check_expr(array);
+                self.check_expr(index);
+            }
+            Expr::Unary { operand, .. } => self.check_expr(operand),
+            Expr::Assignment { value, .. } => self.check_expr(value),
+            Expr::TypeCast { expr, .. } => self.check_expr(expr),
+            Expr::Integer(_) | Expr::Float(_) | Expr::Char(_) | Expr::Bool(_) | Expr::String(_) | Expr::Identifier(_) => {}
+        }
+    }
+
+    fn check_call(&mut self, name: &str, arguments: &[Expr], location: Location) {
+        let Some((signature, parameter_names)) = self.functions.get(name) else {
+            return;
+        };
+
+        let expected = signature.parameters.len();
+        let count_matches = if signature.is_variadic { arguments.len() >= expected } else { arguments.len() == expected };
+        if !count_matches {
+            self.errors.push(CallError::ArgumentCount { function: name.to_string(), expected, found: arguments.len(), location });
+            return;
+        }
+
+        let mismatches: Vec<CallError> = signature.parameters.iter()
+            .zip(parameter_names)
+            .zip(arguments)
+            .filter_map(|((parameter_type, parameter), argument)| {
+                let argument_type = self.known_type(argument)?;
+                (!accepts(parameter_type, &argument_type)).then(|| CallError::ArgumentType {
+                    function: name.to_string(),
+                    parameter: parameter.clone(),
+                    expected: parameter_type.to_string(),
+                    found: argument_type.to_string(),
+                    location,
+                })
+            })
+            .collect();
+        self.errors.extend(mismatches);
+    }
+
+    /// Type of an argument when it can be told without full inference
+    fn known_type(&self, expr: &Expr) -> Option<Type> {
+        let primitive = match expr {
+            Expr::Integer(_) => PrimitiveType::Int32,
+            Expr::Float(_) => PrimitiveType::Float64,
+            Expr::Char(_) => PrimitiveType::Char,
+            Expr::Bool(_) => PrimitiveType::Bool,
+            Expr::String(_) => return Some(Type::pointer(Type::primitive(PrimitiveType::Char))),
+            Expr::Identifier(name) => return self.variables.get(name).cloned(),
+            _ => return None,
+        };
+        Some(Type::primitive(primitive))
+    }
+}
+
+/// Signature of a non-variadic function
+fn signature(return_type: Type, parameter_types: Vec<Type>) -> FunctionType {
+    let Type { kind: TypeKind::Function(signature), .. } = Type::function(return_type, parameter_types, false) else {
+        unreachable!("Type::function builds a function type")
+    };
+    signature
+}
+
+/// Whether a parameter of type `parameter` can take an argument of type
+/// `argument`
+fn accepts(parameter: &Type, argument: &Type) -> bool {
+    let is_arithmetic = |t: &Type| matches!(&t.kind,
+        TypeKind::Primitive(primitive) if !matches!(primitive, PrimitiveType::Void | PrimitiveType::String));
+    (is_arithmetic(parameter) && is_arithmetic(argument)) || parameter.is_compatible_with(argument)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    fn check(source: &str) -> Vec<CallError> {
+        let tokens = Lexer::new(source).tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let ast = parser.parse();
+        assert!(parser.get_errors().is_empty(), "{:?}", parser.get_errors());
+        check_calls(&ast)
+    }
+
+    #[test]
+    fn test_too_few_arguments() {
+        let errors = check("int add(int a, int b) { return a + b; } int main() { return add(1); }");
+        assert_eq!(errors, vec![CallError::ArgumentCount { function: "add".to_string(), expected: 2, found: 1, location: Location::new(1, 61) }]);
+        assert_eq!(errors[0].to_string(), "Function 'add' expects 2 arguments but 1 was given");
+    }
+
+    #[test]
+    fn test_too_many_arguments() {
+        let errors = check("int one() { return 1; } int main() { int x = 2; println(one(x, 3)); return 0; }");
+        assert_eq!(errors, vec![CallError::ArgumentCount { function: "one".to_string(), expected: 0, found: 2, location: Location::new(1, 57) }]);
+    }
+
+    #[test]
+    fn test_argument_types_are_checked_where_known() {
+        let errors = check("int twice(int n) { return n * 2; } int main() { float f = 1.5; return twice(f) + twice(\"no\"); }");
+        assert_eq!(errors, vec![CallError::ArgumentType {
+            function: "twice".to_string(),
+            parameter: "n".to_string(),
+            expected: "int".to_string(),
+            found: "char *".to_string(),
+            location: Location::new(1, 82),
+        }]);
+    }
+
+    #[test]
+    fn test_matching_and_unknown_calls_pass() {
+        assert!(check("int add(int a, int b) { return a + b; } int main() { int x = 1; return add(x, add(2, 3)); }").is_empty());
+        assert!(check("int main() { return external(1, 2, 3); }").is_empty());
+    }
+
+    #[test]
+    fn test_trap_takes_no_arguments() {
+        assert!(check("int main() { trap(); return 0; }").is_empty());
+        let errors = check("int main() { trap(1); return 0; }");
+        assert_eq!(errors, vec![CallError::ArgumentCount { function: "trap".to_string(), expected: 0, found: 1, location: Location::new(1, 14) }]);
+    }
+
+    #[test]
+    fn test_builtin_names_cannot_be_redefined() {
+        let errors = check("int trap(int x) { return x + 1; } int main() { return trap(41); }");
+        assert_eq!(errors, vec![CallError::BuiltinRedefined { function: "trap".to_string(), location: Location::new(1, 5) }]);
+        assert_eq!(errors[0].to_string(), "Function 'trap' is provided by the compiler and cannot be redefined");
+    }
+}
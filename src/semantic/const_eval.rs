@@ -0,0 +1,131 @@
+use crate::ir::IrBinaryOp;
+use crate::lexer::TokenType;
+use crate::parser::ast::Expr;
+use crate::semantic::symbol_table::SymbolTable;
+use crate::semantic::Builtin;
+use crate::types::{PrimitiveType, TypeKind};
+
+/// Names in scope while checking constant expressions. A `const` local
+/// with a constant initializer holds its value; every other variable holds
+/// `None`, so it hides an outer constant of the same name.
+pub type ConstantTable = SymbolTable<Option<i64>>;
+
+impl Expr {
+    /// Whether this is an integer constant expression, as array sizes and
+    /// static initializers require, given the names in `constants`
+    pub fn is_const_evaluable(&self, constants: &ConstantTable) -> bool {
+        eval_const_expr_in(self, constants).is_some()
+    }
+}
+
+/// Evaluate an integer constant expression at compile time, for contexts
+/// such as array sizes that require one. Binary operators use the same
+/// arithmetic as `ConstantFoldingPass`. A string literal indexed by a
+/// constant is the character there, and `__builtin_strlen` of one its
+/// length. `sizeof(type)` arrives already folded to an integer by the
+/// parser. Returns `None` when the expression is not a compile-time
+/// integer constant.
+pub fn eval_const_expr(expr: &Expr) -> Option<i64> {
+    eval_const_expr_with(expr, &|_| None)
+}
+
+/// Like `eval_const_expr`, with names resolved through `constants`
+pub fn eval_const_expr_in(expr: &Expr, constants: &ConstantTable) -> Option<i64> {
+    eval_const_expr_with(expr, &|name| constants.lookup(name).and_then(|symbol| symbol.value))
+}
+
+/// Like `eval_const_expr`, with `constant` giving the value of a name
+/// that is an integer constant
+pub fn eval_const_expr_with(expr: &Expr, constant: &dyn Fn(&str) -> Option<i64>) -> Option<i64> {
+    let eval_const_expr = |expr| eval_const_expr_with(expr, constant);
+    match expr {
+        Expr::Integer(value) => Some(*value),
+        Expr::Char(c) => Some(*c as i64),
+        Expr::Bool(b) => Some(*b as i64),
+        Expr::Identifier(name) => constant(name),
+        Expr::Unary { operator, operand } => {
+            let value = eval_const_expr(operand)?;
+            match operator {
+                TokenType::Minus => IrBinaryOp::Sub.fold_int(0, value),
+                TokenType::LogicalNot => Some((value == 0) as i64),
+                _ => None,
+            }
+        }
+        Expr::Binary { left, operator, right } => {
+            let op = IrBinaryOp::try_from_token(operator.clone()).ok()?;
+            op.fold_int(eval_const_expr(left)?, eval_const_expr(right)?)
+        }
+        Expr::TypeCast { expr, target_type } => match target_type.kind {
+            TypeKind::Primitive(PrimitiveType::Int32 | PrimitiveType::Char) => eval_const_expr(expr),
+            _ => None,
+        },
+        Expr::Index { array, index } => match array.as_ref() {
+            Expr::String(text) => string_literal_char(text, eval_const_expr(index)?),
+            _ => None,
+        },
+        Expr::Call { callee, arguments, .. } => match (Builtin::of_callee(callee), arguments.as_slice()) {
+            (Some(Builtin::Strlen), [Expr::String(text)]) => Some(text.len() as i64),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Character `index` of the string literal `text`, where index
+/// `text.len()` is its terminating NUL. `None` past either end.
+pub fn string_literal_char(text: &str, index: i64) -> Option<i64> {
+    let index = usize::try_from(index).ok()?;
+    match index.cmp(&text.len()) {
+        std::cmp::Ordering::Less => Some(text.as_bytes()[index] as i64),
+        std::cmp::Ordering::Equal => Some(0),
+        std::cmp::Ordering::Greater => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+    use crate::parser::ast::Stmt;
+
+    fn initializer(source: &str) -> Expr {
+        let tokens = Lexer::new(&format!("int main() {{ int x = {}; }}", source)).tokenize().unwrap();
+        match Parser::new(tokens).parse().remove(0) {
+            Stmt::Function { mut body, .. } => match body.remove(0) {
+                Stmt::VarDecl { initializer: Some(expr), .. } => expr,
+                other => panic!("expected a declaration, got {:?}", other),
+            },
+            other => panic!("expected a function, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_folds_integer_arithmetic() {
+        assert_eq!(eval_const_expr(&initializer("2 + 3 * 4")), Some(14));
+        assert_eq!(eval_const_expr(&initializer("(10 - 4) / 2")), Some(3));
+        assert_eq!(eval_const_expr(&initializer("-3 + 5")), Some(2));
+        assert_eq!(eval_const_expr(&initializer("1 < 2")), Some(1));
+        assert_eq!(eval_const_expr(&initializer("1 << 4 | 6 & 3 ^ 1")), Some(19));
+        assert_eq!(eval_const_expr(&initializer("-64 >> 3")), Some(-8));
+        assert_eq!(eval_const_expr(&initializer("1 << 31")), None);
+        assert_eq!(eval_const_expr(&initializer("1 << 32")), None);
+        assert_eq!(eval_const_expr(&initializer("sizeof(int) * 2 + sizeof(char)")), Some(9));
+    }
+
+    #[test]
+    fn test_non_constant_expressions() {
+        assert_eq!(eval_const_expr(&initializer("y + 1")), None);
+        assert_eq!(eval_const_expr(&initializer("4 / 0")), None);
+        assert_eq!(eval_const_expr(&initializer("1.5")), None);
+    }
+
+    #[test]
+    fn test_folds_string_literal_indexing_and_length() {
+        assert_eq!(eval_const_expr(&initializer("\"abc\"[0]")), Some('a' as i64));
+        assert_eq!(eval_const_expr(&initializer("\"abc\"[1 + 1]")), Some('c' as i64));
+        assert_eq!(eval_const_expr(&initializer("\"abc\"[3]")), Some(0));
+        assert_eq!(eval_const_expr(&initializer("__builtin_strlen(\"hello\") * 2")), Some(10));
+        assert_eq!(eval_const_expr(&initializer("__builtin_strlen(\"\")")), Some(0));
+    }
+}
@@ -0,0 +1,314 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::lexer::TokenType;
+use crate::parser::ast::{Expr, Stmt};
+use crate::semantic::Builtin;
+use crate::semantic::memory_manager::MemorySafetyWarning;
+
+/// Assignment state of a local declared in the function being checked
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Assignment {
+    /// Assigned on every path reaching the current point
+    definitely: bool,
+    /// Assigned on at least one path reaching the current point
+    possibly: bool,
+}
+
+impl Assignment {
+    const NONE: Self = Self { definitely: false, possibly: false };
+    const DONE: Self = Self { definitely: true, possibly: true };
+
+    /// State after two paths join
+    fn join(self, other: Self) -> Self {
+        Self {
+            definitely: self.definitely && other.definitely,
+            possibly: self.possibly || other.possibly,
+        }
+    }
+}
+
+type State = HashMap<String, Assignment>;
+
+/// Find reads of locals that are not assigned on every path leading to
+/// them. A read no path assigns is reported as an error, one that only
+/// some paths assign (such as a single branch of an `if`) as a warning.
+/// Each variable is reported once, for its first such read.
+pub fn find_uninitialized_reads(program: &[Stmt]) -> Vec<MemorySafetyWarning> {
+    let mut checker = InitializationChecker::default();
+    for stmt in program {
+        if let Stmt::Function { body, .. } = stmt {
+            checker.state.clear();
+            checker.reported.clear();
+            checker.check_block(body);
+        }
+    }
+    checker.warnings
+}
+
+#[derive(Default)]
+struct InitializationChecker {
+    state: State,
+    /// Names already reported in the current function
+    reported: HashSet<String>,
+    warnings: Vec<MemorySafetyWarning>,
+    /// Suppresses reports while a loop body is pre-scanned
+    silent: bool,
+}
+
+impl InitializationChecker {
+    /// Check a block in its own scope. Returns true when control cannot
+    /// fall out of its end.
+    fn check_block(&mut self, stmts: &[Stmt]) -> bool {
+        let outer = self.state.clone();
+        let mut declared = Vec::new();
+        let mut diverges = false;
+
+        for stmt in stmts {
+            if let Stmt::VarDecl { name, .. } = stmt {
+                declared.push(name.clone());
+            }
+            if self.check_stmt(stmt) {
+                // The rest of the block is unreachable
+                diverges = true;
+                break;
+            }
+        }
+
+        // Locals of the block go out of scope; shadowed outer ones return
+        for name in declared {
+            match outer.get(&name) {
+                Some(assignment) => self.state.insert(name, *assignment),
+                None => self.state.remove(&name),
+            };
+        }
+        diverges
+    }
+
+    /// Check a statement, returning true when it never completes normally
+    fn check_stmt(&mut self, stmt: &Stmt) -> bool {
+        match stmt {
+            Stmt::VarDecl { name, initializer, .. } => {
+                if let Some(init) = initializer {
+                    self.check_expr(init);
+                }
+                let assignment = if initializer.is_some() { Assignment::DONE } else { Assignment::NONE };
+                self.state.insert(name.clone(), assignment);
+                false
+            }
+            Stmt::ExprStmt(expr) => {
+                self.check_expr(expr);
+                matches!(expr, Expr::Call { callee, .. } if Builtin::of_callee(callee).is_some_and(Builtin::diverges))
+            }
+            Stmt::Return(value, _) => {
+                if let Some(value) = value {
+                    self.check_expr(value);
+                }
+                true
+            }
+            Stmt::Break | Stmt::Continue => true,
+            Stmt::Block(body) => self.check_block(body),
+            Stmt::If { condition, then_branch, else_branch } => {
+                self.check_expr(condition);
+                let before = self.state.clone();
+                let then_diverges = self.check_block(then_branch);
+                let after_then = std::mem::replace(&mut self.state, before.clone());
+                // Without an else, the other path skips the branch
+                let else_diverges = else_branch.as_ref().is_some_and(|else_branch| self.check_block(else_branch));
+                match (then_diverges, else_diverges) {
+                    // Join the paths through both branches
+                    (false, false) => self.join_into(&after_then),
+                    (false, true) => self.state = after_then,
+                    (true, false) => {}
+                    (true, true) => {
+                        self.state = before;
+                        return true;
+                    }
+                }
+                false
+            }
+            Stmt::While { condition, body } => {
+                self.check_loop(Some(condition), body, &[]);
+                false
+            }
+            Stmt::For { init, condition, update, body } => {
+                let before = self.state.clone();
+                for init in init {
+                    self.check_stmt(init);
+                }
+                self.check_loop(condition.as_ref(), body, update);
+                // Variables declared in the initializer are scoped to the loop
+                for init in init {
+                    let Stmt::VarDecl { name, .. } = init else { continue };
+                    match before.get(name) {
+                        Some(assignment) => self.state.insert(name.clone(), *assignment),
+                        None => self.state.remove(name),
+                    };
+                }
+                false
+            }
+            Stmt::PrintStmt { format_string, args, .. } => {
+                self.check_expr(format_string);
+                for arg in args {
+                    self.check_expr(arg);
+                }
+                false
+            }
+            // Nested functions are rejected later; their bodies are not checked here
+            Stmt::Function { .. } => false,
+        }
+    }
+
+    /// Check a loop whose body may run any number of times, including none
+    fn check_loop(&mut self, condition: Option<&Expr>, body: &[Stmt], update: &[Expr]) {
+        // Values assigned late in one iteration are visible early in the
+        // next, so scan the body once silently to learn what it may assign
+        let before = self.state.clone();
+        let silent = std::mem::replace(&mut self.silent, true);
+        self.check_iteration(condition, body, update);
+        self.silent = silent;
+        let possibly: Vec<String> = self.state.iter()
+            .filter(|(_, assignment)| assignment.possibly)
+            .map(|(name, _)| name.clone())
+            .collect();
+
+        self.state = before.clone();
+        for name in possibly {
+            if let Some(assignment) = self.state.get_mut(&name) {
+                assignment.possibly = true;
+            }
+        }
+        let entry = self.state.clone();
+        self.check_iteration(condition, body, update);
+
+        // The loop may exit before the body ever runs
+        self.join_into(&entry);
+    }
+
+    fn check_iteration(&mut self, condition: Option<&Expr>, body: &[Stmt], update: &[Expr]) {
+        if let Some(condition) = condition {
+            self.check_expr(condition);
+        }
+        let diverges = self.check_block(body);
+        if !diverges {
+            for update in update {
+                self.check_expr(update);
+            }
+        }
+    }
+
+    fn check_expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Identifier(name) => self.read(name),
+            Expr::Assignment { name, value } => {
+                self.check_expr(value);
+                if let Some(assignment) = self.state.get_mut(name) {
+                    *assignment = Assignment::DONE;
+                }
+            }
+            Expr::Binary { left, operator: TokenType::LogicalAnd | TokenType::LogicalOr, right } => {
+                // The right operand is evaluated on only some paths
+                self.check_expr(left);
+                let before = self.state.clone();
+                self.check_expr(right);
+                self.join_into(&before);
+            }
+            Expr::Binary { left, right, .. } => {
+                self.check_expr(left);
+                self.check_expr(right);
+            }
+            Expr::Unary { operator: TokenType::Ampersand, operand } => {
+                // Taking the address lets the variable be written through
+                // the pointer, so it is no longer tracked as unassigned
+                if let Expr::Identifier(name) = operand.as_ref() {
+                    if let Some(assignment) = self.state.get_mut(name) {
+                        *assignment = Assignment::DONE;
+                    }
+                } else {
+                    self.check_expr(operand);
+                }
+            }
+            Expr::Unary { operand, .. } => self.check_expr(operand),
+            Expr::Call { callee, arguments, .. } => {
+                self.check_expr(callee);
+                for argument in arguments {
+                    self.check_expr(argument);
+                }
+            }
+            Expr::Index { array, index } => {
+                self.check_expr(array);
+                self.check_expr(index);
+            }
+            Expr::TypeCast { expr, .. } => self.check_expr(expr),
+            Expr::Integer(_) | Expr::Float(_) | Expr::Char(_) | Expr::Bool(_) | Expr::String(_) => {}
+        }
+    }
+
+    /// Join the current state with `other`, the state along another path
+    /// reaching the same point
+    fn join_into(&mut self, other: &State) {
+        for (name, assignment) in self.state.iter_mut() {
+            if let Some(other) = other.get(name) {
+                *assignment = assignment.join(*other);
+            }
+        }
+    }
+
+    fn read(&mut self, name: &str) {
+        let Some(assignment) = self.state.get(name).copied() else {
+            return; // Parameters, functions and unknown names
+        };
+        if assignment.definitely || self.silent || !self.reported.insert(name.to_string()) {
+            return;
+        }
+        self.warnings.push(MemorySafetyWarning::UninitializedRead {
+            variable: name.to_string(),
+            conditional: assignment.possibly,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    fn check(source: &str) -> Vec<MemorySafetyWarning> {
+        let tokens = Lexer::new(source).tokenize().unwrap();
+        let ast = Parser::new(tokens).parse();
+        find_uninitialized_reads(&ast)
+    }
+
+    #[test]
+    fn test_read_before_any_assignment_is_definite() {
+        let warnings = check("int main() { int x; return x; }");
+        assert_eq!(warnings, vec![MemorySafetyWarning::UninitializedRead { variable: "x".to_string(), conditional: false }]);
+    }
+
+    #[test]
+    fn test_assignment_in_one_branch_is_conditional() {
+        let warnings = check("int main() { int c = 1; int x; if (c > 0) { x = 1; } return x; }");
+        assert_eq!(warnings, vec![MemorySafetyWarning::UninitializedRead { variable: "x".to_string(), conditional: true }]);
+    }
+
+    #[test]
+    fn test_assignment_in_both_branches_is_definite() {
+        assert!(check("int main() { int c = 1; int x; if (c > 0) { x = 1; } else { x = 2; } return x; }").is_empty());
+        assert!(check("int main() { int c = 1; int x; if (c > 0) { x = 1; } else { return 0; } return x; }").is_empty());
+        let warnings = check("int main() { int c = 1; int x; if (c > 0) { x = 1; } else if (c < 0) { x = 2; } return x; }");
+        assert_eq!(warnings, vec![MemorySafetyWarning::UninitializedRead { variable: "x".to_string(), conditional: true }]);
+    }
+
+    #[test]
+    fn test_assigned_variables_are_not_reported() {
+        assert!(check("int main() { int x; x = 2; int y = x + 1; return y; }").is_empty());
+        assert!(check("int main() { int c = 1; int x; if (c > 0) { x = 1; return x; } x = 2; return x; }").is_empty());
+        assert!(check("int f(int p) { return p; } int main() { return f(1); }").is_empty());
+    }
+
+    #[test]
+    fn test_loop_assignments_reach_later_iterations() {
+        let warnings = check("int main() { int x; int i = 0; while (i < 3) { if (i > 0) { println(x); } x = i; i = i + 1; } return 0; }");
+        assert_eq!(warnings, vec![MemorySafetyWarning::UninitializedRead { variable: "x".to_string(), conditional: true }]);
+    }
+}
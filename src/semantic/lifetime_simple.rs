@@ -1,6 +1,7 @@
 use crate::types::Type;
 use crate::parser::ast::{Stmt, Expr};
-use std::collections::HashMap;
+use crate::semantic::Builtin;
+use std::collections::{HashMap, HashSet};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Lifetime {
@@ -113,6 +114,7 @@ pub struct LifetimeAnalyzer {
     lifetimes: HashMap<String, Lifetime>,
     constraints: Vec<LifetimeConstraint>,
     variable_usages: HashMap<String, VariableUsage>,
+    function_names: HashSet<String>,
     next_lifetime_id: usize,
     current_line: usize,
 }
@@ -123,12 +125,19 @@ impl LifetimeAnalyzer {
             lifetimes: HashMap::new(),
             constraints: Vec::new(),
             variable_usages: HashMap::new(),
+            function_names: HashSet::new(),
             next_lifetime_id: 0,
             current_line: 1,
         }
     }
     
     pub fn analyze_statements(&mut self, statements: &[Stmt]) -> Result<(), String> {
+        // Functions can be referenced before their definition
+        for stmt in statements {
+            if let Stmt::Function { name, .. } = stmt {
+                self.function_names.insert(name.clone());
+            }
+        }
         for stmt in statements {
             self.analyze_statement(stmt)?;
         }
@@ -143,13 +152,13 @@ impl LifetimeAnalyzer {
             Stmt::ExprStmt(expr) => {
                 self.analyze_expression(expr)?;
             }
-            Stmt::If { condition, then_branch } => {
+            Stmt::If { condition, then_branch, else_branch } => {
                 self.analyze_expression(condition)?;
-                for stmt in then_branch {
+                for stmt in then_branch.iter().chain(else_branch.iter().flatten()) {
                     self.analyze_statement(stmt)?;
                 }
             }
-            Stmt::Return(value) => {
+            Stmt::Return(value, _) => {
                 if let Some(expr) = value {
                     self.analyze_expression(expr)?;
                 }
@@ -164,7 +173,7 @@ impl LifetimeAnalyzer {
                     self.analyze_statement(body_stmt)?;
                 }
             }
-            Stmt::PrintStmt { format_string, args } => {
+            Stmt::PrintStmt { format_string, args, .. } => {
                 self.analyze_expression(format_string)?;
                 for arg in args {
                     self.analyze_expression(arg)?;
@@ -177,13 +186,13 @@ impl LifetimeAnalyzer {
                 }
             }
             Stmt::For { init, condition, update, body } => {
-                if let Some(init_stmt) = init {
+                for init_stmt in init {
                     self.analyze_statement(init_stmt)?;
                 }
                 if let Some(cond_expr) = condition {
                     self.analyze_expression(cond_expr)?;
                 }
-                if let Some(update_expr) = update {
+                for update_expr in update {
                     self.analyze_expression(update_expr)?;
                 }
                 for stmt in body {
@@ -215,11 +224,15 @@ impl LifetimeAnalyzer {
                     self.analyze_expression(arg)?;
                 }
             }
+            Expr::Index { array, index } => {
+                self.analyze_expression(array)?;
+                self.analyze_expression(index)?;
+            }
             Expr::Assignment { name, value } => {
                 self.record_variable_usage(name)?;
                 self.analyze_expression(value)?;
             }
-            Expr::Integer(_) | Expr::Float(_) | Expr::String(_) | Expr::Char(_) => {
+            Expr::Integer(_) | Expr::Float(_) | Expr::String(_) | Expr::Char(_) | Expr::Bool(_) => {
             }
             Expr::TypeCast { expr, .. } => {
                 self.analyze_expression(expr)?;
@@ -253,7 +266,7 @@ impl LifetimeAnalyzer {
     fn record_variable_usage(&mut self, name: &str) -> Result<(), String> {
         if let Some(usage) = self.variable_usages.get_mut(name) {
             usage.add_usage(self.current_line);
-        } else {
+        } else if !self.function_names.contains(name) && Builtin::lookup(name).is_none() {
             return Err(format!("Variable '{}' used before declaration at line {}", name, self.current_line));
         }
         Ok(())
@@ -301,6 +314,7 @@ impl LifetimeAnalyzer {
         self.lifetimes.clear();
         self.constraints.clear();
         self.variable_usages.clear();
+        self.function_names.clear();
         self.next_lifetime_id = 0;
         self.current_line = 1;
     }
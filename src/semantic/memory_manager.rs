@@ -1,7 +1,9 @@
 use crate::types::{Type, TargetTypeConfig};
+use crate::parser::ast::{Expr, Stmt};
 use crate::semantic::symbol_table::SymbolTable;
 use crate::semantic::lifetime_simple::{LifetimeAnalyzer, Lifetime};
-use std::collections::HashMap;
+use crate::semantic::initialization::find_uninitialized_reads;
+use std::collections::{HashMap, HashSet};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum AllocationStrategy {
@@ -37,20 +39,26 @@ impl MemoryLayout {
     }
     
     pub fn is_aligned(&self, address: usize) -> bool {
-        address % self.alignment == 0
+        address % self.alignment.max(1) == 0
     }
     
+    /// `base_offset` rounded up to this layout's alignment. Frame offsets
+    /// are negative, so the remainder is taken with `rem_euclid`.
     pub fn aligned_offset(&self, base_offset: i32) -> i32 {
-        let alignment = self.alignment as i32;
-        let misalignment = base_offset % alignment;
-        if misalignment == 0 {
-            base_offset
-        } else {
-            base_offset + (alignment - misalignment)
+        let alignment = alignment_as_i32(self.alignment);
+        match base_offset.rem_euclid(alignment) {
+            0 => base_offset,
+            misalignment => base_offset.saturating_add(alignment - misalignment),
         }
     }
 }
 
+/// Alignment as a frame offset step. Zero means unaligned and alignments
+/// past `i32::MAX` are clamped rather than wrapping negative.
+fn alignment_as_i32(alignment: usize) -> i32 {
+    i32::try_from(alignment.max(1)).unwrap_or(i32::MAX)
+}
+
 pub struct StackFrameManager {
     current_offset: i32,
     max_offset: i32,
@@ -77,9 +85,17 @@ impl StackFrameManager {
     pub fn allocate_variable(&mut self, name: String, var_type: &Type) -> MemoryLayout {
         let size = var_type.size_with_config(&self.target_config);
         let alignment = var_type.alignment_with_config(&self.target_config);
-        
-        self.current_offset = self.align_offset(self.current_offset, alignment);
-        self.current_offset -= size as i32; // Stack grows downward
+        self.allocate_slot(name, size, alignment)
+    }
+
+    /// Allocate `size` bytes at `alignment` in the current scope, for values
+    /// whose layout is already known rather than derived from a source type
+    pub fn allocate_slot(&mut self, name: String, size: usize, alignment: usize) -> MemoryLayout {
+        // Stack grows downward: reserve the bytes first, then round the
+        // slot's start down, so the offset itself is aligned whatever the
+        // size, odd-sized aggregates included
+        let size_step = i32::try_from(size).unwrap_or(i32::MAX);
+        self.current_offset = self.align_offset(self.current_offset.saturating_sub(size_step), alignment);
         
         let layout = MemoryLayout::new(
             AllocationStrategy::Stack,
@@ -137,13 +153,10 @@ impl StackFrameManager {
     }
     
     fn align_offset(&self, offset: i32, alignment: usize) -> i32 {
-        let alignment = alignment as i32;
-        let misalignment = offset % alignment;
-        if misalignment == 0 {
-            offset
-        } else {
-            offset - misalignment
-        }
+        // Round down, away from the frame base, so an aligned slot never
+        // overlaps the one allocated before it
+        let alignment = alignment_as_i32(alignment);
+        offset.saturating_sub(offset.rem_euclid(alignment))
     }
     
     pub fn reset(&mut self) {
@@ -183,20 +196,42 @@ impl MemorySafetyChecker {
         }
     }
     
-    pub fn check_memory_safety(&mut self, statements: &[crate::parser::ast::Stmt]) -> Result<Vec<MemorySafetyWarning>, String> {
+    pub fn check_memory_safety(&mut self, statements: &[Stmt]) -> Result<Vec<MemorySafetyWarning>, String> {
         let mut warnings = Vec::new();
         
         self.lifetime_analyzer.analyze_statements(statements)?;
         self.lifetime_analyzer.generate_lifetimes();
         
+        warnings.extend(self.check_scopes(statements)?);
         warnings.extend(self.check_use_after_free()?);
         warnings.extend(self.check_double_free()?);
         warnings.extend(self.check_memory_leaks()?);
         warnings.extend(self.check_stack_overflow()?);
+        warnings.extend(find_uninitialized_reads(statements));
         
         Ok(warnings)
     }
     
+    /// Lay out every local in the stack frame, entering and exiting a
+    /// scope for each block, and report uses of a variable after the
+    /// block declaring it has ended
+    fn check_scopes(&mut self, statements: &[Stmt]) -> Result<Vec<MemorySafetyWarning>, String> {
+        self.stack_manager.reset();
+        let mut checker = ScopeChecker {
+            frame: &mut self.stack_manager,
+            scopes: vec![Vec::new()],
+            ended: HashSet::new(),
+            reported: HashSet::new(),
+            warnings: Vec::new(),
+        };
+        
+        for stmt in statements {
+            checker.check_stmt(stmt)?;
+        }
+        
+        Ok(checker.warnings)
+    }
+    
     fn check_use_after_free(&self) -> Result<Vec<MemorySafetyWarning>, String> {
         let mut warnings = Vec::new();
         
@@ -264,6 +299,138 @@ impl MemorySafetyChecker {
     }
 }
 
+/// Walks a program in step with a `StackFrameManager`, tracking which
+/// locals are visible at each point
+struct ScopeChecker<'a> {
+    frame: &'a mut StackFrameManager,
+    /// Names declared in each open scope, outermost first
+    scopes: Vec<Vec<String>>,
+    /// Locals whose declaring block has ended and that are not visible
+    ended: HashSet<String>,
+    /// Names already reported in the current function
+    reported: HashSet<String>,
+    warnings: Vec<MemorySafetyWarning>,
+}
+
+impl ScopeChecker<'_> {
+    fn check_stmt(&mut self, stmt: &Stmt) -> Result<(), String> {
+        match stmt {
+            Stmt::Function { parameters, body, .. } => {
+                self.ended.clear();
+                self.reported.clear();
+                self.enter_scope();
+                for parameter in parameters {
+                    self.declare(&parameter.name, &parameter.param_type);
+                }
+                for stmt in body {
+                    self.check_stmt(stmt)?;
+                }
+                self.exit_scope()?;
+            }
+            Stmt::VarDecl { var_type, name, initializer } => {
+                if let Some(value) = initializer {
+                    self.check_expr(value);
+                }
+                self.declare(name, var_type);
+            }
+            Stmt::ExprStmt(expr) | Stmt::Return(Some(expr), _) => self.check_expr(expr),
+            Stmt::PrintStmt { format_string, args, .. } => {
+                self.check_expr(format_string);
+                args.iter().for_each(|arg| self.check_expr(arg));
+            }
+            Stmt::If { condition, then_branch, else_branch } => {
+                self.check_expr(condition);
+                self.check_block(then_branch)?;
+                if let Some(else_branch) = else_branch {
+                    self.check_block(else_branch)?;
+                }
+            }
+            Stmt::While { condition, body } => {
+                self.check_expr(condition);
+                self.check_block(body)?;
+            }
+            Stmt::For { init, condition, update, body } => {
+                // The initializer's variables live until the loop ends
+                self.enter_scope();
+                for init in init {
+                    self.check_stmt(init)?;
+                }
+                condition.iter().chain(update).for_each(|expr| self.check_expr(expr));
+                self.check_block(body)?;
+                self.exit_scope()?;
+            }
+            Stmt::Block(body) => self.check_block(body)?,
+            Stmt::Return(None, _) | Stmt::Break | Stmt::Continue => {}
+        }
+        Ok(())
+    }
+
+    fn check_block(&mut self, body: &[Stmt]) -> Result<(), String> {
+        self.enter_scope();
+        for stmt in body {
+            self.check_stmt(stmt)?;
+        }
+        self.exit_scope()
+    }
+
+    fn check_expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Identifier(name) => self.use_variable(name),
+            Expr::Assignment { name, value } => {
+                self.use_variable(name);
+                self.check_expr(value);
+            }
+            Expr::Binary { left, right, .. } => {
+                self.check_expr(left);
+                self.check_expr(right);
+            }
+            Expr::Index { array: left, index: right } => {
+                self.check_expr(left);
+                self.check_expr(right);
+            }
+            Expr::Unary { operand, .. } | Expr::TypeCast { expr: operand, .. } => self.check_expr(operand),
+            Expr::Call { callee, arguments, .. } => {
+                self.check_expr(callee);
+                arguments.iter().for_each(|arg| self.check_expr(arg));
+            }
+            Expr::Integer(_) | Expr::Float(_) | Expr::String(_) | Expr::Char(_) | Expr::Bool(_) => {}
+        }
+    }
+
+    fn declare(&mut self, name: &str, var_type: &Type) {
+        self.frame.allocate_variable(name.to_string(), var_type);
+        self.ended.remove(name);
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.push(name.to_string());
+        }
+    }
+
+    fn is_visible(&self, name: &str) -> bool {
+        self.scopes.iter().any(|scope| scope.iter().any(|declared| declared == name))
+    }
+
+    fn use_variable(&mut self, name: &str) {
+        if !self.is_visible(name) && self.ended.contains(name) && self.reported.insert(name.to_string()) {
+            self.warnings.push(MemorySafetyWarning::DanglingScopeAccess { variable: name.to_string() });
+        }
+    }
+
+    fn enter_scope(&mut self) {
+        self.frame.enter_scope();
+        self.scopes.push(Vec::new());
+    }
+
+    fn exit_scope(&mut self) -> Result<(), String> {
+        let deallocated = self.frame.exit_scope()?;
+        self.scopes.pop();
+        // A name the frame released may still be visible from an outer
+        // scope it shadowed
+        let ended: Vec<String> = deallocated.into_iter().filter(|name| !self.is_visible(name)).collect();
+        self.ended.extend(ended);
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum MemorySafetyWarning {
     UseAfterFree {
@@ -289,6 +456,16 @@ pub enum MemorySafetyWarning {
         expected_alignment: usize,
         actual_alignment: usize,
     },
+    /// A local read before it is assigned; `conditional` when only some
+    /// paths to the read leave it unassigned
+    UninitializedRead {
+        variable: String,
+        conditional: bool,
+    },
+    /// A local used after the block declaring it has ended
+    DanglingScopeAccess {
+        variable: String,
+    },
 }
 
 impl MemorySafetyWarning {
@@ -299,6 +476,9 @@ impl MemorySafetyWarning {
             MemorySafetyWarning::StackOverflow { .. } => MemorySafetySeverity::Error,
             MemorySafetyWarning::PotentialLeak { .. } => MemorySafetySeverity::Warning,
             MemorySafetyWarning::UnalignedAccess { .. } => MemorySafetySeverity::Warning,
+            MemorySafetyWarning::UninitializedRead { conditional: false, .. } => MemorySafetySeverity::Error,
+            MemorySafetyWarning::UninitializedRead { conditional: true, .. } => MemorySafetySeverity::Warning,
+            MemorySafetyWarning::DanglingScopeAccess { .. } => MemorySafetySeverity::Error,
         }
     }
     
@@ -319,6 +499,15 @@ impl MemorySafetyWarning {
             MemorySafetyWarning::UnalignedAccess { variable, expected_alignment, actual_alignment } => {
                 format!("Variable '{}' has misaligned access: expected {}-byte alignment, got {}", variable, expected_alignment, actual_alignment)
             }
+            MemorySafetyWarning::UninitializedRead { variable, conditional: false } => {
+                format!("Variable '{}' is read before being initialized", variable)
+            }
+            MemorySafetyWarning::UninitializedRead { variable, conditional: true } => {
+                format!("Variable '{}' may be read before being initialized", variable)
+            }
+            MemorySafetyWarning::DanglingScopeAccess { variable } => {
+                format!("Variable '{}' used after the block declaring it has ended", variable)
+            }
         }
     }
 }
@@ -339,7 +528,15 @@ impl Default for MemorySafetyChecker {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::types::{Type, PrimitiveType};
+    use crate::types::{Type, PrimitiveType, StructType, TypeKind, TypeQualifiers};
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    fn check(source: &str) -> Vec<MemorySafetyWarning> {
+        let tokens = Lexer::new(source).tokenize().unwrap();
+        let ast = Parser::new(tokens).parse();
+        MemorySafetyChecker::new().check_memory_safety(&ast).unwrap()
+    }
 
     #[test]
     fn test_stack_frame_allocation() {
@@ -396,5 +593,79 @@ mod tests {
         assert_eq!(char_layout.offset % char_layout.alignment as i32, 0);
         assert_eq!(int_layout.offset % int_layout.alignment as i32, 0);
         assert_eq!(double_layout.offset % double_layout.alignment as i32, 0);
+
+        // Slots do not overlap once padding is inserted
+        assert!(int_layout.offset + int_layout.size as i32 <= char_layout.offset);
+        assert!(double_layout.offset + double_layout.size as i32 <= int_layout.offset);
+    }
+
+    #[test]
+    fn test_odd_sized_aggregate_then_aligned_type() {
+        let mut manager = StackFrameManager::new(TargetTypeConfig::x86_64());
+
+        let char_type = Type::primitive(PrimitiveType::Char);
+        let triple = Type {
+            kind: TypeKind::Struct(StructType {
+                name: "Triple".to_string(),
+                fields: vec![("a".to_string(), char_type.clone()), ("b".to_string(), char_type.clone()), ("c".to_string(), char_type)],
+            }),
+            qualifiers: TypeQualifiers::default(),
+            size_hint: None,
+        };
+        let triple_layout = manager.allocate_variable("t".to_string(), &triple);
+        let double_layout = manager.allocate_variable("d".to_string(), &Type::primitive(PrimitiveType::Float64));
+
+        assert_eq!(triple_layout.size, 3);
+        assert_eq!(double_layout.offset.rem_euclid(8), 0);
+        assert!(double_layout.offset + 8 <= triple_layout.offset);
+
+        // A slot whose size is not a multiple of its alignment still starts aligned
+        let odd = manager.allocate_slot("odd".to_string(), 3, 8);
+        assert_eq!(odd.offset.rem_euclid(8), 0);
+        assert!(odd.offset + 3 <= double_layout.offset);
+        assert_eq!(odd.aligned_offset(-3), 0);
+        assert_eq!(odd.aligned_offset(-9), -8);
+    }
+
+    #[test]
+    fn test_32_bit_frame_uses_four_byte_pointers() {
+        let mut manager = StackFrameManager::new(TargetTypeConfig::x86_32());
+
+        let pointer = Type::pointer(Type::primitive(PrimitiveType::Int32));
+        manager.allocate_variable("p".to_string(), &pointer);
+        manager.allocate_variable("q".to_string(), &pointer);
+
+        assert_eq!(manager.get_layout("q").unwrap().offset, -8);
+        assert_eq!(manager.frame_size(), 8);
+    }
+
+    #[test]
+    fn test_sibling_scopes_reuse_slots() {
+        let mut manager = StackFrameManager::new(TargetTypeConfig::x86_64());
+
+        manager.enter_scope();
+        let first = manager.allocate_slot("a".to_string(), 4, 4);
+        manager.exit_scope().unwrap();
+
+        manager.enter_scope();
+        let second = manager.allocate_slot("b".to_string(), 4, 4);
+        manager.exit_scope().unwrap();
+
+        assert_eq!(first.offset, second.offset);
+        assert_eq!(manager.frame_size(), 4);
+    }
+
+    #[test]
+    fn test_use_of_block_local_after_its_block_is_flagged() {
+        let warnings = check("int main() { { int inner = 1; println(inner); } return inner; }");
+        let dangling = MemorySafetyWarning::DanglingScopeAccess { variable: "inner".to_string() };
+        assert!(warnings.contains(&dangling), "{:?}", warnings);
+        assert_eq!(dangling.severity(), MemorySafetySeverity::Error);
+    }
+
+    #[test]
+    fn test_shadowed_variable_is_visible_after_inner_block() {
+        let warnings = check("int main() { int x = 1; { int x = 2; println(x); } for (int i = 0; i < 2; i = i + 1) { x = x + i; } return x; }");
+        assert!(!warnings.iter().any(|w| matches!(w, MemorySafetyWarning::DanglingScopeAccess { .. })), "{:?}", warnings);
     }
 }
@@ -1,7 +1,18 @@
 pub mod symbol_table;
 pub mod lifetime_simple;
 pub mod memory_manager;
+pub mod symbol_collector;
+pub mod const_eval;
+pub mod initialization;
+pub mod call_check;
+pub mod return_check;
+pub mod builtins;
 
 pub use symbol_table::{SymbolTable, Symbol, Visibility, Mutability};
 pub use lifetime_simple::{LifetimeAnalyzer, Lifetime, LifetimeConstraint};
 pub use memory_manager::{MemoryLayout, StackFrameManager, MemorySafetyChecker, MemorySafetyWarning, MemorySafetySeverity, AllocationStrategy};
+pub use symbol_collector::{SymbolCollector, SymbolKind, format_symbols};
+pub use const_eval::{eval_const_expr, eval_const_expr_in, eval_const_expr_with, string_literal_char, ConstantTable};
+pub use call_check::{check_calls, CallError};
+pub use return_check::{check_returns, ReturnError};
+pub use builtins::Builtin;
@@ -0,0 +1,159 @@
+use std::fmt;
+
+use crate::error::CompilerError;
+use crate::parser::ast::{Expr, Location, Stmt};
+use crate::semantic::{eval_const_expr, Builtin};
+use crate::types::{PrimitiveType, TypeKind};
+
+/// A function declared to return a value that does not always return one
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReturnError {
+    /// `return;` in a function declared to return a value, located at
+    /// the first one
+    MissingValue { function: String, location: Location },
+    /// Control can reach the end of the function without a `return`;
+    /// located at the function's name
+    MissingReturn { function: String, location: Location },
+}
+
+impl ReturnError {
+    /// Whether the program is rejected for it. A missing `return` is only
+    /// a warning, as it is only wrong when that path is taken.
+    pub fn is_error(&self) -> bool {
+        matches!(self, ReturnError::MissingValue { .. })
+    }
+
+    pub fn location(&self) -> Location {
+        match self {
+            ReturnError::MissingValue { location, .. } | ReturnError::MissingReturn { location, .. } => *location,
+        }
+    }
+}
+
+impl fmt::Display for ReturnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReturnError::MissingValue { function, .. } => {
+                write!(f, "Function '{}' must return a value, but `return;` returns none", function)
+            }
+            ReturnError::MissingReturn { function, .. } => {
+                write!(f, "Control can reach the end of function '{}' without returning a value", function)
+            }
+        }
+    }
+}
+
+impl From<&ReturnError> for CompilerError {
+    fn from(error: &ReturnError) -> Self {
+        let location = error.location();
+        CompilerError::semantic_error(error.to_string(), location.line, location.column)
+    }
+}
+
+/// Check the `return`s of every function declared to return a value:
+/// each must have a value, and control must not fall off the end of the
+/// body. `main` may fall off its end, as it returns 0 when it does.
+pub fn check_returns(program: &[Stmt]) -> Vec<ReturnError> {
+    let mut errors = Vec::new();
+    for stmt in program {
+        let Stmt::Function { name, return_type, body, location, .. } = stmt else { continue };
+        if return_type.kind == TypeKind::Primitive(PrimitiveType::Void) {
+            continue;
+        }
+        if let Some(return_location) = valueless_return(body) {
+            errors.push(ReturnError::MissingValue { function: name.clone(), location: return_location });
+        }
+        if name != "main" && completes(body) {
+            errors.push(ReturnError::MissingReturn { function: name.clone(), location: *location });
+        }
+    }
+    errors
+}
+
+/// Location of the first `return;` in `stmts`
+fn valueless_return(stmts: &[Stmt]) -> Option<Location> {
+    stmts.iter().find_map(|stmt| match stmt {
+        Stmt::Return(None, location) => Some(*location),
+        Stmt::If { then_branch, else_branch, .. } => {
+            valueless_return(then_branch).or_else(|| else_branch.as_deref().and_then(valueless_return))
+        }
+        Stmt::Block(body) | Stmt::While { body, .. } | Stmt::For { body, .. } => valueless_return(body),
+        _ => None,
+    })
+}
+
+/// Whether control can fall out of the end of `stmts`
+fn completes(stmts: &[Stmt]) -> bool {
+    !stmts.iter().any(never_completes)
+}
+
+/// Whether control never continues past `stmt`: it returns, traps,
+/// leaves its loop, or loops forever
+fn never_completes(stmt: &Stmt) -> bool {
+    match stmt {
+        Stmt::Return(..) | Stmt::Break | Stmt::Continue => true,
+        Stmt::ExprStmt(Expr::Call { callee, .. }) => Builtin::of_callee(callee).is_some_and(Builtin::diverges),
+        Stmt::Block(body) => !completes(body),
+        Stmt::If { then_branch, else_branch: Some(else_branch), .. } => !completes(then_branch) && !completes(else_branch),
+        Stmt::While { condition, body } => always_true(condition) && !breaks(body),
+        Stmt::For { condition, body, .. } => condition.as_ref().is_none_or(always_true) && !breaks(body),
+        _ => false,
+    }
+}
+
+fn always_true(condition: &Expr) -> bool {
+    eval_const_expr(condition).is_some_and(|value| value != 0)
+}
+
+/// Whether a `break` in `stmts` leaves the loop they are the body of;
+/// one in a nested loop only leaves that loop
+fn breaks(stmts: &[Stmt]) -> bool {
+    stmts.iter().any(|stmt| match stmt {
+        Stmt::Break => true,
+        Stmt::Block(body) => breaks(body),
+        Stmt::If { then_branch, else_branch, .. } => breaks(then_branch) || else_branch.as_deref().is_some_and(breaks),
+        _ => false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    fn check(source: &str) -> Vec<ReturnError> {
+        let tokens = Lexer::new(source).tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let ast = parser.parse();
+        assert!(parser.get_errors().is_empty(), "{:?}", parser.get_errors());
+        check_returns(&ast)
+    }
+
+    #[test]
+    fn test_bare_return_in_int_function_is_an_error() {
+        let errors = check("int pick(int a) { if (a) { return; } return 1; } int main() { return pick(1); }");
+        assert_eq!(errors, vec![ReturnError::MissingValue { function: "pick".to_string(), location: Location::new(1, 28) }]);
+        assert!(errors[0].is_error());
+        assert_eq!(errors[0].to_string(), "Function 'pick' must return a value, but `return;` returns none");
+        assert!(check("void stop() { return; } int main() { stop(); return 0; }").is_empty());
+    }
+
+    #[test]
+    fn test_missing_return_at_the_end_is_a_warning() {
+        let errors = check("int sign(int a) { if (a < 0) { return -1; } } int main() { int s = sign(2); }");
+        assert_eq!(errors, vec![ReturnError::MissingReturn { function: "sign".to_string(), location: Location::new(1, 5) }]);
+        assert!(!errors[0].is_error());
+    }
+
+    #[test]
+    fn test_paths_that_never_fall_off_the_end() {
+        assert!(check("int f() { { return 1; } }").is_empty());
+        assert!(check("int f() { trap(); }").is_empty());
+        assert!(check("int f() { while (1) { } }").is_empty());
+        assert!(check("int f() { for (;;) { while (1) { break; } } }").is_empty());
+        assert_eq!(check("int f() { while (1) { if (1) { break; } } }").len(), 1);
+        assert!(check("int f(int a) { if (a) { return 1; } else if (a < 0) { return 2; } else { return 3; } }").is_empty());
+        assert_eq!(check("int f(int a) { if (a) { return 1; } else if (a < 0) { return 2; } }").len(), 1);
+    }
+}
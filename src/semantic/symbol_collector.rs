@@ -0,0 +1,164 @@
+use crate::parser::ast::Stmt;
+use crate::semantic::symbol_table::{Mutability, Symbol, SymbolTable, Visibility};
+use crate::types::Type;
+
+/// What a collected symbol names
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SymbolKind {
+    Function,
+    Parameter,
+    Variable,
+}
+
+/// Builds a `SymbolTable` for a program, keeping every symbol it declares
+/// so the table can be inspected once analysis has left each scope.
+pub struct SymbolCollector {
+    table: SymbolTable<SymbolKind>,
+    symbols: Vec<Symbol<SymbolKind>>,
+}
+
+impl SymbolCollector {
+    pub fn new() -> Self {
+        Self {
+            table: SymbolTable::new(),
+            symbols: Vec::new(),
+        }
+    }
+
+    /// Collect all symbols of `program` in declaration order
+    pub fn collect(mut self, program: &[Stmt]) -> Result<Vec<Symbol<SymbolKind>>, String> {
+        for stmt in program {
+            self.collect_stmt(stmt)?;
+        }
+        Ok(self.symbols)
+    }
+
+    fn collect_stmt(&mut self, stmt: &Stmt) -> Result<(), String> {
+        match stmt {
+            Stmt::Function { is_static, return_type, name, parameters, body, .. } => {
+                let visibility = if *is_static { Visibility::Private } else { Visibility::Public };
+                self.declare(name, return_type.clone(), SymbolKind::Function, visibility, Mutability::Immutable)?;
+
+                self.table.enter_scope();
+                for parameter in parameters {
+                    let mutability = if parameter.is_mutable { Mutability::Mutable } else { Mutability::Immutable };
+                    self.declare(&parameter.name, parameter.param_type.clone(), SymbolKind::Parameter, Visibility::Private, mutability)?;
+                }
+                for stmt in body {
+                    self.collect_stmt(stmt)?;
+                }
+                self.table.exit_scope()
+            }
+            Stmt::VarDecl { var_type, name, .. } => {
+                let mutability = if var_type.qualifiers.is_const { Mutability::Immutable } else { Mutability::Mutable };
+                self.declare(name, var_type.clone(), SymbolKind::Variable, Visibility::Private, mutability)
+            }
+            Stmt::If { then_branch, else_branch, .. } => {
+                self.collect_block(then_branch)?;
+                else_branch.as_deref().map_or(Ok(()), |else_branch| self.collect_block(else_branch))
+            }
+            Stmt::While { body, .. } => self.collect_block(body),
+            Stmt::Block(body) => self.collect_block(body),
+            Stmt::For { init, body, .. } => {
+                self.table.enter_scope();
+                for init in init {
+                    self.collect_stmt(init)?;
+                }
+                self.collect_block(body)?;
+                self.table.exit_scope()
+            }
+            Stmt::ExprStmt(_) | Stmt::Return(..) | Stmt::Break | Stmt::Continue | Stmt::PrintStmt { .. } => Ok(()),
+        }
+    }
+
+    fn collect_block(&mut self, body: &[Stmt]) -> Result<(), String> {
+        self.table.enter_scope();
+        for stmt in body {
+            self.collect_stmt(stmt)?;
+        }
+        self.table.exit_scope()
+    }
+
+    fn declare(&mut self, name: &str, symbol_type: Type, kind: SymbolKind, visibility: Visibility, mutability: Mutability) -> Result<(), String> {
+        let scope_level = self.table.current_scope_level();
+        // The AST does not keep source positions, so symbols carry none
+        let symbol = Symbol::new(name.to_string(), symbol_type, kind, visibility, mutability, scope_level, 0, 0);
+
+        self.table.insert(symbol.clone())?;
+        self.symbols.push(symbol);
+        Ok(())
+    }
+}
+
+impl Default for SymbolCollector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Render collected symbols as an aligned table, one symbol per line
+pub fn format_symbols(symbols: &[Symbol<SymbolKind>]) -> String {
+    let mut output = format!("{:<20} {:<10} {:<8} {:<10} {:<10} {}\n", "name", "kind", "type", "visibility", "mutability", "scope");
+    for symbol in symbols {
+        output.push_str(&format!(
+            "{:<20} {:<10} {:<8} {:<10} {:<10} {}\n",
+            symbol.name,
+            format!("{:?}", symbol.value).to_lowercase(),
+            symbol.symbol_type.to_string(),
+            format!("{:?}", symbol.visibility).to_lowercase(),
+            format!("{:?}", symbol.mutability).to_lowercase(),
+            symbol.scope_level,
+        ));
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    fn collect(source: &str) -> Vec<Symbol<SymbolKind>> {
+        let tokens = Lexer::new(source).tokenize().unwrap();
+        let ast = Parser::new(tokens).parse();
+        SymbolCollector::new().collect(&ast).unwrap()
+    }
+
+    #[test]
+    fn test_function_locals_are_listed_with_types_and_scopes() {
+        let symbols = collect("int main() { int count = 1; float ratio = 2.0; return count; }");
+
+        assert_eq!(symbols.len(), 3);
+        assert_eq!(symbols[0].name, "main");
+        assert_eq!(symbols[0].scope_level, 0);
+        assert_eq!(symbols[0].visibility, Visibility::Public);
+
+        assert_eq!(symbols[1].name, "count");
+        assert_eq!(symbols[1].symbol_type.to_string(), "int");
+        assert_eq!(symbols[1].scope_level, 1);
+        assert_eq!(symbols[1].mutability, Mutability::Mutable);
+
+        assert_eq!(symbols[2].name, "ratio");
+        assert_eq!(symbols[2].symbol_type.to_string(), "float");
+        assert_eq!(symbols[2].scope_level, 1);
+
+        let dump = format_symbols(&symbols);
+        assert!(dump.lines().any(|line| line.starts_with("count") && line.contains("int") && line.ends_with('1')));
+        assert!(dump.lines().any(|line| line.starts_with("ratio") && line.contains("float") && line.ends_with('1')));
+    }
+
+    #[test]
+    fn test_nested_block_increases_scope_level() {
+        let symbols = collect("int main() { int a = 1; if (a > 0) { int b = 2; } return a; }");
+        let b = symbols.iter().find(|symbol| symbol.name == "b").unwrap();
+        assert_eq!(b.scope_level, 2);
+    }
+
+    #[test]
+    fn test_redeclaration_in_same_scope_error() {
+        let tokens = Lexer::new("int main() { int a = 1; int a = 2; return a; }").tokenize().unwrap();
+        let ast = Parser::new(tokens).parse();
+        assert!(SymbolCollector::new().collect(&ast).is_err());
+    }
+}
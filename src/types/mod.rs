@@ -1,3 +1,5 @@
+use std::fmt;
+
 use crate::lexer::TokenType;
 
 pub mod target_config;
@@ -46,6 +48,10 @@ pub struct TypeQualifiers {
     pub is_const: bool,
     pub is_volatile: bool,
     pub is_restrict: bool,
+    /// Declared `register`: keep the variable in a register when one is free
+    pub is_register: bool,
+    /// Declared `static` inside a function: one copy, kept across calls
+    pub is_static: bool,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -98,6 +104,14 @@ impl Type {
         }
     }
     
+    pub fn structure(struct_type: StructType) -> Self {
+        Type {
+            kind: TypeKind::Struct(struct_type),
+            qualifiers: TypeQualifiers::default(),
+            size_hint: None,
+        }
+    }
+
     pub fn generic(name: String) -> Self {
         Type {
             kind: TypeKind::Generic(name),
@@ -165,13 +179,22 @@ impl Type {
         }
     }
 
+    /// The keyword naming this type, for the types MiniC source can spell
+    /// with a single keyword; None for every other type
     pub fn to_token_type(&self) -> Option<TokenType> {
         match &self.kind {
-            TypeKind::Primitive(PrimitiveType::Void) => Some(TokenType::Void),
-            TypeKind::Primitive(PrimitiveType::Int32) => Some(TokenType::Int),
-            TypeKind::Primitive(PrimitiveType::Float64) => Some(TokenType::FloatType),
-            TypeKind::Primitive(PrimitiveType::Char) => Some(TokenType::CharType),
-            _ => None,
+            TypeKind::Primitive(primitive) => match primitive {
+                PrimitiveType::Void => Some(TokenType::Void),
+                PrimitiveType::Int32 => Some(TokenType::Int),
+                PrimitiveType::Float64 => Some(TokenType::FloatType),
+                PrimitiveType::Char => Some(TokenType::CharType),
+                PrimitiveType::Bool => Some(TokenType::BoolType),
+                PrimitiveType::Int8 | PrimitiveType::Int16 | PrimitiveType::Int64 |
+                PrimitiveType::UInt8 | PrimitiveType::UInt16 | PrimitiveType::UInt32 | PrimitiveType::UInt64 |
+                PrimitiveType::Float32 | PrimitiveType::String => None,
+            },
+            TypeKind::Pointer(_) | TypeKind::Array(..) | TypeKind::Function(_) | TypeKind::Struct(_) |
+            TypeKind::Union(_) | TypeKind::Enum(_) | TypeKind::Generic(_) => None,
         }
     }
 
@@ -201,18 +224,90 @@ impl Default for TypeQualifiers {
             is_const: false,
             is_volatile: false,
             is_restrict: false,
+            is_register: false,
+            is_static: false,
+        }
+    }
+}
+
+/// The C spelling of a type, for diagnostics: `int`, `char *`, `int[10]`,
+/// `const int *`. `const` and `volatile` go before the type they qualify,
+/// except on pointers, where they follow the `*` as in `int * const`.
+/// Storage classes (`static`, `register`) are not part of the type.
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let qualifiers = [
+            (self.qualifiers.is_const, "const"),
+            (self.qualifiers.is_volatile, "volatile"),
+            (self.qualifiers.is_restrict, "restrict"),
+        ];
+        let mut qualifiers = qualifiers.iter().filter(|(set, _)| *set).map(|(_, name)| *name);
+        if let TypeKind::Pointer(target) = &self.kind {
+            let target = target.to_string();
+            let separator = if target.ends_with('*') { "" } else { " " };
+            write!(f, "{}{}*", target, separator)?;
+            return qualifiers.try_for_each(|qualifier| write!(f, " {}", qualifier));
+        }
+        // `restrict` only qualifies pointers
+        for qualifier in qualifiers.filter(|qualifier| *qualifier != "restrict") {
+            write!(f, "{} ", qualifier)?;
+        }
+        match &self.kind {
+            TypeKind::Primitive(primitive) => write!(f, "{}", primitive),
+            TypeKind::Pointer(_) => unreachable!("pointers are written above"),
+            TypeKind::Array(element, size) => write!(f, "{}[{}]", element, size),
+            TypeKind::Function(function) => {
+                let mut parameters: Vec<String> = function.parameters.iter().map(Type::to_string).collect();
+                if function.is_variadic {
+                    parameters.push("...".to_string());
+                }
+                write!(f, "{} ({})", function.return_type, parameters.join(", "))
+            }
+            TypeKind::Struct(struct_type) => write!(f, "struct {}", struct_type.name),
+            TypeKind::Union(union_type) => write!(f, "union {}", union_type.name),
+            TypeKind::Enum(enum_type) => write!(f, "enum {}", enum_type.name),
+            TypeKind::Generic(name) => write!(f, "{}", name),
         }
     }
 }
 
-impl From<TokenType> for Type {
-    fn from(token_type: TokenType) -> Self {
+impl fmt::Display for PrimitiveType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            PrimitiveType::Void => "void",
+            PrimitiveType::Bool => "bool",
+            PrimitiveType::Int8 => "int8_t",
+            PrimitiveType::Int16 => "int16_t",
+            PrimitiveType::Int32 => "int",
+            PrimitiveType::Int64 => "int64_t",
+            PrimitiveType::UInt8 => "uint8_t",
+            PrimitiveType::UInt16 => "uint16_t",
+            PrimitiveType::UInt32 => "uint32_t",
+            PrimitiveType::UInt64 => "uint64_t",
+            // MiniC's `float` is 64 bits wide
+            PrimitiveType::Float32 => "float32_t",
+            PrimitiveType::Float64 => "float",
+            PrimitiveType::Char => "char",
+            // The type of string literals
+            PrimitiveType::String => "char *",
+        };
+        f.write_str(name)
+    }
+}
+
+/// The type a type keyword names. Any other token is an error rather than
+/// a silent `int`.
+impl TryFrom<TokenType> for Type {
+    type Error = String;
+
+    fn try_from(token_type: TokenType) -> Result<Self, Self::Error> {
         match token_type {
-            TokenType::Void => Type::primitive(PrimitiveType::Void),
-            TokenType::Int => Type::primitive(PrimitiveType::Int32),
-            TokenType::FloatType => Type::primitive(PrimitiveType::Float64),
-            TokenType::CharType => Type::primitive(PrimitiveType::Char),
-            _ => Type::primitive(PrimitiveType::Int32), // Default fallback
+            TokenType::Void => Ok(Type::primitive(PrimitiveType::Void)),
+            TokenType::Int => Ok(Type::primitive(PrimitiveType::Int32)),
+            TokenType::FloatType => Ok(Type::primitive(PrimitiveType::Float64)),
+            TokenType::CharType => Ok(Type::primitive(PrimitiveType::Char)),
+            TokenType::BoolType => Ok(Type::primitive(PrimitiveType::Bool)),
+            other => Err(format!("{:?} does not name a type", other)),
         }
     }
 }
@@ -264,3 +359,39 @@ impl Default for TypeChecker {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn int() -> Type {
+        Type::primitive(PrimitiveType::Int32)
+    }
+
+    fn constant(mut qualified: Type) -> Type {
+        qualified.qualifiers.is_const = true;
+        qualified
+    }
+
+    #[test]
+    fn test_types_display_with_their_c_names() {
+        assert_eq!(int().to_string(), "int");
+        assert_eq!(Type::primitive(PrimitiveType::Float64).to_string(), "float");
+        assert_eq!(Type::pointer(Type::primitive(PrimitiveType::Char)).to_string(), "char *");
+        assert_eq!(Type::array(int(), 10).to_string(), "int[10]");
+        assert_eq!(constant(int()).to_string(), "const int");
+        assert_eq!(Type::function(int(), vec![int(), Type::pointer(int())], true).to_string(), "int (int, int *, ...)");
+    }
+
+    #[test]
+    fn test_pointer_qualifiers_follow_what_they_qualify() {
+        assert_eq!(Type::pointer(constant(int())).to_string(), "const int *");
+        assert_eq!(constant(Type::pointer(int())).to_string(), "int * const");
+        assert_eq!(Type::pointer(Type::pointer(constant(int()))).to_string(), "const int **");
+
+        // Storage classes are not part of the type
+        let mut register = int();
+        register.qualifiers.is_register = true;
+        assert_eq!(register.to_string(), "int");
+    }
+}
@@ -1,10 +1,13 @@
-use crate::types::{TypeKind, PrimitiveType};
+use crate::types::{Type, TypeKind, PrimitiveType, StructType};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct TargetTypeConfig {
     pub pointer_size: usize,
     pub default_alignment: usize,
     pub stack_alignment: usize,
+    /// Largest alignment a primitive gets; wider primitives are aligned
+    /// to this instead of their size
+    pub max_primitive_alignment: usize,
 }
 
 impl TargetTypeConfig {
@@ -13,6 +16,18 @@ impl TargetTypeConfig {
             pointer_size: 8,
             default_alignment: 8,
             stack_alignment: 16,
+            max_primitive_alignment: 8,
+        }
+    }
+
+    /// 32-bit x86 (System V i386): 4-byte pointers, and 8-byte scalars
+    /// such as `double` aligned to 4 bytes
+    pub fn x86_32() -> Self {
+        Self {
+            pointer_size: 4,
+            default_alignment: 4,
+            stack_alignment: 16,
+            max_primitive_alignment: 4,
         }
     }
     
@@ -33,14 +48,13 @@ impl TargetTypeConfig {
             }
             TypeKind::Function(_) => self.pointer_size, // Function pointer
             TypeKind::Struct(s) => {
-                let mut total_size = 0;
-                for (_, field_type) in &s.fields {
-                    let field_size = self.size_of(&field_type.kind);
-                    let field_alignment = self.alignment_of(&field_type.kind);
-                    total_size = self.align_offset(total_size, field_alignment);
-                    total_size += field_size;
-                }
-                self.align_offset(total_size, self.default_alignment)
+                let total_size = self.field_offsets(s).last()
+                    .map(|(offset, (_, field_type))| offset + self.size_of(&field_type.kind))
+                    .unwrap_or(0);
+                // Pad to the struct's own alignment, so `{ char a, b, c; }`
+                // stays three bytes while every element of an array of
+                // structs still starts aligned
+                self.align_offset(total_size, self.alignment_of(type_kind))
             }
             TypeKind::Union(u) => {
                 u.variants.iter()
@@ -61,7 +75,7 @@ impl TargetTypeConfig {
                 PrimitiveType::Int8 | PrimitiveType::UInt8 | PrimitiveType::Char => 1,
                 PrimitiveType::Int16 | PrimitiveType::UInt16 => 2,
                 PrimitiveType::Int32 | PrimitiveType::UInt32 | PrimitiveType::Float32 => 4,
-                PrimitiveType::Int64 | PrimitiveType::UInt64 | PrimitiveType::Float64 => 8,
+                PrimitiveType::Int64 | PrimitiveType::UInt64 | PrimitiveType::Float64 => 8.min(self.max_primitive_alignment),
                 PrimitiveType::String => self.pointer_size,
             },
             TypeKind::Pointer(_) => self.pointer_size,
@@ -84,8 +98,23 @@ impl TargetTypeConfig {
         }
     }
     
+    /// Byte offset of each field in declaration order, with padding inserted
+    /// so every field starts at its natural alignment
+    pub fn field_offsets<'a>(&self, struct_type: &'a StructType) -> Vec<(usize, &'a (String, Type))> {
+        let mut offset = 0;
+        struct_type.fields.iter().map(|field| {
+            let field_type = &field.1;
+            offset = self.align_offset(offset, self.alignment_of(&field_type.kind));
+            let field_offset = offset;
+            offset += self.size_of(&field_type.kind);
+            (field_offset, field)
+        }).collect()
+    }
+    
+    /// Round `offset` up to a multiple of `alignment`. Works for any
+    /// alignment, not only powers of two, and treats 0 as 1.
     pub fn align_offset(&self, offset: usize, alignment: usize) -> usize {
-        (offset + alignment - 1) & !(alignment - 1)
+        offset.next_multiple_of(alignment.max(1))
     }
 }
 
@@ -94,3 +123,74 @@ impl Default for TargetTypeConfig {
         Self::x86_64()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn struct_of(name: &str, fields: &[(&str, Type)]) -> StructType {
+        StructType {
+            name: name.to_string(),
+            fields: fields.iter().map(|(n, t)| (n.to_string(), t.clone())).collect(),
+        }
+    }
+
+    fn offsets(config: &TargetTypeConfig, struct_type: &StructType) -> Vec<usize> {
+        config.field_offsets(struct_type).into_iter().map(|(offset, _)| offset).collect()
+    }
+
+    #[test]
+    fn test_point_fields_are_packed() {
+        let int = Type::primitive(PrimitiveType::Int32);
+        let point = struct_of("Point", &[("x", int.clone()), ("y", int)]);
+        let config = TargetTypeConfig::x86_64();
+
+        assert_eq!(offsets(&config, &point), vec![0, 4]);
+    }
+
+    #[test]
+    fn test_field_offsets_include_padding() {
+        let mixed = struct_of("Mixed", &[
+            ("tag", Type::primitive(PrimitiveType::Char)),
+            ("value", Type::primitive(PrimitiveType::Float64)),
+        ]);
+        let config = TargetTypeConfig::x86_64();
+
+        assert_eq!(offsets(&config, &mixed), vec![0, 8]);
+        assert_eq!(config.size_of(&TypeKind::Struct(mixed)), 16);
+    }
+
+    #[test]
+    fn test_pointer_size_follows_target_width() {
+        let pointer = Type::pointer(Type::primitive(PrimitiveType::Int32));
+
+        assert_eq!(pointer.size_with_config(&TargetTypeConfig::x86_32()), 4);
+        assert_eq!(pointer.size_with_config(&TargetTypeConfig::x86_64()), 8);
+        assert_eq!(pointer.alignment_with_config(&TargetTypeConfig::x86_32()), 4);
+    }
+
+    #[test]
+    fn test_x86_32_aligns_doubles_to_four_bytes() {
+        let mixed = struct_of("Mixed", &[
+            ("tag", Type::primitive(PrimitiveType::Char)),
+            ("value", Type::primitive(PrimitiveType::Float64)),
+        ]);
+        let config = TargetTypeConfig::x86_32();
+
+        assert_eq!(config.size_of(&TypeKind::Primitive(PrimitiveType::Float64)), 8);
+        assert_eq!(offsets(&config, &mixed), vec![0, 4]);
+        assert_eq!(config.size_of(&TypeKind::Struct(mixed)), 12);
+    }
+
+    #[test]
+    fn test_struct_of_chars_is_not_padded() {
+        let char_type = Type::primitive(PrimitiveType::Char);
+        let triple = struct_of("Triple", &[("a", char_type.clone()), ("b", char_type.clone()), ("c", char_type)]);
+        let config = TargetTypeConfig::x86_64();
+
+        assert_eq!(config.size_of(&TypeKind::Struct(triple.clone())), 3);
+        assert_eq!(config.alignment_of(&TypeKind::Struct(triple)), 1);
+        assert_eq!(config.align_offset(5, 3), 6);
+        assert_eq!(config.align_offset(5, 0), 5);
+    }
+}
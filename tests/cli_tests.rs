@@ -0,0 +1,268 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Fresh scratch directory for one test
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("minic_cli_{}_{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Run the compiler binary with `args`, returning whether it succeeded
+fn run_compiler(args: &[&str]) -> bool {
+    let output = Command::new(env!("CARGO_BIN_EXE_compiler-minic"))
+        .args(args)
+        .output()
+        .expect("compiler binary runs");
+    if !output.status.success() {
+        eprintln!("{}", String::from_utf8_lossy(&output.stderr));
+    }
+    output.status.success()
+}
+
+#[test]
+fn test_emit_all_writes_every_intermediate_representation() {
+    let dir = scratch_dir("emit_all");
+    let source = dir.join("program.minic");
+    fs::write(&source, "int main() { int x = 1 + 2; println(x); return x; }\n").unwrap();
+    let output_dir = dir.join("out");
+
+    assert!(run_compiler(&[
+        source.to_str().unwrap(),
+        "--target", "linux-x64",
+        "--emit", "all",
+        "--output-dir", output_dir.to_str().unwrap(),
+    ]));
+
+    for file in ["output.tokens", "output.ast.json", "output.ir", "output_optimized.ir", "output.asm"] {
+        let contents = fs::read_to_string(output_dir.join(file))
+            .unwrap_or_else(|e| panic!("{} was not written: {}", file, e));
+        assert!(!contents.is_empty(), "{} is empty", file);
+    }
+    let tokens = fs::read_to_string(output_dir.join("output.tokens")).unwrap();
+    assert!(tokens.lines().next().unwrap().starts_with("Int 'int' at 1:1"), "{}", tokens);
+    let ast = fs::read_to_string(output_dir.join("output.ast.json")).unwrap();
+    assert!(ast.contains("\"node\": \"Function\""), "{}", ast);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+/// Run the compiler binary with `args`, returning what it wrote to stderr
+fn compiler_stderr(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_compiler-minic"))
+        .args(args)
+        .output()
+        .expect("compiler binary runs");
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn test_color_flag_controls_ansi_codes_in_diagnostics() {
+    let dir = scratch_dir("color");
+    let source = dir.join("broken.minic");
+    fs::write(&source, "int main() {\n    int x = 1\n    return x;\n}\n").unwrap();
+    let source = source.to_str().unwrap();
+    let output_dir = dir.join("out");
+    let output_dir = output_dir.to_str().unwrap();
+
+    let colored = compiler_stderr(&[source, "--output-dir", output_dir, "--color=always"]);
+    assert!(colored.contains("\x1b[1;31merror\x1b[0m:"), "{}", colored);
+
+    let plain = compiler_stderr(&[source, "--output-dir", output_dir, "--color=never"]);
+    assert!(plain.contains("error:"), "{}", plain);
+    assert!(!plain.contains('\x1b'), "{}", plain);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_diagnostics_show_the_line_as_written_in_its_file() {
+    let dir = scratch_dir("origin_line");
+    fs::write(dir.join("one.minic"), "// Included lines come first\n#define ONE 1\n\n").unwrap();
+    let source = dir.join("program.minic");
+    fs::write(&source, "#include \"one.minic\"\nint main() {\n    int x = ONE\n    return x;\n}\n").unwrap();
+    let output_dir = dir.join("out");
+
+    let stderr = compiler_stderr(&[source.to_str().unwrap(), "--output-dir", output_dir.to_str().unwrap(), "--color=never"]);
+    assert!(stderr.contains(&format!("--> {}:4:5", source.display())), "{}", stderr);
+    assert!(stderr.contains("   3 |     int x = ONE\n"), "{}", stderr);
+    assert!(stderr.contains("   4 |     return x;\n"), "{}", stderr);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_call_and_return_diagnostics_point_at_the_source() {
+    let dir = scratch_dir("semantic_location");
+    let source = dir.join("program.minic");
+    fs::write(&source, "int add(int a, int b) {\n    return a + b;\n}\nint main() {\n    return add(1);\n}\n").unwrap();
+    let output_dir = dir.join("out");
+
+    let stderr = compiler_stderr(&[source.to_str().unwrap(), "--output-dir", output_dir.to_str().unwrap(), "--color=never"]);
+    assert!(stderr.contains("error: Semantic error: Function 'add' expects 2 arguments but 1 was given"), "{}", stderr);
+    assert!(stderr.contains(&format!("--> {}:5:12", source.display())), "{}", stderr);
+
+    fs::write(&source, "int sign(int a) {\n    if (a < 0) { return -1; }\n}\nint main() {\n    return 0;\n}\n").unwrap();
+    let stderr = compiler_stderr(&[source.to_str().unwrap(), "--output-dir", output_dir.to_str().unwrap(), "--color=never"]);
+    assert!(stderr.contains("warning: Control can reach the end of function 'sign' without returning a value"), "{}", stderr);
+    assert!(stderr.contains(&format!("--> {}:1:5", source.display())), "{}", stderr);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_emit_deps_lists_the_input_and_its_includes() {
+    let dir = scratch_dir("emit_deps");
+    fs::write(dir.join("first.minic"), "#define FIRST 1\n").unwrap();
+    fs::write(dir.join("second.minic"), "#define SECOND 2\n").unwrap();
+    let source = dir.join("program.minic");
+    fs::write(&source, "#include \"first.minic\"\n#include \"second.minic\"\nint main() { return FIRST + SECOND; }\n").unwrap();
+    let output_dir = dir.join("out");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_compiler-minic"))
+        .args([source.to_str().unwrap(), "--target", "linux-x64", "--emit-deps", "--output-dir", output_dir.to_str().unwrap()])
+        .output()
+        .expect("compiler binary runs");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let rule = String::from_utf8(output.stdout).unwrap();
+    let (target, prerequisites) = rule.trim_end().split_once(": ").unwrap();
+    assert_eq!(target, output_dir.join("output.asm").display().to_string());
+    let prerequisites: Vec<&str> = prerequisites.split(' ').collect();
+    assert_eq!(prerequisites, [source.clone(), dir.join("first.minic"), dir.join("second.minic")]
+        .iter().map(|path| path.to_str().unwrap()).collect::<Vec<_>>());
+    assert!(!output_dir.exists(), "--emit-deps should not compile");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_output_path_names_every_artifact() {
+    let dir = scratch_dir("output_path");
+    let source = dir.join("program.minic");
+    fs::write(&source, "int main() { return 7; }\n").unwrap();
+    let custom = dir.join("nested").join("custom");
+
+    assert!(run_compiler(&[
+        source.to_str().unwrap(),
+        "--target", "linux-x64",
+        "-o", custom.to_str().unwrap(),
+        "--output-dir", dir.join("unused").to_str().unwrap(),
+    ]));
+
+    for file in ["custom.asm", "custom.ir", "custom_optimized.ir"] {
+        assert!(dir.join("nested").join(file).is_file(), "{} was not written", file);
+    }
+    assert!(!dir.join("nested").join("output.asm").exists());
+    assert!(!dir.join("unused").exists());
+
+    // An extension on the path names the same outputs
+    assert!(run_compiler(&[source.to_str().unwrap(), "--target", "linux-x64", "--emit", "obj", "-o", dir.join("prog.o").to_str().unwrap()]));
+    assert!(dir.join("prog.o").is_file());
+    assert!(dir.join("prog.ir").is_file());
+
+    // Any other extension is kept rather than followed by `.asm`
+    assert!(run_compiler(&[source.to_str().unwrap(), "--target", "linux-x64", "-o", dir.join("listing.s").to_str().unwrap()]));
+    assert!(dir.join("listing.s").is_file());
+    assert!(!dir.join("listing.s.asm").exists());
+    assert!(dir.join("listing.ir").is_file());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_parse_only_reports_syntax_errors_through_the_exit_status() {
+    let dir = scratch_dir("parse_only");
+    let valid = dir.join("valid.minic");
+    // Parses, though the call would fail semantic checks
+    fs::write(&valid, "int one() { return 1; }\nint main() { return one(2); }\n").unwrap();
+    let invalid = dir.join("invalid.minic");
+    fs::write(&invalid, "int main() {\n    int x = 1\n    return x;\n}\n").unwrap();
+    let output_dir = dir.join("out");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_compiler-minic"))
+        .args([valid.to_str().unwrap(), "--parse-only", "--verbose", "--output-dir", output_dir.to_str().unwrap()])
+        .output()
+        .expect("compiler binary runs");
+    assert_eq!(output.status.code(), Some(0), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Parsed 2 functions"));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_compiler-minic"))
+        .args([invalid.to_str().unwrap(), "--parse-only", "--output-dir", output_dir.to_str().unwrap()])
+        .output()
+        .expect("compiler binary runs");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("error"));
+    assert!(!output_dir.exists(), "--parse-only should write nothing");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_ir_only_no_codegen_checks_the_ir_text_and_writes_no_assembly() {
+    let dir = scratch_dir("ir_only");
+    let source = dir.join("prog.minic");
+    fs::write(&source, "int main() {\n    int t0 = 2;\n    float half = 0.5;\n    println(\"%d %f\", t0, half);\n    return 0;\n}\n").unwrap();
+    let output_dir = dir.join("out");
+
+    assert!(run_compiler(&[source.to_str().unwrap(), "--ir-only-no-codegen", "--output-dir", output_dir.to_str().unwrap()]));
+    assert!(output_dir.join("output.ir").is_file());
+    assert!(!output_dir.join("output.asm").exists());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_missing_main_is_an_error_unless_compiling_a_library() {
+    let dir = scratch_dir("no_main");
+    let source = dir.join("helper.minic");
+    fs::write(&source, "int helper() { return 1; }\n").unwrap();
+    let output_dir = dir.join("out");
+    let args = [source.to_str().unwrap(), "--target", "linux-x64", "--output-dir", output_dir.to_str().unwrap()];
+
+    let stderr = compiler_stderr(&args);
+    assert!(stderr.contains("The program has no 'main' function; pass --no-main"), "{}", stderr);
+    assert!(!output_dir.join("output.asm").exists());
+
+    assert!(run_compiler(&[&args[..], &["--no-main"]].concat()));
+    let asm = fs::read_to_string(output_dir.join("output.asm")).unwrap();
+    assert!(asm.contains("global helper") && !asm.contains("main"), "{}", asm);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+/// Compile `source` to an object, link it with the system C compiler and
+/// run it, returning its exit status. `None` when no C compiler is
+/// installed to link with.
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+fn run_program(name: &str, source: &str) -> Option<i32> {
+    let dir = scratch_dir(name);
+    let source_path = dir.join("program.minic");
+    fs::write(&source_path, source).unwrap();
+    let object = dir.join("program.o");
+    assert!(run_compiler(&[source_path.to_str().unwrap(), "--target", "linux-x64", "--emit", "obj", "-o", object.to_str().unwrap()]));
+
+    let executable = dir.join("program");
+    let linked = Command::new("cc")
+        .args(["-no-pie", object.to_str().unwrap(), "-o", executable.to_str().unwrap()])
+        .output()
+        .ok()?;
+    assert!(linked.status.success(), "{}", String::from_utf8_lossy(&linked.stderr));
+
+    let status = Command::new(&executable).status().expect("program runs");
+    fs::remove_dir_all(&dir).unwrap();
+    status.code()
+}
+
+#[test]
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+fn test_shadowing_local_of_another_type_leaves_the_outer_one_alone() {
+    let source = "int main() {\n    int t = 7;\n    {\n        float t = 2.5;\n        t = t * 2.0;\n    }\n    return t;\n}\n";
+    let Some(status) = run_program("shadowing", source) else {
+        eprintln!("no C compiler to link with; skipping");
+        return;
+    };
+    assert_eq!(status, 7);
+}
@@ -1,4 +1,5 @@
-use compiler_minic::{lexer::Lexer, parser::Parser, ir::generator::IrGenerator, codegen::{Codegen}};
+use compiler_minic::{lexer::Lexer, parser::Parser, ir::generator::IrGenerator, ir::{verify_round_trip, IrOptimizer}, codegen::{Codegen}};
+use compiler_minic::parser::ast::{Expr, Stmt};
 
 #[cfg(test)]
 mod ir_integration_tests {
@@ -17,9 +18,15 @@ mod ir_integration_tests {
         let ir_codegen = Codegen::new();
         let ir_asm = ir_codegen.generate(&ir_program);
 
-        // For now, we only have IR-based compilation, so we return the same assembly for both
-        // The first return value is kept for backward compatibility but is the same as the second
-        (ir_asm.clone(), ir_asm, ir_output, source.to_string())
+        // The second way goes through the IR text: it must read back as
+        // the same program and compile to the same assembly
+        let reparsed = verify_round_trip(&ir_program).unwrap_or_else(|e| panic!("{}\nFull IR:\n{}", e, ir_output));
+        let reparsed_asm = Codegen::new().generate(&reparsed);
+        assert_eq!(reparsed_asm, ir_asm, "Assembly differs after the IR round trip");
+        let optimized = IrOptimizer::new().optimize(ir_program);
+        verify_round_trip(&optimized).unwrap_or_else(|e| panic!("{}\nFull optimized IR:\n{}", e, optimized));
+
+        (reparsed_asm, ir_asm, ir_output, source.to_string())
     }
 
     fn validate_ir_structure(ir_output: &str, expected_elements: &[&str]) {
@@ -107,14 +114,13 @@ int main() {
         let (_direct_asm, ir_asm, ir_output, _) = compile_both_ways(source);
 
         validate_ir_structure(&ir_output, &[
-            "gt i32",
-            "br %t1, label %if_then",
+            "br gt i32 %t0, 10, label %if_then_0, label %if_end_1",
             "if_then_0:",
             "if_end_1:"
         ]);
 
         validate_asm_structure(&_direct_asm, &["cmp"]);
-        validate_asm_structure(&ir_asm, &["setg", "je"]);
+        validate_asm_structure(&ir_asm, &["cmp", "jle"]);
     }
 
     #[test]
@@ -296,7 +302,7 @@ int main() {
     int i = 42;
     float f = 3.14;
     char c = 'X';
-    int sum = i + (int)f;
+    int sum = i + c;
     return sum;
 }
 "#;
@@ -356,15 +362,14 @@ int main() {
         let (_direct_asm, ir_asm, ir_output, _) = compile_both_ways(source);
 
         validate_ir_structure(&ir_output, &[
-            "gt i32",
-            "br %t1, label %if_then",
+            "br gt i32 %t0, 10, label %if_then_0, label %if_end_1",
             "ret i32",
             "if_then_0:",
             "if_end_1:"
         ]);
 
         validate_asm_structure(&_direct_asm, &["cmp", "ret"]);
-        validate_asm_structure(&ir_asm, &["setg", "je", "ret"]);
+        validate_asm_structure(&ir_asm, &["cmp", "jle", "ret"]);
     }
 
     #[test]
@@ -493,6 +498,23 @@ int add(int a, int b) {
         validate_asm_structure(&ir_asm, &["add:", "mov", "add"]);
     }
 
+    #[test]
+    fn test_calls_have_the_return_type_of_their_callee() {
+        let source = r#"
+float pi() {
+    return 3.25;
+}
+int main() {
+    float x = pi();
+    return 0;
+}
+"#;
+
+        let (_direct_asm, _ir_asm, ir_output, _) = compile_both_ways(source);
+
+        validate_ir_structure(&ir_output, &["call f64 pi()"]);
+    }
+
     #[test]
     fn test_logical_and_operator() {
         let source = r#"
@@ -694,4 +716,1584 @@ int test(int x, int y) {
 
         validate_asm_structure(&ir_asm, &["test:", "je", "jmp"]);
     }
+
+    #[test]
+    fn test_void_main_exits_with_zero() {
+        let source = r#"
+void main() {
+}
+"#;
+
+        let (_, ir_asm, ir_output, _) = compile_both_ways(source);
+
+        validate_ir_structure(&ir_output, &["define void @main()"]);
+        validate_asm_structure(&ir_asm, &["main.epilogue:", "ret"]);
+
+        let epilogue = ir_asm.split("main.epilogue:").nth(1).unwrap();
+        assert!(epilogue.lines().any(|line| line.trim_start().starts_with("xor") && line.contains("eax, eax")),
+            "void main must zero eax in its epilogue:\n{}", epilogue);
+    }
+
+    #[test]
+    fn test_synthetic_return_matches_declared_type() {
+        let source = r#"
+float half() {
+    float x = 0.5;
+}
+
+int main() {
+    return 0;
+}
+"#;
+
+        let (_, _, ir_output, _) = compile_both_ways(source);
+
+        validate_ir_structure(&ir_output, &["ret f64 0"]);
+    }
+
+    #[test]
+    fn test_early_return_jumps_to_epilogue() {
+        let source = r#"
+int main() {
+    int x = 1;
+    if (x > 0) {
+        return 1;
+    }
+    return 2;
+}
+"#;
+
+        let (_, ir_asm, _, _) = compile_both_ways(source);
+
+        let epilogue_jumps = ir_asm.lines()
+            .filter(|line| line.trim_start().starts_with("jmp") && line.contains("main.epilogue"))
+            .count();
+        assert_eq!(epilogue_jumps, 2, "{}", ir_asm);
+    }
+
+    #[test]
+    fn test_if_comparison_branches_without_materializing_bool() {
+        let source = r#"
+int main() {
+    int x = 15;
+    if (x > 10) {
+        x = 1;
+    }
+    return x;
+}
+"#;
+
+        let (_, ir_asm, _, _) = compile_both_ways(source);
+
+        let mnemonics: Vec<&str> = ir_asm.lines()
+            .filter_map(|line| line.split_whitespace().next())
+            .collect();
+        assert_eq!(mnemonics.iter().filter(|m| **m == "cmp").count(), 1, "{}", ir_asm);
+        assert!(mnemonics.contains(&"jle"), "{}", ir_asm);
+        assert!(!mnemonics.contains(&"setg"), "{}", ir_asm);
+        assert!(!mnemonics.contains(&"movzx"), "{}", ir_asm);
+    }
+
+    #[test]
+    fn test_byte_print_arg_is_widened_explicitly() {
+        let source = r#"
+int main() {
+    char letter = 'Z';
+    println("%c", letter);
+    return 0;
+}
+"#;
+
+        let (_, ir_asm, _, _) = compile_both_ways(source);
+
+        let lines: Vec<&str> = ir_asm.lines().map(str::trim_start).collect();
+        let arg_load = lines.iter()
+            .position(|line| line.contains("load arg 0"))
+            .unwrap_or_else(|| panic!("no arg load\n{}", ir_asm));
+        assert!(lines[arg_load].contains("al, "), "{}", ir_asm);
+        let widen = &lines[arg_load + 1];
+        assert!(widen.starts_with("movsx") || widen.starts_with("movzx"), "{}", ir_asm);
+        assert!(widen.contains("rdx, al"), "{}", ir_asm);
+    }
+
+    #[test]
+    fn test_function_name_as_value_loads_its_address() {
+        let source = r#"
+int helper() {
+    return 7;
+}
+
+int main() {
+    int (*fp)();
+    fp = helper;
+    fp = &helper;
+    return fp();
+}
+"#;
+
+        let (_, ir_asm, ir_output, _) = compile_both_ways(source);
+
+        validate_ir_structure(&ir_output, &[
+            "%fp = alloca i32 ()*",
+            "store i32 ()* @helper, %fp",
+        ]);
+
+        let lines: Vec<&str> = ir_asm.lines().map(str::trim_start).collect();
+        let address_loads = lines.iter()
+            .filter(|line| line.starts_with("lea") && line.contains("[helper]"))
+            .count();
+        assert_eq!(address_loads, 2, "{}", ir_asm);
+        assert!(!lines.iter().any(|line| line.starts_with("call") && line.contains("helper")), "{}", ir_asm);
+        assert!(lines.iter().any(|line| line.starts_with("call") && line.contains("call through fp")), "{}", ir_asm);
+
+        // Any other variable keeps its declared type and cannot take the address
+        for source in ["int helper() { return 7; } int main() { int fp = &helper; return 0; }",
+                       "int helper() { return 7; } int main() { int fp; fp = helper; return 0; }"] {
+            let ast = Parser::new(Lexer::new(source).tokenize().unwrap()).parse();
+            let error = IrGenerator::new().generate(&ast).unwrap_err();
+            assert!(format!("{:?}", error).contains("'fp' is declared 'int', so it cannot hold the address of function 'helper'"), "{:?}", error);
+        }
+    }
+
+    #[test]
+    fn test_comparison_results_are_bytes_typed_bool() {
+        use compiler_minic::ir::{IrBinaryOp, IrInstruction, IrType};
+
+        let source = r#"
+int main() {
+    int x = 15;
+    int big = x > 10;
+    return big;
+}
+"#;
+
+        let mut lexer = Lexer::new(source);
+        let ast = Parser::new(lexer.tokenize().unwrap()).parse();
+        let ir_program = IrGenerator::new().generate(&ast).unwrap();
+
+        let comparison = ir_program.functions[0].instructions.iter()
+            .find(|instruction| matches!(instruction, IrInstruction::BinaryOp { op: IrBinaryOp::Gt, .. }))
+            .expect("comparison should be lowered to a BinaryOp");
+        assert_eq!(comparison.result_type(), Some(IrType::Bool));
+        validate_ir_structure(&format!("{}", ir_program), &["cast i1"]);
+
+        let ir_asm = Codegen::new().generate(&ir_program);
+        let lines: Vec<&str> = ir_asm.lines().map(str::trim_start).collect();
+        let setg = lines.iter().position(|line| line.starts_with("setg")).expect("setg");
+        assert!(lines[setg + 1].starts_with("mov") && lines[setg + 1].contains("byte ["), "{}", ir_asm);
+        assert!(lines.iter().any(|line| line.starts_with("movzx") && line.contains("eax, al")), "{}", ir_asm);
+    }
+
+    #[test]
+    fn test_indent_style_applies_to_every_indented_line() {
+        use compiler_minic::codegen::IndentStyle;
+        use compiler_minic::codegen::targets::TargetPlatform;
+
+        let source = r#"
+int main() {
+    int x = 42;
+    println("x = %d", x);
+    return x;
+}
+"#;
+        let tokens = Lexer::new(source).tokenize().unwrap();
+        let ast = Parser::new(tokens).parse();
+        let program = IrGenerator::new().generate(&ast).unwrap();
+
+        let spaces = Codegen::new_with_target(TargetPlatform::LinuxX64).generate(&program);
+        let tabs = Codegen::new_with_target(TargetPlatform::LinuxX64)
+            .with_indent(IndentStyle::Tab)
+            .generate(&program);
+
+        let indented = |asm: &str| asm.lines()
+            .filter(|line| line.starts_with(char::is_whitespace))
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        let space_lines = indented(&spaces);
+        let tab_lines = indented(&tabs);
+
+        assert!(!space_lines.is_empty());
+        assert_eq!(space_lines.len(), tab_lines.len());
+        for (space_line, tab_line) in space_lines.iter().zip(&tab_lines) {
+            assert!(space_line.starts_with("    ") && !space_line.starts_with("     "), "{:?}", space_line);
+            assert!(tab_line.starts_with('\t') && !tab_line.starts_with("\t "), "{:?}", tab_line);
+            assert_eq!(tab_line.split_whitespace().next(), space_line.split_whitespace().next());
+        }
+    }
+}
+
+/// One line of assembly with its comment and column padding removed
+fn instruction(line: &str) -> String {
+    line.split(';').next().unwrap().split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The lines of `asm` that hold an instruction, label or directive, each
+/// as `instruction` gives it
+fn instructions_of(asm: &str) -> Vec<String> {
+    asm.lines().map(instruction).filter(|line| !line.is_empty()).collect()
+}
+
+/// Compile `source` for the default target and list its assembly as
+/// `instructions_of` does
+fn asm_instructions(source: &str) -> Vec<String> {
+    let tokens = Lexer::new(source).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse();
+    let ir = IrGenerator::new().generate(&ast).unwrap();
+    instructions_of(&Codegen::new().generate(&ir))
+}
+
+#[test]
+fn test_emit_obj_writes_elf_with_printf_relocation() {
+    use compiler_minic::codegen::encoder::assemble_object;
+    use compiler_minic::codegen::targets::TargetPlatform;
+
+    let source = r#"
+        int main() {
+            println("hi %d", 1);
+            return 0;
+        }
+    "#;
+    let tokens = Lexer::new(source).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse();
+    let ir = IrGenerator::new().generate(&ast).unwrap();
+    let lines = Codegen::new_with_target(TargetPlatform::LinuxX64).generate_lines(&ir).unwrap();
+
+    let object = assemble_object(&lines, TargetPlatform::LinuxX64).expect("assembly should encode");
+    assert_eq!(&object[..4], b"\x7FELF", "object should start with the ELF magic");
+    assert!(object.windows(7).any(|w| w == b"printf\0"), "printf should be an undefined symbol");
+
+    assert!(assemble_object(&lines, TargetPlatform::WindowsX64).is_err(), "only ELF output is implemented");
+}
+
+#[test]
+fn test_include_splices_helper_function() {
+    use compiler_minic::lexer::preprocess_file;
+
+    let dir = std::env::temp_dir().join(format!("minic_include_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("helper.minic"), "int helper() {\n    return 7;\n}\n").unwrap();
+    std::fs::write(dir.join("main.minic"), "#include \"helper.minic\"\nint main() {\n    int x = helper();\n    return x;\n}\n").unwrap();
+
+    let source = preprocess_file(&dir.join("main.minic"), &[]).expect("include should resolve");
+    let tokens = Lexer::new(&source.code).tokenize().unwrap();
+    let mut parser = Parser::new(tokens);
+    let ast = parser.parse();
+    assert!(parser.get_errors().is_empty());
+    let ir = format!("{}", IrGenerator::new().generate(&ast).unwrap());
+
+    assert!(ir.contains("define i32 @helper"), "included function should be compiled:\n{}", ir);
+    assert!(ir.contains("call i32 helper()"), "main should call the included function:\n{}", ir);
+
+    // Lines are reported against the file they came from
+    let origin = source.locate(4).unwrap();
+    assert!(origin.file.ends_with("main.minic"));
+    assert_eq!(origin.line, 2);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_static_function_is_not_exported() {
+    let source = r#"
+        static int helper() {
+            return 1;
+        }
+
+        int main() {
+            return helper();
+        }
+    "#;
+    let tokens = Lexer::new(source).tokenize().unwrap();
+    let mut parser = Parser::new(tokens);
+    let ast = parser.parse();
+    assert!(parser.get_errors().is_empty());
+    let ir = IrGenerator::new().generate(&ast).unwrap();
+    assert!(format!("{}", ir).contains("define internal i32 @helper"));
+
+    let asm = Codegen::new().generate(&ir);
+    let globals: Vec<&str> = asm.lines()
+        .filter_map(|line| line.trim().strip_prefix("global "))
+        .collect();
+    assert!(globals.contains(&"main"), "main should be exported:\n{}", asm);
+    assert!(!globals.contains(&"helper"), "static helper must stay local:\n{}", asm);
+    assert!(asm.contains("helper:"), "helper should still be emitted:\n{}", asm);
+}
+
+#[test]
+fn test_empty_function_gets_synthetic_return() {
+    let tokens = Lexer::new("int f() {}\nvoid g() {}").tokenize().unwrap();
+    let ast = Parser::new(tokens).parse();
+    let ir = IrGenerator::new().generate(&ast).unwrap();
+
+    let f = ir.functions.iter().find(|function| function.name == "f").unwrap();
+    assert_eq!(f.instructions.len(), 1);
+    assert!(matches!(
+        f.instructions.last(),
+        Some(compiler_minic::ir::IrInstruction::Return { value: Some(compiler_minic::ir::IrValue::IntConstant(0)), .. })
+    ), "empty int function should return 0: {:?}", f.instructions);
+
+    let g = ir.functions.iter().find(|function| function.name == "g").unwrap();
+    assert!(matches!(g.instructions.last(), Some(compiler_minic::ir::IrInstruction::Return { value: None, .. })));
+}
+
+#[test]
+fn test_address_of_local_uses_lea_with_its_offset() {
+    let source = r#"
+        int main() {
+            int x = 5;
+            int p = &x;
+            return p;
+        }
+    "#;
+    let tokens = Lexer::new(source).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse();
+    let ir = IrGenerator::new().generate(&ast).unwrap();
+    let asm = Codegen::new().generate(&ir);
+
+    let slot = asm.lines()
+        .find_map(|line| line.split("alloca i32 x at ").nth(1))
+        .and_then(|rest| rest.split_whitespace().next())
+        .expect("x should have a stack slot");
+    let lea = asm.lines()
+        .find(|line| line.trim_start().starts_with("lea"))
+        .expect("address-of should emit lea");
+
+    assert!(lea.contains(&format!("rax, {}", slot)), "expected `lea rax, {}`, got: {}", slot, lea);
+}
+
+#[test]
+fn test_streamed_assembly_matches_string_output() {
+    use compiler_minic::codegen::targets::TargetPlatform;
+
+    let source = r#"
+        int helper() {
+            return 3;
+        }
+
+        int main() {
+            int x = helper();
+            if (x > 2) {
+                println("big %d", x);
+            }
+            return 0;
+        }
+    "#;
+    let tokens = Lexer::new(source).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse();
+    let ir = IrGenerator::new().generate(&ast).unwrap();
+
+    let expected = Codegen::new_with_target(TargetPlatform::LinuxX64).generate(&ir);
+    let mut buffer: Vec<u8> = Vec::new();
+    Codegen::new_with_target(TargetPlatform::LinuxX64).generate_to(&ir, &mut buffer).unwrap();
+
+    assert_eq!(String::from_utf8(buffer).unwrap(), expected);
+}
+
+#[test]
+fn test_type_without_ir_mapping_is_an_error() {
+    use compiler_minic::ir::{IrGeneratorError, IrType};
+    use compiler_minic::types::{PrimitiveType, Type};
+
+    // A 32-bit float local: the IR only has 64-bit floats
+    let tokens = Lexer::new("int main() { float x = 1.0; return 0; }").tokenize().unwrap();
+    let mut ast = Parser::new(tokens).parse();
+    if let compiler_minic::parser::ast::Stmt::Function { body, .. } = &mut ast[0]
+        && let compiler_minic::parser::ast::Stmt::VarDecl { var_type, .. } = &mut body[0] {
+        *var_type = Type::primitive(PrimitiveType::Float32);
+    }
+
+    assert!(IrType::try_from(&Type::primitive(PrimitiveType::Float32)).is_err());
+    match IrGenerator::new().generate(&ast) {
+        Err(IrGeneratorError::UnsupportedType(message)) => assert!(message.contains("float32_t"), "{}", message),
+        other => panic!("expected an unsupported type error, got {:?}", other.map(|ir| ir.to_string())),
+    }
+}
+
+#[test]
+fn test_type_conversions_have_no_int_fallback() {
+    use compiler_minic::ir::IrType;
+    use compiler_minic::lexer::TokenType;
+    use compiler_minic::types::{PrimitiveType, Type};
+
+    assert_eq!(Type::try_from(TokenType::Int), Ok(Type::primitive(PrimitiveType::Int32)));
+    assert!(Type::try_from(TokenType::Plus).is_err());
+    assert!(Type::try_from(TokenType::Identifier("size_t".to_string())).is_err());
+    assert!(IrType::try_from(TokenType::Return).is_err());
+
+    // Types without a keyword of their own have no token, rather than `int`
+    assert_eq!(Type::primitive(PrimitiveType::Char).to_token_type(), Some(TokenType::CharType));
+    assert_eq!(Type::primitive(PrimitiveType::Int64).to_token_type(), None);
+    assert_eq!(Type::pointer(Type::primitive(PrimitiveType::Int32)).to_token_type(), None);
+}
+
+#[test]
+fn test_print_omits_trailing_newline_byte() {
+    fn data_literal(source: &str) -> String {
+        let tokens = Lexer::new(source).tokenize().unwrap();
+        let ast = Parser::new(tokens).parse();
+        let ir = IrGenerator::new().generate(&ast).unwrap();
+        let asm = Codegen::new().generate(&ir);
+
+        // The literal the print call actually loads
+        let label = asm.lines()
+            .find(|line| line.trim_start().starts_with("lea") && line.contains("load format string"))
+            .and_then(|line| line.split(',').nth(1))
+            .and_then(|operand| operand.split_whitespace().next())
+            .expect("print should load its format string")
+            .to_string();
+        asm.lines()
+            .map(str::trim)
+            .find(|line| line.starts_with(&format!("{}: db", label)))
+            .expect("format string literal")
+            .to_string()
+    }
+
+    assert!(data_literal("int main() { print(\"x\"); return 0; }").ends_with("db \"x\", 0"));
+    assert!(data_literal("int main() { printf(\"x\"); return 0; }").ends_with("db \"x\", 0"));
+    assert!(data_literal("int main() { println(\"x\"); return 0; }").ends_with("db \"x\", 10, 0"));
+}
+
+#[test]
+fn test_float_negation_uses_sse_subtraction() {
+    let instructions = asm_instructions("int main() { float pi = 3.14; float n = -pi; return 0; }");
+    assert!(instructions.iter().any(|line| line == "subsd xmm0, xmm1"), "float negation should subtract from 0.0: {:#?}", instructions);
+    assert!(!instructions.iter().any(|line| line.starts_with("neg ")), "float negation must not use integer neg: {:#?}", instructions);
+
+    // A negated literal is loaded from its bit pattern rather than memory
+    let instructions = asm_instructions("int main() { float n = -1.5; return 0; }");
+    assert!(instructions.iter().any(|line| line.starts_with("subsd ")), "{:#?}", instructions);
+}
+
+#[test]
+fn test_bool_variable_drives_if() {
+    let source = "int main() { bool b = true; int r = 0; if (b) { r = 1; } return r; }";
+    let tokens = Lexer::new(source).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse();
+    let ir = IrGenerator::new().generate(&ast).unwrap();
+    let ir_text = ir.to_string();
+
+    // The literal is stored as 1 into a byte-sized slot, and branched on directly
+    assert!(ir_text.contains("%b = alloca i1"), "{}", ir_text);
+    assert!(ir_text.contains("store i1 1, %b"), "{}", ir_text);
+    assert!(ir_text.lines().any(|line| line.trim_start().starts_with("br %t")), "{}", ir_text);
+
+    let asm = Codegen::new().generate(&ir);
+    assert!(asm.contains("byte [rbp"), "bool local should live in a byte:\n{}", asm);
+}
+
+#[test]
+fn test_sibling_block_locals_share_a_stack_slot() {
+    let source = "int main() { int keep = 0; { int a = 1; keep = a; } { int b = 2; keep = b; } return keep; }";
+    let tokens = Lexer::new(source).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse();
+    let ir = IrGenerator::new().generate(&ast).unwrap();
+
+    let main = &ir.functions[0];
+    assert_eq!(main.local_offsets["a"], main.local_offsets["b"]);
+    assert_ne!(main.local_offsets["a"], main.local_offsets["keep"]);
+
+    let asm = Codegen::new().generate(&ir);
+    let offset_of = |name: &str| asm.lines()
+        .find(|line| line.contains(&format!("Local '{}' at offset", name)))
+        .and_then(|line| line.rsplit(' ').next())
+        .unwrap_or_else(|| panic!("no slot for '{}':\n{}", name, asm))
+        .to_string();
+    assert_eq!(offset_of("a"), offset_of("b"));
+    assert_ne!(offset_of("a"), offset_of("keep"));
+}
+
+#[test]
+fn test_address_comparisons_use_unsigned_conditions() {
+    let mnemonic = |lines: &[String], name: &str| lines.iter().any(|line| line.split(' ').next() == Some(name));
+
+    // Materialized comparison of two addresses
+    let lines = asm_instructions("int main() { int a = 1; int b = 2; bool below = &a < &b; bool above = &a > &b; return 0; }");
+    assert!(mnemonic(&lines, "setb") && mnemonic(&lines, "seta"), "{:#?}", lines);
+    assert!(!mnemonic(&lines, "setl") && !mnemonic(&lines, "setg"), "{:#?}", lines);
+    assert!(lines.iter().any(|line| line.starts_with("cmp rax,")), "addresses compare at 64 bits: {:#?}", lines);
+
+    // Fused compare-and-branch jumps on the inverted unsigned condition
+    let lines = asm_instructions("int main() { int a = 1; int b = 2; if (&a < &b) { return 1; } return 0; }");
+    assert!(mnemonic(&lines, "jae"), "{:#?}", lines);
+    assert!(!mnemonic(&lines, "jge"), "{:#?}", lines);
+
+    // Plain ints keep the signed conditions
+    let lines = asm_instructions("int main() { int a = 1; bool less = a < 2; return 0; }");
+    assert!(mnemonic(&lines, "setl") && !mnemonic(&lines, "setb"), "{:#?}", lines);
+}
+
+#[test]
+fn test_logical_loop_condition_branches_without_boolean_temp() {
+    let source = "int main() { int a = 3; int b = 5; while (a && b) { a = a - 1; } return a; }";
+    let tokens = Lexer::new(source).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse();
+    let ir = IrGenerator::new().generate(&ast).unwrap();
+    let ir_text = ir.to_string();
+
+    // Each operand branches on its own: `a` to the right operand or the
+    // loop end, `b` to the body or the loop end
+    let branches: Vec<&str> = ir_text.lines().map(str::trim).filter(|line| line.starts_with("br ")).collect();
+    assert_eq!(branches.len(), 2, "{}", ir_text);
+    assert!(branches[0].contains("label %and_eval_right_") && branches[0].ends_with("label %loop_end_1"), "{}", ir_text);
+    assert!(branches[1].contains("label %loop_body_") && branches[1].ends_with("label %loop_end_1"), "{}", ir_text);
+    assert!(!ir_text.contains("and_false"), "no boolean should be materialized:\n{}", ir_text);
+
+    let asm = Codegen::new().generate(&ir);
+    assert!(!asm.contains("movzx"), "no boolean should be widened:\n{}", asm);
+}
+
+#[test]
+fn test_wide_immediate_is_loaded_into_register_before_use() {
+    let instructions = asm_instructions("int main() { int x = 7; int y = x + 5000000000; return y; }");
+    let load = instructions.iter().position(|line| line == "mov r11, 5000000000")
+        .unwrap_or_else(|| panic!("constant should be materialized: {:#?}", instructions));
+    assert_eq!(instructions[load + 1], "add eax, r11d", "{:#?}", instructions);
+    assert!(!instructions.iter().any(|line| line.starts_with("add") && line.ends_with("5000000000")), "{:#?}", instructions);
+}
+
+#[test]
+fn test_return_value_is_converted_to_declared_type() {
+    use compiler_minic::ir::{IrInstruction, IrType, IrValue};
+
+    let source = "float f() { return 5; } int main() { return 0; }";
+    let tokens = Lexer::new(source).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse();
+    let ir = IrGenerator::new().generate(&ast).unwrap();
+
+    let f = ir.functions.iter().find(|function| function.name == "f").unwrap();
+    match &f.instructions[..] {
+        [IrInstruction::Cast { dest, src: IrValue::IntConstant(5), src_type: IrType::Int, dest_type: IrType::Float },
+         IrInstruction::Return { value: Some(value), var_type: IrType::Float }] => assert_eq!(dest, value),
+        other => panic!("expected an int to float cast before the return, got {:?}", other),
+    }
+
+    let instructions = instructions_of(&Codegen::new().generate(&ir));
+    assert!(instructions.iter().any(|line| line == "cvtsi2sd xmm0, eax"), "{:#?}", instructions);
+}
+
+#[test]
+fn test_large_frames_are_probed_on_windows_only() {
+    use compiler_minic::codegen::targets::TargetPlatform;
+
+    // 600 doubles need more than a 4 KiB page of stack
+    let locals: String = (0..600).map(|i| format!("float v{} = 1.0; ", i)).collect();
+    let source = format!("int main() {{ {}return 0; }}", locals);
+    let tokens = Lexer::new(&source).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse();
+    let ir = IrGenerator::new().generate(&ast).unwrap();
+
+    let instructions = |platform: TargetPlatform| instructions_of(&Codegen::new_with_target(platform).generate(&ir));
+
+    let windows = instructions(TargetPlatform::WindowsX64);
+    let probe = windows.iter().position(|line| line == "call __chkstk").expect("windows frame should be probed");
+    assert!(windows[probe - 1].starts_with("mov rax, "), "{:?}", &windows[probe - 1..=probe + 1]);
+    assert!(windows[probe + 1].starts_with("sub rsp, "), "{:?}", &windows[probe - 1..=probe + 1]);
+
+    let linux = instructions(TargetPlatform::LinuxX64);
+    assert!(!linux.iter().any(|line| line.contains("__chkstk")));
+
+    // Small frames need no probe
+    let tokens = Lexer::new("int main() { int x = 1; return x; }").tokenize().unwrap();
+    let ir = IrGenerator::new().generate(&Parser::new(tokens).parse()).unwrap();
+    let asm = Codegen::new_with_target(TargetPlatform::WindowsX64).generate(&ir);
+    assert!(!asm.contains("call __chkstk"), "{}", asm);
+}
+
+#[test]
+fn test_float_precision_is_the_same_for_every_print_form_and_target() {
+    use compiler_minic::codegen::DEFAULT_FLOAT_PRECISION;
+    use compiler_minic::codegen::targets::TargetPlatform;
+
+    let source = r#"int main() { float x = 1.5; println(x); println("%f and 100%%f", x); printf("%s", "%f"); return 0; }"#;
+    let tokens = Lexer::new(source).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse();
+    let ir = IrGenerator::new().generate(&ast).unwrap();
+
+    let default_conversion = format!("%.{}f", DEFAULT_FLOAT_PRECISION);
+    for platform in [TargetPlatform::WindowsX64, TargetPlatform::LinuxX64, TargetPlatform::MacOSX64] {
+        let asm = Codegen::new_with_target(platform).generate(&ir);
+        let literals: Vec<&str> = asm.lines().filter(|line| line.contains(": db ")).collect();
+        // Both the bare println and the explicit format string get the default
+        assert!(literals.iter().any(|line| line.contains(&format!("\"{}\"", default_conversion))), "{:?}", literals);
+        assert!(literals.iter().any(|line| line.contains(&format!("\"{} and 100%%f\"", default_conversion))), "{:?}", literals);
+        assert!(!asm.contains("%.2f"), "{}", asm);
+        // A string printed as data is not a format, even when it reads as one
+        assert!(literals.iter().any(|line| line.contains("\"%f\"")), "{:?}", literals);
+    }
+
+    let asm = Codegen::new_with_target(TargetPlatform::LinuxX64).with_float_precision(3).generate(&ir);
+    assert!(asm.contains("\"%.3f and 100%%f\""), "{}", asm);
+}
+
+#[test]
+fn test_every_codegen_constructor_produces_the_same_assembly() {
+    use compiler_minic::codegen::targets::{create_target, TargetPlatform, TargetRegistry};
+
+    let source = "int main() { int x = 2; float y = 1.5; if (x > 1) { println(\"%d\", x); } println(y); return x; }";
+    let tokens = Lexer::new(source).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse();
+    let ir = IrGenerator::new().generate(&ast).unwrap();
+
+    for platform in [TargetPlatform::WindowsX64, TargetPlatform::LinuxX64, TargetPlatform::MacOSX64] {
+        let expected = Codegen::new_with_target(platform).generate(&ir);
+        assert_eq!(Codegen::with_target(create_target(platform).unwrap()).generate(&ir), expected);
+        let registered = TargetRegistry::default().create(platform).expect("built-in target is registered");
+        assert_eq!(Codegen::with_target(registered).generate(&ir), expected);
+    }
+    assert_eq!(Codegen::new().generate(&ir), Codegen::new_with_target(TargetPlatform::WindowsX64).generate(&ir));
+}
+
+#[test]
+fn test_declarations_are_commented_in_the_ir() {
+    use compiler_minic::ir::IrOptimizer;
+
+    let source = "int main() { int x = 40 + 2; while (x > 50) { x = x - 1; } return x; }";
+    let tokens = Lexer::new(source).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse();
+    let ir = IrGenerator::new().with_source_comments(true).generate(&ast).unwrap();
+
+    let text = format!("{}", ir);
+    let lines: Vec<&str> = text.lines().map(str::trim).collect();
+    let declaration = lines.iter().position(|line| *line == "; int x = 40 + 2;").expect(&text);
+    assert!(lines[declaration + 1].starts_with("%x = alloca"), "{}", text);
+    assert!(lines.contains(&"; while (x > 50)"), "{}", text);
+    assert!(lines.contains(&"; return x;"), "{}", text);
+
+    // Folding rewrites the instructions but keeps the comments in place
+    let optimized = format!("{}", IrOptimizer::new().optimize(ir));
+    assert!(optimized.contains("; int x = 40 + 2;"), "{}", optimized);
+    assert!(optimized.contains("; x = x - 1;"), "{}", optimized);
+
+    // Comments are opt-in, so the default output is unchanged
+    let plain = format!("{}", IrGenerator::new().generate(&ast).unwrap());
+    assert!(!plain.contains("; int x"), "{}", plain);
+}
+
+#[test]
+fn test_float_arguments_are_passed_in_xmm_registers() {
+    use compiler_minic::codegen::targets::TargetPlatform;
+
+    let source = "float twice(float x, int n) { return x * n; } int main() { float y = twice(1.25, 9); return 0; }";
+    let tokens = Lexer::new(source).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse();
+    let ir = IrGenerator::new().generate(&ast).unwrap();
+
+    // System V numbers float and integer arguments separately
+    let linux = instructions_of(&Codegen::new_with_target(TargetPlatform::LinuxX64).generate(&ir));
+    assert!(linux.iter().any(|line| line == "movq xmm0, rax"), "{:#?}", linux);
+    assert!(linux.iter().any(|line| line == "mov rdi, 9"), "{:#?}", linux);
+
+    // Microsoft x64 gives the second argument the second register of its kind
+    let windows = instructions_of(&Codegen::new_with_target(TargetPlatform::WindowsX64).generate(&ir));
+    assert!(windows.iter().any(|line| line == "movq xmm0, rax"), "{:#?}", windows);
+    assert!(windows.iter().any(|line| line == "mov rdx, 9"), "{:#?}", windows);
+}
+
+#[test]
+fn test_undeclared_local_fails_code_generation() {
+    use compiler_minic::codegen::targets::TargetPlatform;
+    use compiler_minic::ir::parse_ir;
+
+    let ir = parse_ir("define i32 @main() {\n  %t0 = load i32, %missing\n  ret i32 %t0\n}\n").unwrap();
+    let error = Codegen::new_with_target(TargetPlatform::LinuxX64).generate_lines(&ir).unwrap_err();
+    assert!(error.to_string().contains("%missing has no stack slot"), "{}", error);
+}
+
+#[test]
+fn test_argument_of_unknown_type_fails_code_generation() {
+    use compiler_minic::codegen::targets::TargetPlatform;
+    use compiler_minic::ir::parse_ir;
+
+    // Nothing defines %t3, so nothing tells how wide it is
+    let ir = parse_ir("@str_0 = constant str \"%d\"\n\ndefine i32 @main() {\n  print \"str_0\", [%t3], newline\n  ret i32 0\n}\n").unwrap();
+    let error = Codegen::new_with_target(TargetPlatform::LinuxX64).generate_lines(&ir).unwrap_err();
+    assert!(error.to_string().contains("%t3 has no known type"), "{}", error);
+}
+
+#[test]
+fn test_float_parameters_are_spilled_from_xmm_registers() {
+    use compiler_minic::codegen::targets::TargetPlatform;
+
+    let source = "float scale(int n, float x) { return x * n; } int main() { return 0; }";
+    let tokens = Lexer::new(source).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse();
+    let ir = IrGenerator::new().generate(&ast).unwrap();
+    let asm = Codegen::new_with_target(TargetPlatform::LinuxX64).generate(&ir);
+    let spills: Vec<String> = asm.lines()
+        .filter(|line| line.contains("spill parameter"))
+        .map(instruction)
+        .collect();
+    assert_eq!(spills.len(), 2, "{}", asm);
+    assert!(spills[0].ends_with(", edi"), "{}", asm);
+    assert!(spills[1].starts_with("movsd ") && spills[1].ends_with(", xmm0"), "{}", asm);
+}
+
+#[test]
+fn test_call_to_an_undefined_function_is_an_error() {
+    use compiler_minic::ir::IrGeneratorError;
+
+    let tokens = Lexer::new("int main() { return missing(1); }").tokenize().unwrap();
+    let ast = Parser::new(tokens).parse();
+    let error = IrGenerator::new().generate(&ast).unwrap_err();
+    assert!(matches!(&error, IrGeneratorError::UndefinedFunction(name) if name == "missing"), "{:?}", error);
+}
+
+#[test]
+fn test_indexing_a_non_array_is_an_error() {
+    use compiler_minic::ir::IrGeneratorError;
+
+    let tokens = Lexer::new("int main() { int x = 1; return x[0]; }").tokenize().unwrap();
+    let ast = Parser::new(tokens).parse();
+    let error = IrGenerator::new().generate(&ast).unwrap_err();
+    assert!(matches!(&error, IrGeneratorError::UnsupportedConstruct(message) if message.contains("'x'")), "{:?}", error);
+}
+
+#[test]
+fn test_address_of_a_temporary_is_an_error() {
+    use compiler_minic::ir::IrGeneratorError;
+
+    let tokens = Lexer::new("int main() { int a = 1; int b = 2; int p = &(a + b); return 0; }").tokenize().unwrap();
+    let ast = Parser::new(tokens).parse();
+    let error = IrGenerator::new().generate(&ast).unwrap_err();
+    assert!(matches!(&error, IrGeneratorError::UnsupportedConstruct(message) if message.contains("address")), "{:?}", error);
+}
+
+#[test]
+fn test_unsupported_types_in_casts_and_prints_are_errors() {
+    use compiler_minic::ir::IrGeneratorError;
+    use compiler_minic::parser::ast::{Expr, Stmt};
+    use compiler_minic::types::{PrimitiveType, Type};
+
+    // Printing needs a conversion for the argument's type
+    let tokens = Lexer::new("int main() { int a[3]; println(a); return 0; }").tokenize().unwrap();
+    let ast = Parser::new(tokens).parse();
+    let error = IrGenerator::new().generate(&ast).unwrap_err();
+    assert!(matches!(&error, IrGeneratorError::UnsupportedConstruct(message) if message.contains("int *")), "{:?}", error);
+
+    // The parser produces no casts, so build one to a type the IR lacks
+    let tokens = Lexer::new("int main() { return 0; }").tokenize().unwrap();
+    let mut ast = Parser::new(tokens).parse();
+    let Stmt::Function { body, .. } = &mut ast[0] else { panic!("expected main") };
+    body.insert(0, Stmt::ExprStmt(Expr::TypeCast {
+        expr: Box::new(Expr::Integer(1)),
+        target_type: Type::primitive(PrimitiveType::Int64),
+    }));
+    let error = IrGenerator::new().generate(&ast).unwrap_err();
+    assert!(matches!(error, IrGeneratorError::UnsupportedType(_)), "{:?}", error);
+}
+
+#[test]
+fn test_int_overflow_is_left_for_run_time() {
+    use compiler_minic::ir::{IrBinaryOp, IrInstruction, IrOptimizer};
+
+    // The add wraps to INT_MIN at run time, so the branch is not taken;
+    // folding it in 64 bits would take it
+    let source = "int main() { if (2147483647 + 1 > 0) { return 1; } return 0; }";
+    let tokens = Lexer::new(source).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse();
+    let optimized = IrOptimizer::new().optimize(IrGenerator::new().generate(&ast).unwrap());
+
+    let main = &optimized.functions[0];
+    assert!(main.instructions.iter().any(|instruction| matches!(instruction,
+        IrInstruction::BinaryOp { op: IrBinaryOp::Add, .. })), "{}", optimized);
+    assert!(optimized.to_string().contains("br gt i32"), "{}", optimized);
+}
+
+#[test]
+fn test_register_loop_counter_is_kept_in_a_register() {
+    use compiler_minic::codegen::targets::TargetPlatform;
+
+    let source = "int main() { register int i; int total = 0; for (i = 0; i < 10; i = i + 1) { total = total + i; } return total; }";
+    let tokens = Lexer::new(source).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse();
+    let ir = IrGenerator::new().generate(&ast).unwrap();
+    let asm = Codegen::new_with_target(TargetPlatform::LinuxX64).generate(&ir);
+
+    assert!(asm.contains("alloca i32 i in rbx"), "{}", asm);
+    assert!(asm.contains("mov      ebx, 0"), "{}", asm);
+    assert!(asm.contains("mov      ebx, [rbp"), "{}", asm);
+    assert!(!asm.lines().any(|line| line.trim_start().starts_with("mov      [rbp") && line.ends_with("to %i")), "{}", asm);
+    // The caller's rbx is saved after the frame is set up and restored before returning
+    let save = asm.find("save callee-saved rbx").expect(&asm);
+    let restore = asm.find("restore callee-saved rbx").expect(&asm);
+    assert!(asm.find("sub      rsp").unwrap() < save && save < asm.find("alloca i32 i").unwrap(), "{}", asm);
+    assert!(asm.find("main.epilogue:").unwrap() < restore && restore < asm.rfind("ret").unwrap(), "{}", asm);
+}
+
+#[test]
+fn test_register_hints_fall_back_to_the_stack() {
+    use compiler_minic::codegen::targets::TargetPlatform;
+
+    let source = "int main() {
+        register int a = 1; register int b = 2; register int c = 3;
+        register int d = 4; register int e = 5; register int f = 6;
+        register float g = 1.5; register int h = 7; int *p = &h;
+        return a + b + c + d + e + f + *p;
+    }";
+    let tokens = Lexer::new(source).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse();
+    let ir = IrGenerator::new().generate(&ast).unwrap();
+    let asm = Codegen::new_with_target(TargetPlatform::LinuxX64).generate(&ir);
+
+    for (name, register) in [("a", "rbx"), ("b", "r12"), ("c", "r13"), ("d", "r14"), ("e", "r15")] {
+        assert!(asm.contains(&format!("alloca i32 {} in {}", name, register)), "{}", asm);
+    }
+    // Out of registers, a float, and a variable whose address is taken
+    assert!(asm.contains("alloca i32 f at [rbp"), "{}", asm);
+    assert!(asm.contains("alloca f64 g at [rbp"), "{}", asm);
+    assert!(asm.contains("alloca i32 h at [rbp"), "{}", asm);
+}
+
+#[test]
+fn test_caller_saved_register_variable_is_saved_around_calls() {
+    use compiler_minic::codegen::core::Register;
+    use compiler_minic::codegen::targets::TargetPlatform;
+
+    let source = "int f() { return 5; } int main() { register int a = 7; register int b = 1; int x = f(); return a + b + x; }";
+    let tokens = Lexer::new(source).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse();
+    let ir = IrGenerator::new().generate(&ast).unwrap();
+    let asm = Codegen::new_with_target(TargetPlatform::LinuxX64)
+        .with_register_order(vec![Register::R10, Register::Rax, Register::Rbx])
+        .generate(&ir);
+
+    // rax is scratch, so b skips it for rbx
+    assert!(asm.contains("alloca i32 a in r10"), "{}", asm);
+    assert!(asm.contains("alloca i32 b in rbx"), "{}", asm);
+    // Only the callee-saved register goes through the prologue and epilogue
+    assert!(asm.contains("save callee-saved rbx") && asm.contains("restore callee-saved rbx"), "{}", asm);
+    assert!(!asm.contains("callee-saved r10"), "{}", asm);
+    let save = asm.find("save caller-saved r10 across the call").expect(&asm);
+    let call = asm.find("; call f\n").expect(&asm);
+    let restore = asm.find("restore caller-saved r10").expect(&asm);
+    assert!(save < call && call < restore && restore < asm.find("store return value").unwrap(), "{}", asm);
+}
+
+#[test]
+fn test_structurally_identical_programs_are_equal() {
+    use compiler_minic::ir::{IrBinaryOp, IrFunction, IrInstruction, IrProgram, IrType, IrValue};
+    use std::collections::HashMap;
+
+    let source = "int main() { int x = 2; float f = 0.5; return x + 1; }";
+    let tokens = Lexer::new(source).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse();
+    let generated = IrGenerator::new().generate(&ast).unwrap();
+
+    let x = IrValue::Local("x".to_string());
+    let f = IrValue::Local("f".to_string());
+    let built = IrProgram {
+        functions: vec![IrFunction {
+            name: "main".to_string(),
+            return_type: IrType::Int,
+            parameters: vec![],
+            instructions: vec![
+                IrInstruction::Alloca { var_type: IrType::Int, name: "x".to_string() },
+                IrInstruction::Store { value: IrValue::IntConstant(2), dest: x.clone(), var_type: IrType::Int },
+                IrInstruction::Alloca { var_type: IrType::Float, name: "f".to_string() },
+                IrInstruction::Store { value: IrValue::FloatConstant(0.5), dest: f.clone(), var_type: IrType::Float },
+                IrInstruction::Load { dest: IrValue::Temp(0), src: x, var_type: IrType::Int },
+                IrInstruction::BinaryOp {
+                    dest: IrValue::Temp(1),
+                    op: IrBinaryOp::Add,
+                    left: IrValue::Temp(0),
+                    right: IrValue::IntConstant(1),
+                    var_type: IrType::Int,
+                },
+                IrInstruction::Return { value: Some(IrValue::Temp(1)), var_type: IrType::Int },
+            ],
+            local_vars: vec![("x".to_string(), IrType::Int), ("f".to_string(), IrType::Float)],
+            local_offsets: HashMap::from([("x".to_string(), -4), ("f".to_string(), -16)]),
+            is_static: false,
+            register_hints: vec![],
+        }],
+        global_strings: vec![],
+        global_vars: vec![],
+    };
+    assert_eq!(generated.first_difference(&built), None);
+    assert_eq!(generated, built);
+
+    // A difference is reported at the instruction where it occurs
+    let mut changed = built.clone();
+    changed.functions[0].instructions[3] = IrInstruction::Store {
+        value: IrValue::FloatConstant(-0.5),
+        dest: f,
+        var_type: IrType::Float,
+    };
+    assert_ne!(generated, changed);
+    assert_eq!(
+        generated.first_difference(&changed).as_deref(),
+        Some("function 'main', instruction 3: `store f64 0.5, %f` vs `store f64 -0.5, %f`")
+    );
+
+    // Float constants compare by their bits
+    assert_eq!(IrValue::FloatConstant(f64::NAN), IrValue::FloatConstant(f64::NAN));
+    assert_ne!(IrValue::FloatConstant(0.0), IrValue::FloatConstant(-0.0));
+}
+
+#[test]
+fn test_avx_feature_selects_vex_float_instructions() {
+    use compiler_minic::codegen::targets::{TargetFeatures, TargetPlatform};
+
+    let source = "int main() { float a = 1.5; float b = a + 2.0; int n = 3; float c = n; return b * c; }";
+    let tokens = Lexer::new(source).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse();
+    let ir = IrGenerator::new().generate(&ast).unwrap();
+    let instructions = |asm: &str| asm.lines()
+        .filter_map(|line| line.split_whitespace().next())
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+
+    let sse = Codegen::new_with_target(TargetPlatform::LinuxX64).generate(&ir);
+    let sse_instructions = instructions(&sse);
+    assert!(sse_instructions.iter().any(|instruction| instruction == "addsd"), "{}", sse);
+    assert!(!sse_instructions.iter().any(|instruction| instruction.starts_with('v')), "{}", sse);
+
+    let features: TargetFeatures = "+avx".parse().unwrap();
+    let avx = Codegen::new_with_target(TargetPlatform::LinuxX64).with_target_features(features).generate(&ir);
+    let avx_instructions = instructions(&avx);
+    for sse_only in ["addsd", "mulsd", "movsd", "cvtsi2sd", "cvttsd2si"] {
+        assert!(!avx_instructions.iter().any(|instruction| instruction == sse_only), "{}\n{}", sse_only, avx);
+    }
+    assert!(avx.contains("vaddsd   xmm0, xmm0, "), "{}", avx);
+
+    // The integrated assembler encodes the VEX forms as well
+    let lines = Codegen::new_with_target(TargetPlatform::LinuxX64).with_target_features(features).generate_lines(&ir).unwrap();
+    let object = compiler_minic::codegen::encoder::assemble_object(&lines, TargetPlatform::LinuxX64).unwrap();
+    assert!(object.windows(2).any(|bytes| bytes == [0xC5, 0xFB]), "no VEX prefix in the object");
+
+    assert!("-sse2".parse::<TargetFeatures>().is_err());
+    assert!("+mmx".parse::<TargetFeatures>().is_err());
+    assert_eq!("+avx,-avx".parse::<TargetFeatures>(), Ok(TargetFeatures::default()));
+}
+
+#[test]
+fn test_for_without_condition_loops_until_break() {
+    use compiler_minic::ir::{IrInstruction, IrOptimizer};
+
+    let source = "int main() { int x = 3; for (;;) { if (x) { x = x - 1; } if (x == 0) { break; } } return x; }";
+    let tokens = Lexer::new(source).tokenize().unwrap();
+    let mut parser = Parser::new(tokens);
+    let ast = parser.parse();
+    assert!(parser.get_errors().is_empty(), "{:?}", parser.get_errors());
+    let ir = IrGenerator::new().generate(&ast).unwrap();
+
+    for program in [ir.clone(), IrOptimizer::new().optimize(ir)] {
+        let main = &program.functions[0];
+        let label = |prefix: &str| main.instructions.iter().find_map(|instruction| match instruction {
+            IrInstruction::Label { name } if name.starts_with(prefix) => Some(name.clone()),
+            _ => None,
+        }).expect(prefix);
+        let (start, end) = (label("for_start"), label("for_end"));
+
+        // The loop jumps back to its start after the body...
+        let back_edges = main.instructions.iter()
+            .filter(|instruction| matches!(instruction, IrInstruction::Jump { label } if *label == start))
+            .count();
+        assert_eq!(back_edges, 1, "{}", main);
+        // ...and no conditional branch leaves it: only the break jumps to its end
+        assert!(!main.instructions.iter().any(|instruction| matches!(instruction,
+            IrInstruction::Branch { true_label, false_label, .. } |
+            IrInstruction::CompareBranch { true_label, false_label, .. } if *true_label == end || *false_label == end)), "{}", main);
+        let exits = main.instructions.iter()
+            .filter(|instruction| matches!(instruction, IrInstruction::Jump { label } if *label == end))
+            .count();
+        assert_eq!(exits, 1, "{}", main);
+    }
+}
+
+#[test]
+fn test_continue_in_while_retests_the_condition() {
+    use compiler_minic::ir::IrInstruction;
+
+    let source = "int main() { int i = 0; int odd = 0; while (i < 10) { i = i + 1; if (i == 2 * (i / 2)) { continue; } odd = odd + 1; } return odd; }";
+    let tokens = Lexer::new(source).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse();
+    let main = IrGenerator::new().generate(&ast).unwrap().functions.remove(0);
+    let instructions = &main.instructions;
+
+    let label_at = |name: &str| instructions.iter()
+        .position(|instruction| matches!(instruction, IrInstruction::Label { name: label } if label == name))
+        .unwrap_or_else(|| panic!("no label {}\n{}", name, main));
+    let condition = instructions.iter().find_map(|instruction| match instruction {
+        IrInstruction::Branch { true_label, false_label, .. } |
+        IrInstruction::CompareBranch { true_label, false_label, .. } if true_label.starts_with("loop_body") => Some((true_label.clone(), false_label.clone())),
+        _ => None,
+    }).unwrap_or_else(|| panic!("no loop condition\n{}", main));
+    let (body, end) = condition;
+    let start = match &instructions[label_at(&end) - 1] {
+        IrInstruction::Jump { label } => label.clone(),
+        other => panic!("loop should end with its back edge, found {}\n{}", other, main),
+    };
+
+    // The header computes the condition before entering the body
+    assert!(label_at(&start) < label_at(&body) && label_at(&body) < label_at(&end), "{}", main);
+    assert!(instructions[label_at(&start)..label_at(&body)].iter().any(|instruction| matches!(instruction,
+        IrInstruction::Branch { .. } | IrInstruction::CompareBranch { .. })), "{}", main);
+
+    // Both the continue and the back edge return to the header, so every
+    // iteration re-tests `i < 10`; nothing jumps straight into the body
+    let jumps: Vec<&str> = instructions.iter().filter_map(|instruction| match instruction {
+        IrInstruction::Jump { label } if *label == start || *label == body => Some(label.as_str()),
+        _ => None,
+    }).collect();
+    assert_eq!(jumps, vec![start.as_str(), start.as_str()], "{}", main);
+}
+
+#[test]
+fn test_binary_operands_are_evaluated_left_to_right() {
+    use compiler_minic::codegen::targets::TargetPlatform;
+    use compiler_minic::ir::{IrInstruction, IrOptimizer};
+
+    let source = "int f() { println(\"f\"); return 1; } int g() { println(\"g\"); return 2; } int main() { return f() - g(); }";
+    let tokens = Lexer::new(source).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse();
+    let ir = IrGenerator::new().generate(&ast).unwrap();
+
+    for program in [ir.clone(), IrOptimizer::new().optimize(ir)] {
+        let main = program.functions.iter().find(|function| function.name == "main").unwrap();
+        let calls: Vec<&str> = main.instructions.iter().filter_map(|instruction| match instruction {
+            IrInstruction::Call { func, .. } => Some(func.as_str()),
+            _ => None,
+        }).collect();
+        assert_eq!(calls, vec!["f", "g"], "{}", main);
+
+        // The emitted code calls them in the same order
+        for platform in [TargetPlatform::LinuxX64, TargetPlatform::WindowsX64] {
+            let asm = Codegen::new_with_target(platform).generate(&program);
+            let main_body = &asm[asm.find("FUNCTION: main").unwrap()..];
+            let call_f = main_body.find("call     f ").expect(&asm);
+            let call_g = main_body.find("call     g ").expect(&asm);
+            assert!(call_f < call_g, "{}", asm);
+        }
+    }
+}
+
+#[test]
+fn test_static_local_lives_in_the_data_section() {
+    use compiler_minic::codegen::targets::TargetPlatform;
+    use compiler_minic::ir::{IrType, IrValue, IrOptimizer};
+
+    let source = "int counter() { static int count = 5; count = count + 1; return count; } \
+                  int main() { counter(); return counter(); }";
+    let tokens = Lexer::new(source).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse();
+    let ir = IrOptimizer::new().optimize(IrGenerator::new().generate(&ast).unwrap());
+    assert_eq!(ir.global_vars, vec![("counter.static.count".to_string(), IrType::Int, IrValue::IntConstant(5))]);
+
+    let counter = ir.functions.iter().find(|function| function.name == "counter").unwrap();
+    assert!(counter.local_vars.is_empty(), "{}", counter);
+
+    let asm = Codegen::new_with_target(TargetPlatform::LinuxX64).generate(&ir);
+    let data = &asm[asm.find("section .data").unwrap()..asm.find("section .text").unwrap()];
+    assert!(data.contains("counter.static.count: dd 5"), "{}", asm);
+
+    // Every use goes through the global, none through a stack slot
+    let body = &asm[asm.find("FUNCTION: counter").unwrap()..asm.find("FUNCTION: main").unwrap()];
+    let uses: Vec<&str> = body.lines().filter(|line| line.contains("@counter.static.count")).collect();
+    assert!(uses.len() >= 2, "{}", body);
+    for line in uses {
+        assert!(line.contains("[counter.static.count]") && !line.contains("[rbp"), "{}", line);
+    }
+}
+
+#[test]
+fn test_char_array_initialized_from_string_literal() {
+    use compiler_minic::codegen::targets::TargetPlatform;
+    use compiler_minic::ir::{IrInstruction, IrType, IrValue, IrOptimizer};
+
+    let source = "int main() { char msg[] = \"ab\"; char first = msg[0]; return first; }";
+    let tokens = Lexer::new(source).tokenize().unwrap();
+    let mut parser = Parser::new(tokens);
+    let ast = parser.parse();
+    assert!(parser.get_errors().is_empty(), "{:?}", parser.get_errors());
+    let ir = IrOptimizer::new().optimize(IrGenerator::new().generate(&ast).unwrap());
+    let main = &ir.functions[0];
+
+    // Three bytes: the two characters and the terminating NUL
+    let msg_type = IrType::Array(Box::new(IrType::Char), 3);
+    assert_eq!(msg_type.size(), 3);
+    assert!(main.local_vars.contains(&("msg".to_string(), msg_type)), "{}", main);
+    let stored: Vec<(IrValue, IrValue)> = main.instructions.iter().filter_map(|instruction| match instruction {
+        IrInstruction::StoreElement { value, index, .. } => Some((index.clone(), value.clone())),
+        _ => None,
+    }).collect();
+    assert_eq!(stored, vec![
+        (IrValue::IntConstant(0), IrValue::CharConstant('a')),
+        (IrValue::IntConstant(1), IrValue::CharConstant('b')),
+        (IrValue::IntConstant(2), IrValue::CharConstant('\0')),
+    ]);
+    assert!(main.instructions.iter().any(|instruction| matches!(instruction,
+        IrInstruction::LoadElement { array: IrValue::Local(name), index: IrValue::IntConstant(0), var_type: IrType::Char, .. } if name == "msg")),
+        "{}", main);
+
+    // msg[0] reads the byte the 'a' was stored to
+    let asm = Codegen::new_with_target(TargetPlatform::LinuxX64).generate(&ir);
+    let store = asm.lines().find(|line| line.contains("store 'a' to %msg[0]")).unwrap_or_else(|| panic!("{}", asm));
+    let load = asm.lines().find(|line| line.contains("load %msg[0]")).unwrap_or_else(|| panic!("{}", asm));
+    let slot = |line: &str| line[line.find('[').unwrap()..=line.find(']').unwrap()].to_string();
+    assert_eq!(slot(store), slot(load), "{}", asm);
+}
+
+#[test]
+fn test_char_array_too_small_for_its_string_is_an_error() {
+    let tokens = Lexer::new("int main() { char s[2] = \"abc\"; return 0; }").tokenize().unwrap();
+    let mut parser = Parser::new(tokens);
+    parser.parse();
+    assert!(parser.get_errors().iter().any(|error| error.to_string().contains("'s' is 3 characters long")), "{:?}", parser.get_errors());
+}
+
+#[test]
+fn test_print_call_expression_matches_print_statement() {
+    use compiler_minic::parser::ast::{Expr, Stmt};
+
+    let ir_of = |source: &str| {
+        let tokens = Lexer::new(source).tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let ast = parser.parse();
+        assert!(parser.get_errors().is_empty(), "{:?}", parser.get_errors());
+        (IrGenerator::new().generate(&ast).unwrap(), ast)
+    };
+
+    // A parenthesized print is an expression statement holding a call
+    let (statement_ir, statement_ast) = ir_of("int main() { int n = 3; printf(\"n=%d\\n\", n); println(n); return 0; }");
+    let (call_ir, call_ast) = ir_of("int main() { int n = 3; (printf(\"n=%d\\n\", n)); (println(n)); return 0; }");
+    let Stmt::Function { body, .. } = &statement_ast[0] else { panic!() };
+    assert!(matches!(body[1], Stmt::PrintStmt { .. }), "{:?}", body[1]);
+    let Stmt::Function { body, .. } = &call_ast[0] else { panic!() };
+    assert!(matches!(&body[1], Stmt::ExprStmt(Expr::Call { .. })), "{:?}", body[1]);
+
+    assert_eq!(statement_ir.first_difference(&call_ir), None);
+
+    // Printing produces no value to use
+    for source in ["int main() { int n = println(1); return n; }", "int main() { return 1 + print(\"x\"); }"] {
+        let ast = Parser::new(Lexer::new(source).tokenize().unwrap()).parse();
+        let error = IrGenerator::new().generate(&ast).unwrap_err();
+        assert!(format!("{:?}", error).contains("returns no value"), "{}: {:?}", source, error);
+    }
+}
+
+#[test]
+fn test_aggregate_ir_types_report_their_size() {
+    use compiler_minic::ir::{IrFunction, IrInstruction, IrProgram, IrType};
+    use std::collections::HashMap;
+
+    let array = IrType::Array(Box::new(IrType::Int), 5);
+    assert_eq!(array.size(), 20);
+    assert_eq!(array.alignment(), 4);
+    assert_eq!(array.to_string(), "[5 x i32]");
+
+    // Fields are padded to their alignment, and the whole struct to its own
+    let record = IrType::Struct(vec![IrType::Char, IrType::Int, IrType::Char]);
+    assert_eq!(record.field_offsets(), vec![0, 4, 8]);
+    assert_eq!(record.size(), 12);
+    assert_eq!(record.to_string(), "{ i8, i32, i8 }");
+    assert_eq!(IrType::Array(Box::new(record.clone()), 3).size(), 36);
+    assert_eq!(IrType::Struct(vec![IrType::Char; 3]).size(), 3);
+
+    // A struct the generator did not lay out still gets an aligned slot
+    let program = IrProgram {
+        functions: vec![IrFunction {
+            name: "main".to_string(),
+            return_type: IrType::Void,
+            parameters: vec![],
+            instructions: vec![
+                IrInstruction::Alloca { var_type: IrType::Char, name: "c".to_string() },
+                IrInstruction::Alloca { var_type: record.clone(), name: "r".to_string() },
+                IrInstruction::Return { value: None, var_type: IrType::Void },
+            ],
+            local_vars: vec![("c".to_string(), IrType::Char), ("r".to_string(), record)],
+            local_offsets: HashMap::new(),
+            is_static: false,
+            register_hints: vec![],
+        }],
+        global_strings: vec![],
+        global_vars: vec![],
+    };
+    let asm = Codegen::new().generate(&program);
+    assert!(asm.contains("alloca { i8, i32, i8 } r at [rbp-48] (12 bytes)"), "{}", asm);
+}
+
+#[test]
+fn test_trap_aborts_and_ends_its_block() {
+    use compiler_minic::ir::format_cfg;
+
+    let source = "int main() { int x = 1; trap(); x = 2; return x; }";
+    let tokens = Lexer::new(source).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse();
+    let ir = IrGenerator::new().generate(&ast).unwrap();
+    let ir_text = ir.to_string();
+    assert!(ir_text.lines().any(|line| line.trim() == "trap"), "{}", ir_text);
+    assert!(!ir_text.contains("call"), "trap() must not be a call by name:\n{}", ir_text);
+
+    // The trapping block has no successor, so nothing reaches the code after it
+    let cfg = format_cfg(&ir.functions[0]);
+    let headers: Vec<&str> = cfg.lines().filter(|line| line.starts_with("  bb")).collect();
+    assert!(headers[0].starts_with("  bb0") && headers[0].ends_with("-> exit"), "{}", cfg);
+    assert!(headers.iter().all(|header| !header.split("->").nth(1).unwrap().contains("bb1")), "{}", cfg);
+
+    let instructions = instructions_of(&Codegen::new().generate(&ir));
+    assert!(instructions.iter().any(|line| line == "ud2"), "{:#?}", instructions);
+}
+
+#[test]
+fn test_zero_checks_use_test_instead_of_cmp() {
+    let instructions = asm_instructions("int main() { int x = 3; int y = 0; bool b = x && y; bool c = x || y; if (b) { y = 1; } if (x != 0) { y = !x; } return y; }");
+    assert!(!instructions.iter().any(|line| line.starts_with("cmp ") && line.ends_with(", 0")), "zero check still uses cmp: {:#?}", instructions);
+    assert!(instructions.iter().any(|line| line == "test al, al"), "bool branch condition: {:#?}", instructions);
+    assert!(instructions.iter().any(|line| line == "test eax, eax"), "int zero check: {:#?}", instructions);
+}
+
+#[test]
+fn test_redeclared_locals_get_their_own_names() {
+    fn compile(source: &str) -> (compiler_minic::ir::IrFunction, String) {
+        let tokens = Lexer::new(source).tokenize().unwrap();
+        let ast = Parser::new(tokens).parse();
+        let ir = IrGenerator::new().generate(&ast).unwrap();
+        let asm = Codegen::new().generate(&ir);
+        (ir.functions[0].clone(), asm)
+    }
+    let frame_size = |asm: &str| instructions_of(asm).into_iter()
+        .find_map(|line| line.strip_prefix("sub rsp, ").map(str::to_string))
+        .unwrap_or_else(|| panic!("no frame:\n{}", asm));
+
+    // Sibling blocks each declaring `t` list each once and share a slot
+    let (main, asm) = compile("int main() { { int t; t = 1; } { int t; t = 2; } return 0; }");
+    let names: Vec<&str> = main.local_vars.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, vec!["t", "t.1"]);
+    assert_eq!(main.local_offsets["t"], main.local_offsets["t.1"]);
+    let (_, single_asm) = compile("int main() { { int t; t = 1; } return 0; }");
+    assert_eq!(frame_size(&asm), frame_size(&single_asm));
+
+    // An inner `t` has its own slot, so the outer one keeps its value
+    let (main, _) = compile("int main() { int t = 1; { int t = 2; t = t + 1; } return t; }");
+    assert_ne!(main.local_offsets["t"], main.local_offsets["t.1"]);
+    let ir_text = main.to_string();
+    assert!(ir_text.contains("store i32 %t1, %t.1"), "{}", ir_text);
+    assert!(ir_text.lines().rev().find(|line| line.contains("= load")).unwrap().ends_with("%t"), "{}", ir_text);
+}
+
+#[test]
+fn test_parameters_locals_and_temps_have_disjoint_slots() {
+    let source = "int scale(int a, float b) { int s = a + 1; char c = 'x'; float r = b * 2.0; return s + a; }";
+    let tokens = Lexer::new(source).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse();
+    let ir = IrGenerator::new().generate(&ast).unwrap();
+    let function = &ir.functions[0];
+
+    let mut codegen = Codegen::new();
+    let frame = codegen.calculate_stack_space(function);
+
+    // (start, end) byte range below rbp of every slot
+    let mut slots: Vec<(String, i32, i32)> = function.parameters.iter()
+        .chain(&function.local_vars)
+        .map(|(name, ir_type)| {
+            let offset = codegen.locals[name];
+            (name.clone(), offset, offset + ir_type.size() as i32)
+        })
+        .collect();
+    slots.extend(codegen.temp_locations.iter().map(|(id, offset)| (format!("%t{}", id), *offset, offset + 8)));
+    assert!(slots.len() > 5, "{:?}", slots);
+
+    slots.sort_by_key(|(_, start, _)| *start);
+    for pair in slots.windows(2) {
+        assert!(pair[0].2 <= pair[1].1, "{} and {} overlap: {:?}", pair[0].0, pair[1].0, slots);
+    }
+    assert!(slots.iter().all(|(_, start, end)| *start >= -frame && *end <= 0), "{:?} outside a {}-byte frame", slots, frame);
+
+    // The prologue spills both argument registers into the parameter slots
+    let asm = Codegen::new().generate(&ir);
+    assert_eq!(asm.matches("; spill parameter").count(), 2, "{}", asm);
+}
+
+#[test]
+fn test_for_loop_variable_is_scoped_to_the_loop() {
+    let source = r#"
+int main() {
+    int i = 7;
+    int total = 0;
+    for (int i = 0; i < 3; i = i + 1) {
+        total = total + i;
+    }
+    for (int i = 0; i < 2; i = i + 1) {
+        total = total + i;
+    }
+    return i + total;
+}
+"#;
+    let tokens = Lexer::new(source).tokenize().unwrap();
+    let mut parser = Parser::new(tokens);
+    let ast = parser.parse();
+    assert!(parser.get_errors().is_empty(), "{:?}", parser.get_errors());
+    let ir = IrGenerator::new().generate(&ast).unwrap();
+    let main = &ir.functions[0];
+
+    // Each loop's `i` is a local of its own, sharing one slot
+    let names: Vec<&str> = main.local_vars.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, vec!["i", "total", "i.1", "i.2"]);
+    assert_eq!(main.local_offsets["i.1"], main.local_offsets["i.2"]);
+    assert_ne!(main.local_offsets["i"], main.local_offsets["i.1"]);
+
+    // Past the loops `i` is the outer variable again
+    let ir_text = main.to_string();
+    let (_, after_loops) = ir_text.rsplit_once("for_end").unwrap();
+    assert!(after_loops.lines().any(|line| line.ends_with("load i32, %i")), "{}", ir_text);
+    assert!(!after_loops.contains("%i."), "{}", ir_text);
+}
+
+#[test]
+fn test_no_crt_emits_an_entry_point_that_exits_with_mains_result() {
+    use compiler_minic::codegen::targets::TargetPlatform;
+
+    let tokens = Lexer::new("int main() { return 42; }").tokenize().unwrap();
+    let ast = Parser::new(tokens).parse();
+    let ir = IrGenerator::new().generate(&ast).unwrap();
+    let asm = instructions_of(&Codegen::new_with_target(TargetPlatform::LinuxX64).with_no_crt(true).generate(&ir));
+    assert!(asm.contains(&"global _start".to_string()), "{:#?}", asm);
+    let entry = asm.iter().position(|line| line == "_start:").expect("no _start");
+    assert_eq!(&asm[entry + 1..entry + 5], ["call main", "mov edi, eax", "mov rax, 60", "syscall"]);
+
+    // The C runtime brings its own `_start`, so none is emitted without the flag
+    let asm = instructions_of(&Codegen::new_with_target(TargetPlatform::LinuxX64).generate(&ir));
+    assert!(!asm.iter().any(|line| line.contains("_start")), "{:#?}", asm);
+}
+
+#[test]
+fn test_ir_types_name_themselves_in_c_for_diagnostics() {
+    use compiler_minic::ir::IrType;
+    use compiler_minic::lexer::TokenType;
+    use compiler_minic::types::Type;
+
+    let pointer = IrType::Pointer(Box::new(IrType::Int));
+    assert_eq!(pointer.c_name(), "int *");
+    assert_eq!(IrType::Pointer(Box::new(pointer.clone())).c_name(), "int **");
+    assert_eq!(IrType::Array(Box::new(IrType::Char), 10).c_name(), "char[10]");
+    assert_eq!(IrType::function_pointer(IrType::Float).c_name(), "float () *");
+    // The IR text keeps its own syntax
+    assert_eq!(pointer.to_string(), "i32*");
+
+    // A token that is no type is reported as the source type reports it
+    assert_eq!(IrType::try_from(TokenType::FloatType), Ok(IrType::Float));
+    assert_eq!(IrType::try_from(TokenType::Return).unwrap_err(), Type::try_from(TokenType::Return).unwrap_err());
+}
+
+#[test]
+fn test_arguments_past_the_registers_are_passed_on_the_stack() {
+    use compiler_minic::codegen::targets::TargetPlatform;
+
+    let source = "int pick(int a, int b, int c, int d, int e, int f) { return e - f; } int main() { return pick(1, 2, 3, 4, 5, 6); }";
+    let tokens = Lexer::new(source).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse();
+    let ir = IrGenerator::new().generate(&ast).unwrap();
+    let asm = Codegen::new_with_target(TargetPlatform::WindowsX64).generate(&ir);
+
+    // Four register arguments under the Microsoft convention; the 6th is
+    // pushed first, so the 5th sits right above the shadow space
+    let sixth = asm.find("pass arg 5 on the stack").expect(&asm);
+    let fifth = asm.find("pass arg 4 on the stack").expect(&asm);
+    let shadow = asm.find("shadow space for the register arguments").expect(&asm);
+    let call = asm.find("; call pick\n").expect(&asm);
+    assert!(sixth < fifth && fifth < shadow && shadow < call, "{}", asm);
+    assert!(!asm.contains("pass arg 3 on the stack"), "{}", asm);
+    assert!(asm.contains("add      rsp, 48              ; release the stack arguments"), "{}", asm);
+    assert!(asm.contains("mov      eax, [rbp+48]        ; load stack parameter e"), "{}", asm);
+    assert!(asm.contains("mov      eax, [rbp+56]        ; load stack parameter f"), "{}", asm);
+}
+
+#[test]
+fn test_generated_assembly_is_available_line_by_line() {
+    use compiler_minic::codegen::{render_lines, AsmLineKind};
+    use compiler_minic::codegen::targets::TargetPlatform;
+
+    let tokens = Lexer::new("int main() { int x = 2; return x + 1; }").tokenize().unwrap();
+    let ast = Parser::new(tokens).parse();
+    let ir = IrGenerator::new().generate(&ast).unwrap();
+    let lines = Codegen::new_with_target(TargetPlatform::LinuxX64).generate_lines(&ir).unwrap();
+
+    let count = |kind| lines.iter().filter(|line| line.kind() == kind).count();
+    let instructions = count(AsmLineKind::Instruction);
+    let comments = count(AsmLineKind::Comment);
+    assert!(instructions >= 6, "{}", render_lines(&lines));
+    assert!(comments > instructions, "{}", render_lines(&lines));
+    assert!(lines.iter().any(|line| line.kind() == AsmLineKind::Label && line.to_string() == "main:"));
+    assert!(lines.iter().any(|line| line.kind() == AsmLineKind::Directive && line.to_string() == "section .text"));
+    assert!(lines.iter().filter(|line| line.kind() == AsmLineKind::Instruction).all(|line| !line.to_string().trim_start().starts_with(';')));
+
+    // The text is a rendering of the same lines
+    assert_eq!(render_lines(&lines), Codegen::new_with_target(TargetPlatform::LinuxX64).generate(&ir));
+}
+
+#[test]
+fn test_for_loop_declares_and_updates_several_counters() {
+    let source = r#"
+int main() {
+    int steps = 0;
+    for (int i = 0, j = 10; i < j; i = i + 1, j = j - 1) {
+        steps = steps + 1;
+    }
+    return steps;
+}
+"#;
+    let tokens = Lexer::new(source).tokenize().unwrap();
+    let mut parser = Parser::new(tokens);
+    let ast = parser.parse();
+    assert!(parser.get_errors().is_empty(), "{:?}", parser.get_errors());
+
+    let Stmt::Function { body, .. } = &ast[0] else { panic!("{:?}", ast) };
+    let Stmt::For { init, update, .. } = &body[1] else { panic!("{:?}", body) };
+    let declared: Vec<&str> = init.iter().map(|stmt| match stmt {
+        Stmt::VarDecl { name, .. } => name.as_str(),
+        other => panic!("{:?}", other),
+    }).collect();
+    assert_eq!(declared, vec!["i", "j"]);
+    assert_eq!(update.len(), 2);
+
+    // Both counters are stored each iteration, `i` before `j`
+    let ir = IrGenerator::new().generate(&ast).unwrap().functions[0].to_string();
+    let (_, update_block) = ir.split_once("for_continue").unwrap();
+    let (update_block, _) = update_block.split_once("jmp").unwrap();
+    let stored: Vec<&str> = update_block.lines()
+        .filter(|line| line.trim_start().starts_with("store"))
+        .filter_map(|line| line.rsplit_once(", %").map(|(_, name)| name))
+        .collect();
+    assert_eq!(stored, vec!["i", "j"], "{}", ir);
+
+    // Counters declared before the loop are set by a list of expressions
+    let source = "int main() { int i; int j; for (i = 0, j = 5; i < j; i = i + 1) { } return i + j; }";
+    let tokens = Lexer::new(source).tokenize().unwrap();
+    let mut parser = Parser::new(tokens);
+    let ast = parser.parse();
+    assert!(parser.get_errors().is_empty(), "{:?}", parser.get_errors());
+
+    let Stmt::Function { body, .. } = &ast[0] else { panic!("{:?}", ast) };
+    let Stmt::For { init, .. } = &body[2] else { panic!("{:?}", body) };
+    let assigned: Vec<&str> = init.iter().map(|stmt| match stmt {
+        Stmt::ExprStmt(Expr::Assignment { name, .. }) => name.as_str(),
+        other => panic!("{:?}", other),
+    }).collect();
+    assert_eq!(assigned, vec!["i", "j"]);
+    IrGenerator::new().generate(&ast).unwrap();
+}
+
+#[test]
+fn test_else_branch_runs_when_the_condition_is_false() {
+    let source = r#"
+int main() {
+    int x = 5;
+    int r = 0;
+    if (x < 0) {
+        r = 1;
+    } else if (x == 0) {
+        r = 2;
+    } else {
+        r = 3;
+    }
+    return r;
+}
+"#;
+    let tokens = Lexer::new(source).tokenize().unwrap();
+    let mut parser = Parser::new(tokens);
+    let ast = parser.parse();
+    assert!(parser.get_errors().is_empty(), "{:?}", parser.get_errors());
+    let ir = IrGenerator::new().generate(&ast).unwrap();
+    let text = ir.functions[0].to_string();
+
+    // The false edge of each test goes to its else, which rejoins the end
+    let first_branch = text.lines().find(|line| line.trim_start().starts_with("br ")).unwrap();
+    assert!(first_branch.contains("label %if_else_"), "{}", text);
+    for value in ["store i32 1, %r", "store i32 2, %r", "store i32 3, %r"] {
+        let (_, after) = text.split_once(value).unwrap_or_else(|| panic!("{}", text));
+        let next = after.lines().nth(1).unwrap();
+        assert!(next.trim_start().starts_with("jmp label %if_end_"), "{}", text);
+    }
+    verify_round_trip(&ir).unwrap();
+}
+
+#[test]
+fn test_signed_int_printf_argument_is_sign_extended() {
+    use compiler_minic::codegen::targets::TargetPlatform;
+
+    let source = "int main() { int x = 0 - 5; printf(\"%ld\\n\", x); return 0; }";
+    let tokens = Lexer::new(source).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse();
+    let ir = IrGenerator::new().generate(&ast).unwrap();
+
+    // The argument after the format string goes in the second register
+    for (target, register) in [(TargetPlatform::LinuxX64, "rsi"), (TargetPlatform::WindowsX64, "rdx")] {
+        let asm = Codegen::new_with_target(target).generate(&ir);
+        let extend = asm.find(&format!("movsxd   {}, eax", register)).expect(&asm);
+        let call = asm.find("; call printf\n").expect(&asm);
+        assert!(extend < call, "{}", asm);
+        assert!(!asm.contains(&format!("mov      e{}, eax", &register[1..])), "{}", asm);
+    }
+}
+
+#[test]
+fn test_bitwise_operators_use_the_matching_instructions() {
+    let source = "int main() { int x = 13; int n = 2; return (x & 3) + (x | 16) + (x ^ 5) + (x << n) + (x >> 1); }";
+    let tokens = Lexer::new(source).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse();
+    let ir = IrGenerator::new().generate(&ast).unwrap();
+    let text = ir.to_string();
+    for op in ["bitand i32", "bitor i32", "bitxor i32", "shl i32", "shr i32"] {
+        assert!(text.contains(op), "{}\n{}", op, text);
+    }
+    verify_round_trip(&ir).unwrap();
+
+    let asm = Codegen::new().generate(&ir);
+    assert!(asm.contains("and      eax, 3"), "{}", asm);
+    assert!(asm.contains("or       eax, 16"), "{}", asm);
+    assert!(asm.contains("xor      eax, 5"), "{}", asm);
+    // A count that is not a constant goes through cl
+    assert!(asm.contains("shl      eax, cl"), "{}", asm);
+    assert!(asm.contains("sar      eax, 1"), "{}", asm);
+}
+
+#[test]
+fn test_bitwise_operators_on_floats_are_errors() {
+    use compiler_minic::ir::IrGeneratorError;
+
+    let cases = [
+        ("float half() { return 0.5; } int main() { return half() & 1; }", "'&'"),
+        ("int main() { float f = 1.5; int y = 2; return y << f; }", "'<<'"),
+        ("int main() { int y = 2; return y ^ 2.0 * y; }", "'^'"),
+    ];
+    for (source, operator) in cases {
+        let tokens = Lexer::new(source).tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let ast = parser.parse();
+        assert!(parser.get_errors().is_empty(), "{:?}", parser.get_errors());
+        let error = IrGenerator::new().generate(&ast).unwrap_err();
+        assert!(matches!(&error, IrGeneratorError::UnsupportedConstruct(message) if message.contains(operator) && message.contains("float")), "{}: {:?}", source, error);
+    }
+}
+
+#[test]
+fn test_string_literal_index_and_length_fold_to_constants() {
+    let source = "int main() { char c = \"abc\"[0]; int n = __builtin_strlen(\"hello\"); return c + n; }";
+    let tokens = Lexer::new(source).tokenize().unwrap();
+    let ast = Parser::new(tokens).parse();
+    let ir = IrGenerator::new().generate(&ast).unwrap();
+    let text = ir.to_string();
+    assert!(text.contains("store i8 'a', %c"), "{}", text);
+    assert!(text.contains("store i32 5, %n"), "{}", text);
+    assert!(!text.contains("call"), "{}", text);
+}
+
+#[test]
+fn test_modulo_takes_the_remainder_from_edx() {
+    let instructions = asm_instructions("int main() { int a = 17; int b = 5; int r = a % b; int s = a % 4; return r + s; }");
+    assert_eq!(instructions.iter().filter(|line| line.starts_with("idiv ")).count(), 2, "{:#?}", instructions);
+    assert!(instructions.iter().any(|line| line.starts_with("mov ") && line.ends_with(", edx")), "{:#?}", instructions);
+    // A constant divisor goes through a register, as idiv takes no immediate
+    assert!(instructions.iter().any(|line| line == "mov ecx, 4"), "{:#?}", instructions);
+}
+
+#[test]
+fn test_stepping_by_one_uses_inc_and_dec() {
+    let instructions = asm_instructions("int main() { int i = 0; i = i + 1; i = i - 1; int j = i + 2; return i + j; }");
+    assert!(instructions.iter().any(|line| line == "inc eax"), "{:#?}", instructions);
+    assert!(instructions.iter().any(|line| line == "dec eax"), "{:#?}", instructions);
+    assert!(!instructions.iter().any(|line| line == "add eax, 1" || line == "sub eax, 1"), "{:#?}", instructions);
+    // Other constants keep add, which sets the carry flag
+    assert!(instructions.iter().any(|line| line == "add eax, 2"), "{:#?}", instructions);
 }